@@ -0,0 +1,38 @@
+#![feature(test)]
+
+extern crate test;
+
+use rand::{distributions::Uniform, Rng};
+use similari::distance::{cosine, euclidean};
+use similari::track::utils::FromVec;
+use similari::track::Feature;
+use test::Bencher;
+
+fn features(vec_len: usize, count: usize) -> Vec<Feature> {
+    let mut rng = rand::thread_rng();
+    let gen = Uniform::new(0.0, 1.0);
+    (0..count)
+        .map(|_| Feature::from_vec((0..vec_len).map(|_| rng.sample(gen)).collect::<Vec<_>>()))
+        .collect()
+}
+
+#[bench]
+fn euclidean_0512_0100k(b: &mut Bencher) {
+    let query = features(512, 1).pop().unwrap();
+    let gallery = features(512, 100_000);
+    b.iter(|| gallery.iter().map(|f| euclidean(&query, f)).sum::<f32>());
+}
+
+#[bench]
+fn cosine_0512_0100k(b: &mut Bencher) {
+    let query = features(512, 1).pop().unwrap();
+    let gallery = features(512, 100_000);
+    b.iter(|| gallery.iter().map(|f| cosine(&query, f)).sum::<f32>());
+}
+
+#[bench]
+fn euclidean_0032_0100k(b: &mut Bencher) {
+    let query = features(32, 1).pop().unwrap();
+    let gallery = features(32, 100_000);
+    b.iter(|| gallery.iter().map(|f| euclidean(&query, f)).sum::<f32>());
+}
@@ -7,7 +7,7 @@ use similari::examples::FeatGen;
 use similari::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
 use similari::track::{
     MetricOutput, MetricQuery, NoopLookup, Observation, ObservationMetric, ObservationMetricOk,
-    ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackStatus,
+    ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackId, TrackStatus,
 };
 use similari::voting::topn::TopNVoting;
 use similari::voting::Voting;
@@ -63,7 +63,7 @@ impl ObservationMetric<NoopAttributes, ()> for TrackMetric {
     fn optimize(
         &mut self,
         _feature_class: u64,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
         _attrs: &mut NoopAttributes,
         observations: &mut Vec<Observation<()>>,
         _prev_length: usize,
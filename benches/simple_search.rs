@@ -7,7 +7,7 @@ use similari::examples::{UnboundAttributeUpdate, UnboundAttrs, UnboundMetric};
 use similari::prelude::{ObservationBuilder, TrackStoreBuilder};
 use similari::track::notify::NoopNotifier;
 use similari::track::utils::FromVec;
-use similari::track::Feature;
+use similari::track::{Feature, TrackId};
 use test::Bencher;
 
 #[bench]
@@ -42,7 +42,7 @@ fn bench_capacity_len(vec_len: usize, count: usize, b: &mut Bencher) {
 
     for i in 0..count {
         let res = db.add(
-            i as u64,
+            i as TrackId,
             DEFAULT_FEATURE,
             Some(1.0),
             Some(Feature::from_vec(
@@ -55,7 +55,7 @@ fn bench_capacity_len(vec_len: usize, count: usize, b: &mut Bencher) {
 
     b.iter(|| {
         let t = db
-            .new_track(count as u64 + 1)
+            .new_track(count as TrackId + 1)
             .observation(
                 ObservationBuilder::new(DEFAULT_FEATURE)
                     .observation_attributes(1.0)
@@ -4,7 +4,7 @@ extern crate test;
 
 use rand::{distributions::Uniform, Rng};
 use similari::examples::{UnboundAttributeUpdate, UnboundAttrs, UnboundMetric};
-use similari::track::Feature;
+use similari::track::{Feature, TrackId};
 
 use similari::prelude::TrackStoreBuilder;
 use similari::track::notify::NoopNotifier;
@@ -24,7 +24,7 @@ fn bench_capacity_len(vec_len: usize, track_len: usize, count: usize, b: &mut Be
     for i in 0..count {
         for _j in 0..track_len {
             let res = db.add(
-                i as u64,
+                i as TrackId,
                 DEFAULT_FEATURE,
                 Some(1.0),
                 Some(Feature::from_vec(
@@ -36,7 +36,7 @@ fn bench_capacity_len(vec_len: usize, track_len: usize, count: usize, b: &mut Be
         }
     }
 
-    let mut t = db.new_track(count as u64 + 1).build().unwrap();
+    let mut t = db.new_track(count as TrackId + 1).build().unwrap();
     for _j in 0..track_len {
         let _ = t.add_observation(
             DEFAULT_FEATURE,
@@ -3,7 +3,8 @@ use similari::examples::{BoxGen2, FeatGen2};
 use similari::prelude::*;
 use similari::track::{
     MetricOutput, MetricQuery, NoopLookup, Observation, ObservationAttributes, ObservationMetric,
-    ObservationMetricOk, ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackStatus,
+    ObservationMetricOk, ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackId,
+    TrackStatus,
 };
 use similari::utils::bbox::BoundingBox;
 use similari::voting::topn::TopNVoting;
@@ -67,7 +68,7 @@ impl ObservationMetric<BBoxAttributes, f32> for TrackMetric {
     fn optimize(
         &mut self,
         _feature_class: u64,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
         _attrs: &mut BBoxAttributes,
         observations: &mut Vec<Observation<f32>>,
         _prev_length: usize,
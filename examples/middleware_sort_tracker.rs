@@ -36,7 +36,7 @@ fn main() {
         let obj1b = b1.next().unwrap();
         let obj2b = b2.next().unwrap();
 
-        let track_id = u64::try_from(current_time_ms()).unwrap();
+        let track_id = current_time_ms();
         let obj1t = store
             .new_track(track_id)
             .observation(
@@ -39,7 +39,7 @@ fn main() {
             .filter(|(_b, r)| r.is_ok())
             .map(|(x, _)| x)
             .collect::<Vec<_>>(),
-        vec![0u64]
+        vec![0]
     );
 
     let res = db.add(
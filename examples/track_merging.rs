@@ -9,7 +9,7 @@ use similari::store::TrackStore;
 use similari::track::notify::NoopNotifier;
 use similari::track::{
     MetricOutput, MetricQuery, NoopLookup, Observation, ObservationAttributes, ObservationMetric,
-    ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackStatus,
+    ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackId, TrackStatus,
 };
 use similari::voting::topn::TopNVoting;
 use similari::voting::Voting;
@@ -279,7 +279,7 @@ impl ObservationMetric<CamTrackingAttributes, f32> for CamTrackingAttributesMetr
     fn optimize(
         &mut self,
         _feature_class: u64,
-        merge_history: &[u64],
+        merge_history: &[TrackId],
         _attrs: &mut CamTrackingAttributes,
         features: &mut Vec<Observation<f32>>,
         _prev_length: usize,
@@ -298,7 +298,7 @@ impl ObservationMetric<CamTrackingAttributes, f32> for CamTrackingAttributesMetr
 }
 
 struct TrackObservation {
-    pub track_id: u64,
+    pub track_id: TrackId,
     pub age: Option<u8>,
     pub gender: Option<Gender>,
     pub camera_id: u64,
@@ -309,7 +309,7 @@ struct TrackObservation {
 
 impl TrackObservation {
     pub fn new(
-        track_id: u64,
+        track_id: TrackId,
         age: Option<u8>,
         gender: Option<Gender>,
         camera_id: u64,
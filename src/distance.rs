@@ -1,5 +1,252 @@
+/// Batched pairwise distances calculated with the matrix multiplication
+///
+pub mod matrix;
+
+/// Pluggable backends for the brute-force pairwise distances
+///
+pub mod backend;
+
+/// Distance backend that calculates the pairwise distances on GPU with wgpu
+///
+#[cfg(feature = "gpu")]
+pub mod gpu;
+
 use crate::track::Feature;
-use std::ops::{Mul, MulAssign, SubAssign};
+use once_cell::sync::OnceCell;
+use std::ops::MulAssign;
+use ultraviolet::f32x8;
+
+/// Minimal amount of feature blocks starting from which the kernels use several independent accumulators
+///
+/// Long vectors (e.g. 512-d embeddings) are processed by the kernels of the [LaneWidth](LaneWidth) selected for
+/// the CPU, short vectors are processed one block per step.
+///
+pub const WIDE_KERNEL_MIN_BLOCKS: usize = 8;
+
+/// SIMD lane width of the distance kernels for long vectors
+///
+/// The width is selected at runtime with [LaneWidth::detect](LaneWidth::detect). Besides the portable kernels,
+/// the kernels are written with the AVX2 and FMA intrinsics, so the binary built for the baseline CPU uses the
+/// 256-bit instructions on the CPUs that support them.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaneWidth {
+    /// the portable kernels (e.g. SSE, NEON), two feature blocks per step
+    X4,
+    /// the 256-bit AVX2 kernels with FMA, four feature blocks per step
+    X8,
+}
+
+impl LaneWidth {
+    /// The widest lane width supported by the CPU, the CPU features are detected once
+    ///
+    pub fn detect() -> Self {
+        static WIDTH: OnceCell<LaneWidth> = OnceCell::new();
+        *WIDTH.get_or_init(|| {
+            #[cfg(target_arch = "x86_64")]
+            if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+                return LaneWidth::X8;
+            }
+            LaneWidth::X4
+        })
+    }
+}
+
+/// The distance kernel that folds the pairs of blocks of two vectors into `K` sums
+///
+trait Kernel<const K: usize> {
+    /// Folds the pair of blocks into `K` vector accumulators
+    ///
+    fn fold(acc: &mut [f32x8; K], a: f32x8, b: f32x8);
+
+    /// Folds the vectors with the AVX2 and FMA instructions
+    ///
+    /// # Safety
+    /// The CPU must support AVX2 and FMA.
+    ///
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn fold_avx2(f1: &[f32x8], f2: &[f32x8]) -> [f32; K];
+}
+
+/// Sum of the squared differences
+///
+struct SquaredDiff;
+
+impl Kernel<1> for SquaredDiff {
+    #[inline(always)]
+    fn fold([acc]: &mut [f32x8; 1], a: f32x8, b: f32x8) {
+        let d = a - b;
+        *acc = d.mul_add(d, *acc);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn fold_avx2(f1: &[f32x8], f2: &[f32x8]) -> [f32; 1] {
+        avx2::squared_diff(f1, f2)
+    }
+}
+
+/// The dot product and the squared norms of both vectors
+///
+struct DotNorms;
+
+impl Kernel<3> for DotNorms {
+    #[inline(always)]
+    fn fold([ab, aa, bb]: &mut [f32x8; 3], a: f32x8, b: f32x8) {
+        *ab = a.mul_add(b, *ab);
+        *aa = a.mul_add(a, *aa);
+        *bb = b.mul_add(b, *bb);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn fold_avx2(f1: &[f32x8], f2: &[f32x8]) -> [f32; 3] {
+        avx2::dot_norms(f1, f2)
+    }
+}
+
+/// The dot product
+///
+struct Dot;
+
+impl Kernel<1> for Dot {
+    #[inline(always)]
+    fn fold([acc]: &mut [f32x8; 1], a: f32x8, b: f32x8) {
+        *acc = a.mul_add(b, *acc);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn fold_avx2(f1: &[f32x8], f2: &[f32x8]) -> [f32; 1] {
+        avx2::dot(f1, f2)
+    }
+}
+
+/// Folds the pairs of blocks of two vectors with `W` independent accumulator sets and reduces them to `K` sums
+///
+#[inline(always)]
+fn fold_blocks<KE: Kernel<K>, const W: usize, const K: usize>(
+    f1: &[f32x8],
+    f2: &[f32x8],
+) -> [f32; K] {
+    let mut acc = [[f32x8::ZERO; K]; W];
+    let (c1, c2) = (f1.chunks_exact(W), f2.chunks_exact(W));
+    let (r1, r2) = (c1.remainder(), c2.remainder());
+    for (b1, b2) in c1.zip(c2) {
+        for i in 0..W {
+            KE::fold(&mut acc[i], b1[i], b2[i]);
+        }
+    }
+    for (b1, b2) in r1.iter().zip(r2) {
+        KE::fold(&mut acc[0], *b1, *b2);
+    }
+
+    let mut res = [0.0; K];
+    for (k, r) in res.iter_mut().enumerate() {
+        *r = acc.iter().fold(f32x8::ZERO, |s, a| s + a[k]).reduce_add();
+    }
+    res
+}
+
+/// The kernels written with the AVX2 and FMA intrinsics, four feature blocks per step
+///
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+    use ultraviolet::f32x8;
+
+    const BLOCKS: usize = 4;
+
+    #[inline]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn load(block: &f32x8) -> __m256 {
+        _mm256_loadu_ps(block.as_array_ref().as_ptr())
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn reduce_add(v: __m256) -> f32 {
+        let s = _mm_add_ps(_mm256_castps256_ps128(v), _mm256_extractf128_ps(v, 1));
+        let s = _mm_add_ps(s, _mm_movehl_ps(s, s));
+        _mm_cvtss_f32(_mm_add_ss(s, _mm_shuffle_ps(s, s, 1)))
+    }
+
+    /// Defines the kernel that folds the pair of the loaded blocks `a` and `b` into the accumulators `acc`
+    ///
+    macro_rules! kernel {
+        ($name:ident, $k:literal, |$acc:ident, $a:ident, $b:ident| $body:block) => {
+            #[target_feature(enable = "avx2,fma")]
+            pub(super) unsafe fn $name(f1: &[f32x8], f2: &[f32x8]) -> [f32; $k] {
+                let mut accs = [[_mm256_setzero_ps(); $k]; BLOCKS];
+                let (c1, c2) = (f1.chunks_exact(BLOCKS), f2.chunks_exact(BLOCKS));
+                let (r1, r2) = (c1.remainder(), c2.remainder());
+                for (b1, b2) in c1.zip(c2) {
+                    for i in 0..BLOCKS {
+                        let ($acc, $a, $b) = (&mut accs[i], load(&b1[i]), load(&b2[i]));
+                        $body
+                    }
+                }
+                for (b1, b2) in r1.iter().zip(r2) {
+                    let ($acc, $a, $b) = (&mut accs[0], load(b1), load(b2));
+                    $body
+                }
+
+                let mut res = [0.0; $k];
+                for (k, r) in res.iter_mut().enumerate() {
+                    let mut sum = accs[0][k];
+                    for acc in &accs[1..] {
+                        sum = _mm256_add_ps(sum, acc[k]);
+                    }
+                    *r = reduce_add(sum);
+                }
+                res
+            }
+        };
+    }
+
+    kernel!(squared_diff, 1, |acc, a, b| {
+        let d = _mm256_sub_ps(a, b);
+        acc[0] = _mm256_fmadd_ps(d, d, acc[0]);
+    });
+
+    kernel!(dot_norms, 3, |acc, a, b| {
+        acc[0] = _mm256_fmadd_ps(a, b, acc[0]);
+        acc[1] = _mm256_fmadd_ps(a, a, acc[1]);
+        acc[2] = _mm256_fmadd_ps(b, b, acc[2]);
+    });
+
+    kernel!(dot, 1, |acc, a, b| {
+        acc[0] = _mm256_fmadd_ps(a, b, acc[0]);
+    });
+}
+
+/// Folds the long vectors with the kernel of the lane width
+///
+/// The portable kernel is used when the CPU doesn't support the width.
+///
+#[inline(always)]
+fn fold_wide<KE: Kernel<K>, const K: usize>(
+    width: LaneWidth,
+    f1: &[f32x8],
+    f2: &[f32x8],
+) -> [f32; K] {
+    match width {
+        // SAFETY: the AVX2 kernel is called only when the CPU supports AVX2 and FMA
+        #[cfg(target_arch = "x86_64")]
+        LaneWidth::X8 if LaneWidth::detect() == LaneWidth::X8 => unsafe { KE::fold_avx2(f1, f2) },
+        _ => fold_blocks::<KE, 2, K>(f1, f2),
+    }
+}
+
+/// Selects the kernel for the length of the vectors and the lane width of the CPU and folds the vectors
+///
+#[inline(always)]
+fn fold<KE: Kernel<K>, const K: usize>(f1: &Feature, f2: &Feature) -> [f32; K] {
+    let len = f1.len().min(f2.len());
+    let (f1, f2) = (&f1[..len], &f2[..len]);
+    if len >= WIDE_KERNEL_MIN_BLOCKS {
+        fold_wide::<KE, K>(LaneWidth::detect(), f1, f2)
+    } else {
+        fold_blocks::<KE, 1, K>(f1, f2)
+    }
+}
 
 /// Euclidian distance between two feature vectors
 ///
@@ -7,14 +254,7 @@ use std::ops::{Mul, MulAssign, SubAssign};
 /// shorter one when the distance is calculated
 ///
 pub fn euclidean(f1: &Feature, f2: &Feature) -> f32 {
-    let mut acc = 0.0;
-    for i in 0..f1.len().min(f2.len()) {
-        let mut block1 = f1[i];
-        let block2 = &f2[i];
-        block1.sub_assign(block2);
-        block1.mul_assign(block1);
-        acc += block1.reduce_add();
-    }
+    let [acc] = fold::<SquaredDiff, 1>(f1, f2);
     acc.sqrt()
 }
 
@@ -22,33 +262,86 @@ pub fn euclidean(f1: &Feature, f2: &Feature) -> f32 {
 ///
 /// When the features distances lengths don't match, the longer feature vector is truncated to
 /// shorter one when the distance is calculated
-///  
+///
 pub fn cosine(f1: &Feature, f2: &Feature) -> f32 {
-    let mut divided = 0.0;
-    let len = f1.len().min(f2.len());
-    for i in 0..len {
-        let mut block1 = f1[i];
-        let block2 = &f2[i];
-        block1.mul_assign(block2);
-        divided += block1.reduce_add();
-    }
+    let [divided, f1_divisor, f2_divisor] = fold::<DotNorms, 3>(f1, f2);
+    divided / (f1_divisor * f2_divisor).sqrt()
+}
 
-    let f1_divisor = f1
-        .iter()
-        .take(len)
-        .fold(0.0_f32, |acc, a| acc + a.mul(a).reduce_add());
+/// Angular distance between two vectors
+///
+/// The distance is the angle between the vectors divided by `PI`, so it is within `[0.0, 1.0]`. Unlike the cosine
+/// distance, it is a proper metric distance that satisfies the triangle inequality.
+///
+/// When the features distances lengths don't match, the longer feature vector is truncated to
+/// shorter one when the distance is calculated
+///
+pub fn angular(f1: &Feature, f2: &Feature) -> f32 {
+    cosine(f1, f2).clamp(-1.0, 1.0).acos() / std::f32::consts::PI
+}
 
-    let f2_divisor = f2
-        .iter()
-        .take(len)
-        .fold(0.0_f32, |acc, a| acc + a.mul(a).reduce_add());
+/// Inner (dot) product of two vectors
+///
+/// When the features distances lengths don't match, the longer feature vector is truncated to
+/// shorter one when the distance is calculated
+///
+pub fn dot(f1: &Feature, f2: &Feature) -> f32 {
+    let [acc] = fold::<Dot, 1>(f1, f2);
+    acc
+}
 
-    divided / (f1_divisor * f2_divisor).sqrt()
+/// Scales the vector to the unit L2 norm in place
+///
+/// Zero vectors are left unchanged
+///
+pub fn normalize(f: &mut Feature) {
+    let norm = dot(f, f).sqrt();
+    if norm > 0.0 {
+        let scale = f32x8::splat(1.0 / norm);
+        f.iter_mut().for_each(|block| block.mul_assign(scale));
+    }
+}
+
+/// Minkowski (Lp) distance between two feature vectors
+///
+/// `p = 1.0` gives the manhattan distance, `p = 2.0` gives the euclidean distance and `p = f32::INFINITY`
+/// gives the chebyshev distance.
+///
+/// When the features distances lengths don't match, the longer feature vector is truncated to
+/// shorter one when the distance is calculated
+///
+pub fn minkowski(f1: &Feature, f2: &Feature, p: f32) -> f32 {
+    let diffs = f1.iter().zip(f2.iter()).map(|(a, b)| (*a - *b).abs());
+    if p == f32::INFINITY {
+        diffs.fold(0.0_f32, |acc, d| {
+            d.as_array_ref().iter().fold(acc, |acc, d| acc.max(*d))
+        })
+    } else if p == 1.0 {
+        diffs.fold(0.0_f32, |acc, d| acc + d.reduce_add())
+    } else {
+        diffs
+            .fold(0.0_f32, |acc, d| acc + d.powf(p).reduce_add())
+            .powf(1.0 / p)
+    }
+}
+
+/// Hamming distance between two binary vectors packed into 64-bit words
+///
+/// When the vectors lengths don't match, the longer vector is truncated to
+/// shorter one when the distance is calculated
+///
+pub fn hamming(w1: &[u64], w2: &[u64]) -> u32 {
+    w1.iter()
+        .zip(w2.iter())
+        .fold(0, |acc, (a, b)| acc + (a ^ b).count_ones())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::distance::{cosine, euclidean};
+    use crate::distance::{
+        angular, cosine, dot, euclidean, fold_wide, hamming, minkowski, normalize, Dot, DotNorms,
+        LaneWidth, SquaredDiff, WIDE_KERNEL_MIN_BLOCKS,
+    };
     use crate::track::utils::FromVec;
     use crate::track::Feature;
     use crate::EPS;
@@ -64,6 +357,19 @@ mod tests {
         assert!((d - 2.0f32.sqrt()).abs() < EPS);
     }
 
+    #[test]
+    fn angular_distances() {
+        let v1 = Feature::from_vec(vec![1f32, 0.0]);
+        let v2 = Feature::from_vec(vec![1f32, 1.0]);
+        let v3 = Feature::from_vec(vec![0f32, 2.0]);
+        let v4 = Feature::from_vec(vec![-3f32, 0.0]);
+        assert!(angular(&v1, &v1).abs() < 1e-3);
+        assert!((angular(&v1, &v2) - 0.25).abs() < EPS);
+        assert!((angular(&v1, &v3) - 0.5).abs() < EPS);
+        assert!((angular(&v1, &v4) - 1.0).abs() < EPS);
+        assert!(angular(&v1, &v3) <= angular(&v1, &v2) + angular(&v2, &v3) + EPS);
+    }
+
     #[test]
     fn cosine_distances() {
         let v1 = dbg!(Feature::from_vec(vec![1f32, 0.0, 0.0]));
@@ -76,4 +382,66 @@ mod tests {
         let d = cosine(&v1, &v2);
         assert!(d.abs() < EPS);
     }
+
+    #[test]
+    fn dot_and_normalize() {
+        let mut v1 = Feature::from_vec(vec![3f32, 0.0, 4.0]);
+        let v2 = Feature::from_vec(vec![1f32, 2.0, 1.0]);
+        assert!((dot(&v1, &v2) - 7.0).abs() < EPS);
+
+        normalize(&mut v1);
+        assert!((dot(&v1, &v1) - 1.0).abs() < EPS);
+        assert!((dot(&v1, &v2) - 1.4).abs() < EPS);
+
+        let mut zero = Feature::from_vec(vec![0f32; 3]);
+        normalize(&mut zero);
+        assert_eq!(dot(&zero, &zero), 0.0);
+    }
+
+    #[test]
+    fn hamming_distances() {
+        assert_eq!(hamming(&[0b1011, u64::MAX], &[0b0001, 0]), 66);
+        assert_eq!(hamming(&[0b1011], &[0b1011, u64::MAX]), 0);
+    }
+
+    #[test]
+    fn minkowski_distances() {
+        let v1 = Feature::from_vec(vec![1f32, 2.0, 3.0]);
+        let v2 = Feature::from_vec(vec![4f32, 0.0, 3.0]);
+        assert!((minkowski(&v1, &v2, 1.0) - 5.0).abs() < EPS);
+        assert!((minkowski(&v1, &v2, 2.0) - euclidean(&v1, &v2)).abs() < EPS);
+        assert!((minkowski(&v1, &v2, 3.0) - 35.0f32.powf(1.0 / 3.0)).abs() < EPS);
+        assert!((minkowski(&v1, &v2, f32::INFINITY) - 3.0).abs() < EPS);
+    }
+
+    #[test]
+    fn wide_kernels() {
+        let len = WIDE_KERNEL_MIN_BLOCKS * 8 * 3 + 5;
+        let v1 = (0..len).map(|i| (i % 7) as f32 - 3.0).collect::<Vec<_>>();
+        let v2 = (0..len).map(|i| (i % 5) as f32 * 0.5).collect::<Vec<_>>();
+
+        let dot_expected = v1.iter().zip(&v2).map(|(a, b)| a * b).sum::<f32>();
+        let sq_expected = v1
+            .iter()
+            .zip(&v2)
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f32>();
+        let norm = |v: &[f32]| v.iter().map(|a| a * a).sum::<f32>().sqrt();
+
+        let (f1, f2) = (Feature::from_vec(v1.clone()), Feature::from_vec(v2.clone()));
+        assert!((dot(&f1, &f2) - dot_expected).abs() < EPS * dot_expected.abs());
+        assert!((euclidean(&f1, &f2) - sq_expected.sqrt()).abs() < EPS * sq_expected);
+        assert!((cosine(&f1, &f2) - dot_expected / (norm(&v1) * norm(&v2))).abs() < EPS);
+
+        // the kernels of every lane width agree, the widths the CPU doesn't support fall back to the portable ones
+        for width in [LaneWidth::X4, LaneWidth::X8] {
+            let [dot] = fold_wide::<Dot, 1>(width, &f1, &f2);
+            let [sq] = fold_wide::<SquaredDiff, 1>(width, &f1, &f2);
+            let [ab, aa, bb] = fold_wide::<DotNorms, 3>(width, &f1, &f2);
+            assert!((dot - dot_expected).abs() < EPS * dot_expected.abs());
+            assert!((sq - sq_expected).abs() < EPS * sq_expected);
+            assert!((ab - dot_expected).abs() < EPS * dot_expected.abs());
+            assert!((aa.sqrt() * bb.sqrt() - norm(&v1) * norm(&v2)).abs() < EPS * aa);
+        }
+    }
 }
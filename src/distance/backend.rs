@@ -0,0 +1,123 @@
+use crate::distance::matrix::{pairwise, BatchDistance, GEMM_MIN_CANDIDATES};
+use crate::track::Feature;
+use anyhow::Result;
+use nalgebra::DMatrix;
+
+/// Backend that calculates the brute-force pairwise distances of the batched path.
+///
+/// The store uses the backend for the metrics that define the
+/// [batch distance](crate::track::ObservationMetric::batch_distance) when the store shard holds at least
+/// [min_candidates](DistanceBackend::min_candidates) candidate tracks. The default backend is
+/// [CpuBackend](CpuBackend), the offloading backends (e.g. `WgpuBackend` of the `gpu` feature) implement the trait
+/// and are set with [TrackStore::set_distance_backend](crate::store::TrackStore::set_distance_backend).
+///
+pub trait DistanceBackend: Send + Sync {
+    /// Calculates the distances for all (query, candidate) pairs
+    ///
+    /// # Returns
+    /// The matrix with the row for every query and the column for every candidate. When the error is returned,
+    /// the distances are calculated with [CpuBackend](CpuBackend).
+    ///
+    fn pairwise(
+        &self,
+        distance: BatchDistance,
+        queries: &[&Feature],
+        candidates: &[&Feature],
+    ) -> Result<DMatrix<f32>>;
+
+    /// Minimal amount of candidate tracks starting from which the backend is used, the transfer to the device
+    /// doesn't pay off for small batches
+    ///
+    fn min_candidates(&self) -> usize {
+        GEMM_MIN_CANDIDATES
+    }
+}
+
+/// Default backend that calculates the distances on CPU with [pairwise](crate::distance::matrix::pairwise)
+///
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CpuBackend;
+
+impl DistanceBackend for CpuBackend {
+    fn pairwise(
+        &self,
+        distance: BatchDistance,
+        queries: &[&Feature],
+        candidates: &[&Feature],
+    ) -> Result<DMatrix<f32>> {
+        Ok(pairwise(distance, queries, candidates))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::distance::backend::{CpuBackend, DistanceBackend};
+    use crate::distance::matrix::BatchDistance;
+    use crate::examples::UnboundAttrs;
+    use crate::metrics::minkowski::MinkowskiMetric;
+    use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+    use anyhow::{anyhow, Result};
+    use nalgebra::DMatrix;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct CountingBackend {
+        calls: Arc<AtomicUsize>,
+        fail: bool,
+    }
+
+    impl DistanceBackend for CountingBackend {
+        fn pairwise(
+            &self,
+            distance: BatchDistance,
+            queries: &[&Feature],
+            candidates: &[&Feature],
+        ) -> Result<DMatrix<f32>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.fail {
+                return Err(anyhow!("device lost"));
+            }
+            CpuBackend.pairwise(distance, queries, candidates)
+        }
+
+        fn min_candidates(&self) -> usize {
+            1
+        }
+    }
+
+    #[test]
+    fn selectable_backend() -> Result<()> {
+        let mut store = TrackStoreBuilder::new(1)
+            .default_attributes(UnboundAttrs)
+            .metric(MinkowskiMetric::new(2.0))
+            .notifier(NoopNotifier)
+            .build();
+        store.add(1, 0, Some(0.0), Some(Feature::from_vec(vec![3.0])), None)?;
+
+        for fail in [false, true] {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let q = store
+                .new_track(10)
+                .observation(
+                    ObservationBuilder::new(0)
+                        .observation_attributes(0.0)
+                        .observation(Feature::from_vec(vec![0.0]))
+                        .build(),
+                )
+                .build()?;
+            store.set_distance_backend(CountingBackend {
+                calls: calls.clone(),
+                fail,
+            });
+            let (dists, _) = store.foreign_track_distances(vec![q], 0, false);
+            let dists = dists.all();
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+            // failed backend falls back to CPU
+            assert_eq!(dists[0].feature_distance, Some(3.0));
+        }
+        Ok(())
+    }
+}
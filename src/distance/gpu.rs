@@ -0,0 +1,349 @@
+use crate::distance::backend::DistanceBackend;
+use crate::distance::matrix::BatchDistance;
+use crate::track::utils::FromVec;
+use crate::track::Feature;
+use crate::Errors;
+use anyhow::Result;
+use nalgebra::DMatrix;
+use std::borrow::Cow;
+use std::sync::mpsc;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BufferDescriptor, BufferUsages, CommandEncoderDescriptor,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device, DeviceDescriptor,
+    Instance, Maintain, MapMode, Queue, RequestAdapterOptions, ShaderModuleDescriptor,
+    ShaderSource,
+};
+
+/// Minimal amount of candidate tracks starting from which [WgpuBackend](WgpuBackend) is used by default
+///
+pub const GPU_MIN_CANDIDATES: usize = 4096;
+
+const WORKGROUP_SIZE: u32 = 8;
+
+const SHADER: &str = r#"
+struct Params {
+    queries: u32,
+    candidates: u32,
+    dimensions: u32,
+    distance: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> queries: array<f32>;
+@group(0) @binding(2) var<storage, read> candidates: array<f32>;
+@group(0) @binding(3) var<storage, read_write> distances: array<f32>;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let q = id.x;
+    let c = id.y;
+    if (q >= params.queries || c >= params.candidates) {
+        return;
+    }
+    var product = 0.0;
+    var query_norm = 0.0;
+    var candidate_norm = 0.0;
+    var squared = 0.0;
+    for (var i = 0u; i < params.dimensions; i = i + 1u) {
+        let a = queries[q * params.dimensions + i];
+        let b = candidates[c * params.dimensions + i];
+        product = product + a * b;
+        query_norm = query_norm + a * a;
+        candidate_norm = candidate_norm + b * b;
+        squared = squared + (a - b) * (a - b);
+    }
+    var distance: f32;
+    switch params.distance {
+        case 0u: { distance = sqrt(squared); }
+        case 1u: { distance = product; }
+        case 2u: { distance = 1.0 - product; }
+        default: { distance = 1.0 - product / sqrt(query_norm * candidate_norm); }
+    }
+    distances[q * params.candidates + c] = distance;
+}
+"#;
+
+/// Backend that calculates the pairwise distances with the compute shader on the GPU available to
+/// [wgpu](https://wgpu.rs) (Vulkan, Metal, DX12)
+///
+/// The features are uploaded to the device for every batch, so the backend pays off for the large galleries,
+/// it is used from [GPU_MIN_CANDIDATES](GPU_MIN_CANDIDATES) candidates by default. The candidates are split into
+/// the chunks which fit the limits of the device. The features of the different lengths are not supported by the
+/// backend, the distances for such batches are calculated on CPU by the store.
+///
+/// ```no_run
+/// use similari::distance::gpu::WgpuBackend;
+/// use similari::examples::UnboundAttrs;
+/// use similari::metrics::minkowski::MinkowskiMetric;
+/// use similari::prelude::{NoopNotifier, TrackStoreBuilder};
+///
+/// let store = TrackStoreBuilder::new(4)
+///     .default_attributes(UnboundAttrs)
+///     .metric(MinkowskiMetric::new(2.0))
+///     .notifier(NoopNotifier)
+///     .distance_backend(WgpuBackend::new().unwrap())
+///     .build();
+/// ```
+///
+pub struct WgpuBackend {
+    device: Device,
+    queue: Queue,
+    pipeline: ComputePipeline,
+    min_candidates: usize,
+}
+
+impl WgpuBackend {
+    /// Acquires the default GPU adapter and compiles the shader
+    ///
+    /// # Returns
+    /// * `Ok(backend)` - the backend
+    /// * `Err(Errors::DeviceUnavailable(reason))` - when there is no adapter or the device cannot be created
+    ///
+    pub fn new() -> Result<Self> {
+        let instance = Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .ok_or_else(|| Errors::DeviceUnavailable("no GPU adapter found".to_string()))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &DeviceDescriptor {
+                label: Some("similari"),
+                required_features: wgpu::Features::empty(),
+                required_limits: adapter.limits(),
+            },
+            None,
+        ))
+        .map_err(|e| Errors::DeviceUnavailable(e.to_string()))?;
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("pairwise distances"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("pairwise distances"),
+            layout: None,
+            module: &module,
+            entry_point: "main",
+            compilation_options: Default::default(),
+        });
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            min_candidates: GPU_MIN_CANDIDATES,
+        })
+    }
+
+    /// Sets the minimal amount of candidate tracks starting from which the backend is used
+    ///
+    pub fn with_min_candidates(mut self, min_candidates: usize) -> Self {
+        self.min_candidates = min_candidates;
+        self
+    }
+
+    fn pack(features: &[&Feature], dimensions: usize) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(features.len() * dimensions * 4);
+        for f in features {
+            if f.len() * 8 != dimensions {
+                return Err(Errors::DimensionsMismatch(dimensions, f.len() * 8).into());
+            }
+            data.extend(Vec::<f32>::from_vec(f).iter().flat_map(|v| v.to_ne_bytes()));
+        }
+        Ok(data)
+    }
+
+    /// The number of the candidates of the chunk calculated with the single dispatch
+    ///
+    fn chunk(&self, queries: usize, dimensions: usize) -> usize {
+        let limits = self.device.limits();
+        let binding = limits
+            .max_storage_buffer_binding_size
+            .min(limits.max_buffer_size.min(u32::MAX as u64) as u32) as usize;
+        let groups = limits.max_compute_workgroups_per_dimension as usize;
+        (groups * WORKGROUP_SIZE as usize)
+            .min(binding / (dimensions * 4).max(1))
+            .min(binding / (queries * 4).max(1))
+            .max(1)
+    }
+
+    fn dispatch(
+        &self,
+        distance: BatchDistance,
+        queries: &[u8],
+        candidates: &[u8],
+        shape: (usize, usize, usize),
+    ) -> Result<Vec<f32>> {
+        let (rows, cols, dimensions) = shape;
+        let distance = match distance {
+            BatchDistance::Euclidean => 0u32,
+            BatchDistance::InnerProduct => 1,
+            BatchDistance::InnerProductDistance => 2,
+            BatchDistance::CosineDistance => 3,
+        };
+        let params = [rows as u32, cols as u32, dimensions as u32, distance]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect::<Vec<_>>();
+
+        let init = |contents: &[u8], usage| {
+            self.device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents,
+                usage,
+            })
+        };
+        let params = init(&params, BufferUsages::UNIFORM);
+        let queries = init(queries, BufferUsages::STORAGE);
+        let candidates = init(candidates, BufferUsages::STORAGE);
+        let size = (rows * cols * 4) as u64;
+        let output = self.device.create_buffer(&BufferDescriptor {
+            label: None,
+            size,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&BufferDescriptor {
+            label: None,
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[&params, &queries, &candidates, &output]
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer)| BindGroupEntry {
+                    binding: binding as u32,
+                    resource: buffer.as_entire_binding(),
+                })
+                .collect::<Vec<_>>(),
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            let groups = |n: usize| ((n as u32) + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+            pass.dispatch_workgroups(groups(rows), groups(cols), 1);
+        }
+        encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(MapMode::Read, move |r| {
+            let _ = sender.send(r);
+        });
+        self.device.poll(Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|e| Errors::DeviceUnavailable(e.to_string()))?
+            .map_err(|e| Errors::DeviceUnavailable(e.to_string()))?;
+        let distances = slice
+            .get_mapped_range()
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        staging.unmap();
+        Ok(distances)
+    }
+}
+
+impl DistanceBackend for WgpuBackend {
+    fn pairwise(
+        &self,
+        distance: BatchDistance,
+        queries: &[&Feature],
+        candidates: &[&Feature],
+    ) -> Result<DMatrix<f32>> {
+        let mut m = DMatrix::zeros(queries.len(), candidates.len());
+        let dimensions = match queries.first() {
+            Some(f) if !candidates.is_empty() => f.len() * 8,
+            _ => return Ok(m),
+        };
+        if dimensions == 0 {
+            return Err(Errors::DimensionsMismatch(8, 0).into());
+        }
+        let packed_queries = Self::pack(queries, dimensions)?;
+        let chunk = self.chunk(queries.len(), dimensions);
+        for (n, candidates) in candidates.chunks(chunk).enumerate() {
+            let distances = self.dispatch(
+                distance,
+                &packed_queries,
+                &Self::pack(candidates, dimensions)?,
+                (queries.len(), candidates.len(), dimensions),
+            )?;
+            m.columns_mut(n * chunk, candidates.len())
+                .copy_from(&DMatrix::from_row_slice(
+                    queries.len(),
+                    candidates.len(),
+                    &distances,
+                ));
+        }
+        Ok(m)
+    }
+
+    fn min_candidates(&self) -> usize {
+        self.min_candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::distance::backend::{CpuBackend, DistanceBackend};
+    use crate::distance::gpu::WgpuBackend;
+    use crate::distance::matrix::BatchDistance;
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+
+    #[test]
+    fn same_as_cpu() {
+        // the test is skipped on the machines without GPU
+        let backend = match WgpuBackend::new() {
+            Ok(backend) => backend,
+            Err(_) => return,
+        };
+        let features = (0..100)
+            .map(|i| {
+                Feature::from_vec(
+                    (0..20)
+                        .map(|j| ((i * 7 + j * 3) % 11) as f32 / 11.0 - 0.3)
+                        .collect::<Vec<f32>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let candidates = features.iter().collect::<Vec<_>>();
+        let queries = candidates[..5].to_vec();
+        for distance in [
+            BatchDistance::Euclidean,
+            BatchDistance::InnerProduct,
+            BatchDistance::InnerProductDistance,
+            BatchDistance::CosineDistance,
+        ] {
+            let gpu = backend.pairwise(distance, &queries, &candidates).unwrap();
+            let cpu = CpuBackend
+                .pairwise(distance, &queries, &candidates)
+                .unwrap();
+            assert_eq!(gpu.shape(), cpu.shape());
+            assert!(gpu
+                .iter()
+                .zip(cpu.iter())
+                .all(|(g, c)| (g - c).abs() < 1e-3));
+        }
+
+        let short = Feature::from_vec(vec![1.0; 8]);
+        assert!(backend
+            .pairwise(BatchDistance::Euclidean, &queries, &[&short])
+            .is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::distance::{cosine, dot, euclidean};
+use crate::track::utils::FromVec;
+use crate::track::Feature;
+use crate::Errors;
+use anyhow::Result;
+use nalgebra::DMatrix;
+
+/// Minimal amount of candidates starting from which the pairwise distances are calculated with the single
+/// matrix multiplication (gemm) instead of the pair-by-pair kernels
+///
+pub const GEMM_MIN_CANDIDATES: usize = 32;
+
+/// Feature distance that can be calculated for the batch of pairs with the matrix multiplication
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchDistance {
+    /// [euclidean](crate::distance::euclidean) distance
+    Euclidean,
+    /// [dot](crate::distance::dot) product
+    InnerProduct,
+    /// `1 - dot`, the cosine distance of the normalized features
+    InnerProductDistance,
+    /// `1 - cosine`
+    CosineDistance,
+}
+
+impl BatchDistance {
+    /// Calculates the distance for the single pair of features
+    ///
+    pub fn distance(&self, f1: &Feature, f2: &Feature) -> f32 {
+        match self {
+            BatchDistance::Euclidean => euclidean(f1, f2),
+            BatchDistance::InnerProduct => dot(f1, f2),
+            BatchDistance::InnerProductDistance => 1.0 - dot(f1, f2),
+            BatchDistance::CosineDistance => 1.0 - cosine(f1, f2),
+        }
+    }
+}
+
+/// Calculates the distances for all (query, candidate) pairs
+///
+/// The matrix multiplication is used when there are at least [GEMM_MIN_CANDIDATES](GEMM_MIN_CANDIDATES)
+/// candidates and all the features have the same length, otherwise the pairs are calculated one by one.
+///
+/// # Returns
+/// The matrix with the row for every query and the column for every candidate
+///
+pub fn pairwise(
+    distance: BatchDistance,
+    queries: &[&Feature],
+    candidates: &[&Feature],
+) -> DMatrix<f32> {
+    if candidates.len() >= GEMM_MIN_CANDIDATES {
+        if let Ok(m) = pairwise_gemm(distance, queries, candidates) {
+            return m;
+        }
+    }
+    DMatrix::from_fn(queries.len(), candidates.len(), |i, j| {
+        distance.distance(queries[i], candidates[j])
+    })
+}
+
+/// Calculates the distances for all (query, candidate) pairs with the single matrix multiplication
+///
+/// The euclidean distances are calculated as `sqrt(|q|^2 + |c|^2 - 2 q.c)`, so the distances of very close
+/// features are less precise than the ones calculated with [euclidean](crate::distance::euclidean).
+///
+/// # Returns
+/// * `Ok(m)` - the matrix with the row for every query and the column for every candidate
+/// * `Err(Errors::DimensionsMismatch(expected, actual))` - when the features lengths differ
+///
+pub fn pairwise_gemm(
+    distance: BatchDistance,
+    queries: &[&Feature],
+    candidates: &[&Feature],
+) -> Result<DMatrix<f32>> {
+    let blocks = queries
+        .first()
+        .or_else(|| candidates.first())
+        .map(|f| f.len())
+        .unwrap_or(0);
+
+    let pack = |features: &[&Feature]| -> Result<DMatrix<f32>> {
+        let mut data = Vec::with_capacity(features.len() * blocks * 8);
+        for f in features {
+            if f.len() != blocks {
+                return Err(Errors::DimensionsMismatch(blocks * 8, f.len() * 8).into());
+            }
+            data.extend(Vec::<f32>::from_vec(f));
+        }
+        Ok(DMatrix::from_row_slice(features.len(), blocks * 8, &data))
+    };
+
+    let (q, c) = (pack(queries)?, pack(candidates)?);
+    let products = &q * c.transpose();
+
+    let squared_norms =
+        |features: &[&Feature]| features.iter().map(|f| dot(f, f)).collect::<Vec<_>>();
+    let (rows, cols) = products.shape();
+    Ok(match distance {
+        BatchDistance::InnerProduct => products,
+        BatchDistance::InnerProductDistance => products.map(|p| 1.0 - p),
+        BatchDistance::Euclidean => {
+            let (qn, cn) = (squared_norms(queries), squared_norms(candidates));
+            DMatrix::from_fn(rows, cols, |i, j| {
+                (qn[i] + cn[j] - 2.0 * products[(i, j)]).max(0.0).sqrt()
+            })
+        }
+        BatchDistance::CosineDistance => {
+            let (qn, cn) = (squared_norms(queries), squared_norms(candidates));
+            DMatrix::from_fn(rows, cols, |i, j| {
+                1.0 - products[(i, j)] / (qn[i] * cn[j]).sqrt()
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::distance::matrix::{pairwise, pairwise_gemm, BatchDistance, GEMM_MIN_CANDIDATES};
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+
+    #[test]
+    fn gemm_same_as_pairs() {
+        let features = (0..GEMM_MIN_CANDIDATES + 3)
+            .map(|i| {
+                Feature::from_vec(
+                    (0..20)
+                        .map(|j| ((i * 7 + j * 3) % 11) as f32 / 11.0 - 0.3)
+                        .collect::<Vec<f32>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let candidates = features.iter().collect::<Vec<_>>();
+        let queries = candidates[..4].to_vec();
+
+        for distance in [
+            BatchDistance::Euclidean,
+            BatchDistance::InnerProduct,
+            BatchDistance::InnerProductDistance,
+            BatchDistance::CosineDistance,
+        ] {
+            let m = pairwise_gemm(distance, &queries, &candidates).unwrap();
+            assert_eq!(m.shape(), (4, candidates.len()));
+            for (i, q) in queries.iter().enumerate() {
+                for (j, c) in candidates.iter().enumerate() {
+                    // the euclidean distances of the close features lose precision because of the cancellation
+                    assert!((m[(i, j)] - distance.distance(q, c)).abs() < 1e-3);
+                }
+            }
+            assert_eq!(pairwise(distance, &queries, &candidates), m);
+        }
+
+        let short = Feature::from_vec(vec![1.0]);
+        assert!(pairwise_gemm(BatchDistance::Euclidean, &[&short], &candidates).is_err());
+        let m = pairwise(BatchDistance::Euclidean, &[&short], &candidates);
+        assert!(
+            (m[(0, 0)] - BatchDistance::Euclidean.distance(&short, candidates[0])).abs() < 1e-6
+        );
+    }
+}
@@ -4,7 +4,8 @@ use crate::distance::euclidean;
 use crate::track::utils::FromVec;
 use crate::track::{
     Feature, MetricOutput, MetricQuery, NoopLookup, Observation, ObservationAttributes,
-    ObservationMetric, ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackStatus,
+    ObservationMetric, ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackId,
+    TrackStatus,
 };
 use crate::utils::bbox::BoundingBox;
 use anyhow::Result;
@@ -22,6 +23,7 @@ pub enum AppError {
     Incompatible,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Default)]
 pub struct SimpleAttrs {
     set: bool,
@@ -83,7 +85,7 @@ impl ObservationMetric<SimpleAttrs, f32> for SimpleMetric {
     fn optimize(
         &mut self,
         _feature_class: u64,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
         _attrs: &mut SimpleAttrs,
         _features: &mut Vec<Observation<f32>>,
         _prev_length: usize,
@@ -140,7 +142,7 @@ impl ObservationMetric<UnboundAttrs, f32> for UnboundMetric {
     fn optimize(
         &mut self,
         _feature_class: u64,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
         _attrs: &mut UnboundAttrs,
         _features: &mut Vec<Observation<f32>>,
         _prev_length: usize,
@@ -1,6 +1,6 @@
 use crate::track::{
     MetricOutput, MetricQuery, NoopLookup, Observation, ObservationAttributes, ObservationMetric,
-    ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackStatus,
+    ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackId, TrackStatus,
 };
 use crate::utils::bbox::BoundingBox;
 use anyhow::Result;
@@ -67,7 +67,7 @@ impl ObservationMetric<BBoxAttributes, BoundingBox> for IOUMetric {
     fn optimize(
         &mut self,
         _feature_class: u64,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
         attrs: &mut BBoxAttributes,
         features: &mut Vec<Observation<BoundingBox>>,
         prev_length: usize,
@@ -14,6 +14,10 @@ pub mod distance;
 ///
 pub mod examples;
 
+/// Ready-to-use observation metrics that can be used with any track attributes
+///
+pub mod metrics;
+
 /// Frequently used components
 ///
 pub mod prelude;
@@ -36,6 +40,7 @@ pub mod utils;
 pub use track::store;
 pub use track::voting;
 
+use crate::track::TrackId;
 use thiserror::Error;
 
 /// Package errors
@@ -48,11 +53,11 @@ pub enum Errors {
     /// One of tracks doesn't have features for specified class
     ///
     #[error("Requested observations for class={2} are missing in track={0} or track={1} - distance cannot be calculated.")]
-    ObservationForClassNotFound(u64, u64, u64),
+    ObservationForClassNotFound(TrackId, TrackId, u64),
     /// Requested track is not found in the store
     ///
     #[error("Missing track={0}.")]
-    TrackNotFound(u64),
+    TrackNotFound(TrackId),
 
     #[error("Missing requested tracks.")]
     TracksNotFound,
@@ -60,12 +65,12 @@ pub enum Errors {
     /// The distance is calculated against self. Ignore it.
     ///
     #[error("Calculation with self id={0} not permitted")]
-    SameTrackCalculation(u64),
+    SameTrackCalculation(TrackId),
 
     /// Track ID is duplicate
     ///
     #[error("Duplicate track id={0}")]
-    DuplicateTrackId(u64),
+    DuplicateTrackId(TrackId),
 
     /// Object cannot be converted
     ///
@@ -75,6 +80,86 @@ pub enum Errors {
     /// Index is out of range
     #[error("The index is out of range")]
     OutOfRange,
+
+    /// Voting engine with the name is already registered
+    ///
+    #[error("Duplicate voting engine name={0}")]
+    DuplicateVotingEngine(String),
+
+    /// Voting engine with the name is not registered
+    ///
+    #[error("Missing voting engine name={0}.")]
+    VotingEngineNotFound(String),
+
+    /// Dimensions of the vector or matrix don't match the expected ones
+    ///
+    #[error("Dimensions mismatch: expected={0}, actual={1}")]
+    DimensionsMismatch(usize, usize),
+
+    /// The matrix cannot be inverted
+    ///
+    #[error("The matrix is singular and cannot be inverted")]
+    SingularMatrix,
+
+    /// Not enough samples to estimate the parameters
+    ///
+    #[error("Not enough samples: required={0}, actual={1}")]
+    NotEnoughSamples(usize, usize),
+
+    /// The labeled samples don't have both positive and negative samples
+    ///
+    #[error("Both positive and negative samples are required")]
+    SingleClassSamples,
+
+    /// The feature distance is NaN or infinite and the policy rejects such distances
+    ///
+    #[error("Non-finite distance between tracks from={0} and to={1}")]
+    NonFiniteDistance(TrackId, TrackId),
+
+    /// The feature doesn't have the dimensions registered for the feature class
+    ///
+    #[error("Feature of class={0} has wrong dimensions: expected={1}, actual={2}")]
+    FeatureDimensionsMismatch(u64, usize, usize),
+
+    /// The translation metric between the feature classes is not registered
+    ///
+    #[error("Missing translation from query class={0} to gallery class={1}.")]
+    TranslationNotFound(u64, u64),
+
+    /// The serialized state cannot be restored
+    ///
+    #[error("Unable to restore the state: {0}")]
+    RestoreFailed(String),
+
+    /// The file is not the snapshot or the snapshot is damaged
+    ///
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+
+    /// The snapshot is written by the newer version of the format
+    ///
+    #[error("Unsupported snapshot version={0}, the latest supported version={1}")]
+    UnsupportedSnapshotVersion(u32, u32),
+
+    /// The external id is already mapped to the other track
+    ///
+    #[error("External id={0} is already mapped to track={1}")]
+    ExternalIdCollision(String, TrackId),
+
+    /// The store is full and the capacity policy rejects the new track
+    ///
+    #[error("Track={0} is rejected, the store is full: capacity={1}")]
+    StoreFull(TrackId, usize),
+
+    /// The device of the distance backend cannot be acquired or fails
+    ///
+    #[error("Distance backend device is unavailable: {0}")]
+    DeviceUnavailable(String),
+
+    /// The file is not the feature file or the file is damaged
+    ///
+    #[error("Invalid feature file: {0}")]
+    InvalidFeatureFile(String),
 }
 
 pub const EPS: f32 = 0.00001;
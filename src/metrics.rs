@@ -0,0 +1,132 @@
+/// Cosine distance metric
+///
+pub mod cosine;
+
+/// Mahalanobis distance metric
+///
+pub mod mahalanobis;
+
+/// Hamming distance metric for binary descriptors
+///
+pub mod hamming;
+
+/// Jaccard (Tanimoto) distance metric for set-valued observations
+///
+pub mod jaccard;
+
+/// Inner product similarity metric
+///
+pub mod inner_product;
+
+/// Minkowski (Lp) distance metric
+///
+pub mod minkowski;
+
+/// int8-quantized feature vectors and quantization-aware distance metric
+///
+pub mod quantized;
+
+/// Half-precision feature vectors and the metric that keeps them per feature class
+///
+pub mod half;
+
+/// Memory-mapped feature storage and the metric that keeps the features in it per feature class
+///
+#[cfg(feature = "mmap")]
+pub mod mapped;
+
+/// Weighted combination of several metrics
+///
+pub mod combined;
+
+/// Metric that dispatches the calculations to the metrics registered per feature class
+///
+pub mod dispatch;
+
+/// Learned re-ranking of the candidates passed a cheap first-pass metric
+///
+pub mod rerank;
+
+/// Calibration of the metric distances into comparable scores
+///
+pub mod calibration;
+
+/// Memoization of the metric results
+///
+pub mod cache;
+
+/// Attribute-level gate of the metric
+///
+pub mod gate;
+
+/// Metrics with different query-side and gallery-side representations
+///
+pub mod asymmetric;
+
+/// Divergence metrics for probability distribution features
+///
+pub mod divergence;
+
+/// Earth mover's distance metric for histogram features
+///
+pub mod emd;
+
+/// Time decay of the metric distances
+///
+pub mod decay;
+
+/// Instrumentation of the metric calls
+///
+pub mod instrumented;
+
+/// Angular distance metric
+///
+pub mod angular;
+
+#[cfg(test)]
+use crate::examples::SimpleAttrs;
+#[cfg(test)]
+use crate::track::{
+    MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric,
+};
+
+/// Calculates the metric for the pair of observations of the tracks with the default attributes
+///
+#[cfg(test)]
+pub(crate) fn query_metric<M, OA>(
+    metric: &M,
+    feature_class: u64,
+    candidate: &Observation<OA>,
+    track: &Observation<OA>,
+) -> MetricOutput<OA::MetricObject>
+where
+    M: ObservationMetric<SimpleAttrs, OA>,
+    OA: ObservationAttributes,
+{
+    let attrs = SimpleAttrs::default();
+    metric.metric(&MetricQuery {
+        feature_class,
+        candidate_attrs: &attrs,
+        candidate_observation: candidate,
+        track_attrs: &attrs,
+        track_observation: track,
+    })
+}
+
+/// Calculates the feature distance of the pair of observations that the metric must produce
+///
+#[cfg(test)]
+pub(crate) fn feature_distance<M, OA>(
+    metric: &M,
+    candidate: &Observation<OA>,
+    track: &Observation<OA>,
+) -> f32
+where
+    M: ObservationMetric<SimpleAttrs, OA>,
+    OA: ObservationAttributes,
+{
+    query_metric(metric, 0, candidate, track)
+        .unwrap()
+        .1
+        .unwrap()
+}
@@ -0,0 +1,77 @@
+use crate::distance::angular;
+use crate::track::{
+    MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric, TrackId,
+};
+use anyhow::Result;
+
+/// Angular distance observation metric.
+///
+/// The feature distance is the angle between the features divided by `PI`, so it is within `[0.0, 1.0]`. Unlike
+/// the [cosine distance](crate::metrics::cosine::CosineMetric), the distance satisfies the triangle inequality, so
+/// the distances can be used with the structures that require the metric properties (e.g. vantage-point trees).
+/// The attribute metric is calculated with
+/// [ObservationAttributes::calculate_metric_object](ObservationAttributes::calculate_metric_object).
+///
+#[derive(Debug, Clone, Default)]
+pub struct AngularMetric;
+
+impl<TA, OA> ObservationMetric<TA, OA> for AngularMetric
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        Some((
+            OA::calculate_metric_object(&e1.attr().as_ref(), &e2.attr().as_ref()),
+            match (e1.feature().as_ref(), e2.feature().as_ref()) {
+                (Some(x), Some(y)) => Some(angular(x, y)),
+                _ => None,
+            },
+        ))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        _observations: &mut Vec<Observation<OA>>,
+        _prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::angular::AngularMetric;
+    use crate::metrics::feature_distance;
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, Observation};
+    use crate::EPS;
+
+    #[test]
+    fn triangle_inequality() {
+        let observations = [
+            [1.0, 0.0],
+            [0.9, 0.3],
+            [0.2, 1.0],
+            [-0.5, 0.4],
+            [-1.0, -1.0],
+        ]
+        .map(|v| Observation::<f32>::new(None, Some(Feature::from_vec(v.to_vec()))));
+
+        let distance = |o1, o2| feature_distance(&AngularMetric, o1, o2);
+
+        for a in &observations {
+            for b in &observations {
+                assert!((distance(a, b) - distance(b, a)).abs() < EPS);
+                for c in &observations {
+                    assert!(distance(a, c) <= distance(a, b) + distance(b, c) + EPS);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::track::{
+    Feature, MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, TrackId,
+};
+use anyhow::Result;
+
+/// Metric with different query-side and gallery-side observation representations.
+///
+/// E.g. the gallery keeps compressed vectors while the queries keep full-precision ones, or the query
+/// and gallery embeddings are produced with different heads. The metric is used in the track store through
+/// [AsymmetricMetricAdapter](AsymmetricMetricAdapter).
+///
+pub trait AsymmetricMetric: Send + Sync + Clone + 'static {
+    /// Query-side representation
+    type Query: Send + Sync + Clone + 'static;
+    /// Gallery-side representation
+    type Gallery: Send + Sync + Clone + 'static;
+
+    /// Calculates the distance between the query and the gallery representations
+    ///
+    fn distance(&self, query: &Self::Query, gallery: &Self::Gallery) -> Option<f32>;
+
+    /// Calculates the distance between two gallery representations, used when the gallery tracks
+    /// are compared with each other; not supported by default
+    ///
+    fn gallery_distance(&self, _left: &Self::Gallery, _right: &Self::Gallery) -> Option<f32> {
+        None
+    }
+
+    /// Encodes the float feature of the observation added without the representation into the gallery
+    /// representation; not supported by default
+    ///
+    fn encode_gallery(&self, _feature: &Feature) -> Option<Self::Gallery> {
+        None
+    }
+
+    /// Polarity of the distances
+    ///
+    fn polarity(&self) -> MetricPolarity {
+        MetricPolarity::Distance
+    }
+}
+
+/// Observation attributes that hold either query-side or gallery-side representation
+///
+#[derive(Debug, Clone)]
+pub enum AsymmetricObservation<Q, G> {
+    Query(Q),
+    Gallery(G),
+}
+
+impl<Q, G> ObservationAttributes for AsymmetricObservation<Q, G>
+where
+    Q: Send + Sync + Clone + 'static,
+    G: Send + Sync + Clone + 'static,
+{
+    type MetricObject = ();
+
+    fn calculate_metric_object(
+        _l: &Option<&Self>,
+        _r: &Option<&Self>,
+    ) -> Option<Self::MetricObject> {
+        None
+    }
+}
+
+/// Observation metric over the [AsymmetricObservation](AsymmetricObservation) attributes.
+///
+/// The distance between the query and the gallery observations is calculated with
+/// [AsymmetricMetric::distance](AsymmetricMetric::distance) regardless of the side of the track that holds them,
+/// two gallery observations are compared with [AsymmetricMetric::gallery_distance](AsymmetricMetric::gallery_distance),
+/// two query observations are not compared.
+///
+/// The observations added with the float feature and without the representation are encoded with
+/// [AsymmetricMetric::encode_gallery](AsymmetricMetric::encode_gallery), the float feature is dropped.
+///
+#[derive(Debug, Clone)]
+pub struct AsymmetricMetricAdapter<M>(pub M);
+
+type Asymmetric<M> =
+    AsymmetricObservation<<M as AsymmetricMetric>::Query, <M as AsymmetricMetric>::Gallery>;
+
+impl<TA, M> ObservationMetric<TA, Asymmetric<M>> for AsymmetricMetricAdapter<M>
+where
+    TA: Send + Sync + Clone + 'static,
+    M: AsymmetricMetric,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, Asymmetric<M>>) -> MetricOutput<()> {
+        use AsymmetricObservation::{Gallery, Query};
+        let distance = match (
+            mq.candidate_observation.attr().as_ref()?,
+            mq.track_observation.attr().as_ref()?,
+        ) {
+            (Query(q), Gallery(g)) | (Gallery(g), Query(q)) => self.0.distance(q, g),
+            (Gallery(l), Gallery(r)) => self.0.gallery_distance(l, r),
+            (Query(_), Query(_)) => return None,
+        };
+        Some((None, distance))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        observations: &mut Vec<Observation<Asymmetric<M>>>,
+        prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        for o in observations.iter_mut().skip(prev_length) {
+            if o.attr().is_some() {
+                continue;
+            }
+            let encoded = o.feature().as_ref().and_then(|f| self.0.encode_gallery(f));
+            if let Some(g) = encoded {
+                *o.attr_mut() = Some(AsymmetricObservation::Gallery(g));
+                *o.feature_mut() = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        self.0.polarity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::asymmetric::{
+        AsymmetricMetric, AsymmetricMetricAdapter, AsymmetricObservation,
+    };
+    use crate::metrics::quantized::QuantizedFeature;
+    use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+    use crate::track::utils::FromVec;
+    use crate::track::{
+        Feature, NoopLookup, ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackStatus,
+    };
+    use crate::EPS;
+    use anyhow::Result;
+
+    type Observation = AsymmetricObservation<Vec<f32>, QuantizedFeature>;
+
+    #[derive(Debug, Clone, Default)]
+    struct Attrs;
+
+    #[derive(Clone)]
+    struct AttrsUpdate;
+
+    impl TrackAttributesUpdate<Attrs> for AttrsUpdate {
+        fn apply(&self, _attrs: &mut Attrs) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl TrackAttributes<Attrs, Observation> for Attrs {
+        type Update = AttrsUpdate;
+        type Lookup = NoopLookup<Attrs, Observation>;
+
+        fn compatible(&self, _other: &Attrs) -> bool {
+            true
+        }
+
+        fn merge(&mut self, _other: &Attrs) -> Result<()> {
+            Ok(())
+        }
+
+        fn baked(&self, _observations: &ObservationsDb<Observation>) -> Result<TrackStatus> {
+            Ok(TrackStatus::Ready)
+        }
+    }
+
+    /// full-precision queries against the int8-quantized gallery
+    #[derive(Clone)]
+    struct QuantizedGallery;
+
+    impl AsymmetricMetric for QuantizedGallery {
+        type Query = Vec<f32>;
+        type Gallery = QuantizedFeature;
+
+        fn distance(&self, query: &Vec<f32>, gallery: &QuantizedFeature) -> Option<f32> {
+            let gallery = gallery.dequantize();
+            Some(
+                query
+                    .iter()
+                    .zip(gallery)
+                    .map(|(q, g)| (q - g).powi(2))
+                    .sum::<f32>()
+                    .sqrt(),
+            )
+        }
+
+        fn encode_gallery(&self, feature: &Feature) -> Option<QuantizedFeature> {
+            Some(QuantizedFeature::quantize(&Vec::from_vec(feature)))
+        }
+    }
+
+    #[test]
+    fn query_against_encoded_gallery() {
+        let mut store = TrackStoreBuilder::new(1)
+            .default_attributes(Attrs)
+            .metric(AsymmetricMetricAdapter(QuantizedGallery))
+            .notifier(NoopNotifier)
+            .build();
+
+        store
+            .add(1, 0, None, Some(Feature::from_vec(vec![0.0, 1.0])), None)
+            .unwrap();
+        store
+            .add(2, 0, None, Some(Feature::from_vec(vec![1.0, 1.0])), None)
+            .unwrap();
+
+        let query = store
+            .new_track(10)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation_attributes(AsymmetricObservation::Query(vec![1.0, 0.0]))
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        let (dists, errs) = store.foreign_track_distances(vec![query], 0, false);
+        assert!(errs.all().is_empty());
+        let mut dists = dists
+            .all()
+            .into_iter()
+            .map(|d| (d.to, d.feature_distance.unwrap()))
+            .collect::<Vec<_>>();
+        dists.sort_by_key(|(to, _)| *to);
+
+        assert_eq!(dists.len(), 2);
+        assert!((dists[0].1 - 2.0_f32.sqrt()).abs() < 0.01);
+        assert!((dists[1].1 - 1.0).abs() < 0.01 + EPS);
+
+        // the gallery tracks are not compared with each other by default
+        let (dists, _) = store.owned_track_distances(&[1], 0, false);
+        assert!(dists.all().iter().all(|d| d.feature_distance.is_none()));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::track::{
+    Feature, MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, ObservationMetricOk, TrackId,
+};
+use crate::utils::lru::LruCache;
+use anyhow::Result;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+/// Hash of the observation feature vector bits
+///
+pub fn feature_hash(feature: &Feature) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for block in feature {
+        for v in block.as_array_ref() {
+            v.to_bits().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+type CacheKey = (u64, u64, u64);
+
+/// Memoized feature distance together with the features it is calculated for
+///
+struct CacheEntry {
+    candidate: Feature,
+    track: Feature,
+    feature_distance: Option<f32>,
+}
+
+impl CacheEntry {
+    fn matches(&self, candidate: &Feature, track: &Feature) -> bool {
+        same_bits(&self.candidate, candidate) && same_bits(&self.track, track)
+    }
+}
+
+fn same_bits(l: &Feature, r: &Feature) -> bool {
+    l.len() == r.len()
+        && l.iter().zip(r).all(|(l, r)| {
+            l.as_array_ref()
+                .iter()
+                .zip(r.as_array_ref())
+                .all(|(l, r)| l.to_bits() == r.to_bits())
+        })
+}
+
+type CacheShard = Mutex<LruCache<CacheKey, CacheEntry>>;
+
+/// Metric that memoizes the feature distances of the wrapped metric in the LRU cache.
+///
+/// The feature distances are keyed by the hashes of the candidate and the track observation features and the
+/// feature class, so the wrapped metric must calculate the feature distance from the features only. The metrics
+/// that also depend on the track attributes or the observation stamps (e.g. [TimeDecayedMetric](crate::metrics::decay::TimeDecayedMetric))
+/// must wrap the cached metric, not the other way around. Only the feature distance is memoized, the attribute
+/// metric is always recalculated with [calculate_metric_object](ObservationAttributes::calculate_metric_object), like the
+/// [batch](ObservationMetric::batch_distance) path does. The observations without features aren't cached.
+///
+/// The cached features are compared with the queried ones on a hit, so the hash collisions never return a foreign
+/// distance. The clones of the metric share the cache, so all the tracks of the store use the same cache. The cache
+/// is split into the shards selected by the key, every shard has its own lock, so the store shards calculating
+/// the distances concurrently rarely wait for each other. The memoization is beneficial for the expensive metrics
+/// (e.g. learned or high-dimensional), because the hashes are calculated for every pair.
+///
+#[derive(Clone)]
+pub struct CachedMetric<M, OA>
+where
+    OA: ObservationAttributes,
+{
+    metric: M,
+    cache: Arc<[CacheShard]>,
+    _phantom: PhantomData<OA>,
+}
+
+impl<M, OA> CachedMetric<M, OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs the metric with the cache split into a shard per CPU
+    ///
+    /// # Arguments
+    /// * `metric` - the metric which results are memoized
+    /// * `capacity` - max amount of memoized results
+    ///
+    pub fn new(metric: M, capacity: usize) -> Self {
+        Self::with_shards(metric, capacity, num_cpus::get())
+    }
+
+    /// Constructs the metric
+    ///
+    /// # Arguments
+    /// * `metric` - the metric which results are memoized
+    /// * `capacity` - max amount of memoized results, split evenly between the shards
+    /// * `shards` - amount of the independently locked cache shards
+    ///
+    pub fn with_shards(metric: M, capacity: usize, shards: usize) -> Self {
+        assert!(shards > 0, "At least one cache shard is required");
+        let shards = shards.min(capacity.max(1));
+        let shard_capacity = (capacity + shards - 1) / shards;
+        Self {
+            metric,
+            cache: (0..shards)
+                .map(|_| Mutex::new(LruCache::new(shard_capacity)))
+                .collect(),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn shard(&self, key: &CacheKey) -> &CacheShard {
+        let (candidate, track, feature_class) = key;
+        let index = candidate ^ track.rotate_left(21) ^ feature_class.rotate_left(42);
+        &self.cache[(index % self.cache.len() as u64) as usize]
+    }
+
+    /// Current amount of memoized feature distances
+    ///
+    pub fn cached(&self) -> usize {
+        self.cache.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    /// Drops all the memoized feature distances
+    ///
+    pub fn clear(&self) {
+        for shard in self.cache.iter() {
+            shard.lock().unwrap().clear();
+        }
+    }
+}
+
+impl<TA, OA, M> ObservationMetric<TA, OA> for CachedMetric<M, OA>
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+    M: ObservationMetric<TA, OA>,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (candidate, track) = match (
+            mq.candidate_observation.feature().as_ref(),
+            mq.track_observation.feature().as_ref(),
+        ) {
+            (Some(l), Some(r)) => (l, r),
+            _ => return self.metric.metric(mq),
+        };
+
+        let key = (
+            feature_hash(candidate),
+            feature_hash(track),
+            mq.feature_class,
+        );
+        let shard = self.shard(&key);
+        let cached = shard
+            .lock()
+            .unwrap()
+            .get(&key)
+            .filter(|e| e.matches(candidate, track))
+            .map(|e| e.feature_distance);
+        let feature_distance = match cached {
+            Some(feature_distance) => feature_distance,
+            None => {
+                let (_, feature_distance) = self.metric.metric(mq)?;
+                shard.lock().unwrap().put(
+                    key,
+                    CacheEntry {
+                        candidate: candidate.clone(),
+                        track: track.clone(),
+                        feature_distance,
+                    },
+                );
+                feature_distance
+            }
+        };
+        Some((
+            OA::calculate_metric_object(
+                &mq.candidate_observation.attr().as_ref(),
+                &mq.track_observation.attr().as_ref(),
+            ),
+            feature_distance,
+        ))
+    }
+
+    fn gate(&self, mq: &MetricQuery<'_, TA, OA>) -> bool {
+        self.metric.gate(mq)
+    }
+
+    fn optimize(
+        &mut self,
+        feature_class: u64,
+        merge_history: &[TrackId],
+        attributes: &mut TA,
+        observations: &mut Vec<Observation<OA>>,
+        prev_length: usize,
+        is_merge: bool,
+    ) -> Result<()> {
+        self.metric.optimize(
+            feature_class,
+            merge_history,
+            attributes,
+            observations,
+            prev_length,
+            is_merge,
+        )
+    }
+
+    fn postprocess_distances(
+        &self,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.metric.postprocess_distances(unfiltered)
+    }
+
+    fn postprocess_class_distances(
+        &self,
+        feature_class: u64,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.metric
+            .postprocess_class_distances(feature_class, unfiltered)
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        self.metric.polarity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::SimpleAttrs;
+    use crate::metrics::cache::{feature_hash, CacheEntry, CachedMetric};
+    use crate::metrics::query_metric;
+    use crate::track::utils::FromVec;
+    use crate::track::{
+        Feature, MetricOutput, MetricQuery, Observation, ObservationMetric, TrackId,
+    };
+    use anyhow::Result;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct CountingMetric(Arc<AtomicUsize>);
+
+    impl ObservationMetric<SimpleAttrs, f32> for CountingMetric {
+        fn metric(&self, _mq: &MetricQuery<'_, SimpleAttrs, f32>) -> MetricOutput<f32> {
+            Some((None, Some(self.0.fetch_add(1, Ordering::SeqCst) as f32)))
+        }
+
+        fn optimize(
+            &mut self,
+            _feature_class: u64,
+            _merge_history: &[TrackId],
+            _attributes: &mut SimpleAttrs,
+            _observations: &mut Vec<Observation<f32>>,
+            _prev_length: usize,
+            _is_merge: bool,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn memoized_results() {
+        let counter = CountingMetric::default();
+        let metric = CachedMetric::with_shards(counter.clone(), 2, 1);
+        let copy = metric.clone();
+
+        let observations = [
+            Observation::new(None, Some(Feature::from_vec(vec![1.0]))),
+            Observation::new(None, Some(Feature::from_vec(vec![2.0]))),
+            Observation::new(None, Some(Feature::from_vec(vec![3.0]))),
+            Observation::new(None, None),
+        ];
+        let query = |metric: &CachedMetric<CountingMetric, f32>, feature_class, r: usize| {
+            query_metric(metric, feature_class, &observations[0], &observations[r])
+                .unwrap()
+                .1
+                .unwrap()
+        };
+
+        assert_eq!(query(&metric, 0, 1), 0.0);
+        assert_eq!(query(&copy, 0, 1), 0.0);
+        assert_eq!(query(&metric, 1, 1), 1.0);
+        assert_eq!(query(&metric, 0, 2), 2.0);
+        // the least recently used result is evicted
+        assert_eq!(query(&metric, 0, 1), 3.0);
+        assert_eq!(metric.cached(), 2);
+        // not cached
+        assert_eq!(query(&metric, 0, 3), 4.0);
+        assert_eq!(query(&metric, 0, 3), 5.0);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn attribute_metric_recalculated() {
+        let counter = CountingMetric::default();
+        let metric = CachedMetric::new(counter.clone(), 2);
+
+        let candidate = Observation::new(Some(1.0), Some(Feature::from_vec(vec![1.0])));
+        let query = |track_attr: f32| {
+            let track = Observation::new(Some(track_attr), Some(Feature::from_vec(vec![2.0])));
+            query_metric(&metric, 0, &candidate, &track).unwrap()
+        };
+
+        assert_eq!(query(3.0), (Some(2.0), Some(0.0)));
+        assert_eq!(query(5.0), (Some(4.0), Some(0.0)));
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn sharded_cache() {
+        let counter = CountingMetric::default();
+        let metric = CachedMetric::with_shards(counter.clone(), 64, 4);
+        assert_eq!(metric.cache.len(), 4);
+
+        let candidate = Observation::new(None, Some(Feature::from_vec(vec![0.0])));
+        let tracks = (0..32)
+            .map(|i| Observation::new(None, Some(Feature::from_vec(vec![i as f32]))))
+            .collect::<Vec<_>>();
+        for _ in 0..2 {
+            for track in &tracks {
+                query_metric(&metric, 0, &candidate, track).unwrap();
+            }
+        }
+        assert_eq!(metric.cached(), 32);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 32);
+        assert!(metric.cache.iter().all(|s| s.lock().unwrap().len() < 32));
+
+        metric.clear();
+        assert_eq!(metric.cached(), 0);
+    }
+
+    #[test]
+    fn colliding_keys_verified() {
+        let counter = CountingMetric::default();
+        let metric = CachedMetric::with_shards(counter.clone(), 2, 1);
+
+        let candidate = Feature::from_vec(vec![1.0]);
+        let track = Feature::from_vec(vec![2.0]);
+        let key = (feature_hash(&candidate), feature_hash(&track), 0);
+        // a foreign distance memoized for the same key
+        metric.shard(&key).lock().unwrap().put(
+            key,
+            CacheEntry {
+                candidate: Feature::from_vec(vec![3.0]),
+                track: Feature::from_vec(vec![4.0]),
+                feature_distance: Some(100.0),
+            },
+        );
+
+        let candidate = Observation::new(None, Some(candidate));
+        let track = Observation::new(None, Some(track));
+        assert_eq!(
+            query_metric(&metric, 0, &candidate, &track).unwrap(),
+            (None, Some(0.0))
+        );
+        assert_eq!(
+            query_metric(&metric, 0, &candidate, &track).unwrap(),
+            (None, Some(0.0))
+        );
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    }
+}
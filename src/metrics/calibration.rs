@@ -0,0 +1,280 @@
+use crate::track::{
+    MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, TrackId,
+};
+use crate::Errors;
+use anyhow::Result;
+
+/// Fitted monotone mapping of raw distances into the probabilities that the observations are the same.
+///
+/// The mapping is non-increasing, so the larger distance never gets the larger probability. The mappings
+/// are fitted from labeled pairs `(distance, same)`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Calibration {
+    /// Platt scaling `1 / (1 + exp(a * distance + b))`
+    Platt { a: f32, b: f32 },
+    /// Isotonic regression, the probability is interpolated linearly between the fitted points
+    Isotonic {
+        /// distances of the fitted points, sorted increasingly
+        distances: Vec<f32>,
+        /// probabilities of the fitted points, non-increasing
+        probabilities: Vec<f32>,
+    },
+}
+
+fn check_samples(samples: &[(f32, bool)]) -> Result<()> {
+    let positives = samples.iter().filter(|(_, same)| *same).count();
+    if positives == 0 || positives == samples.len() {
+        return Err(Errors::SingleClassSamples.into());
+    }
+    Ok(())
+}
+
+impl Calibration {
+    /// Fits Platt scaling with the Newton method and the regularized targets
+    ///
+    /// # Arguments
+    /// * `samples` - pairs `(distance, same)`, both classes must be present
+    ///
+    pub fn fit_platt(samples: &[(f32, bool)]) -> Result<Self> {
+        check_samples(samples)?;
+        let positives = samples.iter().filter(|(_, same)| *same).count() as f64;
+        let negatives = samples.len() as f64 - positives;
+        let (hi, lo) = (
+            (positives + 1.0) / (positives + 2.0),
+            1.0 / (negatives + 2.0),
+        );
+        let data = samples
+            .iter()
+            .map(|(d, same)| (*d as f64, if *same { hi } else { lo }))
+            .collect::<Vec<_>>();
+
+        let loss = |a: f64, b: f64| {
+            data.iter()
+                .map(|(d, t)| {
+                    let f = a * d + b;
+                    if f >= 0.0 {
+                        t * f + (-f).exp().ln_1p()
+                    } else {
+                        (t - 1.0) * f + f.exp().ln_1p()
+                    }
+                })
+                .sum::<f64>()
+        };
+
+        let (mut a, mut b) = (0.0, ((negatives + 1.0) / (positives + 1.0)).ln());
+        let mut value = loss(a, b);
+        for _ in 0..100 {
+            let (mut h11, mut h22, mut h21, mut g1, mut g2) = (1e-12, 1e-12, 0.0, 0.0, 0.0);
+            for (d, t) in &data {
+                let f = a * d + b;
+                let (p, q) = if f >= 0.0 {
+                    ((-f).exp() / (1.0 + (-f).exp()), 1.0 / (1.0 + (-f).exp()))
+                } else {
+                    (1.0 / (1.0 + f.exp()), f.exp() / (1.0 + f.exp()))
+                };
+                let d2 = p * q;
+                h11 += d * d * d2;
+                h22 += d2;
+                h21 += d * d2;
+                g1 += d * (t - p);
+                g2 += t - p;
+            }
+            if g1.abs() < 1e-5 && g2.abs() < 1e-5 {
+                break;
+            }
+
+            let det = h11 * h22 - h21 * h21;
+            let (da, db) = (-(h22 * g1 - h21 * g2) / det, -(-h21 * g1 + h11 * g2) / det);
+            let gd = g1 * da + g2 * db;
+
+            let mut step = 1.0;
+            while step >= 1e-10 {
+                let (new_a, new_b) = (a + step * da, b + step * db);
+                let new_value = loss(new_a, new_b);
+                if new_value < value + 1e-4 * step * gd {
+                    (a, b, value) = (new_a, new_b, new_value);
+                    break;
+                }
+                step /= 2.0;
+            }
+            if step < 1e-10 {
+                break;
+            }
+        }
+
+        Ok(Calibration::Platt {
+            a: a as f32,
+            b: b as f32,
+        })
+    }
+
+    /// Fits the isotonic regression with the pool adjacent violators algorithm
+    ///
+    /// # Arguments
+    /// * `samples` - pairs `(distance, same)`, both classes must be present
+    ///
+    pub fn fit_isotonic(samples: &[(f32, bool)]) -> Result<Self> {
+        check_samples(samples)?;
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|l, r| l.0.total_cmp(&r.0));
+
+        // blocks of (distances sum, labels sum, count), the block means are non-increasing
+        let mut blocks: Vec<(f64, f64, f64)> = Vec::new();
+        for (d, same) in sorted {
+            let mut block = (d as f64, if same { 1.0 } else { 0.0 }, 1.0);
+            while let Some(prev) = blocks.last() {
+                if prev.1 / prev.2 > block.1 / block.2 {
+                    break;
+                }
+                block = (prev.0 + block.0, prev.1 + block.1, prev.2 + block.2);
+                blocks.pop();
+            }
+            blocks.push(block);
+        }
+
+        let (distances, probabilities) = blocks
+            .into_iter()
+            .map(|(d, p, n)| ((d / n) as f32, (p / n) as f32))
+            .unzip();
+        Ok(Calibration::Isotonic {
+            distances,
+            probabilities,
+        })
+    }
+
+    /// Maps the distance into the probability that the observations are the same
+    ///
+    pub fn probability(&self, distance: f32) -> f32 {
+        match self {
+            Calibration::Platt { a, b } => 1.0 / (1.0 + (a * distance + b).exp()),
+            Calibration::Isotonic {
+                distances,
+                probabilities,
+            } => {
+                let pos = distances.partition_point(|d| *d <= distance);
+                if pos == 0 {
+                    probabilities[0]
+                } else if pos == distances.len() {
+                    probabilities[pos - 1]
+                } else {
+                    let (d0, d1) = (distances[pos - 1], distances[pos]);
+                    let (p0, p1) = (probabilities[pos - 1], probabilities[pos]);
+                    p0 + (p1 - p0) * (distance - d0) / (d1 - d0)
+                }
+            }
+        }
+    }
+}
+
+/// Metric that calibrates the feature distances of the wrapped metric.
+///
+/// The feature distance is `1 - probability`, where the probability is calculated with the [Calibration](Calibration)
+/// from the distance of the wrapped metric, so the distances of metrics with incompatible scales become comparable
+/// and stay within `[0.0, 1.0]`. The attribute metric and the observations optimization come from the wrapped metric.
+///
+/// The values of the wrapped metric are oriented with [MetricPolarity::orient](MetricPolarity::orient) before the
+/// calibration, so for the similarity metrics the calibration is fitted on the negated similarities. The calibrated
+/// metric always produces distances.
+///
+#[derive(Debug, Clone)]
+pub struct CalibratedMetric<M> {
+    metric: M,
+    calibration: Calibration,
+}
+
+impl<M> CalibratedMetric<M> {
+    /// Constructs the metric
+    ///
+    pub fn new(metric: M, calibration: Calibration) -> Self {
+        Self {
+            metric,
+            calibration,
+        }
+    }
+}
+
+impl<TA, OA, M> ObservationMetric<TA, OA> for CalibratedMetric<M>
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+    M: ObservationMetric<TA, OA>,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (attribute_metric, distance) = self.metric.metric(mq)?;
+        let polarity = self.metric.polarity();
+        Some((
+            attribute_metric,
+            distance.map(|d| 1.0 - self.calibration.probability(polarity.orient(d))),
+        ))
+    }
+
+    fn gate(&self, mq: &MetricQuery<'_, TA, OA>) -> bool {
+        self.metric.gate(mq)
+    }
+
+    fn optimize(
+        &mut self,
+        feature_class: u64,
+        merge_history: &[TrackId],
+        attributes: &mut TA,
+        observations: &mut Vec<Observation<OA>>,
+        prev_length: usize,
+        is_merge: bool,
+    ) -> Result<()> {
+        self.metric.optimize(
+            feature_class,
+            merge_history,
+            attributes,
+            observations,
+            prev_length,
+            is_merge,
+        )
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        MetricPolarity::Distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::calibration::Calibration;
+
+    fn samples() -> Vec<(f32, bool)> {
+        (0..100)
+            .map(|i| {
+                let d = i as f32 / 50.0;
+                // same pairs are mostly close, different pairs are mostly far
+                (d, (d < 1.0) ^ (i % 10 == 0))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn platt_scaling() {
+        let c = Calibration::fit_platt(&samples()).unwrap();
+        let (close, mid, far) = (c.probability(0.1), c.probability(1.0), c.probability(1.9));
+        assert!(close > 0.8 && far < 0.2, "{close} {far}");
+        assert!(close > mid && mid > far);
+        assert!((mid - 0.5).abs() < 0.1);
+
+        assert!(Calibration::fit_platt(&[(0.1, true), (0.2, true)]).is_err());
+    }
+
+    #[test]
+    fn isotonic_regression() {
+        let c = Calibration::fit_isotonic(&samples()).unwrap();
+        if let Calibration::Isotonic { probabilities, .. } = &c {
+            assert!(probabilities.windows(2).all(|w| w[0] >= w[1]));
+        }
+        assert!(c.probability(-1.0) >= c.probability(0.5));
+        assert!(c.probability(0.5) > 0.8);
+        assert!(c.probability(1.5) < 0.2);
+        assert!(c.probability(10.0) >= 0.0);
+
+        let c = Calibration::fit_isotonic(&[(0.0, true), (1.0, false)]).unwrap();
+        assert_eq!(c.probability(0.25), 0.75);
+    }
+}
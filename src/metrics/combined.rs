@@ -0,0 +1,238 @@
+use crate::track::{
+    MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, TrackId,
+};
+use anyhow::Result;
+use std::sync::Arc;
+
+type ComponentDistance<TA, OA> = dyn Fn(&MetricQuery<'_, TA, OA>) -> Option<f32> + Send + Sync;
+
+/// Component of [CombinedMetric](CombinedMetric)
+///
+pub struct MetricComponent<TA, OA>
+where
+    OA: ObservationAttributes,
+{
+    distance: Arc<ComponentDistance<TA, OA>>,
+    weight: f32,
+    gate: Option<f32>,
+}
+
+impl<TA, OA> Clone for MetricComponent<TA, OA>
+where
+    OA: ObservationAttributes,
+{
+    fn clone(&self) -> Self {
+        Self {
+            distance: self.distance.clone(),
+            weight: self.weight,
+            gate: self.gate,
+        }
+    }
+}
+
+impl<TA, OA> MetricComponent<TA, OA>
+where
+    TA: 'static,
+    OA: ObservationAttributes,
+{
+    /// Constructs the component from the distance function
+    ///
+    /// # Arguments
+    /// * `weight` - the weight of the component distance in the sum
+    /// * `distance` - the function that calculates the component distance, e.g. IoU of the attributes
+    ///
+    pub fn new<F>(weight: f32, distance: F) -> Self
+    where
+        F: Fn(&MetricQuery<'_, TA, OA>) -> Option<f32> + Send + Sync + 'static,
+    {
+        Self {
+            distance: Arc::new(distance),
+            weight,
+            gate: None,
+        }
+    }
+
+    /// Constructs the component from the feature distance of the metric
+    ///
+    /// The similarities of the metrics with [MetricPolarity::Similarity](MetricPolarity::Similarity) are negated
+    /// with [MetricPolarity::orient](MetricPolarity::orient), so the component distance is smaller for the closer
+    /// observations and the gate is the negated min similarity.
+    ///
+    /// # Arguments
+    /// * `weight` - the weight of the component distance in the sum
+    /// * `metric` - the metric which feature distance is the component distance
+    ///
+    pub fn from_metric<M>(weight: f32, metric: M) -> Self
+    where
+        M: ObservationMetric<TA, OA>,
+    {
+        let polarity = metric.polarity();
+        Self::new(weight, move |mq| {
+            metric
+                .metric(mq)
+                .and_then(|(_, d)| d)
+                .map(|d| polarity.orient(d))
+        })
+    }
+
+    /// Sets the max component distance, the pair with the greater component distance is rejected
+    ///
+    pub fn with_gate(mut self, gate: f32) -> Self {
+        self.gate = Some(gate);
+        self
+    }
+}
+
+/// Weighted multi-metric combinator.
+///
+/// Computes the distances of the components and returns their weighted sum as the feature distance, so e.g.
+/// motion and appearance costs are combined without a custom metric. The attribute metric is calculated with
+/// [ObservationAttributes::calculate_metric_object](ObservationAttributes::calculate_metric_object).
+///
+/// The pair is rejected (the metric returns `None`) when a component distance exceeds the gate of the component.
+/// When a component distance is missing, the feature distance is not calculated.
+///
+/// The combinator doesn't optimize the observations. The sum is a distance, the components built from the similarity
+/// metrics are oriented with [MetricComponent::from_metric](MetricComponent::from_metric).
+///
+pub struct CombinedMetric<TA, OA>
+where
+    OA: ObservationAttributes,
+{
+    components: Vec<MetricComponent<TA, OA>>,
+}
+
+impl<TA, OA> Clone for CombinedMetric<TA, OA>
+where
+    OA: ObservationAttributes,
+{
+    fn clone(&self) -> Self {
+        Self {
+            components: self.components.clone(),
+        }
+    }
+}
+
+impl<TA, OA> Default for CombinedMetric<TA, OA>
+where
+    OA: ObservationAttributes,
+{
+    fn default() -> Self {
+        Self {
+            components: Vec::default(),
+        }
+    }
+}
+
+impl<TA, OA> CombinedMetric<TA, OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs the metric without components
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the component
+    ///
+    pub fn with_component(mut self, component: MetricComponent<TA, OA>) -> Self {
+        self.components.push(component);
+        self
+    }
+}
+
+impl<TA, OA> ObservationMetric<TA, OA> for CombinedMetric<TA, OA>
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        let mut distance = Some(0.0);
+        for c in &self.components {
+            match (c.distance)(mq) {
+                Some(d) if c.gate.map(|g| d > g).unwrap_or(false) => return None,
+                Some(d) => distance = distance.map(|acc| acc + c.weight * d),
+                None => distance = None,
+            }
+        }
+        Some((
+            OA::calculate_metric_object(&e1.attr().as_ref(), &e2.attr().as_ref()),
+            distance,
+        ))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        _observations: &mut Vec<Observation<OA>>,
+        _prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        MetricPolarity::Distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::SimpleAttrs;
+    use crate::metrics::combined::{CombinedMetric, MetricComponent};
+    use crate::metrics::cosine::CosineMetric;
+    use crate::metrics::inner_product::InnerProductMetric;
+    use crate::metrics::query_metric;
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, MetricQuery, Observation};
+    use crate::EPS;
+
+    #[test]
+    fn weighted_sum() {
+        let metric = CombinedMetric::<SimpleAttrs, f32>::new()
+            .with_component(
+                MetricComponent::new(0.25, |mq: &MetricQuery<'_, SimpleAttrs, f32>| {
+                    let (l, r) = (mq.candidate_observation, mq.track_observation);
+                    Some((l.attr().as_ref()? - r.attr().as_ref()?).abs())
+                })
+                .with_gate(2.0),
+            )
+            .with_component(MetricComponent::from_metric(0.75, CosineMetric::new()));
+
+        let query = |o1, o2| query_metric(&metric, 0, o1, o2);
+
+        let o1 = Observation::new(Some(1.0), Some(Feature::from_vec(vec![1.0, 0.0])));
+        let o2 = Observation::new(Some(2.0), Some(Feature::from_vec(vec![0.0, 1.0])));
+        let (attribute_metric, distance) = query(&o1, &o2).unwrap();
+        assert_eq!(attribute_metric, Some(1.0));
+        assert!((distance.unwrap() - (0.25 + 0.75)).abs() < EPS);
+
+        // the first component is gated
+        let o3 = Observation::new(Some(5.0), Some(Feature::from_vec(vec![1.0, 0.0])));
+        assert!(query(&o1, &o3).is_none());
+
+        // the second component is missing
+        let o4 = Observation::new(Some(1.5), None);
+        assert_eq!(query(&o1, &o4).unwrap().1, None);
+    }
+
+    #[test]
+    fn similarity_component() {
+        let metric = CombinedMetric::<SimpleAttrs, f32>::new()
+            .with_component(MetricComponent::from_metric(0.5, InnerProductMetric).with_gate(-0.5));
+        let query = |o1, o2| query_metric(&metric, 0, o1, o2);
+
+        let o1 = Observation::new(None, Some(Feature::from_vec(vec![1.0, 0.0])));
+        let o2 = Observation::new(None, Some(Feature::from_vec(vec![1.0, 0.0])));
+        assert!((query(&o1, &o2).unwrap().1.unwrap() + 0.5).abs() < EPS);
+
+        // the similarity is below the gate
+        let o3 = Observation::new(None, Some(Feature::from_vec(vec![0.25, 0.0])));
+        assert!(query(&o1, &o3).is_none());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::distance::matrix::BatchDistance;
+use crate::distance::{cosine, dot, normalize};
+use crate::track::{
+    MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric, TrackId,
+};
+use anyhow::Result;
+
+/// Cosine distance observation metric.
+///
+/// The feature distance is calculated as `1 - cosine_similarity`, so it is within `[0.0, 2.0]`: identical
+/// directions give `0.0`, opposite directions give `2.0`. The attribute metric is calculated with
+/// [ObservationAttributes::calculate_metric_object](ObservationAttributes::calculate_metric_object).
+///
+/// When the pre-normalization is enabled, the features of the observations are scaled to the unit L2 norm
+/// when they are added to the track, so the distance is calculated with the inner product only. It is the
+/// preferred mode for L2-normalized embeddings. The features of the observations put in the track before
+/// the metric is changed are not normalized.
+///
+#[derive(Debug, Clone, Default)]
+pub struct CosineMetric {
+    normalize: bool,
+}
+
+impl CosineMetric {
+    /// Constructs the metric without the pre-normalization
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables the pre-normalization of stored features
+    ///
+    pub fn with_normalization(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Returns `true` when the stored features are pre-normalized
+    ///
+    pub fn normalization(&self) -> bool {
+        self.normalize
+    }
+}
+
+impl<TA, OA> ObservationMetric<TA, OA> for CosineMetric
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        Some((
+            OA::calculate_metric_object(&e1.attr().as_ref(), &e2.attr().as_ref()),
+            match (e1.feature().as_ref(), e2.feature().as_ref()) {
+                (Some(x), Some(y)) if self.normalize => Some(1.0 - dot(x, y)),
+                (Some(x), Some(y)) => Some(1.0 - cosine(x, y)),
+                _ => None,
+            },
+        ))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        observations: &mut Vec<Observation<OA>>,
+        prev_length: usize,
+        is_merge: bool,
+    ) -> Result<()> {
+        // merged observations are already normalized by the source track
+        if self.normalize && !is_merge {
+            observations
+                .iter_mut()
+                .skip(prev_length)
+                .flat_map(|o| o.feature_mut().as_mut())
+                .for_each(normalize);
+        }
+        Ok(())
+    }
+
+    fn batch_distance(&self) -> Option<BatchDistance> {
+        Some(if self.normalize {
+            BatchDistance::InnerProductDistance
+        } else {
+            BatchDistance::CosineDistance
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::SimpleAttrs;
+    use crate::metrics::cosine::CosineMetric;
+    use crate::metrics::{feature_distance, query_metric};
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, Observation, ObservationMetric};
+    use crate::EPS;
+
+    #[test]
+    fn cosine_distance() {
+        let metric = CosineMetric::new();
+        let o1 = Observation::new(None, Some(Feature::from_vec(vec![2.0, 0.0])));
+        let o2 = Observation::new(None, Some(Feature::from_vec(vec![0.0, 3.0])));
+        let o3 = Observation::new(None, Some(Feature::from_vec(vec![-1.0, 0.0])));
+        let o4 = Observation::<f32>::new(None, None);
+
+        assert!(feature_distance(&metric, &o1, &o1).abs() < EPS);
+        assert!((feature_distance(&metric, &o1, &o2) - 1.0).abs() < EPS);
+        assert!((feature_distance(&metric, &o1, &o3) - 2.0).abs() < EPS);
+        assert!(matches!(
+            query_metric(&metric, 0, &o1, &o4),
+            Some((_, None))
+        ));
+    }
+
+    #[test]
+    fn pre_normalization() {
+        let mut metric = CosineMetric::new().with_normalization(true);
+        let mut observations = vec![
+            Observation::<f32>::new(None, Some(Feature::from_vec(vec![3.0, 4.0]))),
+            Observation::new(None, Some(Feature::from_vec(vec![0.0, 5.0]))),
+        ];
+        let mut attrs = SimpleAttrs::default();
+        metric
+            .optimize(0, &[], &mut attrs, &mut observations, 1, false)
+            .unwrap();
+
+        // only the observation added after `prev_length` is normalized
+        assert_eq!(
+            observations[0].feature().as_ref().unwrap()[0].as_array_ref()[0],
+            3.0
+        );
+        assert_eq!(
+            observations[1].feature().as_ref().unwrap()[0].as_array_ref()[1],
+            1.0
+        );
+
+        metric
+            .optimize(0, &[], &mut attrs, &mut observations, 0, false)
+            .unwrap();
+        assert!((feature_distance(&metric, &observations[0], &observations[1]) - 0.2).abs() < EPS);
+    }
+}
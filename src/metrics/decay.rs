@@ -0,0 +1,249 @@
+use crate::track::{
+    MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, ObservationMetricOk, TrackId,
+};
+use anyhow::Result;
+use std::sync::Arc;
+
+/// Decay function, returns the factor (not less than `1.0`) the distance is inflated with for the observation age
+///
+#[derive(Clone)]
+pub enum Decay {
+    /// `1 + rate * age`
+    Linear(f32),
+    /// `exp(rate * age)`
+    Exponential(f32),
+    /// `penalty` for the observations older than `age`, `1.0` otherwise
+    Step { age: u64, penalty: f32 },
+    /// custom function of the age
+    Custom(Arc<dyn Fn(u64) -> f32 + Send + Sync>),
+}
+
+impl Decay {
+    /// Calculates the factor for the observation age
+    ///
+    pub fn factor(&self, age: u64) -> f32 {
+        let factor = match self {
+            Decay::Linear(rate) => 1.0 + rate * age as f32,
+            Decay::Exponential(rate) => (rate * age as f32).exp(),
+            Decay::Step {
+                age: max_age,
+                penalty,
+            } => {
+                if age > *max_age {
+                    *penalty
+                } else {
+                    1.0
+                }
+            }
+            Decay::Custom(f) => f(age),
+        };
+        factor.max(1.0)
+    }
+}
+
+/// Metric that inflates the distances of the wrapped metric for the stale track observations.
+///
+/// The age of the track observation is the difference between the epochs the candidate observation and the track
+/// observation are stamped with, look at [ObservationStamp](crate::track::ObservationStamp). The candidate tracks
+/// built with [new_track](crate::store::TrackStore::new_track) are stamped with the current epoch of the store.
+/// The feature distance is multiplied by the decay factor of the age, so matching prefers recent evidence. When the
+/// wrapped metric produces similarities ([MetricPolarity::Similarity](MetricPolarity::Similarity)), they are
+/// decreased by `(factor - 1) * |similarity|`, so the penalty grows with the factor for the negative similarities too.
+///
+#[derive(Clone)]
+pub struct TimeDecayedMetric<M> {
+    metric: M,
+    decay: Decay,
+}
+
+impl<M> TimeDecayedMetric<M> {
+    /// Constructs the metric
+    ///
+    /// # Arguments
+    /// * `metric` - the metric which distances are decayed
+    /// * `decay` - the decay function
+    ///
+    pub fn new(metric: M, decay: Decay) -> Self {
+        Self { metric, decay }
+    }
+}
+
+impl<TA, OA, M> ObservationMetric<TA, OA> for TimeDecayedMetric<M>
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+    M: ObservationMetric<TA, OA>,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (attribute_metric, feature_distance) = self.metric.metric(mq)?;
+        let age = mq
+            .candidate_observation
+            .stamp()
+            .epoch
+            .saturating_sub(mq.track_observation.stamp().epoch);
+        let factor = self.decay.factor(age as u64);
+        Some((
+            attribute_metric,
+            feature_distance.map(|d| match self.metric.polarity() {
+                MetricPolarity::Distance => d * factor,
+                MetricPolarity::Similarity => d - (factor - 1.0) * d.abs(),
+            }),
+        ))
+    }
+
+    fn gate(&self, mq: &MetricQuery<'_, TA, OA>) -> bool {
+        self.metric.gate(mq)
+    }
+
+    fn optimize(
+        &mut self,
+        feature_class: u64,
+        merge_history: &[TrackId],
+        attributes: &mut TA,
+        observations: &mut Vec<Observation<OA>>,
+        prev_length: usize,
+        is_merge: bool,
+    ) -> Result<()> {
+        self.metric.optimize(
+            feature_class,
+            merge_history,
+            attributes,
+            observations,
+            prev_length,
+            is_merge,
+        )
+    }
+
+    fn postprocess_distances(
+        &self,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.metric.postprocess_distances(unfiltered)
+    }
+
+    fn postprocess_class_distances(
+        &self,
+        feature_class: u64,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.metric
+            .postprocess_class_distances(feature_class, unfiltered)
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        self.metric.polarity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::UnboundAttrs;
+    use crate::metrics::decay::{Decay, TimeDecayedMetric};
+    use crate::metrics::inner_product::InnerProductMetric;
+    use crate::metrics::minkowski::MinkowskiMetric;
+    use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+    use crate::EPS;
+    use std::sync::Arc;
+
+    #[test]
+    fn decay_factors() {
+        assert!((Decay::Linear(0.5).factor(4) - 3.0).abs() < EPS);
+        assert!((Decay::Exponential(0.0).factor(100) - 1.0).abs() < EPS);
+        let step = Decay::Step {
+            age: 2,
+            penalty: 10.0,
+        };
+        assert!((step.factor(2) - 1.0).abs() < EPS);
+        assert!((step.factor(3) - 10.0).abs() < EPS);
+        // the factor never deflates the distance
+        assert!((Decay::Custom(Arc::new(|_| 0.5)).factor(1) - 1.0).abs() < EPS);
+    }
+
+    #[test]
+    fn stale_observations_inflated() {
+        let mut store = TrackStoreBuilder::new(1)
+            .default_attributes(UnboundAttrs)
+            .metric(TimeDecayedMetric::new(
+                MinkowskiMetric::new(2.0),
+                Decay::Linear(0.1),
+            ))
+            .notifier(NoopNotifier)
+            .build();
+
+        for (id, epoch) in [(1, 0), (2, 10)] {
+            while store.epoch() < epoch {
+                store.advance_epoch();
+            }
+            store
+                .add(id, 0, None, Some(Feature::from_vec(vec![0.0])), None)
+                .unwrap();
+        }
+
+        // the query is stamped with the current epoch of the store
+        let query = store
+            .new_track(10)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation(Feature::from_vec(vec![1.0]))
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(query.get_observations(0).unwrap()[0].stamp().epoch, 10);
+
+        let (dists, _) = store.foreign_track_distances(vec![query], 0, false);
+        let mut dists = dists
+            .all()
+            .into_iter()
+            .map(|d| (d.to, d.feature_distance.unwrap()))
+            .collect::<Vec<_>>();
+        dists.sort_by_key(|(to, _)| *to);
+        assert_eq!(dists.len(), 2);
+        assert!((dists[0].1 - 2.0).abs() < EPS);
+        assert!((dists[1].1 - 1.0).abs() < EPS);
+    }
+
+    #[test]
+    fn stale_similarities_penalized() {
+        let mut store = TrackStoreBuilder::new(1)
+            .default_attributes(UnboundAttrs)
+            .metric(TimeDecayedMetric::new(
+                InnerProductMetric,
+                Decay::Linear(0.1),
+            ))
+            .notifier(NoopNotifier)
+            .build();
+        for (id, feature) in [(1, 0.5), (2, -0.5)] {
+            store
+                .add(id, 0, None, Some(Feature::from_vec(vec![feature])), None)
+                .unwrap();
+        }
+        while store.epoch() < 10 {
+            store.advance_epoch();
+        }
+
+        let query = store
+            .new_track(10)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation(Feature::from_vec(vec![1.0]))
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        let (dists, _) = store.foreign_track_distances(vec![query], 0, false);
+        let mut dists = dists
+            .all()
+            .into_iter()
+            .map(|d| (d.to, d.feature_distance.unwrap()))
+            .collect::<Vec<_>>();
+        dists.sort_by_key(|(to, _)| *to);
+        // the factor is 2.0, both similarities are lowered by their magnitude
+        assert!((dists[0].1 - 0.0).abs() < EPS);
+        assert!((dists[1].1 + 1.0).abs() < EPS);
+    }
+}
@@ -0,0 +1,391 @@
+use crate::track::{
+    MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, ObservationMetricOk, TrackId,
+};
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Object-safe counterpart of [ObservationMetric](ObservationMetric)
+///
+/// Implemented for every metric, so the metrics can be used as trait objects.
+///
+pub trait DynObservationMetric<TA, OA>: Send + Sync
+where
+    OA: ObservationAttributes,
+{
+    /// Calculates the metric like [ObservationMetric::metric](ObservationMetric::metric) does
+    ///
+    fn dyn_metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject>;
+
+    /// Checks the gate like [ObservationMetric::gate](ObservationMetric::gate) does
+    ///
+    fn dyn_gate(&self, mq: &MetricQuery<'_, TA, OA>) -> bool;
+
+    /// Optimizes the observations like [ObservationMetric::optimize](ObservationMetric::optimize) does
+    ///
+    fn dyn_optimize(
+        &mut self,
+        feature_class: u64,
+        merge_history: &[TrackId],
+        attributes: &mut TA,
+        observations: &mut Vec<Observation<OA>>,
+        prev_length: usize,
+        is_merge: bool,
+    ) -> Result<()>;
+
+    /// Postprocesses the distances like [ObservationMetric::postprocess_distances](ObservationMetric::postprocess_distances) does
+    ///
+    fn dyn_postprocess_distances(
+        &self,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>>;
+
+    /// Postprocesses the distances of the feature class like
+    /// [ObservationMetric::postprocess_class_distances](ObservationMetric::postprocess_class_distances) does
+    ///
+    fn dyn_postprocess_class_distances(
+        &self,
+        feature_class: u64,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>>;
+
+    /// Polarity of the metric like [ObservationMetric::polarity](ObservationMetric::polarity)
+    ///
+    fn dyn_polarity(&self) -> MetricPolarity;
+
+    /// Clones the metric into the box
+    ///
+    fn box_clone(&self) -> Box<dyn DynObservationMetric<TA, OA>>;
+}
+
+impl<TA, OA, M> DynObservationMetric<TA, OA> for M
+where
+    OA: ObservationAttributes,
+    M: ObservationMetric<TA, OA>,
+{
+    fn dyn_metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        self.metric(mq)
+    }
+
+    fn dyn_gate(&self, mq: &MetricQuery<'_, TA, OA>) -> bool {
+        self.gate(mq)
+    }
+
+    fn dyn_optimize(
+        &mut self,
+        feature_class: u64,
+        merge_history: &[TrackId],
+        attributes: &mut TA,
+        observations: &mut Vec<Observation<OA>>,
+        prev_length: usize,
+        is_merge: bool,
+    ) -> Result<()> {
+        self.optimize(
+            feature_class,
+            merge_history,
+            attributes,
+            observations,
+            prev_length,
+            is_merge,
+        )
+    }
+
+    fn dyn_postprocess_distances(
+        &self,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.postprocess_distances(unfiltered)
+    }
+
+    fn dyn_postprocess_class_distances(
+        &self,
+        feature_class: u64,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.postprocess_class_distances(feature_class, unfiltered)
+    }
+
+    fn dyn_polarity(&self) -> MetricPolarity {
+        self.polarity()
+    }
+
+    fn box_clone(&self) -> Box<dyn DynObservationMetric<TA, OA>> {
+        Box::new(self.clone())
+    }
+}
+
+/// Per-feature-class metric dispatcher.
+///
+/// Keeps a metric for every registered feature class (e.g. class 0 - bounding box IoU, class 1 - face embedding
+/// cosine) and dispatches the distance calculation, the distances postprocessing and the observations optimization
+/// for the class to its metric. The classes without the registered metric use the default metric, when it is not
+/// set, the distances for such classes are not calculated and the observations are not optimized.
+///
+/// All the metrics must have the same [polarity](ObservationMetric::polarity), it is the polarity of the dispatcher.
+///
+pub struct FeatureClassMetric<TA, OA>
+where
+    OA: ObservationAttributes,
+{
+    metrics: HashMap<u64, Box<dyn DynObservationMetric<TA, OA>>>,
+    default: Option<Box<dyn DynObservationMetric<TA, OA>>>,
+}
+
+impl<TA, OA> Clone for FeatureClassMetric<TA, OA>
+where
+    OA: ObservationAttributes,
+{
+    fn clone(&self) -> Self {
+        Self {
+            metrics: self
+                .metrics
+                .iter()
+                .map(|(cls, m)| (*cls, m.box_clone()))
+                .collect(),
+            default: self.default.as_ref().map(|m| m.box_clone()),
+        }
+    }
+}
+
+impl<TA, OA> Default for FeatureClassMetric<TA, OA>
+where
+    OA: ObservationAttributes,
+{
+    fn default() -> Self {
+        Self {
+            metrics: HashMap::default(),
+            default: None,
+        }
+    }
+}
+
+impl<TA, OA> FeatureClassMetric<TA, OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs the dispatcher without metrics
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the metric for the feature class, replaces the metric registered before
+    ///
+    /// # Panics
+    /// When the polarity of the metric differs from the polarity of the metrics registered before
+    ///
+    pub fn with_class<M>(mut self, feature_class: u64, metric: M) -> Self
+    where
+        M: ObservationMetric<TA, OA>,
+    {
+        self.check_polarity(metric.polarity());
+        self.metrics.insert(feature_class, Box::new(metric));
+        self
+    }
+
+    /// Sets the metric for the feature classes without the registered metric
+    ///
+    /// # Panics
+    /// When the polarity of the metric differs from the polarity of the metrics registered before
+    ///
+    pub fn with_default<M>(mut self, metric: M) -> Self
+    where
+        M: ObservationMetric<TA, OA>,
+    {
+        self.check_polarity(metric.polarity());
+        self.default = Some(Box::new(metric));
+        self
+    }
+
+    fn check_polarity(&self, polarity: MetricPolarity) {
+        if let Some(m) = self.metrics.values().chain(self.default.as_ref()).next() {
+            assert_eq!(
+                m.dyn_polarity(),
+                polarity,
+                "All the metrics must have the same polarity"
+            );
+        }
+    }
+
+    /// Feature classes with the registered metrics
+    ///
+    pub fn classes(&self) -> Vec<u64> {
+        let mut classes = self.metrics.keys().copied().collect::<Vec<_>>();
+        classes.sort_unstable();
+        classes
+    }
+
+    fn get(&self, feature_class: u64) -> Option<&dyn DynObservationMetric<TA, OA>> {
+        self.metrics
+            .get(&feature_class)
+            .or(self.default.as_ref())
+            .map(|m| m.as_ref())
+    }
+}
+
+impl<TA, OA> ObservationMetric<TA, OA> for FeatureClassMetric<TA, OA>
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        self.get(mq.feature_class)?.dyn_metric(mq)
+    }
+
+    fn gate(&self, mq: &MetricQuery<'_, TA, OA>) -> bool {
+        self.get(mq.feature_class)
+            .map(|m| m.dyn_gate(mq))
+            .unwrap_or(false)
+    }
+
+    fn optimize(
+        &mut self,
+        feature_class: u64,
+        merge_history: &[TrackId],
+        attributes: &mut TA,
+        observations: &mut Vec<Observation<OA>>,
+        prev_length: usize,
+        is_merge: bool,
+    ) -> Result<()> {
+        let metric = match self.metrics.get_mut(&feature_class) {
+            Some(m) => Some(m),
+            None => self.default.as_mut(),
+        };
+        match metric {
+            Some(m) => m.dyn_optimize(
+                feature_class,
+                merge_history,
+                attributes,
+                observations,
+                prev_length,
+                is_merge,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    fn postprocess_class_distances(
+        &self,
+        feature_class: u64,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        match self.get(feature_class) {
+            Some(m) => m.dyn_postprocess_class_distances(feature_class, unfiltered),
+            None => unfiltered,
+        }
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        self.metrics
+            .values()
+            .chain(self.default.as_ref())
+            .next()
+            .map(|m| m.dyn_polarity())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::SimpleAttrs;
+    use crate::metrics::cosine::CosineMetric;
+    use crate::metrics::dispatch::FeatureClassMetric;
+    use crate::metrics::inner_product::InnerProductMetric;
+    use crate::metrics::minkowski::MinkowskiMetric;
+    use crate::metrics::query_metric;
+    use crate::track::utils::FromVec;
+    use crate::track::{
+        Feature, MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationMetric,
+        ObservationMetricOk, TrackId,
+    };
+    use crate::EPS;
+    use anyhow::Result;
+
+    #[test]
+    fn dispatch_by_class() {
+        let mut metric = FeatureClassMetric::<SimpleAttrs, f32>::new()
+            .with_class(0, MinkowskiMetric::manhattan())
+            .with_class(1, CosineMetric::new().with_normalization(true));
+        assert_eq!(metric.classes(), vec![0, 1]);
+
+        let mut attrs = SimpleAttrs::default();
+        let mut observations = vec![
+            Observation::new(None, Some(Feature::from_vec(vec![3.0, 4.0]))),
+            Observation::new(None, Some(Feature::from_vec(vec![4.0, 3.0]))),
+        ];
+        // only the cosine metric normalizes the features
+        let mut class0 = observations.clone();
+        metric
+            .optimize(0, &[], &mut attrs, &mut class0, 0, false)
+            .unwrap();
+        metric
+            .optimize(1, &[], &mut attrs, &mut observations, 0, false)
+            .unwrap();
+
+        let distance = |metric: &FeatureClassMetric<SimpleAttrs, f32>,
+                        feature_class,
+                        o: &[Observation<f32>]| {
+            query_metric(metric, feature_class, &o[0], &o[1]).map(|(_, d)| d.unwrap())
+        };
+
+        assert!((distance(&metric, 0, &class0).unwrap() - 2.0).abs() < EPS);
+        assert!((distance(&metric, 1, &observations).unwrap() - 0.04).abs() < EPS);
+        assert!(distance(&metric, 2, &class0).is_none());
+
+        let metric = metric.clone().with_default(MinkowskiMetric::chebyshev());
+        assert!((distance(&metric, 2, &class0).unwrap() - 1.0).abs() < EPS);
+    }
+
+    #[derive(Clone)]
+    struct DropAll;
+
+    impl ObservationMetric<SimpleAttrs, f32> for DropAll {
+        fn metric(&self, _mq: &MetricQuery<'_, SimpleAttrs, f32>) -> MetricOutput<f32> {
+            None
+        }
+
+        fn optimize(
+            &mut self,
+            _feature_class: u64,
+            _merge_history: &[TrackId],
+            _attributes: &mut SimpleAttrs,
+            _observations: &mut Vec<Observation<f32>>,
+            _prev_length: usize,
+            _is_merge: bool,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn postprocess_distances(
+            &self,
+            _unfiltered: Vec<ObservationMetricOk<f32>>,
+        ) -> Vec<ObservationMetricOk<f32>> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn dispatch_postprocessing_and_polarity() {
+        let metric = FeatureClassMetric::<SimpleAttrs, f32>::new()
+            .with_class(0, MinkowskiMetric::manhattan())
+            .with_class(1, DropAll);
+        assert_eq!(metric.polarity(), MetricPolarity::Distance);
+
+        let distances = || vec![ObservationMetricOk::new(1, 2, None, Some(0.5))];
+        assert_eq!(metric.postprocess_class_distances(0, distances()).len(), 1);
+        assert!(metric
+            .postprocess_class_distances(1, distances())
+            .is_empty());
+
+        let metric = FeatureClassMetric::<SimpleAttrs, f32>::new().with_default(InnerProductMetric);
+        assert_eq!(metric.polarity(), MetricPolarity::Similarity);
+    }
+
+    #[test]
+    #[should_panic(expected = "All the metrics must have the same polarity")]
+    fn mixed_polarities() {
+        let _ = FeatureClassMetric::<SimpleAttrs, f32>::new()
+            .with_class(0, MinkowskiMetric::manhattan())
+            .with_class(1, InnerProductMetric);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::track::utils::FromVec;
+use crate::track::{
+    Feature, MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric,
+    TrackId,
+};
+use anyhow::Result;
+
+/// Default smoothing added to the bins before the symmetric KL divergence is calculated
+///
+pub const DEFAULT_DIVERGENCE_SMOOTHING: f32 = 1e-6;
+
+/// Divergence calculated by [DivergenceMetric](DivergenceMetric)
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Divergence {
+    /// `KL(p || q) + KL(q || p)`, the bins are smoothed with the value, so zero bins don't give infinity
+    SymmetricKl(f32),
+    /// Jensen–Shannon divergence within `[0.0, ln 2]`, finite for zero bins without smoothing
+    JensenShannon,
+}
+
+pub(crate) fn distribution(f: &Feature, len: usize, smoothing: f32) -> Vec<f64> {
+    let mut values = Vec::<f32>::from_vec(f);
+    values.truncate(len);
+    let values = values
+        .into_iter()
+        .map(|v| v.max(0.0) as f64 + smoothing as f64)
+        .collect::<Vec<_>>();
+    let sum = values.iter().sum::<f64>();
+    if sum > 0.0 {
+        values.into_iter().map(|v| v / sum).collect()
+    } else {
+        values
+    }
+}
+
+fn kl(p: &[f64], q: &[f64]) -> f64 {
+    p.iter()
+        .zip(q)
+        .filter(|(p, _)| **p > 0.0)
+        .map(|(p, q)| p * (p / q).ln())
+        .sum()
+}
+
+/// Calculates the symmetric KL divergence of two histograms
+///
+/// The histograms are normalized to the unit sum, negative bins are treated as zero bins. When the
+/// histograms lengths don't match, the longer histogram is truncated to shorter one.
+///
+pub fn symmetric_kl(f1: &Feature, f2: &Feature, smoothing: f32) -> f32 {
+    let len = f1.len().min(f2.len()) * 8;
+    let (p, q) = (
+        distribution(f1, len, smoothing),
+        distribution(f2, len, smoothing),
+    );
+    (kl(&p, &q) + kl(&q, &p)) as f32
+}
+
+/// Calculates the Jensen–Shannon divergence of two histograms
+///
+/// The histograms are normalized to the unit sum, negative bins are treated as zero bins. When the
+/// histograms lengths don't match, the longer histogram is truncated to shorter one.
+///
+pub fn jensen_shannon(f1: &Feature, f2: &Feature) -> f32 {
+    let len = f1.len().min(f2.len()) * 8;
+    let (p, q) = (distribution(f1, len, 0.0), distribution(f2, len, 0.0));
+    let m = p
+        .iter()
+        .zip(&q)
+        .map(|(p, q)| (p + q) / 2.0)
+        .collect::<Vec<_>>();
+    ((kl(&p, &m) + kl(&q, &m)) / 2.0).max(0.0) as f32
+}
+
+/// Divergence observation metric for histogram features (color histograms, class-score vectors).
+///
+/// The feature distance is the selected [Divergence](Divergence) of the observation features.
+///
+#[derive(Debug, Clone)]
+pub struct DivergenceMetric {
+    divergence: Divergence,
+}
+
+impl DivergenceMetric {
+    /// Constructs the metric
+    ///
+    pub fn new(divergence: Divergence) -> Self {
+        Self { divergence }
+    }
+
+    /// Symmetric KL divergence metric with the [DEFAULT_DIVERGENCE_SMOOTHING](DEFAULT_DIVERGENCE_SMOOTHING)
+    ///
+    pub fn symmetric_kl() -> Self {
+        Self::new(Divergence::SymmetricKl(DEFAULT_DIVERGENCE_SMOOTHING))
+    }
+
+    /// Jensen–Shannon divergence metric
+    ///
+    pub fn jensen_shannon() -> Self {
+        Self::new(Divergence::JensenShannon)
+    }
+}
+
+impl<TA, OA> ObservationMetric<TA, OA> for DivergenceMetric
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        Some((
+            OA::calculate_metric_object(&e1.attr().as_ref(), &e2.attr().as_ref()),
+            match (e1.feature().as_ref(), e2.feature().as_ref()) {
+                (Some(x), Some(y)) => Some(match self.divergence {
+                    Divergence::SymmetricKl(smoothing) => symmetric_kl(x, y, smoothing),
+                    Divergence::JensenShannon => jensen_shannon(x, y),
+                }),
+                _ => None,
+            },
+        ))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        _observations: &mut Vec<Observation<OA>>,
+        _prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::divergence::{jensen_shannon, symmetric_kl};
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+    use crate::EPS;
+
+    #[test]
+    fn divergences() {
+        let p = Feature::from_vec(vec![2.0, 2.0, 0.0, 0.0]);
+        let q = Feature::from_vec(vec![0.0, 0.0, 1.0, 1.0]);
+        let r = Feature::from_vec(vec![1.0, 2.0, 1.0, 0.0]);
+
+        assert!(symmetric_kl(&p, &p, 1e-6).abs() < EPS);
+        assert!(jensen_shannon(&p, &p).abs() < EPS);
+
+        // disjoint supports: JS reaches its maximum, KL stays finite thanks to the smoothing
+        assert!((jensen_shannon(&p, &q) - 2.0_f32.ln()).abs() < EPS);
+        let kl = symmetric_kl(&p, &q, 1e-6);
+        assert!(kl.is_finite() && kl > 10.0);
+
+        assert!((jensen_shannon(&p, &r) - jensen_shannon(&r, &p)).abs() < EPS);
+        assert!(jensen_shannon(&p, &r) < jensen_shannon(&p, &q));
+        assert!(symmetric_kl(&p, &r, 1e-6) < kl);
+    }
+}
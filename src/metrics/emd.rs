@@ -0,0 +1,279 @@
+use crate::metrics::divergence::distribution;
+use crate::track::{
+    Feature, MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, TrackId,
+};
+use crate::Errors;
+use anyhow::Result;
+use nalgebra::DMatrix;
+use std::sync::Arc;
+
+/// Default number of the Sinkhorn iterations
+///
+pub const DEFAULT_SINKHORN_ITERATIONS: usize = 100;
+
+/// Default entropic regularization of the Sinkhorn approximation
+///
+pub const DEFAULT_SINKHORN_REGULARIZATION: f32 = 0.05;
+
+/// Calculates the exact earth mover's distance of two 1D histograms with the ground distance `|i - j|` between the bins
+///
+/// The histograms are normalized to the unit sum, negative bins are treated as zero bins.
+///
+pub fn emd_1d(f1: &Feature, f2: &Feature) -> f32 {
+    let len = f1.len().min(f2.len()) * 8;
+    let (p, q) = (distribution(f1, len, 0.0), distribution(f2, len, 0.0));
+    p.iter()
+        .zip(&q)
+        .scan(0.0_f64, |cdf, (p, q)| {
+            *cdf += p - q;
+            Some(cdf.abs())
+        })
+        .sum::<f64>() as f32
+}
+
+fn log_sum_exp(values: impl Iterator<Item = f64>) -> f64 {
+    let values = values.collect::<Vec<_>>();
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return max;
+    }
+    max + values.iter().map(|v| (v - max).exp()).sum::<f64>().ln()
+}
+
+/// Approximates the earth mover's distance of two histograms with the Sinkhorn iterations
+///
+/// The iterations run in the log domain, so small regularization values don't underflow. Zero bins don't participate
+/// in the transport plan. The histograms are normalized to the unit sum, negative bins are treated as zero bins.
+///
+/// # Arguments
+/// * `f1`, `f2` - the histograms
+/// * `cost` - ground distance between the bins `i` and `j`
+/// * `iterations` - the number of the Sinkhorn iterations
+/// * `regularization` - entropic regularization, smaller values give closer approximation, but need more iterations
+///
+pub fn sinkhorn<C>(
+    f1: &Feature,
+    f2: &Feature,
+    cost: C,
+    iterations: usize,
+    regularization: f32,
+) -> f32
+where
+    C: Fn(usize, usize) -> f32,
+{
+    let len = f1.len().min(f2.len()) * 8;
+    sinkhorn_bins(f1, f2, len, cost, iterations, regularization)
+}
+
+fn sinkhorn_bins<C>(
+    f1: &Feature,
+    f2: &Feature,
+    len: usize,
+    cost: C,
+    iterations: usize,
+    regularization: f32,
+) -> f32
+where
+    C: Fn(usize, usize) -> f32,
+{
+    let support = |d: Vec<f64>| {
+        d.into_iter()
+            .enumerate()
+            .filter(|(_, v)| *v > 0.0)
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (
+        support(distribution(f1, len, 0.0)),
+        support(distribution(f2, len, 0.0)),
+    );
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let reg = regularization as f64;
+    let c = a
+        .iter()
+        .map(|(i, _)| b.iter().map(|(j, _)| cost(*i, *j) as f64).collect())
+        .collect::<Vec<Vec<f64>>>();
+
+    let mut f = vec![0.0_f64; a.len()];
+    let mut g = vec![0.0_f64; b.len()];
+    for _ in 0..iterations {
+        for (i, (_, ai)) in a.iter().enumerate() {
+            f[i] =
+                reg * ai.ln() - reg * log_sum_exp(g.iter().zip(&c[i]).map(|(g, c)| (g - c) / reg));
+        }
+        for (j, (_, bj)) in b.iter().enumerate() {
+            g[j] =
+                reg * bj.ln() - reg * log_sum_exp(f.iter().zip(&c).map(|(f, c)| (f - c[j]) / reg));
+        }
+    }
+
+    f.iter()
+        .zip(&c)
+        .map(|(f, row)| {
+            g.iter()
+                .zip(row)
+                .map(|(g, c)| ((f + g - c) / reg).exp() * c)
+                .sum::<f64>()
+        })
+        .sum::<f64>() as f32
+}
+
+/// Earth mover's distance observation metric for histogram features, e.g. color signatures.
+///
+/// Unlike the bin-to-bin distances, the distance grows smoothly when the mass shifts to the neighbouring bins. The
+/// distance is approximated with the Sinkhorn iterations. The ground distance between the bins is `|i - j|` unless the
+/// ground cost matrix is set, in that case only the first `n` bins of the features participate, where `n` is the size
+/// of the matrix.
+///
+#[derive(Debug, Clone)]
+pub struct EmdMetric {
+    iterations: usize,
+    regularization: f32,
+    ground_cost: Option<Arc<DMatrix<f32>>>,
+}
+
+impl Default for EmdMetric {
+    fn default() -> Self {
+        Self::new(DEFAULT_SINKHORN_ITERATIONS, DEFAULT_SINKHORN_REGULARIZATION)
+    }
+}
+
+impl EmdMetric {
+    /// Constructs the metric
+    ///
+    /// # Arguments
+    /// * `iterations` - the number of the Sinkhorn iterations
+    /// * `regularization` - entropic regularization, must be positive
+    ///
+    pub fn new(iterations: usize, regularization: f32) -> Self {
+        assert!(regularization > 0.0, "Regularization must be positive");
+        Self {
+            iterations,
+            regularization,
+            ground_cost: None,
+        }
+    }
+
+    /// Sets the ground cost matrix, the element `(i, j)` is the cost of moving the mass from the bin `i` to the bin `j`
+    ///
+    pub fn with_ground_cost(mut self, cost: DMatrix<f32>) -> Result<Self> {
+        if !cost.is_square() {
+            return Err(Errors::DimensionsMismatch(cost.nrows(), cost.ncols()).into());
+        }
+        self.ground_cost = Some(Arc::new(cost));
+        Ok(self)
+    }
+
+    /// The number of the Sinkhorn iterations
+    ///
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Entropic regularization
+    ///
+    pub fn regularization(&self) -> f32 {
+        self.regularization
+    }
+
+    /// Calculates the distance between two features
+    ///
+    pub fn distance(&self, f1: &Feature, f2: &Feature) -> f32 {
+        match &self.ground_cost {
+            Some(cost) => sinkhorn_bins(
+                f1,
+                f2,
+                cost.nrows(),
+                |i, j| cost[(i, j)],
+                self.iterations,
+                self.regularization,
+            ),
+            None => sinkhorn(
+                f1,
+                f2,
+                |i, j| (i as f32 - j as f32).abs(),
+                self.iterations,
+                self.regularization,
+            ),
+        }
+    }
+}
+
+impl<TA, OA> ObservationMetric<TA, OA> for EmdMetric
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        Some((
+            OA::calculate_metric_object(&e1.attr().as_ref(), &e2.attr().as_ref()),
+            match (e1.feature().as_ref(), e2.feature().as_ref()) {
+                (Some(x), Some(y)) => Some(self.distance(x, y)),
+                _ => None,
+            },
+        ))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        _observations: &mut Vec<Observation<OA>>,
+        _prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        MetricPolarity::Distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::emd::{emd_1d, EmdMetric};
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+    use nalgebra::DMatrix;
+
+    #[test]
+    fn bin_shift() {
+        let p = Feature::from_vec(vec![1.0, 0.0, 0.0, 0.0]);
+        let q = Feature::from_vec(vec![0.0, 1.0, 0.0, 0.0]);
+        let r = Feature::from_vec(vec![0.0, 0.0, 0.0, 1.0]);
+        assert!((emd_1d(&p, &q) - 1.0).abs() < 1e-6);
+        assert!((emd_1d(&p, &r) - 3.0).abs() < 1e-6);
+
+        let m = EmdMetric::default();
+        assert!(m.distance(&p, &p).abs() < 1e-3);
+        assert!((m.distance(&p, &q) - 1.0).abs() < 1e-2);
+        assert!((m.distance(&p, &r) - 3.0).abs() < 1e-2);
+
+        let s = Feature::from_vec(vec![0.5, 0.5, 0.0, 0.0]);
+        let t = Feature::from_vec(vec![0.0, 0.5, 0.5, 0.0]);
+        assert!((m.distance(&s, &t) - emd_1d(&s, &t)).abs() < 1e-2);
+    }
+
+    #[test]
+    fn ground_cost() {
+        // circular hue bins: the first and the last bins are neighbours
+        let cost = DMatrix::from_fn(4, 4, |i, j| {
+            let d = (i as f32 - j as f32).abs();
+            d.min(4.0 - d)
+        });
+        let m = EmdMetric::default().with_ground_cost(cost).unwrap();
+        let p = Feature::from_vec(vec![1.0, 0.0, 0.0, 0.0]);
+        let r = Feature::from_vec(vec![0.0, 0.0, 0.0, 1.0]);
+        assert!((m.distance(&p, &r) - 1.0).abs() < 1e-2);
+
+        assert!(EmdMetric::default()
+            .with_ground_cost(DMatrix::zeros(2, 3))
+            .is_err());
+    }
+}
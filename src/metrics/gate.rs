@@ -0,0 +1,143 @@
+use crate::distance::matrix::BatchDistance;
+use crate::track::{
+    MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, ObservationMetricOk, TrackId,
+};
+use anyhow::Result;
+
+/// Metric that adds the cheap attribute-level gate to the wrapped metric.
+///
+/// The gate (e.g. the boxes intersect or the camera ids are the same) is checked before the wrapped
+/// metric is calculated, the pairs that fail the gate are skipped without the feature distance calculation.
+/// The gate of the wrapped metric is checked as well.
+///
+#[derive(Clone)]
+pub struct AttributeGatedMetric<M, G> {
+    metric: M,
+    gate: G,
+}
+
+impl<M, G> AttributeGatedMetric<M, G> {
+    /// Constructs the metric
+    ///
+    /// # Arguments
+    /// * `metric` - the metric calculated for the pairs passed the gate
+    /// * `gate` - the function that returns `true` when the pair passes the gate
+    ///
+    pub fn new(metric: M, gate: G) -> Self {
+        Self { metric, gate }
+    }
+}
+
+impl<TA, OA, M, G> ObservationMetric<TA, OA> for AttributeGatedMetric<M, G>
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+    M: ObservationMetric<TA, OA>,
+    G: Fn(&MetricQuery<'_, TA, OA>) -> bool + Send + Sync + Clone + 'static,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        self.metric.metric(mq)
+    }
+
+    fn gate(&self, mq: &MetricQuery<'_, TA, OA>) -> bool {
+        (self.gate)(mq) && self.metric.gate(mq)
+    }
+
+    fn optimize(
+        &mut self,
+        feature_class: u64,
+        merge_history: &[TrackId],
+        attributes: &mut TA,
+        observations: &mut Vec<Observation<OA>>,
+        prev_length: usize,
+        is_merge: bool,
+    ) -> Result<()> {
+        self.metric.optimize(
+            feature_class,
+            merge_history,
+            attributes,
+            observations,
+            prev_length,
+            is_merge,
+        )
+    }
+
+    fn postprocess_distances(
+        &self,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.metric.postprocess_distances(unfiltered)
+    }
+
+    fn postprocess_class_distances(
+        &self,
+        feature_class: u64,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.metric
+            .postprocess_class_distances(feature_class, unfiltered)
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        self.metric.polarity()
+    }
+
+    fn batch_distance(&self) -> Option<BatchDistance> {
+        self.metric.batch_distance()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::UnboundAttrs;
+    use crate::metrics::gate::AttributeGatedMetric;
+    use crate::metrics::minkowski::MinkowskiMetric;
+    use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, MetricQuery};
+
+    #[test]
+    fn gated_pairs_skipped() {
+        // the observation attribute is the camera id, only the observations of the same camera are compared
+        let same_camera = |mq: &MetricQuery<'_, UnboundAttrs, f32>| {
+            mq.candidate_observation.attr() == mq.track_observation.attr()
+        };
+        let mut store = TrackStoreBuilder::new(1)
+            .default_attributes(UnboundAttrs)
+            .metric(AttributeGatedMetric::new(
+                MinkowskiMetric::new(2.0),
+                same_camera,
+            ))
+            .notifier(NoopNotifier)
+            .build();
+
+        for (id, camera) in [(1, 1.0), (2, 2.0)] {
+            store
+                .add(
+                    id,
+                    0,
+                    Some(camera),
+                    Some(Feature::from_vec(vec![0.0])),
+                    None,
+                )
+                .unwrap();
+        }
+
+        let query = store
+            .new_track(10)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation_attributes(1.0)
+                    .observation(Feature::from_vec(vec![1.0]))
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        let (dists, _) = store.foreign_track_distances(vec![query], 0, false);
+        let dists = dists.all();
+        assert_eq!(dists.len(), 1);
+        assert_eq!(dists[0].to, 1);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::distance::{cosine, euclidean};
+use crate::track::utils::FromVec;
+use crate::track::{
+    MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric, TrackId,
+};
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// Converts `f32` to the IEEE 754 half-precision bits, rounds to the nearest even
+///
+pub fn f32_to_f16(value: f32) -> u16 {
+    let x = value.to_bits();
+    let sign = ((x >> 16) & 0x8000) as u16;
+    let exp = ((x >> 23) & 0xff) as i32;
+    let man = x & 0x7f_ffff;
+
+    if exp == 0xff {
+        let nan = if man != 0 {
+            0x200 | (man >> 13) as u16
+        } else {
+            0
+        };
+        return sign | 0x7c00 | nan;
+    }
+
+    let round = |half: u32, rem: u32, halfway: u32| {
+        if rem > halfway || (rem == halfway && half & 1 == 1) {
+            half + 1
+        } else {
+            half
+        }
+    };
+
+    let e = exp - 127 + 15;
+    if e >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if e <= 0 {
+        if e < -10 {
+            return sign;
+        }
+        let man = man | 0x80_0000;
+        let shift = (14 - e) as u32;
+        let rounded = round(man >> shift, man & ((1 << shift) - 1), 1 << (shift - 1));
+        return sign | rounded as u16;
+    }
+
+    // the carry of the rounding moves to the exponent, the overflow gives the infinity
+    let rounded = round(((e as u32) << 10) | (man >> 13), man & 0x1fff, 0x1000);
+    sign | rounded as u16
+}
+
+/// Converts the IEEE 754 half-precision bits to `f32`
+///
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exp = ((bits >> 10) & 0x1f) as u32;
+    let man = (bits & 0x3ff) as u32;
+    match exp {
+        0 => {
+            let value = man as f32 * 2.0_f32.powi(-24);
+            if sign != 0 {
+                -value
+            } else {
+                value
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (man << 13)),
+        _ => f32::from_bits(sign | ((exp + 112) << 23) | (man << 13)),
+    }
+}
+
+/// Half-precision feature vector.
+///
+/// Keeps the values as IEEE 754 half-precision bits, so the vector takes 2 times less memory than
+/// the float feature vector. The distances are calculated with the conversion on the fly and `f32` accumulation.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HalfFeature(Vec<u16>);
+
+impl HalfFeature {
+    /// Converts the float vector
+    ///
+    pub fn from_f32(vec: &[f32]) -> Self {
+        Self(vec.iter().map(|v| f32_to_f16(*v)).collect())
+    }
+
+    /// Restores the float vector
+    ///
+    pub fn to_f32(&self) -> Vec<f32> {
+        self.0.iter().map(|v| f16_to_f32(*v)).collect()
+    }
+
+    /// Half-precision bits of the values
+    ///
+    pub fn bits(&self) -> &[u16] {
+        &self.0
+    }
+
+    fn pairs<'a>(&'a self, other: &'a HalfFeature) -> impl Iterator<Item = (f32, f32)> + 'a {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| (f16_to_f32(*a), f16_to_f32(*b)))
+    }
+
+    /// Euclidean distance between two vectors
+    ///
+    /// When the vectors lengths don't match, the longer vector is truncated to shorter one
+    ///
+    pub fn euclidean(&self, other: &HalfFeature) -> f32 {
+        self.pairs(other)
+            .fold(0.0_f32, |acc, (a, b)| acc + (a - b) * (a - b))
+            .sqrt()
+    }
+
+    /// Cosine similarity of two vectors
+    ///
+    /// When the vectors lengths don't match, the longer vector is truncated to shorter one
+    ///
+    pub fn cosine(&self, other: &HalfFeature) -> f32 {
+        let (ab, aa, bb) = self
+            .pairs(other)
+            .fold((0.0_f32, 0.0_f32, 0.0_f32), |(ab, aa, bb), (a, b)| {
+                (ab + a * b, aa + a * a, bb + b * b)
+            });
+        ab / (aa * bb).sqrt()
+    }
+}
+
+impl ObservationAttributes for HalfFeature {
+    type MetricObject = ();
+
+    fn calculate_metric_object(
+        _l: &Option<&Self>,
+        _r: &Option<&Self>,
+    ) -> Option<Self::MetricObject> {
+        None
+    }
+}
+
+/// Distance calculated by [HalfMetric](HalfMetric)
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HalfDistance {
+    /// euclidean distance
+    Euclidean,
+    /// `1 - cosine_similarity`
+    Cosine,
+}
+
+/// Observation metric that keeps the features of the selected feature classes in half precision.
+///
+/// When the observation of the selected class is added with the float feature vector, the metric converts
+/// it to [HalfFeature](HalfFeature) kept in the observation attributes and drops the float vector. The
+/// observations of other classes keep the float vectors. The distance is calculated between the half-precision
+/// vectors when both observations have them, otherwise between the float vectors.
+///
+#[derive(Debug, Clone)]
+pub struct HalfMetric {
+    distance: HalfDistance,
+    half_classes: HashSet<u64>,
+}
+
+impl HalfMetric {
+    /// Constructs the metric that keeps all the features in single precision
+    ///
+    pub fn new(distance: HalfDistance) -> Self {
+        Self {
+            distance,
+            half_classes: HashSet::default(),
+        }
+    }
+
+    /// Selects the feature classes which features are kept in half precision
+    ///
+    pub fn with_half_classes(mut self, classes: impl IntoIterator<Item = u64>) -> Self {
+        self.half_classes = classes.into_iter().collect();
+        self
+    }
+
+    /// Returns `true` when the features of the class are kept in half precision
+    ///
+    pub fn is_half(&self, feature_class: u64) -> bool {
+        self.half_classes.contains(&feature_class)
+    }
+}
+
+impl<TA> ObservationMetric<TA, HalfFeature> for HalfMetric
+where
+    TA: Send + Sync + Clone + 'static,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, HalfFeature>) -> MetricOutput<()> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        let distance = match (e1.attr().as_ref(), e2.attr().as_ref()) {
+            (Some(x), Some(y)) => Some(match self.distance {
+                HalfDistance::Euclidean => x.euclidean(y),
+                HalfDistance::Cosine => 1.0 - x.cosine(y),
+            }),
+            _ => match (e1.feature().as_ref(), e2.feature().as_ref()) {
+                (Some(x), Some(y)) => Some(match self.distance {
+                    HalfDistance::Euclidean => euclidean(x, y),
+                    HalfDistance::Cosine => 1.0 - cosine(x, y),
+                }),
+                _ => None,
+            },
+        };
+        Some((None, distance))
+    }
+
+    fn optimize(
+        &mut self,
+        feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        observations: &mut Vec<Observation<HalfFeature>>,
+        prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        if !self.is_half(feature_class) {
+            return Ok(());
+        }
+        for o in observations.iter_mut().skip(prev_length) {
+            if let Some(f) = o.feature_mut().take() {
+                *o.attr_mut() = Some(HalfFeature::from_f32(&Vec::from_vec(&f)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::SimpleAttrs;
+    use crate::metrics::half::{f16_to_f32, f32_to_f16, HalfDistance, HalfMetric};
+    use crate::metrics::query_metric;
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, Observation, ObservationMetric};
+
+    #[test]
+    fn conversion() {
+        for (value, bits) in [
+            (0.0, 0x0000),
+            (-0.0, 0x8000),
+            (1.0, 0x3c00),
+            (-2.0, 0xc000),
+            (65504.0, 0x7bff),
+            (1e6, 0x7c00),
+            (f32::NEG_INFINITY, 0xfc00),
+            (5.960_464_5e-8, 0x0001),
+            (1.0 + 1.0 / 2048.0, 0x3c00),
+            (1.0 + 3.0 / 2048.0, 0x3c02),
+        ] {
+            assert_eq!(f32_to_f16(value), bits, "{value}");
+        }
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+
+        for v in [0.1_f32, -3.75, 1234.5, 6.1e-5, 3.0e-6] {
+            let restored = f16_to_f32(f32_to_f16(v));
+            assert!((restored - v).abs() <= v.abs() / 1024.0 + 6e-8, "{v}");
+        }
+    }
+
+    #[test]
+    fn per_class_storage() {
+        let mut metric = HalfMetric::new(HalfDistance::Euclidean).with_half_classes([1]);
+        let feature = || Some(Feature::from_vec(vec![1.0, 2.0, 2.0]));
+        let mut attrs = SimpleAttrs::default();
+
+        let mut class0 = vec![Observation::new(None, feature())];
+        metric
+            .optimize(0, &[], &mut attrs, &mut class0, 0, false)
+            .unwrap();
+        assert!(class0[0].feature().is_some() && class0[0].attr().is_none());
+
+        let mut class1 = vec![Observation::new(None, feature())];
+        metric
+            .optimize(1, &[], &mut attrs, &mut class1, 0, false)
+            .unwrap();
+        assert!(class1[0].feature().is_none());
+        assert_eq!(class1[0].attr().as_ref().unwrap().bits().len(), 8);
+
+        let zero = Observation::new(None, Some(Feature::from_vec(vec![0.0; 3])));
+        let mut zero_half = vec![zero.clone()];
+        metric
+            .optimize(1, &[], &mut attrs, &mut zero_half, 0, false)
+            .unwrap();
+
+        for (o1, o2) in [(&class0[0], &zero), (&class1[0], &zero_half[0])] {
+            assert_eq!(query_metric(&metric, 0, o1, o2), Some((None, Some(3.0))));
+        }
+    }
+}
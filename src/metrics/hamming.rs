@@ -0,0 +1,161 @@
+use crate::distance::hamming;
+use crate::track::{
+    MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric, TrackId,
+};
+use anyhow::Result;
+
+/// Binary descriptor observation attributes.
+///
+/// Keeps the bits of ORB/BRIEF descriptors or binarized embeddings packed into 64-bit words. The
+/// attribute metric object is the Hamming distance between two descriptors.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BinaryDescriptor(Vec<u64>);
+
+impl BinaryDescriptor {
+    /// Constructs the descriptor from the packed words
+    ///
+    pub fn new(words: Vec<u64>) -> Self {
+        Self(words)
+    }
+
+    /// Constructs the descriptor from bytes, e.g. 32-byte ORB descriptor
+    ///
+    /// Bytes are packed into the words in the little-endian order, the last word is zero-padded.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self(
+            bytes
+                .chunks(8)
+                .map(|chunk| {
+                    let mut word = [0u8; 8];
+                    word[..chunk.len()].copy_from_slice(chunk);
+                    u64::from_le_bytes(word)
+                })
+                .collect(),
+        )
+    }
+
+    /// Packed words of the descriptor
+    ///
+    pub fn words(&self) -> &[u64] {
+        &self.0
+    }
+
+    /// The number of bits the descriptor holds
+    ///
+    pub fn bits(&self) -> usize {
+        self.0.len() * u64::BITS as usize
+    }
+}
+
+impl ObservationAttributes for BinaryDescriptor {
+    type MetricObject = u32;
+
+    fn calculate_metric_object(l: &Option<&Self>, r: &Option<&Self>) -> Option<Self::MetricObject> {
+        match (l, r) {
+            (Some(l), Some(r)) => Some(hamming(&l.0, &r.0)),
+            _ => None,
+        }
+    }
+}
+
+/// Hamming distance observation metric for [BinaryDescriptor](BinaryDescriptor) observations.
+///
+/// The feature distance is the number of different bits between the descriptors of the observations.
+/// When the normalization is enabled, the distance is divided by the number of compared bits, so it is
+/// within `[0.0, 1.0]`. Float feature vectors of the observations are not used.
+///
+#[derive(Debug, Clone, Default)]
+pub struct HammingMetric {
+    normalize: bool,
+}
+
+impl HammingMetric {
+    /// Constructs the metric that returns the number of different bits
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables the normalization of the distance by the number of compared bits
+    ///
+    pub fn with_normalization(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+}
+
+impl<TA> ObservationMetric<TA, BinaryDescriptor> for HammingMetric
+where
+    TA: Send + Sync + Clone + 'static,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, BinaryDescriptor>) -> MetricOutput<u32> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        let (l, r) = (e1.attr().as_ref(), e2.attr().as_ref());
+        let distance = BinaryDescriptor::calculate_metric_object(&l, &r);
+        Some((
+            distance,
+            match (distance, l, r) {
+                (Some(d), Some(l), Some(r)) if self.normalize => {
+                    let bits = l.bits().min(r.bits());
+                    Some(if bits == 0 {
+                        0.0
+                    } else {
+                        d as f32 / bits as f32
+                    })
+                }
+                (d, _, _) => d.map(|d| d as f32),
+            },
+        ))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        _observations: &mut Vec<Observation<BinaryDescriptor>>,
+        _prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::hamming::{BinaryDescriptor, HammingMetric};
+    use crate::metrics::query_metric;
+    use crate::track::Observation;
+
+    #[test]
+    fn hamming_metric() {
+        let d1 = BinaryDescriptor::from_bytes(&[0xff; 32]);
+        let mut bytes = [0xff; 32];
+        bytes[0] = 0x0f;
+        bytes[31] = 0x00;
+        let d2 = BinaryDescriptor::from_bytes(&bytes);
+        assert_eq!(d1.bits(), 256);
+        assert_eq!(d2.words()[0], u64::MAX ^ 0xf0);
+
+        let (o1, o2) = (
+            Observation::new(Some(d1), None),
+            Observation::new(Some(d2), None),
+        );
+        assert_eq!(
+            query_metric(&HammingMetric::new(), 0, &o1, &o2),
+            Some((Some(12), Some(12.0)))
+        );
+        assert_eq!(
+            query_metric(&HammingMetric::new().with_normalization(true), 0, &o1, &o2),
+            Some((Some(12), Some(12.0 / 256.0)))
+        );
+
+        let o3 = Observation::new(None, None);
+        assert_eq!(
+            query_metric(&HammingMetric::new(), 0, &o1, &o3),
+            Some((None, None))
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use crate::distance::dot;
+use crate::distance::matrix::BatchDistance;
+use crate::track::{
+    MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, TrackId,
+};
+use anyhow::Result;
+
+/// Inner product (MIPS) similarity observation metric.
+///
+/// The feature distance is the inner product of the features, so larger values mean closer observations.
+/// The metric has the [MetricPolarity::Similarity](MetricPolarity::Similarity) polarity, the voting engines
+/// that process the distances must be configured with it, e.g. with
+/// [TopNVoting::with_polarity](crate::voting::topn::TopNVoting::with_polarity).
+///
+#[derive(Debug, Clone, Default)]
+pub struct InnerProductMetric;
+
+impl<TA, OA> ObservationMetric<TA, OA> for InnerProductMetric
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        Some((
+            OA::calculate_metric_object(&e1.attr().as_ref(), &e2.attr().as_ref()),
+            match (e1.feature().as_ref(), e2.feature().as_ref()) {
+                (Some(x), Some(y)) => Some(dot(x, y)),
+                _ => None,
+            },
+        ))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        _observations: &mut Vec<Observation<OA>>,
+        _prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        MetricPolarity::Similarity
+    }
+
+    fn batch_distance(&self) -> Option<BatchDistance> {
+        Some(BatchDistance::InnerProduct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::UnboundAttrs;
+    use crate::metrics::inner_product::InnerProductMetric;
+    use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, MetricPolarity};
+    use crate::voting::topn::TopNVoting;
+    use crate::voting::Voting;
+
+    #[test]
+    fn larger_is_better() {
+        let mut store = TrackStoreBuilder::new(1)
+            .default_attributes(UnboundAttrs)
+            .metric(InnerProductMetric)
+            .notifier(NoopNotifier)
+            .build();
+        assert_eq!(store.metric_polarity(), MetricPolarity::Similarity);
+
+        for (id, f) in [
+            (1, vec![1.0, 0.0]),
+            (2, vec![3.0, 0.0]),
+            (3, vec![0.0, 1.0]),
+        ] {
+            store
+                .add(id, 0, None, Some(Feature::from_vec(f)), None)
+                .unwrap();
+        }
+
+        let query = store
+            .new_track(10)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation(Feature::from_vec(vec![1.0, 0.1]))
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        let (dists, errs) = store.foreign_track_distances(vec![query], 0, false);
+        assert!(errs.all().is_empty());
+
+        let v: TopNVoting<f32> = TopNVoting::new(3, 0.5, 1).with_polarity(store.metric_polarity());
+        let winners = v.winners(dists.all());
+        assert_eq!(
+            winners[&10]
+                .iter()
+                .map(|e| e.winner_track)
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::distance::matrix::BatchDistance;
+use crate::track::{
+    MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, ObservationMetricOk, TrackId,
+};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Statistics of the metric calls collected for the feature class
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricStats {
+    /// the amount of the metric calls
+    pub metric_calls: u64,
+    /// total time spent in the metric calls
+    pub metric_time: Duration,
+    /// the longest metric call
+    pub max_metric_time: Duration,
+    /// the amount of the gate checks
+    pub gate_checks: u64,
+    /// the amount of the pairs passed the gate
+    pub gate_passed: u64,
+    /// the amount of the calculated feature distances
+    pub distances: u64,
+    /// the amount of the metric calls that didn't produce the feature distance
+    pub missing_distances: u64,
+    /// the minimal feature distance
+    pub min_distance: f32,
+    /// the maximal feature distance
+    pub max_distance: f32,
+    /// the sum of the feature distances
+    pub distance_sum: f64,
+    /// the sum of the squared feature distances
+    pub distance_squares_sum: f64,
+}
+
+impl MetricStats {
+    fn record_distance(&mut self, distance: Option<f32>) {
+        match distance {
+            Some(d) => {
+                if self.distances == 0 {
+                    self.min_distance = d;
+                    self.max_distance = d;
+                } else {
+                    self.min_distance = self.min_distance.min(d);
+                    self.max_distance = self.max_distance.max(d);
+                }
+                self.distances += 1;
+                self.distance_sum += d as f64;
+                self.distance_squares_sum += (d as f64) * (d as f64);
+            }
+            None => self.missing_distances += 1,
+        }
+    }
+
+    /// Average latency of the metric call
+    ///
+    pub fn mean_metric_time(&self) -> Option<Duration> {
+        (self.metric_calls > 0).then(|| self.metric_time.div_f64(self.metric_calls as f64))
+    }
+
+    /// Share of the checked pairs that passed the gate
+    ///
+    pub fn gate_hit_rate(&self) -> Option<f64> {
+        (self.gate_checks > 0).then(|| self.gate_passed as f64 / self.gate_checks as f64)
+    }
+
+    /// Mean of the feature distances
+    ///
+    pub fn mean_distance(&self) -> Option<f64> {
+        (self.distances > 0).then(|| self.distance_sum / self.distances as f64)
+    }
+
+    /// Standard deviation of the feature distances
+    ///
+    pub fn distance_std(&self) -> Option<f64> {
+        let mean = self.mean_distance()?;
+        Some(
+            (self.distance_squares_sum / self.distances as f64 - mean * mean)
+                .max(0.0)
+                .sqrt(),
+        )
+    }
+}
+
+/// Metric that records the statistics of the wrapped metric calls.
+///
+/// The latency of the metric calls, the distribution of the feature distances and the gate hit rate are
+/// collected per feature class. The clones of the metric share the statistics, so the statistics of all the tracks
+/// of the store are collected together and are accessible with any clone, e.g. the metric passed to the store builder.
+///
+#[derive(Clone)]
+pub struct InstrumentedMetric<M> {
+    metric: M,
+    stats: Arc<Mutex<HashMap<u64, MetricStats>>>,
+}
+
+impl<M> InstrumentedMetric<M> {
+    /// Constructs the metric
+    ///
+    pub fn new(metric: M) -> Self {
+        Self {
+            metric,
+            stats: Arc::new(Mutex::new(HashMap::default())),
+        }
+    }
+
+    /// The statistics collected for all feature classes
+    ///
+    pub fn stats(&self) -> HashMap<u64, MetricStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// The statistics collected for the feature class
+    ///
+    pub fn class_stats(&self, feature_class: u64) -> Option<MetricStats> {
+        self.stats.lock().unwrap().get(&feature_class).cloned()
+    }
+
+    /// Drops the collected statistics
+    ///
+    pub fn reset(&self) {
+        self.stats.lock().unwrap().clear();
+    }
+}
+
+impl<TA, OA, M> ObservationMetric<TA, OA> for InstrumentedMetric<M>
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+    M: ObservationMetric<TA, OA>,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let started = Instant::now();
+        let res = self.metric.metric(mq);
+        let elapsed = started.elapsed();
+
+        let mut stats = self.stats.lock().unwrap();
+        let stats = stats.entry(mq.feature_class).or_default();
+        stats.metric_calls += 1;
+        stats.metric_time += elapsed;
+        stats.max_metric_time = stats.max_metric_time.max(elapsed);
+        stats.record_distance(res.as_ref().and_then(|(_, d)| *d));
+        res
+    }
+
+    fn gate(&self, mq: &MetricQuery<'_, TA, OA>) -> bool {
+        let passed = self.metric.gate(mq);
+        let mut stats = self.stats.lock().unwrap();
+        let stats = stats.entry(mq.feature_class).or_default();
+        stats.gate_checks += 1;
+        stats.gate_passed += passed as u64;
+        passed
+    }
+
+    fn optimize(
+        &mut self,
+        feature_class: u64,
+        merge_history: &[TrackId],
+        attributes: &mut TA,
+        observations: &mut Vec<Observation<OA>>,
+        prev_length: usize,
+        is_merge: bool,
+    ) -> Result<()> {
+        self.metric.optimize(
+            feature_class,
+            merge_history,
+            attributes,
+            observations,
+            prev_length,
+            is_merge,
+        )
+    }
+
+    fn postprocess_distances(
+        &self,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.metric.postprocess_distances(unfiltered)
+    }
+
+    fn postprocess_class_distances(
+        &self,
+        feature_class: u64,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.metric
+            .postprocess_class_distances(feature_class, unfiltered)
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        self.metric.polarity()
+    }
+
+    // the batched path skips the metric calls, so it is disabled to keep every call recorded
+    fn batch_distance(&self) -> Option<BatchDistance> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::UnboundAttrs;
+    use crate::metrics::gate::AttributeGatedMetric;
+    use crate::metrics::instrumented::InstrumentedMetric;
+    use crate::metrics::minkowski::MinkowskiMetric;
+    use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, MetricQuery};
+    use crate::EPS;
+
+    #[test]
+    fn stats_collected() {
+        let same_camera = |mq: &MetricQuery<'_, UnboundAttrs, f32>| {
+            mq.candidate_observation.attr() == mq.track_observation.attr()
+        };
+        let metric = InstrumentedMetric::new(AttributeGatedMetric::new(
+            MinkowskiMetric::new(2.0),
+            same_camera,
+        ));
+        let mut store = TrackStoreBuilder::new(2)
+            .default_attributes(UnboundAttrs)
+            .metric(metric.clone())
+            .notifier(NoopNotifier)
+            .build();
+
+        for (id, camera, x) in [(1, 1.0, 1.0), (2, 1.0, 3.0), (3, 2.0, 0.0)] {
+            store
+                .add(id, 0, Some(camera), Some(Feature::from_vec(vec![x])), None)
+                .unwrap();
+        }
+        store.add(4, 0, Some(1.0), None, None).unwrap();
+
+        let query = store
+            .new_track(10)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation_attributes(1.0)
+                    .observation(Feature::from_vec(vec![0.0]))
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let (dists, _) = store.foreign_track_distances(vec![query], 0, false);
+        assert_eq!(dists.all().len(), 3);
+
+        let stats = metric.class_stats(0).unwrap();
+        assert_eq!(stats.gate_checks, 4);
+        assert_eq!(stats.gate_passed, 3);
+        assert!((stats.gate_hit_rate().unwrap() - 0.75).abs() < EPS as f64);
+        assert_eq!(stats.metric_calls, 3);
+        assert_eq!((stats.distances, stats.missing_distances), (2, 1));
+        assert!((stats.min_distance - 1.0).abs() < EPS);
+        assert!((stats.max_distance - 3.0).abs() < EPS);
+        assert!((stats.mean_distance().unwrap() - 2.0).abs() < EPS as f64);
+        assert!((stats.distance_std().unwrap() - 1.0).abs() < 1e-6);
+        assert!(metric.class_stats(1).is_none());
+
+        metric.reset();
+        assert!(metric.stats().is_empty());
+    }
+}
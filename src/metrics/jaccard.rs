@@ -0,0 +1,143 @@
+use crate::track::{
+    MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric, TrackId,
+};
+use anyhow::Result;
+use std::cmp::Ordering;
+
+/// Sparse set observation attributes.
+///
+/// Keeps the set of element identifiers (e.g. tags or visual words) as the sorted list without duplicates,
+/// so the intersection is calculated with a single merge pass. The attribute metric object is the Jaccard
+/// similarity of two sets.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdSet(Vec<u64>);
+
+impl IdSet {
+    /// Constructs the set from identifiers, the identifiers are sorted and deduplicated
+    ///
+    pub fn new(mut ids: Vec<u64>) -> Self {
+        ids.sort_unstable();
+        ids.dedup();
+        Self(ids)
+    }
+
+    /// Sorted identifiers of the set
+    ///
+    pub fn ids(&self) -> &[u64] {
+        &self.0
+    }
+
+    /// The number of elements in the set
+    ///
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` when the set has no elements
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The number of elements present in both sets
+    ///
+    pub fn intersection_len(&self, other: &IdSet) -> usize {
+        let (mut i, mut j, mut count) = (0, 0, 0);
+        while i < self.0.len() && j < other.0.len() {
+            match self.0[i].cmp(&other.0[j]) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    count += 1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Jaccard (Tanimoto) similarity `|A ∩ B| / |A ∪ B|`
+    ///
+    /// Two empty sets are considered identical
+    ///
+    pub fn jaccard(&self, other: &IdSet) -> f32 {
+        let intersection = self.intersection_len(other);
+        let union = self.len() + other.len() - intersection;
+        if union == 0 {
+            1.0
+        } else {
+            intersection as f32 / union as f32
+        }
+    }
+}
+
+impl ObservationAttributes for IdSet {
+    type MetricObject = f32;
+
+    fn calculate_metric_object(l: &Option<&Self>, r: &Option<&Self>) -> Option<Self::MetricObject> {
+        match (l, r) {
+            (Some(l), Some(r)) => Some(l.jaccard(r)),
+            _ => None,
+        }
+    }
+}
+
+/// Jaccard distance observation metric for [IdSet](IdSet) observations.
+///
+/// The attribute metric is the Jaccard similarity, the feature distance is `1 - similarity`, so it
+/// is within `[0.0, 1.0]`. Float feature vectors of the observations are not used.
+///
+#[derive(Debug, Clone, Default)]
+pub struct JaccardMetric;
+
+impl<TA> ObservationMetric<TA, IdSet> for JaccardMetric
+where
+    TA: Send + Sync + Clone + 'static,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, IdSet>) -> MetricOutput<f32> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        let similarity = IdSet::calculate_metric_object(&e1.attr().as_ref(), &e2.attr().as_ref());
+        Some((similarity, similarity.map(|s| 1.0 - s)))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        _observations: &mut Vec<Observation<IdSet>>,
+        _prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::jaccard::{IdSet, JaccardMetric};
+    use crate::metrics::query_metric;
+    use crate::track::Observation;
+    use crate::EPS;
+
+    #[test]
+    fn jaccard_metric() {
+        let s1 = IdSet::new(vec![5, 1, 3, 3, 7]);
+        let s2 = IdSet::new(vec![3, 7, 9]);
+        assert_eq!(s1.ids(), &[1, 3, 5, 7]);
+        assert_eq!(s1.intersection_len(&s2), 2);
+        assert!((s1.jaccard(&s2) - 0.4).abs() < EPS);
+        assert_eq!(IdSet::default().jaccard(&IdSet::default()), 1.0);
+        assert_eq!(s1.jaccard(&IdSet::default()), 0.0);
+
+        let (o1, o2) = (
+            Observation::new(Some(s1), None),
+            Observation::new(Some(s2), None),
+        );
+        let (similarity, distance) = query_metric(&JaccardMetric, 0, &o1, &o2).unwrap();
+        assert!((similarity.unwrap() - 0.4).abs() < EPS);
+        assert!((distance.unwrap() - 0.6).abs() < EPS);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::track::utils::FromVec;
+use crate::track::{
+    Feature, MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric,
+    TrackId, FEATURE_LANES_SIZE,
+};
+use crate::Errors;
+use anyhow::Result;
+use nalgebra::{DMatrix, DVector};
+use std::sync::Arc;
+
+/// Mahalanobis distance observation metric.
+///
+/// The feature distance is calculated as `sqrt((x - y)^T * P * (x - y))`, where `P` is the precision
+/// (inverse covariance) matrix. The metric accounts for the correlated dimensions of the features,
+/// with the identity precision matrix it is the same as the euclidean distance.
+///
+/// Only the first `dimensions` values of the features participate. When one of the features is shorter
+/// than the precision matrix, the feature distance is not calculated.
+///
+#[derive(Debug, Clone)]
+pub struct MahalanobisMetric {
+    precision: Arc<DMatrix<f32>>,
+}
+
+impl MahalanobisMetric {
+    /// Constructs the metric from the precision (inverse covariance) matrix
+    ///
+    /// # Arguments
+    /// * `precision` - square precision matrix
+    ///
+    pub fn from_precision(precision: DMatrix<f32>) -> Result<Self> {
+        if !precision.is_square() {
+            return Err(Errors::DimensionsMismatch(precision.nrows(), precision.ncols()).into());
+        }
+        Ok(Self {
+            precision: Arc::new(precision),
+        })
+    }
+
+    /// Constructs the metric from the covariance matrix
+    ///
+    /// # Arguments
+    /// * `covariance` - square covariance matrix, must be invertible
+    ///
+    pub fn from_covariance(covariance: DMatrix<f32>) -> Result<Self> {
+        if !covariance.is_square() {
+            return Err(Errors::DimensionsMismatch(covariance.nrows(), covariance.ncols()).into());
+        }
+        let precision = covariance.try_inverse().ok_or(Errors::SingularMatrix)?;
+        Self::from_precision(precision)
+    }
+
+    /// Estimates the covariance matrix from the batch of training features and constructs the metric
+    ///
+    /// # Arguments
+    /// * `features` - training features, at least two
+    /// * `dimensions` - the number of feature dimensions that participate
+    /// * `regularization` - the value added to the diagonal of the estimated covariance, keeps the matrix
+    ///   invertible when the dimensions are degenerate or the batch is small
+    ///
+    pub fn estimate(features: &[Feature], dimensions: usize, regularization: f32) -> Result<Self> {
+        let covariance = estimate_covariance(features, dimensions)?
+            + DMatrix::<f32>::identity(dimensions, dimensions) * regularization;
+        Self::from_covariance(covariance)
+    }
+
+    /// The number of feature dimensions that participate
+    ///
+    pub fn dimensions(&self) -> usize {
+        self.precision.nrows()
+    }
+
+    /// The precision matrix used by the metric
+    ///
+    pub fn precision(&self) -> &DMatrix<f32> {
+        &self.precision
+    }
+
+    /// Calculates the distance between two features
+    ///
+    /// Returns `None` when one of the features is shorter than the precision matrix
+    ///
+    pub fn distance(&self, f1: &Feature, f2: &Feature) -> Option<f32> {
+        let (x, y) = (
+            to_vector(f1, self.dimensions())?,
+            to_vector(f2, self.dimensions())?,
+        );
+        let diff = x - y;
+        Some(diff.dot(&(self.precision.as_ref() * &diff)).max(0.0).sqrt())
+    }
+}
+
+/// Estimates the unbiased sample covariance matrix of the features
+///
+/// # Arguments
+/// * `features` - the samples, at least two
+/// * `dimensions` - the number of feature dimensions that participate
+///
+pub fn estimate_covariance(features: &[Feature], dimensions: usize) -> Result<DMatrix<f32>> {
+    if features.len() < 2 {
+        return Err(Errors::NotEnoughSamples(2, features.len()).into());
+    }
+
+    let samples = features
+        .iter()
+        .map(|f| {
+            to_vector(f, dimensions).ok_or_else(|| {
+                Errors::DimensionsMismatch(dimensions, f.len() * FEATURE_LANES_SIZE).into()
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mean = samples
+        .iter()
+        .fold(DVector::<f32>::zeros(dimensions), |acc, s| acc + s)
+        / samples.len() as f32;
+
+    let covariance =
+        samples
+            .iter()
+            .fold(DMatrix::<f32>::zeros(dimensions, dimensions), |acc, s| {
+                let centered = s - &mean;
+                acc + &centered * centered.transpose()
+            });
+
+    Ok(covariance / (samples.len() - 1) as f32)
+}
+
+fn to_vector(f: &Feature, dimensions: usize) -> Option<DVector<f32>> {
+    let values = Vec::<f32>::from_vec(f);
+    if values.len() < dimensions {
+        return None;
+    }
+    Some(DVector::from_column_slice(&values[..dimensions]))
+}
+
+impl<TA, OA> ObservationMetric<TA, OA> for MahalanobisMetric
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        Some((
+            OA::calculate_metric_object(&e1.attr().as_ref(), &e2.attr().as_ref()),
+            match (e1.feature().as_ref(), e2.feature().as_ref()) {
+                (Some(x), Some(y)) => self.distance(x, y),
+                _ => None,
+            },
+        ))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        _observations: &mut Vec<Observation<OA>>,
+        _prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::distance::euclidean;
+    use crate::metrics::mahalanobis::{estimate_covariance, MahalanobisMetric};
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+    use crate::EPS;
+    use nalgebra::DMatrix;
+
+    #[test]
+    fn mahalanobis_distance() {
+        let v1 = Feature::from_vec(vec![1.0, 2.0, 0.0]);
+        let v2 = Feature::from_vec(vec![3.0, 1.0, 5.0]);
+
+        let metric = MahalanobisMetric::from_precision(DMatrix::identity(3, 3)).unwrap();
+        assert!((metric.distance(&v1, &v2).unwrap() - euclidean(&v1, &v2)).abs() < EPS);
+
+        let metric =
+            MahalanobisMetric::from_covariance(DMatrix::from_diagonal_element(2, 2, 4.0)).unwrap();
+        assert!((metric.distance(&v1, &v2).unwrap() - 5.0f32.sqrt() / 2.0).abs() < EPS);
+
+        let metric = MahalanobisMetric::from_precision(DMatrix::identity(9, 9)).unwrap();
+        assert!(metric.distance(&v1, &v2).is_none());
+
+        assert!(MahalanobisMetric::from_covariance(DMatrix::zeros(2, 2)).is_err());
+        assert!(MahalanobisMetric::from_precision(DMatrix::zeros(2, 3)).is_err());
+    }
+
+    #[test]
+    fn estimated_covariance() {
+        let features = [
+            Feature::from_vec(vec![1.0, 2.0]),
+            Feature::from_vec(vec![2.0, 4.0]),
+            Feature::from_vec(vec![3.0, 6.0]),
+        ];
+        let covariance = estimate_covariance(&features, 2).unwrap();
+        assert_eq!(
+            covariance,
+            DMatrix::from_row_slice(2, 2, &[1.0, 2.0, 2.0, 4.0])
+        );
+
+        // the dimensions are fully correlated, the covariance is singular without the regularization
+        assert!(MahalanobisMetric::estimate(&features, 2, 0.0).is_err());
+        let metric = MahalanobisMetric::estimate(&features, 2, 0.01).unwrap();
+
+        // moving along the correlation is cheaper than moving across it
+        let along = metric
+            .distance(&features[0], &Feature::from_vec(vec![2.0, 4.0]))
+            .unwrap();
+        let across = metric
+            .distance(&features[0], &Feature::from_vec(vec![2.0, 1.0]))
+            .unwrap();
+        assert!(along < across);
+
+        assert!(estimate_covariance(&features[..1], 2).is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use crate::distance::{cosine, euclidean};
+use crate::track::{
+    Feature, MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric,
+    TrackId,
+};
+use crate::Errors;
+use anyhow::Result;
+use memmap2::MmapMut;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+const MAGIC: &[u8; 8] = b"SIMFEAT1";
+const HEADER: usize = 24;
+const MIN_CAPACITY: usize = 1024;
+
+/// The record of the feature vector in [MappedFeatures](MappedFeatures)
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MappedFeature(pub u64);
+
+impl ObservationAttributes for MappedFeature {
+    type MetricObject = ();
+
+    fn calculate_metric_object(
+        _l: &Option<&Self>,
+        _r: &Option<&Self>,
+    ) -> Option<Self::MetricObject> {
+        None
+    }
+}
+
+/// Append-only file of the feature vectors of the same dimensions mapped to the memory
+///
+/// The vectors are kept as the little-endian `f32` records after the header with the number of the dimensions and
+/// the number of the records, so the file is reopened with [open](MappedFeatures::open) without reading the
+/// vectors. The pages of the file are loaded and dropped by OS, so the file may be larger than RAM. The file grows
+/// by doubling, the records of the removed observations are not reclaimed.
+///
+pub struct MappedFeatures {
+    file: File,
+    dimensions: usize,
+    map: RwLock<MmapMut>,
+}
+
+impl MappedFeatures {
+    /// Creates the empty file, the existing file is truncated
+    ///
+    /// # Arguments
+    /// * `path` - the path of the file
+    /// * `dimensions` - the number of the dimensions of the vectors
+    ///
+    pub fn create(path: impl AsRef<Path>, dimensions: usize) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len((HEADER + MIN_CAPACITY * dimensions * 4) as u64)?;
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        map[..8].copy_from_slice(MAGIC);
+        map[8..16].copy_from_slice(&(dimensions as u64).to_le_bytes());
+        map[16..24].copy_from_slice(&0u64.to_le_bytes());
+        Ok(Self {
+            file,
+            dimensions,
+            map: RwLock::new(map),
+        })
+    }
+
+    /// Opens the file created with [create](MappedFeatures::create)
+    ///
+    /// # Returns
+    /// * `Ok(features)` - the features
+    /// * `Err(Errors::InvalidFeatureFile(reason))` - when the file is not the feature file or is damaged
+    ///
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        if map.len() < HEADER || &map[..8] != MAGIC {
+            return Err(Errors::InvalidFeatureFile("unknown format".to_string()).into());
+        }
+        let dimensions = u64::from_le_bytes(map[8..16].try_into()?) as usize;
+        let len = u64::from_le_bytes(map[16..24].try_into()?) as usize;
+        if HEADER + len * dimensions * 4 > map.len() {
+            return Err(Errors::InvalidFeatureFile(format!("truncated, records={}", len)).into());
+        }
+        Ok(Self {
+            file,
+            dimensions,
+            map: RwLock::new(map),
+        })
+    }
+
+    /// The number of the dimensions of the vectors
+    ///
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// The number of the vectors
+    ///
+    pub fn len(&self) -> usize {
+        Self::records(&self.map.read().unwrap())
+    }
+
+    /// Returns `true` when the file has no vectors
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn records(map: &MmapMut) -> usize {
+        u64::from_le_bytes(map[16..24].try_into().unwrap()) as usize
+    }
+
+    /// Appends the vector
+    ///
+    /// # Returns
+    /// * `Ok(record)` - the record of the vector
+    /// * `Err(Errors::DimensionsMismatch(expected, actual))` - when the vector has the other dimensions
+    ///
+    pub fn push(&self, values: &[f32]) -> Result<MappedFeature> {
+        if values.len() != self.dimensions {
+            return Err(Errors::DimensionsMismatch(self.dimensions, values.len()).into());
+        }
+        let record = self.dimensions * 4;
+        let mut map = self.map.write().unwrap();
+        let len = Self::records(&map);
+        if HEADER + (len + 1) * record > map.len() {
+            map.flush()?;
+            let capacity = (len * 2).max(MIN_CAPACITY);
+            self.file.set_len((HEADER + capacity * record) as u64)?;
+            *map = unsafe { MmapMut::map_mut(&self.file)? };
+        }
+        let start = HEADER + len * record;
+        for (bytes, value) in map[start..start + record].chunks_exact_mut(4).zip(values) {
+            bytes.copy_from_slice(&value.to_le_bytes());
+        }
+        map[16..24].copy_from_slice(&(len as u64 + 1).to_le_bytes());
+        Ok(MappedFeature(len as u64))
+    }
+
+    /// Reads the vector
+    ///
+    pub fn get(&self, feature: MappedFeature) -> Option<Vec<f32>> {
+        let map = self.map.read().unwrap();
+        self.values(&map, feature).map(|values| values.collect())
+    }
+
+    /// Flushes the changes of the file to the disk
+    ///
+    pub fn flush(&self) -> Result<()> {
+        Ok(self.map.read().unwrap().flush()?)
+    }
+
+    fn values<'a>(
+        &self,
+        map: &'a MmapMut,
+        feature: MappedFeature,
+    ) -> Option<impl Iterator<Item = f32> + 'a> {
+        let index = feature.0 as usize;
+        if index >= Self::records(map) {
+            return None;
+        }
+        let record = self.dimensions * 4;
+        let start = HEADER + index * record;
+        Some(
+            map[start..start + record]
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        )
+    }
+}
+
+/// Distance calculated by [MappedMetric](MappedMetric)
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappedDistance {
+    /// euclidean distance
+    Euclidean,
+    /// `1 - cosine_similarity`
+    Cosine,
+}
+
+impl MappedDistance {
+    fn calculate(&self, left: impl Iterator<Item = f32>, right: impl Iterator<Item = f32>) -> f32 {
+        let (ab, aa, bb, squared) = left.zip(right).fold(
+            (0.0_f32, 0.0_f32, 0.0_f32, 0.0_f32),
+            |(ab, aa, bb, squared), (a, b)| {
+                (
+                    ab + a * b,
+                    aa + a * a,
+                    bb + b * b,
+                    squared + (a - b) * (a - b),
+                )
+            },
+        );
+        match self {
+            MappedDistance::Euclidean => squared.sqrt(),
+            MappedDistance::Cosine => 1.0 - ab / (aa * bb).sqrt(),
+        }
+    }
+}
+
+/// Observation metric that keeps the features of the selected feature classes in the memory-mapped file.
+///
+/// When the observation of the selected class is added with the float feature vector, the metric appends it to
+/// [MappedFeatures](MappedFeatures) and keeps only its [record](MappedFeature) in the observation attributes, so
+/// the vectors don't take the heap of the store. The observations of other classes keep the float vectors. The
+/// distance is calculated on the mapped vectors when the observations have them, the query observations which
+/// are not added to the store keep the float vectors. The clones of the metric share the file.
+///
+/// The records survive the restart: the tracks restored from the snapshot keep their records and the file is
+/// reopened with [MappedFeatures::open](MappedFeatures::open).
+///
+#[derive(Clone)]
+pub struct MappedMetric {
+    features: Arc<MappedFeatures>,
+    distance: MappedDistance,
+    mapped_classes: HashSet<u64>,
+}
+
+impl MappedMetric {
+    /// Constructs the metric that keeps all the features on the heap
+    ///
+    pub fn new(features: MappedFeatures, distance: MappedDistance) -> Self {
+        Self {
+            features: Arc::new(features),
+            distance,
+            mapped_classes: HashSet::default(),
+        }
+    }
+
+    /// Selects the feature classes which features are kept in the file
+    ///
+    pub fn with_mapped_classes(mut self, classes: impl IntoIterator<Item = u64>) -> Self {
+        self.mapped_classes = classes.into_iter().collect();
+        self
+    }
+
+    /// Returns `true` when the features of the class are kept in the file
+    ///
+    pub fn is_mapped(&self, feature_class: u64) -> bool {
+        self.mapped_classes.contains(&feature_class)
+    }
+
+    /// The file of the features
+    ///
+    pub fn features(&self) -> &MappedFeatures {
+        &self.features
+    }
+}
+
+fn heap_values(feature: &Feature) -> impl Iterator<Item = f32> + '_ {
+    feature.iter().flat_map(|block| *block.as_array_ref())
+}
+
+impl<TA> ObservationMetric<TA, MappedFeature> for MappedMetric
+where
+    TA: Send + Sync + Clone + 'static,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, MappedFeature>) -> MetricOutput<()> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        if let (None, None) = (e1.attr(), e2.attr()) {
+            let distance = match (e1.feature().as_ref(), e2.feature().as_ref()) {
+                (Some(x), Some(y)) => Some(match self.distance {
+                    MappedDistance::Euclidean => euclidean(x, y),
+                    MappedDistance::Cosine => 1.0 - cosine(x, y),
+                }),
+                _ => None,
+            };
+            return Some((None, distance));
+        }
+
+        let map = self.features.map.read().unwrap();
+        let distance = match (
+            (e1.attr(), e1.feature().as_ref()),
+            (e2.attr(), e2.feature().as_ref()),
+        ) {
+            ((Some(x), _), (Some(y), _)) => self
+                .features
+                .values(&map, *x)
+                .zip(self.features.values(&map, *y))
+                .map(|(x, y)| self.distance.calculate(x, y)),
+            ((Some(x), _), (None, Some(y))) => self
+                .features
+                .values(&map, *x)
+                .map(|x| self.distance.calculate(x, heap_values(y))),
+            ((None, Some(x)), (Some(y), _)) => self
+                .features
+                .values(&map, *y)
+                .map(|y| self.distance.calculate(heap_values(x), y)),
+            _ => None,
+        };
+        Some((None, distance))
+    }
+
+    fn optimize(
+        &mut self,
+        feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        observations: &mut Vec<Observation<MappedFeature>>,
+        prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        if !self.is_mapped(feature_class) {
+            return Ok(());
+        }
+        let dimensions = self.features.dimensions();
+        for o in observations.iter_mut().skip(prev_length) {
+            if let Some(f) = o.feature() {
+                // the features are padded to the blocks of 8 values
+                if f.len() != (dimensions + 7) / 8 {
+                    return Err(Errors::DimensionsMismatch(dimensions, f.len() * 8).into());
+                }
+                let values = heap_values(f).take(dimensions).collect::<Vec<_>>();
+                *o.attr_mut() = Some(self.features.push(&values)?);
+                *o.feature_mut() = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::SimpleAttrs;
+    use crate::metrics::mapped::{MappedDistance, MappedFeature, MappedFeatures, MappedMetric};
+    use crate::metrics::query_metric;
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, Observation, ObservationMetric};
+    use crate::EPS;
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+
+    #[test]
+    fn mapped_file() {
+        let path = temp_dir().join(format!("similari-mapped-{}.bin", std::process::id()));
+        let features = MappedFeatures::create(&path, 3).unwrap();
+        assert!(features.push(&[1.0, 2.0]).is_err());
+        for i in 0..2000 {
+            assert_eq!(
+                features.push(&[i as f32, 0.5, -1.0]).unwrap(),
+                MappedFeature(i)
+            );
+        }
+        features.flush().unwrap();
+        drop(features);
+
+        let features = MappedFeatures::open(&path).unwrap();
+        assert_eq!((features.len(), features.dimensions()), (2000, 3));
+        assert_eq!(
+            features.get(MappedFeature(1500)),
+            Some(vec![1500.0, 0.5, -1.0])
+        );
+        assert_eq!(features.get(MappedFeature(2000)), None);
+        drop(features);
+
+        std::fs::write(&path, b"not a feature file").unwrap();
+        assert!(MappedFeatures::open(&path).is_err());
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn per_class_storage() {
+        let path = temp_dir().join(format!("similari-mapped-metric-{}.bin", std::process::id()));
+        let features = MappedFeatures::create(&path, 3).unwrap();
+        let mut metric =
+            MappedMetric::new(features, MappedDistance::Euclidean).with_mapped_classes([1]);
+        let feature = |values: Vec<f32>| Some(Feature::from_vec(values));
+        let mut attrs = SimpleAttrs::default();
+
+        let mut class0 = vec![Observation::new(None, feature(vec![1.0, 2.0, 2.0]))];
+        metric
+            .optimize(0, &[], &mut attrs, &mut class0, 0, false)
+            .unwrap();
+        assert!(class0[0].feature().is_some() && class0[0].attr().is_none());
+
+        let mut class1 = vec![
+            Observation::new(None, feature(vec![1.0, 2.0, 2.0])),
+            Observation::new(None, feature(vec![0.0; 3])),
+        ];
+        metric
+            .optimize(1, &[], &mut attrs, &mut class1, 0, false)
+            .unwrap();
+        assert!(class1.iter().all(|o| o.feature().is_none()));
+        assert_eq!(class1[1].attr(), &Some(MappedFeature(1)));
+        assert_eq!(metric.features().len(), 2);
+
+        let mut wrong = vec![Observation::new(None, feature(vec![0.0; 9]))];
+        assert!(metric
+            .optimize(1, &[], &mut attrs, &mut wrong, 0, false)
+            .is_err());
+
+        let zero = Observation::new(None, feature(vec![0.0; 3]));
+        for (o1, o2) in [
+            (&class0[0], &zero),
+            (&class1[0], &zero),
+            (&zero, &class1[0]),
+            (&class1[0], &class1[1]),
+        ] {
+            let (_, distance) = query_metric(&metric, 1, o1, o2).unwrap();
+            assert!((distance.unwrap() - 3.0).abs() < EPS);
+        }
+
+        let metric = MappedMetric {
+            distance: MappedDistance::Cosine,
+            ..metric
+        };
+        let (_, distance) = query_metric(&metric, 1, &class1[0], &class0[0]).unwrap();
+        assert!(distance.unwrap().abs() < EPS);
+        drop(metric);
+        remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+use crate::distance::matrix::BatchDistance;
+use crate::distance::minkowski;
+use crate::track::{
+    MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric, TrackId,
+};
+use anyhow::Result;
+
+/// Minkowski (Lp) distance observation metric.
+///
+/// The feature distance is `(sum |x_i - y_i|^p)^(1/p)`, the order `p` is configurable, so the same
+/// metric covers the manhattan (`p = 1`), euclidean (`p = 2`) and chebyshev (`p = ∞`) distances.
+///
+#[derive(Debug, Clone)]
+pub struct MinkowskiMetric {
+    p: f32,
+}
+
+impl MinkowskiMetric {
+    /// Constructs the metric
+    ///
+    /// # Arguments
+    /// * `p` - the order of the metric, at least `1.0`, `f32::INFINITY` is permitted
+    ///
+    pub fn new(p: f32) -> Self {
+        assert!(p >= 1.0, "The order of the metric must be at least 1.0");
+        Self { p }
+    }
+
+    /// Manhattan (L1) distance metric
+    ///
+    pub fn manhattan() -> Self {
+        Self::new(1.0)
+    }
+
+    /// Chebyshev (L∞) distance metric
+    ///
+    pub fn chebyshev() -> Self {
+        Self::new(f32::INFINITY)
+    }
+
+    /// The order of the metric
+    ///
+    pub fn p(&self) -> f32 {
+        self.p
+    }
+}
+
+impl<TA, OA> ObservationMetric<TA, OA> for MinkowskiMetric
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        Some((
+            OA::calculate_metric_object(&e1.attr().as_ref(), &e2.attr().as_ref()),
+            match (e1.feature().as_ref(), e2.feature().as_ref()) {
+                (Some(x), Some(y)) => Some(minkowski(x, y, self.p)),
+                _ => None,
+            },
+        ))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        _observations: &mut Vec<Observation<OA>>,
+        _prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn batch_distance(&self) -> Option<BatchDistance> {
+        (self.p == 2.0).then_some(BatchDistance::Euclidean)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::feature_distance;
+    use crate::metrics::minkowski::MinkowskiMetric;
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, Observation};
+    use crate::EPS;
+
+    #[test]
+    fn minkowski_metric() {
+        let o1 = Observation::<f32>::new(None, Some(Feature::from_vec(vec![0.0, 0.0])));
+        let o2 = Observation::<f32>::new(None, Some(Feature::from_vec(vec![3.0, 4.0])));
+
+        for (metric, expected) in [
+            (MinkowskiMetric::manhattan(), 7.0),
+            (MinkowskiMetric::new(2.0), 5.0),
+            (MinkowskiMetric::chebyshev(), 4.0),
+        ] {
+            assert!((feature_distance(&metric, &o1, &o2) - expected).abs() < EPS);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use crate::track::utils::FromVec;
+use crate::track::{
+    MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, TrackId,
+};
+use anyhow::Result;
+
+/// int8-quantized feature vector.
+///
+/// The vector is quantized with the affine scheme `x = scale * (q - zero_point)`, the quantization
+/// parameters are calculated per vector from its min and max values, so zero is represented exactly.
+/// The vector takes 4 times less memory than the float feature vector.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuantizedFeature {
+    values: Vec<i8>,
+    scale: f32,
+    zero_point: i8,
+}
+
+impl QuantizedFeature {
+    /// Constructs the vector from the quantized values and the quantization parameters
+    ///
+    pub fn new(values: Vec<i8>, scale: f32, zero_point: i8) -> Self {
+        Self {
+            values,
+            scale,
+            zero_point,
+        }
+    }
+
+    /// Quantizes the float vector
+    ///
+    pub fn quantize(vec: &[f32]) -> Self {
+        let (min, max) = vec.iter().fold((0.0_f32, 0.0_f32), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+        let scale = (max - min) / 255.0;
+        if scale == 0.0 {
+            return Self::new(vec![0; vec.len()], 1.0, 0);
+        }
+        let zero_point = (-128.0 - min / scale).round().clamp(-128.0, 127.0);
+        let values = vec
+            .iter()
+            .map(|v| (v / scale + zero_point).round().clamp(-128.0, 127.0) as i8)
+            .collect();
+        Self::new(values, scale, zero_point as i8)
+    }
+
+    /// Restores the float vector
+    ///
+    pub fn dequantize(&self) -> Vec<f32> {
+        self.values
+            .iter()
+            .map(|q| self.scale * (*q as i32 - self.zero_point as i32) as f32)
+            .collect()
+    }
+
+    /// Quantized values
+    ///
+    pub fn values(&self) -> &[i8] {
+        &self.values
+    }
+
+    /// Quantization scale
+    ///
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Quantization zero point
+    ///
+    pub fn zero_point(&self) -> i8 {
+        self.zero_point
+    }
+
+    /// Calculates `(sum a*b, sum a*a, sum b*b)` of the zero-point shifted values with integer arithmetic
+    ///
+    /// When the vectors lengths don't match, the longer vector is truncated to shorter one
+    ///
+    fn products(&self, other: &QuantizedFeature) -> (i64, i64, i64) {
+        let (z1, z2) = (self.zero_point as i32, other.zero_point as i32);
+        self.values.iter().zip(other.values.iter()).fold(
+            (0_i64, 0_i64, 0_i64),
+            |(ab, aa, bb), (a, b)| {
+                let (a, b) = (*a as i32 - z1, *b as i32 - z2);
+                (
+                    ab + (a * b) as i64,
+                    aa + (a * a) as i64,
+                    bb + (b * b) as i64,
+                )
+            },
+        )
+    }
+
+    /// Inner product of two quantized vectors
+    ///
+    pub fn dot(&self, other: &QuantizedFeature) -> f32 {
+        let (ab, _, _) = self.products(other);
+        self.scale * other.scale * ab as f32
+    }
+
+    /// Euclidean distance between two quantized vectors
+    ///
+    pub fn euclidean(&self, other: &QuantizedFeature) -> f32 {
+        let (ab, aa, bb) = self.products(other);
+        let (s1, s2) = (self.scale as f64, other.scale as f64);
+        let sq = s1 * s1 * aa as f64 + s2 * s2 * bb as f64 - 2.0 * s1 * s2 * ab as f64;
+        sq.max(0.0).sqrt() as f32
+    }
+
+    /// Cosine similarity of two quantized vectors, the scales are eliminated
+    ///
+    pub fn cosine(&self, other: &QuantizedFeature) -> f32 {
+        let (ab, aa, bb) = self.products(other);
+        (ab as f64 / ((aa as f64) * (bb as f64)).sqrt()) as f32
+    }
+}
+
+impl ObservationAttributes for QuantizedFeature {
+    type MetricObject = ();
+
+    fn calculate_metric_object(
+        _l: &Option<&Self>,
+        _r: &Option<&Self>,
+    ) -> Option<Self::MetricObject> {
+        None
+    }
+}
+
+/// Distance calculated by [QuantizedMetric](QuantizedMetric)
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantizedDistance {
+    /// euclidean distance
+    Euclidean,
+    /// `1 - cosine_similarity`
+    Cosine,
+    /// inner product, has the similarity polarity
+    InnerProduct,
+}
+
+/// Quantization-aware observation metric for [QuantizedFeature](QuantizedFeature) observations.
+///
+/// The metric calculates the distances between the quantized vectors kept in the observation attributes
+/// with integer accumulation. When the observation is added with the float feature vector, the metric quantizes
+/// it into the observation attributes and drops the float vector, so the track keeps only the quantized one.
+///
+#[derive(Debug, Clone)]
+pub struct QuantizedMetric {
+    distance: QuantizedDistance,
+}
+
+impl QuantizedMetric {
+    /// Constructs the metric
+    ///
+    pub fn new(distance: QuantizedDistance) -> Self {
+        Self { distance }
+    }
+}
+
+impl<TA> ObservationMetric<TA, QuantizedFeature> for QuantizedMetric
+where
+    TA: Send + Sync + Clone + 'static,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, QuantizedFeature>) -> MetricOutput<()> {
+        let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+        Some((
+            None,
+            match (e1.attr().as_ref(), e2.attr().as_ref()) {
+                (Some(x), Some(y)) => Some(match self.distance {
+                    QuantizedDistance::Euclidean => x.euclidean(y),
+                    QuantizedDistance::Cosine => 1.0 - x.cosine(y),
+                    QuantizedDistance::InnerProduct => x.dot(y),
+                }),
+                _ => None,
+            },
+        ))
+    }
+
+    fn optimize(
+        &mut self,
+        _feature_class: u64,
+        _merge_history: &[TrackId],
+        _attributes: &mut TA,
+        observations: &mut Vec<Observation<QuantizedFeature>>,
+        prev_length: usize,
+        _is_merge: bool,
+    ) -> Result<()> {
+        for o in observations.iter_mut().skip(prev_length) {
+            if let Some(f) = o.feature_mut().take() {
+                if o.attr().is_none() {
+                    *o.attr_mut() = Some(QuantizedFeature::quantize(&Vec::from_vec(&f)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        match self.distance {
+            QuantizedDistance::InnerProduct => MetricPolarity::Similarity,
+            _ => MetricPolarity::Distance,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::distance::{cosine, dot, euclidean};
+    use crate::examples::SimpleAttrs;
+    use crate::metrics::quantized::{QuantizedDistance, QuantizedFeature, QuantizedMetric};
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, Observation, ObservationMetric};
+
+    #[test]
+    fn quantized_distances() {
+        let v1 = (0..64).map(|i| (i as f32 * 0.37).sin()).collect::<Vec<_>>();
+        let v2 = (0..64)
+            .map(|i| (i as f32 * 0.11).cos() * 0.5)
+            .collect::<Vec<_>>();
+        let (q1, q2) = (
+            QuantizedFeature::quantize(&v1),
+            QuantizedFeature::quantize(&v2),
+        );
+
+        assert!(q1
+            .dequantize()
+            .iter()
+            .zip(&v1)
+            .all(|(q, v)| (q - v).abs() <= q1.scale()));
+
+        let (f1, f2) = (Feature::from_vec(v1), Feature::from_vec(v2));
+        assert!((q1.euclidean(&q2) - euclidean(&f1, &f2)).abs() < 0.02);
+        assert!((q1.cosine(&q2) - cosine(&f1, &f2)).abs() < 0.01);
+        assert!((q1.dot(&q2) - dot(&f1, &f2)).abs() < 0.05);
+
+        let zero = QuantizedFeature::quantize(&[0.0; 4]);
+        assert_eq!(zero.dequantize(), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn quantized_storage() {
+        let mut metric = QuantizedMetric::new(QuantizedDistance::Euclidean);
+        let mut observations = vec![Observation::new(
+            None,
+            Some(Feature::from_vec(vec![1.0, -2.0, 0.5])),
+        )];
+        metric
+            .optimize(
+                0,
+                &[],
+                &mut SimpleAttrs::default(),
+                &mut observations,
+                0,
+                false,
+            )
+            .unwrap();
+
+        assert!(observations[0].feature().is_none());
+        let q = observations[0].attr().as_ref().unwrap();
+        assert_eq!(q.values().len(), 8);
+        assert!((q.dequantize()[1] + 2.0).abs() <= q.scale());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::track::{
+    Feature, MetricOutput, MetricPolarity, MetricQuery, Observation, ObservationAttributes,
+    ObservationMetric, TrackId,
+};
+use anyhow::Result;
+
+/// Pair scorer that runs the ONNX model
+///
+#[cfg(feature = "onnx")]
+pub mod onnx;
+
+/// Learned similarity model over feature pairs.
+///
+/// The trait is the integration point for model runtimes: an implementation runs the model (e.g. a small
+/// ONNX metric-learning head, look at `OnnxScorer` of the `onnx` feature) over the pair of features and returns
+/// the similarity in `[0.0, 1.0]`.
+///
+pub trait PairScorer: Send + Sync + Clone + 'static {
+    /// Calculates the learned similarity of the features, `None` when the model can't score the pair
+    ///
+    fn score(&self, left: &Feature, right: &Feature) -> Option<f32>;
+}
+
+/// Re-ranking metric that refines the cheap first-pass metric with the learned similarity.
+///
+/// It calculates the distance as:
+/// 1. calculates the first-pass distance with the `first_pass` metric
+/// 2. rejects the pair when the first-pass distance is missing or doesn't pass `max_first_pass_distance` in the
+///    [polarity](ObservationMetric::polarity) sense of the first-pass metric, e.g. is lower than it for the similarities
+/// 3. returns `1 - learned_similarity` as the feature distance for the remaining candidates
+///
+/// So the expensive model runs only for the candidates that the cheap metric considers close. When the
+/// scorer can't score the pair, the feature distance is `None`, so the distances of the different scales are not
+/// mixed. The attribute metric and the observations optimization come from the first-pass metric.
+///
+/// The metric re-ranks every candidate within the first-pass threshold, use
+/// [TrackStore::rerank_top_k](crate::store::TrackStore::rerank_top_k) to re-rank only the `k` closest tracks.
+///
+#[derive(Clone)]
+pub struct RerankMetric<M, S> {
+    first_pass: M,
+    scorer: S,
+    max_first_pass_distance: f32,
+}
+
+impl<M, S> RerankMetric<M, S>
+where
+    S: PairScorer,
+{
+    /// Constructs the metric
+    ///
+    /// # Arguments
+    /// * `first_pass` - the cheap metric that selects the candidates
+    /// * `scorer` - the model that calculates the learned similarity of the candidates
+    /// * `max_first_pass_distance` - max first-pass distance of the candidates that are re-ranked
+    ///
+    pub fn new(first_pass: M, scorer: S, max_first_pass_distance: f32) -> Self {
+        Self {
+            first_pass,
+            scorer,
+            max_first_pass_distance,
+        }
+    }
+}
+
+impl<TA, OA, M, S> ObservationMetric<TA, OA> for RerankMetric<M, S>
+where
+    TA: Send + Sync + Clone + 'static,
+    OA: ObservationAttributes,
+    M: ObservationMetric<TA, OA>,
+    S: PairScorer,
+{
+    fn metric(&self, mq: &MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject> {
+        let (attribute_metric, first_pass) = self.first_pass.metric(mq)?;
+        let polarity = self.first_pass.polarity();
+        first_pass.filter(|d| polarity.passes(*d, self.max_first_pass_distance))?;
+        let learned = match (
+            mq.candidate_observation.feature().as_ref(),
+            mq.track_observation.feature().as_ref(),
+        ) {
+            (Some(l), Some(r)) => self.scorer.score(l, r).map(|s| 1.0 - s),
+            _ => None,
+        };
+        Some((attribute_metric, learned))
+    }
+
+    fn gate(&self, mq: &MetricQuery<'_, TA, OA>) -> bool {
+        self.first_pass.gate(mq)
+    }
+
+    fn optimize(
+        &mut self,
+        feature_class: u64,
+        merge_history: &[TrackId],
+        attributes: &mut TA,
+        observations: &mut Vec<Observation<OA>>,
+        prev_length: usize,
+        is_merge: bool,
+    ) -> Result<()> {
+        self.first_pass.optimize(
+            feature_class,
+            merge_history,
+            attributes,
+            observations,
+            prev_length,
+            is_merge,
+        )
+    }
+
+    fn polarity(&self) -> MetricPolarity {
+        MetricPolarity::Distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::inner_product::InnerProductMetric;
+    use crate::metrics::minkowski::MinkowskiMetric;
+    use crate::metrics::query_metric;
+    use crate::metrics::rerank::{PairScorer, RerankMetric};
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, Observation};
+    use crate::EPS;
+
+    #[derive(Clone)]
+    struct FirstLaneScorer;
+
+    impl PairScorer for FirstLaneScorer {
+        fn score(&self, left: &Feature, right: &Feature) -> Option<f32> {
+            let (l, r) = (left[0].as_array_ref()[0], right[0].as_array_ref()[0]);
+            (l >= 0.0 && r >= 0.0).then(|| 1.0 - (l - r).abs())
+        }
+    }
+
+    #[test]
+    fn rerank_candidates() {
+        let metric = RerankMetric::new(MinkowskiMetric::chebyshev(), FirstLaneScorer, 0.5);
+        let distance = |l: Vec<f32>, r: Vec<f32>| {
+            let (o1, o2) = (
+                Observation::<f32>::new(None, Some(Feature::from_vec(l))),
+                Observation::<f32>::new(None, Some(Feature::from_vec(r))),
+            );
+            query_metric(&metric, 0, &o1, &o2).map(|(_, d)| d)
+        };
+
+        // re-ranked with the learned similarity
+        let scored = distance(vec![0.5, 0.0], vec![0.4, 0.3]).unwrap().unwrap();
+        assert!((scored - 0.1).abs() < EPS);
+        // rejected by the first pass
+        assert!(distance(vec![0.5, 0.0], vec![0.4, 0.9]).is_none());
+        // the model can't score the pair, the pair is kept without the feature distance instead of
+        // the first-pass distance which is closer than the learned one of the scored pair
+        assert_eq!(distance(vec![-0.1, 0.0], vec![-0.05, 0.0]), Some(None));
+    }
+
+    #[test]
+    fn similarity_first_pass() {
+        let metric = RerankMetric::new(InnerProductMetric, FirstLaneScorer, 0.1);
+        let distance = |l: Vec<f32>, r: Vec<f32>| {
+            let (o1, o2) = (
+                Observation::<f32>::new(None, Some(Feature::from_vec(l))),
+                Observation::<f32>::new(None, Some(Feature::from_vec(r))),
+            );
+            query_metric(&metric, 0, &o1, &o2).map(|(_, d)| d)
+        };
+
+        let scored = distance(vec![0.5, 0.0], vec![0.4, 0.3]).unwrap().unwrap();
+        assert!((scored - 0.1).abs() < EPS);
+        // the first-pass similarity is lower than the min one
+        assert!(distance(vec![0.5, 0.0], vec![0.1, 0.9]).is_none());
+    }
+}
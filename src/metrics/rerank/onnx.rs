@@ -0,0 +1,187 @@
+use crate::metrics::rerank::PairScorer;
+use crate::track::utils::FromVec;
+use crate::track::Feature;
+use anyhow::Result;
+use std::path::Path;
+use std::sync::Arc;
+use tract_onnx::prelude::{
+    tvec, Datum, Framework, InferenceFact, InferenceModel, InferenceModelExt, Tensor, TypedModel,
+    TypedSimplePlan,
+};
+
+/// [PairScorer](PairScorer) that runs the ONNX model with [tract](https://github.com/sonos/tract)
+///
+/// The model has two inputs, the features of the pair with the shape `[1, dimensions]` of `f32`, and returns the
+/// similarity as the first value of its first output. The similarity is clamped to `[0.0, 1.0]`, the pair is not
+/// scored when the features don't have the dimensions of the model or the similarity is not finite. The model is
+/// optimized for the dimensions when it is loaded and is shared by the clones of the scorer.
+///
+/// ```no_run
+/// use similari::metrics::minkowski::MinkowskiMetric;
+/// use similari::metrics::rerank::onnx::OnnxScorer;
+/// use similari::metrics::rerank::RerankMetric;
+///
+/// let scorer = OnnxScorer::load("siamese_head.onnx", 128).unwrap();
+/// let metric = RerankMetric::new(MinkowskiMetric::new(2.0), scorer, 1.2);
+/// ```
+///
+#[derive(Clone)]
+pub struct OnnxScorer {
+    model: Arc<TypedSimplePlan<TypedModel>>,
+    dimensions: usize,
+}
+
+impl OnnxScorer {
+    /// Loads the model from the ONNX file
+    ///
+    /// # Arguments
+    /// * `path` - the path of the model
+    /// * `dimensions` - the number of the dimensions of the features
+    ///
+    pub fn load(path: impl AsRef<Path>, dimensions: usize) -> Result<Self> {
+        Self::from_model(tract_onnx::onnx().model_for_path(path)?, dimensions)
+    }
+
+    /// Creates the scorer from the model parsed with [tract_onnx](tract_onnx), e.g. from the memory
+    ///
+    /// # Arguments
+    /// * `model` - the model
+    /// * `dimensions` - the number of the dimensions of the features
+    ///
+    pub fn from_model(model: InferenceModel, dimensions: usize) -> Result<Self> {
+        let fact = InferenceFact::dt_shape(f32::datum_type(), [1, dimensions]);
+        let model = model
+            .with_input_fact(0, fact.clone())?
+            .with_input_fact(1, fact)?
+            .into_optimized()?
+            .into_runnable()?;
+        Ok(Self {
+            model: Arc::new(model),
+            dimensions,
+        })
+    }
+
+    fn input(&self, feature: &Feature) -> Option<Tensor> {
+        // the features are padded to the blocks of 8 values
+        if feature.len() != (self.dimensions + 7) / 8 {
+            return None;
+        }
+        let mut values = Vec::<f32>::from_vec(feature);
+        values.truncate(self.dimensions);
+        Tensor::from_shape(&[1, self.dimensions], &values).ok()
+    }
+}
+
+impl PairScorer for OnnxScorer {
+    fn score(&self, left: &Feature, right: &Feature) -> Option<f32> {
+        let inputs = tvec!(self.input(left)?.into(), self.input(right)?.into());
+        let outputs = self.model.run(inputs).ok()?;
+        let similarity = *outputs.first()?.as_slice::<f32>().ok()?.first()?;
+        similarity.is_finite().then(|| similarity.clamp(0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::minkowski::MinkowskiMetric;
+    use crate::metrics::query_metric;
+    use crate::metrics::rerank::onnx::OnnxScorer;
+    use crate::metrics::rerank::{PairScorer, RerankMetric};
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, Observation};
+    use crate::EPS;
+    use tract_onnx::pb::tensor_shape_proto::{dimension, Dimension};
+    use tract_onnx::pb::{
+        type_proto, AttributeProto, GraphProto, ModelProto, NodeProto, OperatorSetIdProto,
+        TensorShapeProto, TypeProto, ValueInfoProto,
+    };
+    use tract_onnx::prelude::Framework;
+
+    const DIMENSIONS: usize = 3;
+
+    /// The model which calculates the dot product of the features
+    ///
+    fn dot_model() -> ModelProto {
+        let value = |name: &str, shape: &[i64]| ValueInfoProto {
+            name: name.to_string(),
+            r#type: Some(TypeProto {
+                value: Some(type_proto::Value::TensorType(type_proto::Tensor {
+                    elem_type: 1,
+                    shape: Some(TensorShapeProto {
+                        dim: shape
+                            .iter()
+                            .map(|d| Dimension {
+                                value: Some(dimension::Value::DimValue(*d)),
+                                ..Default::default()
+                            })
+                            .collect(),
+                    }),
+                })),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let node = |op: &str, inputs: &[&str], output: &str| NodeProto {
+            op_type: op.to_string(),
+            input: inputs.iter().map(|i| i.to_string()).collect(),
+            output: vec![output.to_string()],
+            ..Default::default()
+        };
+        let mut sum = node("ReduceSum", &["product"], "similarity");
+        sum.attribute = vec![AttributeProto {
+            name: "axes".to_string(),
+            r#type: 7,
+            ints: vec![1],
+            ..Default::default()
+        }];
+        ModelProto {
+            ir_version: 7,
+            opset_import: vec![OperatorSetIdProto {
+                domain: String::new(),
+                version: 11,
+            }],
+            graph: Some(GraphProto {
+                name: "dot".to_string(),
+                node: vec![node("Mul", &["left", "right"], "product"), sum],
+                input: vec![
+                    value("left", &[1, DIMENSIONS as i64]),
+                    value("right", &[1, DIMENSIONS as i64]),
+                ],
+                output: vec![value("similarity", &[1, 1])],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn onnx_scorer() {
+        let model = tract_onnx::onnx()
+            .model_for_proto_model(&dot_model())
+            .unwrap();
+        let scorer = OnnxScorer::from_model(model, DIMENSIONS).unwrap();
+
+        let feature = |values: Vec<f32>| Feature::from_vec(values);
+        let similarity = scorer
+            .score(&feature(vec![0.5, 0.5, 0.0]), &feature(vec![0.4, 0.2, 1.0]))
+            .unwrap();
+        assert!((similarity - 0.3).abs() < EPS);
+        // clamped
+        assert_eq!(
+            scorer.score(&feature(vec![2.0, 0.0, 0.0]), &feature(vec![1.0, 0.0, 0.0])),
+            Some(1.0)
+        );
+        // the dimensions of the model don't fit
+        assert!(scorer
+            .score(&feature(vec![0.5; 9]), &feature(vec![0.5; 9]))
+            .is_none());
+
+        let metric = RerankMetric::new(MinkowskiMetric::new(2.0), scorer, 1.0);
+        let (o1, o2) = (
+            Observation::<f32>::new(None, Some(feature(vec![0.5, 0.5, 0.0]))),
+            Observation::<f32>::new(None, Some(feature(vec![0.4, 0.2, 0.5]))),
+        );
+        let (_, distance) = query_metric(&metric, 0, &o1, &o2).unwrap();
+        assert!((distance.unwrap() - 0.7).abs() < EPS);
+    }
+}
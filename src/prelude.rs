@@ -5,6 +5,7 @@ use crate::utils;
 pub use track::builder::{ObservationBuilder, TrackBuilder};
 pub use track::notify::NoopNotifier;
 pub use track::store::builder::TrackStoreBuilder;
+pub use track::TrackId;
 
 pub use crate::trackers::sort::PositionalMetricType;
 pub use trackers::sort::batch_api::BatchSort;
@@ -19,6 +20,7 @@ pub use trackers::visual_sort::VisualSortObservation;
 
 pub use utils::bbox::BoundingBox;
 pub use utils::bbox::Universal2DBox;
+pub use utils::bbox3d::Universal3DBox;
 
 pub use utils::clipping::sutherland_hodgman_clip;
 pub use utils::nms;
@@ -1,19 +1,39 @@
+use crate::distance::backend::DistanceBackend;
+use crate::distance::matrix::{pairwise, BatchDistance};
+use crate::metrics::dispatch::DynObservationMetric;
+use crate::track::metadata::{Metadata, MetadataValue};
 use crate::track::notify::{ChangeNotifier, NoopNotifier};
 use crate::Errors;
 use anyhow::Result;
 use itertools::Itertools;
-use std::collections::HashMap;
+use log::warn;
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::mem::take;
+use std::ops::RangeBounds;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use ultraviolet::f32x8;
 
 pub mod builder;
+pub mod metadata;
 pub mod notify;
+pub mod projection;
+#[cfg(feature = "serde")]
+pub mod serialization;
 pub mod store;
 pub mod utils;
 pub mod voting;
 
+/// The id of the track
+///
+/// The ids are `u128`, so the tracks of the integrations keyed by UUIDs use the UUIDs as is, e.g. with
+/// `Uuid::as_u128` and `Uuid::from_u128`, while the `u64` ids are converted with `into()`.
+///
+pub type TrackId = u128;
+
 /// Return type for distance between the current track's and other track observation pair
 ///
 #[derive(Debug, Clone)]
@@ -22,13 +42,20 @@ where
     OA: ObservationAttributes,
 {
     /// source track ID
-    pub from: u64,
+    pub from: TrackId,
     /// compared track ID
-    pub to: u64,
+    pub to: TrackId,
     /// custom feature attribute metric object calculated for pairwise feature attributes
     pub attribute_metric: Option<OA::MetricObject>,
     /// distance calculated for pairwise feature vectors
     pub feature_distance: Option<f32>,
+    /// weight of the vote (e.g. detector confidence), engines that sum votes scale the vote with it
+    pub weight: f32,
+    /// the moment the observation of the compared track was added, e.g. to prefer the recent observations
+    pub stamp: ObservationStamp,
+    /// the index of the observation of the source track the distance is calculated for, e.g. to rank the candidates
+    /// for every query observation
+    pub query_observation: usize,
 }
 
 impl<OA> ObservationMetricOk<OA>
@@ -36,8 +63,8 @@ where
     OA: ObservationAttributes,
 {
     pub fn new(
-        from: u64,
-        to: u64,
+        from: TrackId,
+        to: TrackId,
         attribute_metric: Option<OA::MetricObject>,
         feature_distance: Option<f32>,
     ) -> Self {
@@ -46,8 +73,32 @@ where
             to,
             attribute_metric,
             feature_distance,
+            weight: 1.0,
+            stamp: ObservationStamp::default(),
+            query_observation: 0,
         }
     }
+
+    /// Sets the weight of the vote
+    ///
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Sets the stamp of the observation of the compared track
+    ///
+    pub fn with_stamp(mut self, stamp: ObservationStamp) -> Self {
+        self.stamp = stamp;
+        self
+    }
+
+    /// Sets the index of the observation of the source track
+    ///
+    pub fn with_query_observation(mut self, query_observation: usize) -> Self {
+        self.query_observation = query_observation;
+        self
+    }
 }
 
 /// Internal feature vector representation.
@@ -55,7 +106,7 @@ where
 pub type Feature = Vec<f32x8>;
 
 /// Number of SIMD lanes used to store observation parts internally
-const FEATURE_LANES_SIZE: usize = 8;
+pub(crate) const FEATURE_LANES_SIZE: usize = 8;
 
 /// Observation specification.
 ///
@@ -64,16 +115,62 @@ const FEATURE_LANES_SIZE: usize = 8;
 /// to calculate the distances between tracks to make merging.
 ///
 #[derive(Default, Clone)]
-pub struct Observation<T>(pub(crate) Option<T>, pub(crate) Option<Feature>)
+pub struct Observation<T>(
+    pub(crate) Option<T>,
+    pub(crate) Option<Feature>,
+    pub(crate) ObservationStamp,
+)
 where
     T: Send + Sync + Clone + 'static;
 
+/// The moment the observation was added to the track
+///
+/// The observation is stamped with the monotonic time when it is added to the track. The store stamps the
+/// observation with its epoch, the observations added to the track directly get the epoch of the last update
+/// of the track. Only the epoch of the stamp is serialized, the deserialized observations are stamped with
+/// the time of the deserialization.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObservationStamp {
+    pub instant: Instant,
+    pub epoch: usize,
+}
+
+impl ObservationStamp {
+    /// The stamp of the observation added now at the epoch
+    ///
+    pub fn new(epoch: usize) -> Self {
+        Self {
+            instant: Instant::now(),
+            epoch,
+        }
+    }
+
+    /// The time elapsed since the observation was added
+    ///
+    pub fn age(&self) -> Duration {
+        self.instant.elapsed()
+    }
+}
+
+impl Default for ObservationStamp {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
 impl<T> Observation<T>
 where
     T: Send + Sync + Clone + 'static,
 {
     pub fn new(attrs: Option<T>, feature: Option<Feature>) -> Self {
-        Self(attrs, feature)
+        Self(attrs, feature, ObservationStamp::default())
+    }
+
+    /// Access to the moment the observation was added to the track
+    ///
+    pub fn stamp(&self) -> &ObservationStamp {
+        &self.2
     }
 
     /// Access to observation attributes
@@ -124,6 +221,101 @@ pub trait ObservationAttributes: Send + Sync + Clone + 'static {
 ///
 pub type MetricOutput<T> = Option<(Option<T>, Option<f32>)>;
 
+/// Defines how the feature distances calculated by the metric are compared
+///
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricPolarity {
+    /// smaller values mean closer observations, the thresholds are upper bounds
+    #[default]
+    Distance,
+    /// larger values mean closer observations, the thresholds are lower bounds
+    Similarity,
+}
+
+impl MetricPolarity {
+    /// Maps the value to the smaller-is-better scale, the mapping is its own inverse
+    ///
+    pub fn orient(&self, value: f32) -> f32 {
+        match self {
+            MetricPolarity::Distance => value,
+            MetricPolarity::Similarity => -value,
+        }
+    }
+
+    /// Returns `true` when the value doesn't exceed the threshold in the polarity sense
+    ///
+    pub fn passes(&self, value: f32, threshold: f32) -> bool {
+        self.orient(value) <= self.orient(threshold)
+    }
+
+    /// Returns `true` when the value `l` is closer than the value `r`
+    ///
+    pub fn is_closer(&self, l: f32, r: f32) -> bool {
+        self.orient(l) < self.orient(r)
+    }
+}
+
+/// Defines how NaN and infinite feature distances are handled, e.g. the cosine distances of zero-norm features
+///
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// the distances are rejected with [Errors::NonFiniteDistance](Errors::NonFiniteDistance)
+    Reject,
+    /// the distances are replaced with the worst value of the polarity: `f32::MAX` for the distances and
+    /// `f32::MIN` for the similarities
+    TreatAsMax,
+    /// the feature distances are dropped, the attribute metrics of the pairs are kept
+    #[default]
+    Skip,
+}
+
+impl NonFinitePolicy {
+    /// Applies the policy to the distances
+    ///
+    /// # Arguments
+    /// * `distances` - the distances to check
+    /// * `polarity` - the polarity of the distances
+    ///
+    pub fn apply<OA>(
+        &self,
+        distances: Vec<ObservationMetricOk<OA>>,
+        polarity: MetricPolarity,
+    ) -> Result<Vec<ObservationMetricOk<OA>>>
+    where
+        OA: ObservationAttributes,
+    {
+        let non_finite =
+            |e: &ObservationMetricOk<OA>| matches!(e.feature_distance, Some(d) if !d.is_finite());
+        match self {
+            NonFinitePolicy::Reject => match distances.iter().find(|e| non_finite(e)) {
+                Some(e) => Err(Errors::NonFiniteDistance(e.from, e.to).into()),
+                None => Ok(distances),
+            },
+            NonFinitePolicy::TreatAsMax => {
+                let worst = polarity.orient(f32::MAX);
+                Ok(distances
+                    .into_iter()
+                    .map(|mut e| {
+                        if non_finite(&e) {
+                            e.feature_distance = Some(worst);
+                        }
+                        e
+                    })
+                    .collect())
+            }
+            NonFinitePolicy::Skip => Ok(distances
+                .into_iter()
+                .map(|mut e| {
+                    if non_finite(&e) {
+                        e.feature_distance = None;
+                    }
+                    e
+                })
+                .collect()),
+        }
+    }
+}
+
 /// Query object that is a parameter of the ``ObservationMetric::metric` method.
 ///
 /// The query is used to make pairwise comparison of observations for two tracks.
@@ -157,6 +349,18 @@ pub trait ObservationMetric<TA, OA: ObservationAttributes>: Send + Sync + Clone
     ///
     fn metric(&self, mq: &'_ MetricQuery<'_, TA, OA>) -> MetricOutput<OA::MetricObject>;
 
+    /// cheap attribute-level gate that is checked before the metric is calculated.
+    ///
+    /// When the gate fails (e.g. the boxes don't intersect or the camera ids differ), the pair is skipped
+    /// and the expensive [metric](ObservationMetric::metric) is not calculated for it.
+    ///
+    /// # Parameters
+    /// * `mq` - query to check
+    ///
+    fn gate(&self, _mq: &'_ MetricQuery<'_, TA, OA>) -> bool {
+        true
+    }
+
     /// the method is used every time, when a new observation is added to the feature storage as well as when
     /// two tracks are merged.
     ///
@@ -176,7 +380,7 @@ pub trait ObservationMetric<TA, OA: ObservationAttributes>: Send + Sync + Clone
     fn optimize(
         &mut self,
         feature_class: u64,
-        merge_history: &[u64],
+        merge_history: &[TrackId],
         attributes: &mut TA,
         observations: &mut Vec<Observation<OA>>,
         prev_length: usize,
@@ -194,6 +398,42 @@ pub trait ObservationMetric<TA, OA: ObservationAttributes>: Send + Sync + Clone
     ) -> Vec<ObservationMetricOk<OA>> {
         unfiltered
     }
+
+    /// The postprocessing of the distances calculated for the feature class, the store runs it instead of
+    /// [postprocess_distances](ObservationMetric::postprocess_distances).
+    ///
+    /// Calls [postprocess_distances](ObservationMetric::postprocess_distances) by default, the metrics that
+    /// dispatch by the feature class (e.g. [FeatureClassMetric](crate::metrics::dispatch::FeatureClassMetric))
+    /// run the postprocessing of the metric of the class.
+    ///
+    fn postprocess_class_distances(
+        &self,
+        _feature_class: u64,
+        unfiltered: Vec<ObservationMetricOk<OA>>,
+    ) -> Vec<ObservationMetricOk<OA>> {
+        self.postprocess_distances(unfiltered)
+    }
+
+    /// Polarity of the feature distances calculated by the metric.
+    ///
+    /// Similarity metrics (e.g. the inner product) return [MetricPolarity::Similarity](MetricPolarity::Similarity),
+    /// so the voting engines configured with the polarity treat larger values as better ones.
+    ///
+    fn polarity(&self) -> MetricPolarity {
+        MetricPolarity::Distance
+    }
+
+    /// Feature distance of the metric that can be calculated for the batch of pairs with the matrix multiplication.
+    ///
+    /// When it is set and the store shard holds at least [GEMM_MIN_CANDIDATES](crate::distance::matrix::GEMM_MIN_CANDIDATES)
+    /// candidate tracks, the feature distances for all of them are calculated with [pairwise](crate::distance::matrix::pairwise)
+    /// instead of calling [metric](ObservationMetric::metric) for every pair. The attribute metric is calculated with
+    /// [calculate_metric_object](ObservationAttributes::calculate_metric_object), so the metric must return the same
+    /// attribute metric and feature distance for the pair as the batch path does.
+    ///
+    fn batch_distance(&self) -> Option<BatchDistance> {
+        None
+    }
 }
 
 /// Enum which specifies the status of feature tracks in storage. When the feature tracks are collected,
@@ -221,7 +461,7 @@ where
         &self,
         _attributes: &TA,
         _observations: &ObservationsDb<OA>,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
     ) -> bool {
         false
     }
@@ -277,7 +517,7 @@ where
         &self,
         _attributes: &TA,
         _observations: &ObservationsDb<OA>,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
     ) -> bool {
         RES
     }
@@ -332,6 +572,132 @@ pub trait TrackAttributesUpdate<TA>: Clone + Send + Sync + 'static {
     fn apply(&self, attrs: &mut TA) -> Result<()>;
 }
 
+/// Defines how the attributes of the merged tracks are combined
+///
+#[derive(Clone)]
+pub enum AttributeMergePolicy<TA> {
+    /// the attributes are merged with [TrackAttributes::merge](TrackAttributes::merge)
+    Merge,
+    /// the attributes of the destination track are kept
+    KeepDestination,
+    /// the attributes of the source track replace the attributes of the destination track
+    TakeSource,
+    /// the attributes are combined with the function of the destination and the source attributes
+    #[allow(clippy::type_complexity)]
+    Custom(Arc<dyn Fn(&mut TA, &TA) -> Result<()> + Send + Sync>),
+}
+
+impl<TA> AttributeMergePolicy<TA> {
+    /// Combines the attributes of the source track into the attributes of the destination track
+    ///
+    pub fn apply<OA>(&self, dest: &mut TA, src: &TA) -> Result<()>
+    where
+        TA: TrackAttributes<TA, OA>,
+        OA: ObservationAttributes,
+    {
+        match self {
+            AttributeMergePolicy::Merge => dest.merge(src),
+            AttributeMergePolicy::KeepDestination => Ok(()),
+            AttributeMergePolicy::TakeSource => {
+                *dest = src.clone();
+                Ok(())
+            }
+            AttributeMergePolicy::Custom(f) => f(dest, src),
+        }
+    }
+}
+
+/// Fuses the observations of the feature class into a single observation
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeatureFusion {
+    /// exponential moving average `fused = alpha * fused + (1 - alpha) * feature`, the fused feature is
+    /// scaled to the unit L2 norm when `normalize` is set, as DeepSORT-like appearance models expect
+    Ema { alpha: f32, normalize: bool },
+    /// the mean of all the fused features, the track keeps the centroid and the number of the features, so
+    /// the gallery tracks need no observation history to be updated incrementally
+    Centroid,
+}
+
+/// Handles the feature that is a near-duplicate of the feature kept by the track, e.g. of a stationary object
+///
+/// The feature is the near-duplicate when the euclidean distance to the closest kept feature of the class is
+/// below the threshold.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Deduplication {
+    /// the near-duplicate feature is dropped
+    Drop(f32),
+    /// the near-duplicate feature is averaged with the closest kept feature
+    Merge(f32),
+}
+
+/// Limits the number of the observations of the feature class kept by the track
+///
+#[derive(Clone)]
+pub enum HistoryCap<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// the last `N` observations are kept
+    KeepLast(usize),
+    /// the `N` observations with the highest quality are kept
+    #[allow(clippy::type_complexity)]
+    KeepBest(usize, Arc<dyn Fn(&Observation<OA>) -> f32 + Send + Sync>),
+    /// the uniform sample of `N` observations of all the observations ever added is kept
+    Reservoir(usize),
+}
+
+/// Defines which observations are moved to the tail track when the track is split
+///
+#[derive(Clone)]
+pub enum SplitPoint<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// the observations for which the predicate of the feature class and the observation is true
+    #[allow(clippy::type_complexity)]
+    Predicate(Arc<dyn Fn(u64, &Observation<OA>) -> bool + Send + Sync>),
+    /// the observations of every feature class starting from the index
+    ///
+    /// The index is the position in the observations of the class, which is the order of the insertion unless
+    /// the metric reorders the observations when optimizes them.
+    ///
+    Index(usize),
+    /// the observations stamped with the epoch or the later ones, look at [ObservationStamp](ObservationStamp)
+    Epoch(usize),
+}
+
+/// The attributes of the track after the update made through the store, look at
+/// [attribute_history](Track::attribute_history)
+///
+#[derive(Debug, Clone)]
+pub struct AttributeVersion<TA> {
+    /// the epoch of the store at the update
+    pub epoch: usize,
+    pub attributes: TA,
+}
+
+/// The moment of the last update of the track made through the store
+///
+/// The store stamps the track when the observations are added to it, when the track is added to the store and
+/// when another track is merged into it. The epoch is the epoch of the store at the update.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct TrackUpdate {
+    pub instant: Instant,
+    pub epoch: usize,
+}
+
+impl Default for TrackUpdate {
+    fn default() -> Self {
+        Self {
+            instant: Instant::now(),
+            epoch: 0,
+        }
+    }
+}
+
 /// Represents track of observations - it's a core concept of the library.
 ///
 /// The track is created for specific attributes(A), Metric(M) and AttributeUpdate(U).
@@ -349,11 +715,17 @@ where
     N: ChangeNotifier,
 {
     attributes: TA,
-    track_id: u64,
+    track_id: TrackId,
     observations: ObservationsDb<OA>,
     metric: M,
-    merge_history: Vec<u64>,
+    merge_history: Vec<TrackId>,
     notifier: N,
+    last_update: TrackUpdate,
+    metadata: Metadata,
+    observation_totals: HashMap<u64, usize>,
+    fused_counts: HashMap<u64, usize>,
+    attribute_history: VecDeque<AttributeVersion<TA>>,
+    attribute_history_cap: usize,
 }
 
 /// One and only parametrized track implementation.
@@ -369,7 +741,7 @@ where
     ///
     /// The `metric` and `attributes` are optional, if `None` is specified, then `Default` initializer is used.
     ///
-    pub fn new(track_id: u64, metric: M, attributes: TA, notifier: N) -> Self {
+    pub fn new(track_id: TrackId, metric: M, attributes: TA, notifier: N) -> Self {
         let mut v = Self {
             notifier,
             attributes,
@@ -377,6 +749,12 @@ where
             metric,
             observations: ObservationsDb::default(),
             merge_history: vec![track_id],
+            last_update: TrackUpdate::default(),
+            metadata: Metadata::default(),
+            observation_totals: HashMap::default(),
+            fused_counts: HashMap::default(),
+            attribute_history: VecDeque::default(),
+            attribute_history_cap: 0,
         };
         v.notifier.send(track_id);
         v
@@ -384,13 +762,13 @@ where
 
     /// Returns track_id.
     ///
-    pub fn get_track_id(&self) -> u64 {
+    pub fn get_track_id(&self) -> TrackId {
         self.track_id
     }
 
     /// Sets track_id.
     ///
-    pub fn set_track_id(&mut self, track_id: u64) -> u64 {
+    pub fn set_track_id(&mut self, track_id: TrackId) -> TrackId {
         let old = self.track_id;
         self.track_id = track_id;
         old
@@ -415,10 +793,285 @@ where
 
     /// Returns the current track merge history for the track
     ///
-    pub fn get_merge_history(&self) -> &Vec<u64> {
+    pub fn get_merge_history(&self) -> &Vec<TrackId> {
         &self.merge_history
     }
 
+    /// Returns the last update of the track made through the store
+    ///
+    pub fn get_last_update(&self) -> &TrackUpdate {
+        &self.last_update
+    }
+
+    pub(crate) fn stamp(&mut self, epoch: usize) {
+        self.last_update = TrackUpdate {
+            instant: Instant::now(),
+            epoch,
+        };
+        if self.attribute_history_cap > 0 {
+            if self.attribute_history.len() == self.attribute_history_cap {
+                self.attribute_history.pop_front();
+            }
+            self.attribute_history.push_back(AttributeVersion {
+                epoch,
+                attributes: self.attributes.clone(),
+            });
+        }
+    }
+
+    /// Sets the number of the attribute versions kept by the track, `0` disables the history
+    ///
+    /// The version of the attributes is recorded every time the store stamps the track, the oldest versions are
+    /// dropped when the history is full.
+    ///
+    pub fn set_attribute_history_cap(&mut self, cap: usize) {
+        self.attribute_history_cap = cap;
+        let excess = self.attribute_history.len().saturating_sub(cap);
+        self.attribute_history.drain(..excess);
+    }
+
+    /// Returns the number of the attribute versions kept by the track
+    ///
+    pub fn attribute_history_cap(&self) -> usize {
+        self.attribute_history_cap
+    }
+
+    /// Returns the kept versions of the attributes from the oldest one
+    ///
+    pub fn attribute_history(&self) -> &VecDeque<AttributeVersion<TA>> {
+        &self.attribute_history
+    }
+
+    /// Sets the metadata value, returns the previous value
+    ///
+    pub fn set_metadata<V: Into<MetadataValue>>(
+        &mut self,
+        key: &str,
+        value: V,
+    ) -> Option<MetadataValue> {
+        self.metadata.insert(key.to_string(), value.into())
+    }
+
+    /// Returns the metadata value
+    ///
+    pub fn get_metadata(&self, key: &str) -> Option<&MetadataValue> {
+        self.metadata.get(key)
+    }
+
+    /// Removes the metadata value, returns the removed value
+    ///
+    pub fn remove_metadata(&mut self, key: &str) -> Option<MetadataValue> {
+        self.metadata.remove(key)
+    }
+
+    /// Returns all the metadata of the track
+    ///
+    pub fn get_all_metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the number of the observations of the feature class ever added to the track
+    ///
+    /// The number is not kept when the track is serialized, the restored tracks count the kept observations.
+    ///
+    pub fn observation_total(&self, feature_class: u64) -> usize {
+        let kept = self.observations.get(&feature_class).map_or(0, Vec::len);
+        // the merged observations are not counted, so the number is not less than the kept ones
+        self.observation_totals
+            .get(&feature_class)
+            .map_or(kept, |total| kept.max(*total))
+    }
+
+    /// Fuses the observations of the feature class into the single observation
+    ///
+    /// The observations are folded in the order they are kept, so when the fused observation is followed by the
+    /// new one, the new one is fused into it. The fused observation gets the attributes of the last observation,
+    /// the observations without the features only update the attributes. The first observation counts as
+    /// [fused_count](Track::fused_count) features when it is the result of the previous fusion, the other
+    /// observations, e.g. the merged ones, count as one feature.
+    ///
+    /// # Returns
+    /// * `Ok(features)` - the observations are fused, the features of the fused observations are returned, e.g. to
+    ///   reuse their buffers
+    /// * `Err(Errors::FeatureDimensionsMismatch(class, expected, actual))` - the features of the class have different
+    ///   dimensions, the observations are left unchanged
+    ///
+    pub fn fuse_observations(
+        &mut self,
+        feature_class: u64,
+        fusion: &FeatureFusion,
+    ) -> Result<Vec<Feature>> {
+        let observations = match self.observations.get_mut(&feature_class) {
+            Some(observations) if !observations.is_empty() => observations,
+            _ => return Ok(Vec::default()),
+        };
+        let mut weights = observations.iter().map(|_| 1).collect::<Vec<usize>>();
+        if let Some(count) = self.fused_counts.get(&feature_class) {
+            weights[0] = (*count).max(1);
+        }
+
+        let mut fused: Option<Feature> = None;
+        let mut count = 0;
+        for (Observation(_, feature, _), weight) in observations.iter().zip(weights) {
+            let feature = match feature {
+                Some(feature) => feature,
+                None => continue,
+            };
+            count += weight;
+            fused = Some(match fused {
+                None => feature.clone(),
+                Some(mut fused) => {
+                    if fused.len() != feature.len() {
+                        return Err(Errors::FeatureDimensionsMismatch(
+                            feature_class,
+                            fused.len() * FEATURE_LANES_SIZE,
+                            feature.len() * FEATURE_LANES_SIZE,
+                        )
+                        .into());
+                    }
+                    let added = match fusion {
+                        FeatureFusion::Ema { alpha, .. } => 1.0 - alpha,
+                        FeatureFusion::Centroid => weight as f32 / count as f32,
+                    };
+                    let (kept, added) = (f32x8::splat(1.0 - added), f32x8::splat(added));
+                    fused
+                        .iter_mut()
+                        .zip(feature)
+                        .for_each(|(f, v)| *f = *f * kept + *v * added);
+                    fused
+                }
+            });
+        }
+        if let (
+            FeatureFusion::Ema {
+                normalize: true, ..
+            },
+            Some(fused),
+        ) = (fusion, &mut fused)
+        {
+            crate::distance::normalize(fused);
+        }
+
+        let Observation(attributes, last, stamp) = observations.pop().unwrap();
+        let fused_features = observations
+            .drain(..)
+            .flat_map(|o| o.1)
+            .chain(last)
+            .collect();
+        observations.push(Observation(attributes, fused, stamp));
+        self.fused_counts.insert(feature_class, count);
+        Ok(fused_features)
+    }
+
+    /// Returns the number of the features fused into the observation of the feature class, `0` when the
+    /// observations of the class are not fused
+    ///
+    pub fn fused_count(&self, feature_class: u64) -> usize {
+        self.fused_counts.get(&feature_class).copied().unwrap_or(0)
+    }
+
+    /// Absorbs the feature into the kept observations of the feature class when it is the near-duplicate
+    ///
+    /// # Returns
+    /// `true` when the feature is dropped or merged according to the deduplication, then the feature must not be
+    /// added to the track, `false` when the feature is not the near-duplicate
+    ///
+    pub fn deduplicate_feature(
+        &mut self,
+        feature_class: u64,
+        feature: &Feature,
+        deduplication: &Deduplication,
+    ) -> bool {
+        let threshold = match deduplication {
+            Deduplication::Drop(threshold) | Deduplication::Merge(threshold) => *threshold,
+        };
+        let closest = self
+            .observations
+            .get_mut(&feature_class)
+            .into_iter()
+            .flatten()
+            .filter_map(|o| o.1.as_mut())
+            .filter(|kept| kept.len() == feature.len())
+            .map(|kept| (crate::distance::euclidean(kept, feature), kept))
+            .filter(|(distance, _)| *distance < threshold)
+            .min_by(|(l, _), (r, _)| l.total_cmp(r));
+        match (deduplication, closest) {
+            (_, None) => false,
+            (Deduplication::Drop(_), Some(_)) => true,
+            (Deduplication::Merge(_), Some((_, kept))) => {
+                let half = f32x8::splat(0.5);
+                kept.iter_mut()
+                    .zip(feature)
+                    .for_each(|(k, f)| *k = (*k + *f) * half);
+                true
+            }
+        }
+    }
+
+    /// Returns the observations of the feature class added within the range of the epochs, look at
+    /// [ObservationStamp](ObservationStamp)
+    ///
+    pub fn observations_in_range<R>(&self, feature_class: u64, epochs: R) -> Vec<&Observation<OA>>
+    where
+        R: RangeBounds<usize>,
+    {
+        self.observations
+            .get(&feature_class)
+            .into_iter()
+            .flatten()
+            .filter(|o| epochs.contains(&o.2.epoch))
+            .collect()
+    }
+
+    /// Returns `true` when any observation of the track was added within the range of the epochs
+    ///
+    pub fn active_in_range<R>(&self, epochs: &R) -> bool
+    where
+        R: RangeBounds<usize>,
+    {
+        self.observations
+            .values()
+            .flatten()
+            .any(|o| epochs.contains(&o.2.epoch))
+    }
+
+    /// Limits the number of the observations of the feature class according to the cap
+    ///
+    /// Returns the features of the dropped observations, e.g. to reuse their buffers.
+    ///
+    pub fn cap_history(&mut self, feature_class: u64, cap: &HistoryCap<OA>) -> Vec<Feature> {
+        let total = self.observation_total(feature_class);
+        let observations = match self.observations.get_mut(&feature_class) {
+            Some(observations) => observations,
+            None => return Vec::default(),
+        };
+        let mut dropped = Vec::default();
+        match cap {
+            HistoryCap::KeepLast(n) => {
+                let excess = observations.len().saturating_sub(*n);
+                dropped.extend(observations.drain(..excess));
+            }
+            HistoryCap::KeepBest(n, quality) => {
+                if observations.len() > *n {
+                    observations.sort_by(|l, r| quality(r).total_cmp(&quality(l)));
+                    dropped.extend(observations.drain(*n..));
+                }
+            }
+            HistoryCap::Reservoir(n) => {
+                while observations.len() > *n {
+                    // the last observation replaces the kept one with the probability of n / total
+                    let j = rand::thread_rng().gen_range(0..total);
+                    if j < *n {
+                        dropped.push(observations.swap_remove(j));
+                    } else {
+                        dropped.extend(observations.pop());
+                    }
+                }
+            }
+        }
+        dropped.into_iter().flat_map(|o| o.1).collect()
+    }
+
     /// Returns all classes present
     ///
     pub fn get_feature_classes(&self) -> Vec<u64> {
@@ -450,6 +1103,24 @@ where
         feature_attributes: Option<OA>,
         feature: Option<Feature>,
         track_attributes_update: Option<TA::Update>,
+    ) -> Result<()> {
+        let stamp = ObservationStamp::new(self.last_update.epoch);
+        self.add_stamped_observation(
+            feature_class,
+            feature_attributes,
+            feature,
+            track_attributes_update,
+            stamp,
+        )
+    }
+
+    pub(crate) fn add_stamped_observation(
+        &mut self,
+        feature_class: u64,
+        feature_attributes: Option<OA>,
+        feature: Option<Feature>,
+        track_attributes_update: Option<TA::Update>,
+        stamp: ObservationStamp,
     ) -> Result<()> {
         let last_attributes = self.attributes.clone();
         let last_observations = self.observations.clone();
@@ -469,15 +1140,17 @@ where
             return Ok(());
         }
 
+        let total = self.observation_total(feature_class) + 1;
+        self.observation_totals.insert(feature_class, total);
         match self.observations.get_mut(&feature_class) {
             None => {
                 self.observations.insert(
                     feature_class,
-                    vec![Observation(feature_attributes, feature)],
+                    vec![Observation(feature_attributes, feature, stamp)],
                 );
             }
             Some(observations) => {
-                observations.push(Observation(feature_attributes, feature));
+                observations.push(Observation(feature_attributes, feature, stamp));
             }
         }
         let observations = self.observations.get_mut(&feature_class).unwrap();
@@ -502,6 +1175,74 @@ where
         Ok(())
     }
 
+    /// Removes the observations selected by the predicate, e.g. for the deletion requests or the quality pruning
+    ///
+    /// The predicate gets the feature class and the observation. The classes that lost the observations are
+    /// optimized by the metric again with `prev_length` equal to the number of the kept observations, so the
+    /// metric can recompute the attributes from the kept observations. The classes left without the observations
+    /// are removed, the fused observation is removed with its count.
+    ///
+    /// # Returns
+    /// * `Ok(removed)` - the number of the removed observations
+    /// * `Err(e)` - the metric failed to optimize the kept observations, the track is left unchanged
+    ///
+    pub fn remove_observations<F>(&mut self, predicate: F) -> Result<usize>
+    where
+        F: Fn(u64, &Observation<OA>) -> bool,
+    {
+        let last_attributes = self.attributes.clone();
+        let last_observations = self.observations.clone();
+        let last_metric = self.metric.clone();
+
+        let mut removed = 0;
+        let mut res = Ok(());
+        for (feature_class, observations) in self.observations.iter_mut() {
+            let length = observations.len();
+            observations.retain(|o| !predicate(*feature_class, o));
+            if observations.len() == length {
+                continue;
+            }
+            removed += length - observations.len();
+            if observations.is_empty() {
+                continue;
+            }
+            let prev_length = observations.len();
+            res = self.metric.optimize(
+                *feature_class,
+                &self.merge_history,
+                &mut self.attributes,
+                observations,
+                prev_length,
+                false,
+            );
+            if res.is_err() {
+                break;
+            }
+        }
+        if let Err(e) = res {
+            self.attributes = last_attributes;
+            self.observations = last_observations;
+            self.metric = last_metric;
+            return Err(e);
+        }
+
+        self.observations
+            .retain(|_, observations| !observations.is_empty());
+        let observations = &self.observations;
+        self.fused_counts
+            .retain(|feature_class, _| observations.contains_key(feature_class));
+        if removed > 0 {
+            self.notifier.send(self.track_id);
+        }
+        Ok(removed)
+    }
+
+    /// Removes all the observations of the feature class, look at [remove_observations](Track::remove_observations)
+    ///
+    pub fn remove_feature_class(&mut self, feature_class: u64) -> Result<usize> {
+        self.remove_observations(|class, _| class == feature_class)
+    }
+
     /// Merges vector into current track across specified feature classes.
     ///
     /// The merge works across specified feature classes:
@@ -520,8 +1261,24 @@ where
     /// * `merge_history` - defines add merged track id into self merge history or not
     ///
     pub fn merge(&mut self, other: &Self, classes: &[u64], merge_history: bool) -> Result<()> {
+        self.merge_with_policy(other, classes, merge_history, &AttributeMergePolicy::Merge)
+    }
+
+    /// Merges vector into current track across specified feature classes, the attributes are combined
+    /// according to the policy
+    ///
+    /// The features are merged like in [merge](Track::merge), the metadata keys that are missing in the current
+    /// track are copied from the other track.
+    ///
+    pub fn merge_with_policy(
+        &mut self,
+        other: &Self,
+        classes: &[u64],
+        merge_history: bool,
+        policy: &AttributeMergePolicy<TA>,
+    ) -> Result<()> {
         let last_attributes = self.attributes.clone();
-        let res = self.attributes.merge(&other.attributes);
+        let res = policy.apply(&mut self.attributes, &other.attributes);
         if res.is_err() {
             self.attributes = last_attributes;
             res?;
@@ -583,10 +1340,79 @@ where
             }
         }
 
+        for (key, value) in &other.metadata {
+            self.metadata
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+
         self.notifier.send(self.track_id);
         Ok(())
     }
 
+    /// Splits the track in two, the observations selected by the split point are moved to the new tail track
+    ///
+    /// The tail track gets the copy of the attributes, the metadata, the metric and the notifier of the track, its merge history
+    /// starts with `tail_id`. The feature classes without the observations left are removed from both tracks.
+    ///
+    pub fn split(&mut self, tail_id: TrackId, point: &SplitPoint<OA>) -> Self {
+        let mut tail = ObservationsDb::default();
+        for (feature_class, observations) in self.observations.iter_mut() {
+            let moved = match point {
+                SplitPoint::Predicate(predicate) => {
+                    let (moved, kept) = take(observations)
+                        .into_iter()
+                        .partition::<Vec<_>, _>(|o| predicate(*feature_class, o));
+                    *observations = kept;
+                    moved
+                }
+                SplitPoint::Index(index) => {
+                    observations.split_off((*index).min(observations.len()))
+                }
+                SplitPoint::Epoch(epoch) => {
+                    let (moved, kept) = take(observations)
+                        .into_iter()
+                        .partition::<Vec<_>, _>(|o| o.2.epoch >= *epoch);
+                    *observations = kept;
+                    moved
+                }
+            };
+            if !moved.is_empty() {
+                tail.insert(*feature_class, moved);
+            }
+        }
+        self.observations
+            .retain(|_, observations| !observations.is_empty());
+        // the fused observations are moved with their counts
+        let fused_counts = tail
+            .keys()
+            .filter(|feature_class| !self.observations.contains_key(feature_class))
+            .flat_map(|feature_class| {
+                self.fused_counts
+                    .remove(feature_class)
+                    .map(|count| (*feature_class, count))
+            })
+            .collect();
+        self.notifier.send(self.track_id);
+
+        let mut tail = Self {
+            attributes: self.attributes.clone(),
+            track_id: tail_id,
+            observations: tail,
+            metric: self.metric.clone(),
+            merge_history: vec![tail_id],
+            notifier: self.notifier.clone(),
+            last_update: TrackUpdate::default(),
+            metadata: self.metadata.clone(),
+            observation_totals: HashMap::default(),
+            fused_counts,
+            attribute_history: VecDeque::default(),
+            attribute_history_cap: self.attribute_history_cap,
+        };
+        tail.notifier.send(tail_id);
+        tail
+    }
+
     /// Calculates distances between all features for two tracks for a class.
     ///
     /// First it calculates cartesian product `S X O` and calculates the distance for every pair.
@@ -606,51 +1432,272 @@ where
         other: &Self,
         feature_class: u64,
     ) -> Result<Vec<ObservationMetricOk<OA>>> {
+        self.class_distances(other, feature_class, feature_class, &self.metric)
+    }
+
+    /// Calculates distances between the features of the query class of the track and the features of the
+    /// gallery class of the other track with the translation metric
+    ///
+    /// The translation metric compares the features of different modalities, e.g. text embeddings with image
+    /// embeddings. The metric is queried with the gallery class as the feature class. The errors are the same as
+    /// the errors of [distances](Track::distances).
+    ///
+    /// # Parameters
+    /// * `other` - track to find distances to
+    /// * `query_class` - the feature class of the track
+    /// * `gallery_class` - the feature class of the other track
+    /// * `translation` - the metric that calculates the distances between the features of the classes
+    ///
+    pub fn cross_class_distances(
+        &self,
+        other: &Self,
+        query_class: u64,
+        gallery_class: u64,
+        translation: &dyn DynObservationMetric<TA, OA>,
+    ) -> Result<Vec<ObservationMetricOk<OA>>> {
+        self.class_distances(other, query_class, gallery_class, translation)
+    }
+
+    fn class_distances<DM>(
+        &self,
+        other: &Self,
+        query_class: u64,
+        gallery_class: u64,
+        metric: &DM,
+    ) -> Result<Vec<ObservationMetricOk<OA>>>
+    where
+        DM: DynObservationMetric<TA, OA> + ?Sized,
+    {
         if !self.attributes.compatible(&other.attributes) {
             Err(Errors::IncompatibleAttributes.into())
         } else {
             match (
-                self.observations.get(&feature_class),
-                other.observations.get(&feature_class),
+                self.observations.get(&query_class),
+                other.observations.get(&gallery_class),
             ) {
                 (Some(left), Some(right)) => Ok(left
                     .iter()
+                    .enumerate()
                     .cartesian_product(right.iter())
-                    .flat_map(|(l, r)| {
+                    .flat_map(|((query_observation, l), r)| {
                         let mq = MetricQuery {
-                            feature_class,
+                            feature_class: gallery_class,
                             candidate_attrs: self.get_attributes(),
                             candidate_observation: l,
                             track_attrs: other.get_attributes(),
                             track_observation: r,
                         };
 
-                        // let (attribute_metric, feature_distance) = self.metric.new_metric(&mq)?;
-                        let (attribute_metric, feature_distance) = self.metric.metric(
-                            &mq, // feature_class,
-                                // self.get_attributes(),
-                                // other.get_attributes(),
-                                // l,
-                                // r,
-                        )?;
+                        if !metric.dyn_gate(&mq) {
+                            return None;
+                        }
+
+                        let (attribute_metric, feature_distance) = metric.dyn_metric(&mq)?;
                         Some(ObservationMetricOk {
                             from: self.track_id,
                             to: other.track_id,
                             attribute_metric,
                             feature_distance,
+                            weight: 1.0,
+                            stamp: r.2,
+                            query_observation,
                         })
                     })
                     .collect()),
-                _ => Err(Errors::ObservationForClassNotFound(
+                (None, _) => Err(Errors::ObservationForClassNotFound(
                     self.track_id,
                     other.track_id,
-                    feature_class,
+                    query_class,
+                )
+                .into()),
+                (_, None) => Err(Errors::ObservationForClassNotFound(
+                    self.track_id,
+                    other.track_id,
+                    gallery_class,
                 )
                 .into()),
             }
         }
     }
 
+    /// Finds the closest distance between the features of the class of the tracks without collecting the
+    /// distances of all the pairs
+    ///
+    /// The pairs which are not closer than `bound` in the polarity sense are skipped, so `Ok(None)` is returned
+    /// when the other track is not closer than the bound. The non-finite distances are handled by the policy as
+    /// [apply](NonFinitePolicy::apply) does. The errors are the same as the errors of
+    /// [distances](Track::distances).
+    ///
+    pub(crate) fn closest_distance(
+        &self,
+        other: &Self,
+        feature_class: u64,
+        bound: Option<f32>,
+        policy: NonFinitePolicy,
+    ) -> Result<Option<ObservationMetricOk<OA>>> {
+        if !self.attributes.compatible(&other.attributes) {
+            return Err(Errors::IncompatibleAttributes.into());
+        }
+        let (left, right) = match (
+            self.observations.get(&feature_class),
+            other.observations.get(&feature_class),
+        ) {
+            (Some(left), Some(right)) => (left, right),
+            _ => {
+                return Err(Errors::ObservationForClassNotFound(
+                    self.track_id,
+                    other.track_id,
+                    feature_class,
+                )
+                .into())
+            }
+        };
+        let polarity = self.metric.polarity();
+        let mut bound = bound;
+        let mut closest = None;
+        for ((query_observation, l), r) in left.iter().enumerate().cartesian_product(right.iter()) {
+            let mq = MetricQuery {
+                feature_class,
+                candidate_attrs: self.get_attributes(),
+                candidate_observation: l,
+                track_attrs: other.get_attributes(),
+                track_observation: r,
+            };
+            if !self.metric.gate(&mq) {
+                continue;
+            }
+            let (attribute_metric, distance) = match self.metric.metric(&mq) {
+                Some((attribute_metric, Some(distance))) => (attribute_metric, distance),
+                _ => continue,
+            };
+            let distance = match policy {
+                _ if distance.is_finite() => distance,
+                NonFinitePolicy::Reject => {
+                    return Err(Errors::NonFiniteDistance(self.track_id, other.track_id).into())
+                }
+                NonFinitePolicy::TreatAsMax => polarity.orient(f32::MAX),
+                NonFinitePolicy::Skip => continue,
+            };
+            let rank = polarity.orient(distance);
+            if matches!(bound, Some(bound) if rank >= bound) {
+                continue;
+            }
+            bound = Some(rank);
+            closest = Some(ObservationMetricOk {
+                from: self.track_id,
+                to: other.track_id,
+                attribute_metric,
+                feature_distance: Some(distance),
+                weight: 1.0,
+                stamp: r.2,
+                query_observation,
+            });
+        }
+        Ok(closest)
+    }
+
+    /// Calculates the distances to the batch of tracks with the single matrix multiplication
+    ///
+    /// The results are the same as the results of [distances](Track::distances) called for every track of the batch,
+    /// but the feature distances are calculated with the `distance` kernel for all the observations at once by the
+    /// `backend`, the [metric](ObservationMetric::metric) is not called. When the backend fails, the distances are
+    /// calculated on CPU.
+    ///
+    pub fn batch_distances(
+        &self,
+        others: &[&Self],
+        feature_class: u64,
+        distance: BatchDistance,
+        backend: &dyn DistanceBackend,
+    ) -> Vec<Result<Vec<ObservationMetricOk<OA>>>> {
+        let left = self.observations.get(&feature_class);
+        let mut results = Vec::with_capacity(others.len());
+        let mut candidates = Vec::new();
+        for (index, other) in others.iter().enumerate() {
+            results.push(if !self.attributes.compatible(&other.attributes) {
+                Err(Errors::IncompatibleAttributes.into())
+            } else {
+                match (left, other.observations.get(&feature_class)) {
+                    (Some(_), Some(right)) => {
+                        candidates.extend(right.iter().map(|r| (index, r)));
+                        Ok(Vec::new())
+                    }
+                    _ => Err(Errors::ObservationForClassNotFound(
+                        self.track_id,
+                        other.track_id,
+                        feature_class,
+                    )
+                    .into()),
+                }
+            });
+        }
+
+        let left = match left {
+            Some(left) if !candidates.is_empty() => left,
+            _ => return results,
+        };
+
+        // positions of the observations in the packed features, the observations without features are skipped
+        fn with_features<'a, OA: ObservationAttributes>(
+            observations: impl Iterator<Item = &'a Observation<OA>>,
+        ) -> (Vec<Option<usize>>, Vec<&'a Feature>) {
+            let mut positions = Vec::new();
+            let mut features = Vec::new();
+            for o in observations {
+                positions.push(o.feature().as_ref().map(|f| {
+                    features.push(f);
+                    features.len() - 1
+                }));
+            }
+            (positions, features)
+        }
+        let (left_positions, left_features) = with_features(left.iter());
+        let (right_positions, right_features) = with_features(candidates.iter().map(|(_, r)| *r));
+        let matrix = backend
+            .pairwise(distance, &left_features, &right_features)
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Distance backend failed, distances are calculated on CPU. Error is: {:?}",
+                    e
+                );
+                pairwise(distance, &left_features, &right_features)
+            });
+
+        for (query_observation, (l, lp)) in left.iter().zip(&left_positions).enumerate() {
+            for ((index, r), rp) in candidates.iter().zip(&right_positions) {
+                let other = others[*index];
+                let mq = MetricQuery {
+                    feature_class,
+                    candidate_attrs: self.get_attributes(),
+                    candidate_observation: l,
+                    track_attrs: other.get_attributes(),
+                    track_observation: r,
+                };
+
+                if !self.metric.gate(&mq) {
+                    continue;
+                }
+
+                if let Ok(dists) = &mut results[*index] {
+                    dists.push(ObservationMetricOk {
+                        from: self.track_id,
+                        to: other.track_id,
+                        attribute_metric: OA::calculate_metric_object(
+                            &l.attr().as_ref(),
+                            &r.attr().as_ref(),
+                        ),
+                        feature_distance: lp.zip(*rp).map(|(i, j)| matrix[(i, j)]),
+                        weight: 1.0,
+                        stamp: r.2,
+                        query_observation,
+                    });
+                }
+            }
+        }
+
+        results
+    }
+
     pub fn lookup(&self, query: &TA::Lookup) -> bool {
         query.lookup(&self.attributes, &self.observations, &self.merge_history)
     }
@@ -663,9 +1710,9 @@ mod tests {
     use crate::prelude::{NoopNotifier, TrackBuilder};
     use crate::track::utils::{feature_attributes_sort_dec, FromVec};
     use crate::track::{
-        Feature, LookupRequest, MetricOutput, MetricQuery, NoopLookup, Observation,
-        ObservationAttributes, ObservationMetric, ObservationsDb, Track, TrackAttributes,
-        TrackAttributesUpdate, TrackStatus,
+        Feature, LookupRequest, MetricOutput, MetricQuery, NonFinitePolicy, NoopLookup,
+        Observation, ObservationAttributes, ObservationMetric, ObservationsDb, Track,
+        TrackAttributes, TrackAttributesUpdate, TrackId, TrackStatus,
     };
     use crate::EPS;
     use anyhow::Result;
@@ -716,7 +1763,7 @@ mod tests {
         fn optimize(
             &mut self,
             _feature_class: u64,
-            _merge_history: &[u64],
+            _merge_history: &[TrackId],
             _attributes: &mut DefaultAttrs,
             features: &mut Vec<Observation<f32>>,
             _prev_length: usize,
@@ -779,6 +1826,48 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn closest_distance() -> Result<()> {
+        let mut t1 = Track::new(1, DefaultMetric, DefaultAttrs, NoopNotifier);
+        t1.add_observation(
+            0,
+            Some(0.3),
+            Some(Feature::from_vec(vec![1f32, 0.0, 0.0])),
+            None,
+        )?;
+
+        let mut t2 = Track::new(2, DefaultMetric, DefaultAttrs, NoopNotifier);
+        t2.add_observation(
+            0,
+            Some(0.3),
+            Some(Feature::from_vec(vec![0f32, 1.0f32, 0.0])),
+            None,
+        )?;
+        t2.add_observation(
+            0,
+            Some(0.2),
+            Some(Feature::from_vec(vec![1f32, 1.0f32, 0.0])),
+            None,
+        )?;
+
+        let closest = t1
+            .closest_distance(&t2, 0, None, NonFinitePolicy::Reject)?
+            .unwrap();
+        assert!((closest.feature_distance.unwrap() - 1.0).abs() < EPS);
+        assert_eq!(closest.to, 2);
+
+        assert!(t1
+            .closest_distance(&t2, 0, Some(1.0), NonFinitePolicy::Reject)?
+            .is_none());
+        assert!(t1
+            .closest_distance(&t2, 0, Some(1.5), NonFinitePolicy::Reject)?
+            .is_some());
+        assert!(t1
+            .closest_distance(&t2, 1, None, NonFinitePolicy::Reject)
+            .is_err());
+        Ok(())
+    }
+
     #[test]
     fn merge_same() -> Result<()> {
         let mut t1 = Track::new(1, DefaultMetric, DefaultAttrs, NoopNotifier);
@@ -889,7 +1978,7 @@ mod tests {
             fn optimize(
                 &mut self,
                 _feature_class: u64,
-                _merge_history: &[u64],
+                _merge_history: &[TrackId],
                 _attributes: &mut TimeAttrs,
                 features: &mut Vec<Observation<f32>>,
                 _prev_length: usize,
@@ -1033,7 +2122,7 @@ mod tests {
             fn optimize(
                 &mut self,
                 _feature_class: u64,
-                _merge_history: &[u64],
+                _merge_history: &[TrackId],
                 _attributes: &mut LocalAttrs,
                 _features: &mut Vec<Observation<f32>>,
                 prev_length: usize,
@@ -1180,7 +2269,7 @@ mod tests {
             fn optimize(
                 &mut self,
                 _feature_class: u64,
-                _merge_history: &[u64],
+                _merge_history: &[TrackId],
                 _attributes: &mut UnitAttrs,
                 features: &mut Vec<Observation<()>>,
                 _prev_length: usize,
@@ -1204,7 +2293,7 @@ mod tests {
                 &self,
                 _attributes: &LookupAttrs,
                 _observations: &ObservationsDb<f32>,
-                _merge_history: &[u64],
+                _merge_history: &[TrackId],
             ) -> bool {
                 true
             }
@@ -1257,7 +2346,7 @@ mod tests {
             fn optimize(
                 &mut self,
                 _feature_class: u64,
-                _merge_history: &[u64],
+                _merge_history: &[TrackId],
                 _attrs: &mut LookupAttrs,
                 _features: &mut Vec<Observation<f32>>,
                 _prev_length: usize,
@@ -1,5 +1,7 @@
 use crate::track::notify::{ChangeNotifier, NoopNotifier};
-use crate::track::{Feature, ObservationAttributes, ObservationMetric, Track, TrackAttributes};
+use crate::track::{
+    Feature, ObservationAttributes, ObservationMetric, Track, TrackAttributes, TrackId,
+};
 use anyhow::Result;
 use rand::Rng;
 
@@ -79,10 +81,11 @@ where
     OA: ObservationAttributes,
     N: ChangeNotifier,
 {
-    id: u64,
+    id: TrackId,
     track_attrs: Option<TA>,
     metric: Option<M>,
     notifier: Option<N>,
+    epoch: usize,
     observations: Vec<TrackBuilderObservationRepr<OA, TA::Update>>,
 }
 
@@ -95,7 +98,7 @@ where
 {
     fn default() -> Self {
         let mut rng = rand::thread_rng();
-        TrackBuilder::new(rng.gen::<u64>())
+        TrackBuilder::new(rng.gen::<TrackId>())
     }
 }
 
@@ -111,12 +114,13 @@ where
     /// # Parameters
     /// * `id` - unique track ID
     ///
-    pub fn new(id: u64) -> TrackBuilder<TA, M, OA, N> {
+    pub fn new(id: TrackId) -> TrackBuilder<TA, M, OA, N> {
         Self {
             id,
             track_attrs: None,
             metric: None,
             notifier: None,
+            epoch: 0,
             observations: Vec::new(),
         }
     }
@@ -148,6 +152,14 @@ where
         self
     }
 
+    /// Sets the epoch the observations of the track are stamped with, look at
+    /// [ObservationStamp](crate::track::ObservationStamp)
+    ///
+    pub fn epoch(mut self, epoch: usize) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
     /// Sets additional observation. The method can be called multiple times to add several observations.
     ///
     /// # Parameters
@@ -172,6 +184,7 @@ where
             self.track_attrs.unwrap(),
             self.notifier.unwrap(),
         );
+        track.last_update.epoch = self.epoch;
         for (cls, oa, feat, upd) in self.observations {
             track.add_observation(cls, oa, feat, upd)?;
         }
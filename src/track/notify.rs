@@ -1,10 +1,12 @@
+use crate::track::TrackId;
+
 pub trait ChangeNotifier: Clone + Sync + Send + 'static {
-    fn send(&mut self, id: u64);
+    fn send(&mut self, id: TrackId);
 }
 
 #[derive(Clone, Debug)]
 pub struct NoopNotifier;
 
 impl ChangeNotifier for NoopNotifier {
-    fn send(&mut self, _id: u64) {}
+    fn send(&mut self, _id: TrackId) {}
 }
@@ -0,0 +1,188 @@
+use crate::metrics::mahalanobis::estimate_covariance;
+use crate::track::utils::FromVec;
+use crate::track::{Feature, FEATURE_LANES_SIZE};
+use crate::Errors;
+use anyhow::Result;
+use nalgebra::{DMatrix, DVector};
+
+/// The value added to the eigenvalues when the whitening scale is calculated, keeps the degenerate
+/// components finite
+///
+pub const DEFAULT_WHITENING_EPSILON: f32 = 1e-5;
+
+/// Linear projection of the feature vectors
+///
+/// The projected feature is `M * (x - mean)`, where `M` is the `output x input` projection matrix.
+/// Only the first `input_dimensions` values of the feature participate. The projection fitted with
+/// [fit_pca](FeatureProjection::fit_pca) keeps the principal components with the largest variance,
+/// which reduces the dimensionality of the features, whitening additionally scales the components to unit variance,
+/// which improves the separability of the features for the cosine distance.
+///
+#[derive(Debug, Clone)]
+pub struct FeatureProjection {
+    mean: DVector<f32>,
+    matrix: DMatrix<f32>,
+}
+
+impl FeatureProjection {
+    /// Constructs the projection
+    ///
+    /// # Arguments
+    /// * `mean` - the vector subtracted from the features before the projection
+    /// * `matrix` - `output x input` projection matrix
+    ///
+    pub fn new(mean: DVector<f32>, matrix: DMatrix<f32>) -> Result<Self> {
+        if mean.len() != matrix.ncols() {
+            return Err(Errors::DimensionsMismatch(matrix.ncols(), mean.len()).into());
+        }
+        Ok(Self { mean, matrix })
+    }
+
+    /// Fits the PCA projection from the sample features
+    ///
+    /// # Arguments
+    /// * `samples` - the sample features, at least two
+    /// * `dimensions` - the number of feature dimensions that participate
+    /// * `components` - the number of principal components to keep, not greater than `dimensions`
+    /// * `whiten` - scale the components to unit variance
+    ///
+    pub fn fit_pca(
+        samples: &[Feature],
+        dimensions: usize,
+        components: usize,
+        whiten: bool,
+    ) -> Result<Self> {
+        if components == 0 || components > dimensions {
+            return Err(Errors::DimensionsMismatch(dimensions, components).into());
+        }
+
+        let covariance = estimate_covariance(samples, dimensions)?;
+        let mean = samples.iter().fold(DVector::zeros(dimensions), |acc, f| {
+            acc + DVector::from_column_slice(&Vec::<f32>::from_vec(f)[..dimensions])
+        }) / samples.len() as f32;
+
+        let eigen = covariance.symmetric_eigen();
+        let mut order = (0..dimensions).collect::<Vec<_>>();
+        order.sort_by(|a, b| eigen.eigenvalues[*b].total_cmp(&eigen.eigenvalues[*a]));
+
+        let mut matrix = DMatrix::zeros(components, dimensions);
+        for (row, component) in order.into_iter().take(components).enumerate() {
+            let scale = if whiten {
+                1.0 / (eigen.eigenvalues[component].max(0.0) + DEFAULT_WHITENING_EPSILON).sqrt()
+            } else {
+                1.0
+            };
+            matrix.set_row(
+                row,
+                &(eigen.eigenvectors.column(component).transpose() * scale),
+            );
+        }
+
+        Self::new(mean, matrix)
+    }
+
+    /// The number of feature dimensions that participate
+    ///
+    pub fn input_dimensions(&self) -> usize {
+        self.matrix.ncols()
+    }
+
+    /// The number of dimensions of the projected features
+    ///
+    pub fn output_dimensions(&self) -> usize {
+        self.matrix.nrows()
+    }
+
+    /// The vector subtracted from the features before the projection
+    ///
+    pub fn mean(&self) -> &DVector<f32> {
+        &self.mean
+    }
+
+    /// The projection matrix
+    ///
+    pub fn matrix(&self) -> &DMatrix<f32> {
+        &self.matrix
+    }
+
+    /// Projects the feature
+    ///
+    /// Returns `Errors::DimensionsMismatch` when the feature is shorter than the projection input. The features are
+    /// stored in the lanes of `FEATURE_LANES_SIZE` values padded with zeros, so only the features with fewer lanes
+    /// than the projection input requires are detected as short.
+    ///
+    pub fn project(&self, feature: &Feature) -> Result<Feature> {
+        let values = Vec::<f32>::from_vec(feature);
+        if values.len() < self.input_dimensions() {
+            return Err(Errors::DimensionsMismatch(
+                self.input_dimensions(),
+                feature.len() * FEATURE_LANES_SIZE,
+            )
+            .into());
+        }
+        let x = DVector::from_column_slice(&values[..self.input_dimensions()]) - &self.mean;
+        Ok(Feature::from_vec((&self.matrix * x).as_slice().to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::distance::cosine;
+    use crate::track::projection::FeatureProjection;
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+    use nalgebra::{DMatrix, DVector};
+
+    #[test]
+    fn pca() {
+        // the samples lay on the line y = 2x, z = 0
+        let samples = (0..10)
+            .map(|i| Feature::from_vec(vec![i as f32, 2.0 * i as f32, 0.0]))
+            .collect::<Vec<_>>();
+
+        let p = FeatureProjection::fit_pca(&samples, 3, 1, false).unwrap();
+        assert_eq!(p.input_dimensions(), 3);
+        assert_eq!(p.output_dimensions(), 1);
+
+        let f = Vec::<f32>::from_vec(&p.project(&samples[9]).unwrap());
+        // the distance of the sample from the mean is kept by the principal component
+        assert!((f[0].abs() - 4.5 * 5.0_f32.sqrt()).abs() < 1e-3);
+        assert!(f[1..].iter().all(|v| *v == 0.0));
+
+        assert!(FeatureProjection::fit_pca(&samples, 3, 4, false).is_err());
+        // the features are stored in the lanes, so only the features with fewer lanes are short
+        assert!(p.project(&Feature::from_vec(vec![1.0])).is_ok());
+        let p = FeatureProjection::new(DVector::zeros(9), DMatrix::identity(9, 9)).unwrap();
+        assert!(p.project(&Feature::from_vec(vec![1.0])).is_err());
+    }
+
+    #[test]
+    fn whitening() {
+        // the first dimension has much larger variance than the second one
+        let samples = (0..20)
+            .map(|i| {
+                let (x, y) = ((i % 5) as f32 * 10.0, (i / 5) as f32 * 0.1);
+                Feature::from_vec(vec![x, y])
+            })
+            .collect::<Vec<_>>();
+
+        let p = FeatureProjection::fit_pca(&samples, 2, 2, true).unwrap();
+        let projected = samples
+            .iter()
+            .map(|f| Vec::<f32>::from_vec(&p.project(f).unwrap()))
+            .collect::<Vec<_>>();
+
+        for c in 0..2 {
+            let var = projected.iter().map(|f| f[c] * f[c]).sum::<f32>() / 19.0;
+            assert!((var - 1.0).abs() < 1e-2);
+        }
+
+        // the features that differ in the low-variance dimension become distinguishable
+        let (a, b) = (
+            Feature::from_vec(vec![20.0, 0.0]),
+            Feature::from_vec(vec![20.0, 0.3]),
+        );
+        assert!(cosine(&a, &b) > 0.999);
+        assert!(cosine(&p.project(&a).unwrap(), &p.project(&b).unwrap()) < 0.9);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::track::metadata::Metadata;
+use crate::track::notify::ChangeNotifier;
+use crate::track::utils::FromVec;
+use crate::track::{
+    Feature, Observation, ObservationAttributes, ObservationMetric, ObservationStamp,
+    ObservationsDb, Track, TrackAttributes, TrackId, TrackUpdate,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{HashMap, VecDeque};
+
+/// The observation is serialized as the `(attributes, feature, epoch)` tuple, the feature is serialized as the
+/// sequence of its values padded to the lanes, the epoch is the epoch of the stamp of the observation
+///
+impl<T> Serialize for Observation<T>
+where
+    T: Serialize + Send + Sync + Clone + 'static,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (
+            &self.0,
+            self.1.as_ref().map(Vec::<f32>::from_vec),
+            self.2.epoch,
+        )
+            .serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Observation<T>
+where
+    T: Deserialize<'de> + Send + Sync + Clone + 'static,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (attrs, feature, epoch) =
+            <(Option<T>, Option<Vec<f32>>, usize)>::deserialize(deserializer)?;
+        Ok(Observation(
+            attrs,
+            feature.map(Feature::from_vec),
+            ObservationStamp::new(epoch),
+        ))
+    }
+}
+
+#[derive(Serialize)]
+struct TrackRef<'a, TA, OA>
+where
+    OA: Send + Sync + Clone + 'static,
+{
+    track_id: TrackId,
+    attributes: &'a TA,
+    observations: &'a HashMap<u64, Vec<Observation<OA>>>,
+    merge_history: &'a [TrackId],
+    metadata: &'a Metadata,
+    fused_counts: &'a HashMap<u64, usize>,
+    last_update_epoch: Option<usize>,
+}
+
+/// The serialized state of the track, restored with the metric and the notifier of the store
+///
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "TA: DeserializeOwned, OA: DeserializeOwned"))]
+pub struct TrackState<TA, OA>
+where
+    OA: Send + Sync + Clone + 'static,
+{
+    pub track_id: TrackId,
+    pub attributes: TA,
+    pub observations: ObservationsDb<OA>,
+    pub merge_history: Vec<TrackId>,
+    #[serde(default)]
+    pub metadata: Metadata,
+    /// the number of the features fused into the observations of the classes
+    #[serde(default)]
+    pub fused_counts: HashMap<u64, usize>,
+    /// the epoch of the last update of the track made through the store, `None` for the states saved without it
+    #[serde(default)]
+    pub last_update_epoch: Option<usize>,
+}
+
+impl<TA, OA> TrackState<TA, OA>
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+{
+    /// Builds the track from the state with the metric and the notifier
+    ///
+    pub fn into_track<M, N>(self, metric: M, notifier: N) -> Track<TA, M, OA, N>
+    where
+        M: ObservationMetric<TA, OA>,
+        N: ChangeNotifier,
+    {
+        Track {
+            attributes: self.attributes,
+            track_id: self.track_id,
+            observations: self.observations,
+            metric,
+            merge_history: self.merge_history,
+            notifier,
+            last_update: TrackUpdate {
+                epoch: self.last_update_epoch.unwrap_or_default(),
+                ..TrackUpdate::default()
+            },
+            metadata: self.metadata,
+            observation_totals: HashMap::default(),
+            attribute_history: VecDeque::default(),
+            attribute_history_cap: 0,
+            fused_counts: self.fused_counts,
+        }
+    }
+}
+
+/// The track is serialized without the metric and the notifier, they are not the part of the track state
+///
+impl<TA, M, OA, N> Serialize for Track<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA> + Serialize,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes + Serialize,
+    N: ChangeNotifier,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TrackRef {
+            track_id: self.track_id,
+            attributes: &self.attributes,
+            observations: &self.observations,
+            merge_history: &self.merge_history,
+            metadata: &self.metadata,
+            fused_counts: &self.fused_counts,
+            last_update_epoch: Some(self.last_update.epoch),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// The track is deserialized with the default metric and the default notifier, use
+/// [TrackState](TrackState) to restore the track with the custom ones
+///
+impl<'de, TA, M, OA, N> Deserialize<'de> for Track<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA> + DeserializeOwned,
+    M: ObservationMetric<TA, OA> + Default,
+    OA: ObservationAttributes + DeserializeOwned,
+    N: ChangeNotifier + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(TrackState::deserialize(deserializer)?.into_track(M::default(), N::default()))
+    }
+}
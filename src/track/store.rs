@@ -1,24 +1,62 @@
+pub mod async_store;
 pub mod builder;
+mod compaction;
+pub mod cow;
+pub mod events;
+#[cfg(feature = "arrow")]
+pub mod export;
+mod external_ids;
+pub mod maintenance;
+mod pool;
+mod rerank;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "serde")]
+pub mod snapshot;
+pub mod stats;
 mod store_tests;
 pub mod track_distance;
+pub mod transaction;
+pub mod view;
 
+use crate::distance::backend::{CpuBackend, DistanceBackend};
+use crate::metrics::dispatch::DynObservationMetric;
 use crate::prelude::TrackBuilder;
+use crate::store::view::TrackView;
+use crate::track::metadata::{Metadata, MetadataValue};
 use crate::track::notify::{ChangeNotifier, NoopNotifier};
+use crate::track::projection::FeatureProjection;
+use crate::track::utils::FromVec;
 use crate::track::{
-    Feature, Observation, ObservationAttributes, ObservationMetric, ObservationMetricOk, Track,
-    TrackAttributes, TrackStatus,
+    AttributeMergePolicy, Deduplication, Feature, FeatureFusion, HistoryCap, MetricPolarity,
+    NonFinitePolicy, Observation, ObservationAttributes, ObservationMetric, ObservationMetricOk,
+    ObservationStamp, SplitPoint, Track, TrackAttributes, TrackId, TrackStatus, TrackUpdate,
+    FEATURE_LANES_SIZE,
 };
 use crate::Errors;
 use anyhow::Result;
 use crossbeam::channel::{Receiver, Sender};
+use events::{EventBus, TrackEvent};
+use external_ids::ExternalIds;
+use itertools::Itertools;
 use log::{error, warn};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex, MutexGuard};
+use maintenance::MaintenanceWorker;
+use pool::FeaturePool;
+use rand::Rng;
+use stats::QueryCounters;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::ops::{ControlFlow, RangeBounds};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LockResult, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use std::{mem, thread};
-use track_distance::{TrackDistanceErr, TrackDistanceOk};
+use track_distance::{CandidateDistances, TopK, TrackDistanceErr, TrackDistanceOk};
 
 #[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
 enum Commands<TA, M, OA, N>
 where
     TA: TrackAttributes<TA, OA>,
@@ -32,12 +70,41 @@ where
         Arc<Track<TA, M, OA, N>>,
         u64,
         bool,
+        Arc<dyn DistanceBackend>,
+        NonFinitePolicy,
         Sender<Results<OA>>,
         Sender<Results<OA>>,
     ),
+    CrossClassDistances(
+        Arc<Track<TA, M, OA, N>>,
+        (u64, u64),
+        bool,
+        Arc<dyn DynObservationMetric<TA, OA>>,
+        NonFinitePolicy,
+        Sender<Results<OA>>,
+        Sender<Results<OA>>,
+    ),
+    BatchDistances(
+        Arc<Vec<Track<TA, M, OA, N>>>,
+        u64,
+        bool,
+        Arc<dyn DistanceBackend>,
+        NonFinitePolicy,
+        Sender<Results<OA>>,
+    ),
     Lookup(TA::Lookup, Sender<Results<OA>>),
-    Merge(
+    TopK(
+        Arc<Track<TA, M, OA, N>>,
         u64,
+        usize,
+        bool,
+        NonFinitePolicy,
+        Sender<Results<OA>>,
+    ),
+    Find(Arc<TrackPredicate<TA, M, OA, N>>, Sender<Results<OA>>),
+    Merge(
+        TrackId,
+        usize,
         Track<TA, M, OA, N>,
         Vec<u64>,
         bool,
@@ -45,15 +112,337 @@ where
     ),
 }
 
+/// Folds the halves of the track id, so the `u64` ids are routed as is and both halves of the UUIDs select
+/// the shard
+///
+fn folded_id(track_id: TrackId) -> u64 {
+    (track_id ^ (track_id >> 64)) as u64
+}
+
+/// Selects the shard of the track by its id, look at [set_shard_router](TrackStore::set_shard_router)
+///
+/// The result is taken modulo the number of the shards.
+///
+pub type ShardRouter = dyn Fn(TrackId) -> usize + Send + Sync;
+
+/// The predicate that selects the tracks in [find_tracks](TrackStore::find_tracks)
+///
+pub type TrackPredicate<TA, M, OA, N> = dyn Fn(&Track<TA, M, OA, N>) -> bool + Send + Sync;
+
+/// The tracks of the store shard, shared with the snapshots of the store until the shard is changed
+///
+/// The distance queries read the shard under the read lock, so the concurrent queries don't wait for each other
+/// and the tracks are copied only when the shard is changed while a snapshot shares them.
+///
+pub(crate) struct Shard<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    #[allow(clippy::type_complexity)]
+    tracks: RwLock<Arc<HashMap<TrackId, Track<TA, M, OA, N>>>>,
+    /// the last updates of the tracks, the oldest first, the entries are checked against the tracks when
+    /// they reach the top
+    updates: Mutex<BinaryHeap<Reverse<(Instant, TrackId)>>>,
+    /// the number of the tracks of the store, shared by the shards
+    count: Arc<AtomicUsize>,
+    /// the counters of the store, shared by the shards
+    counters: Arc<QueryCounters>,
+    /// the shard is changed since the last maintenance
+    pub(crate) changed: AtomicBool,
+    /// the recycled feature buffers of the shard
+    pub(crate) pool: FeaturePool,
+}
+
+impl<TA, M, OA, N> Shard<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    fn new(count: Arc<AtomicUsize>, counters: Arc<QueryCounters>) -> Self {
+        Self {
+            tracks: RwLock::default(),
+            updates: Mutex::default(),
+            count,
+            counters,
+            changed: AtomicBool::new(false),
+            pool: FeaturePool::default(),
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn read(
+        &self,
+    ) -> LockResult<RwLockReadGuard<'_, Arc<HashMap<TrackId, Track<TA, M, OA, N>>>>> {
+        self.tracks.read()
+    }
+}
+
 /// The type that provides lock-ed access to certain shard store
 ///
-pub type StoreMutexGuard<'a, TA, M, FA, N> = MutexGuard<'a, HashMap<u64, Track<TA, M, FA, N>>>;
+/// The shard is copied when it is changed through the guard while a [snapshot](TrackStore::snapshot) of the
+/// store shares it, so the snapshots are not affected by the changes.
+///
+pub struct StoreMutexGuard<'a, TA, M, FA, N>
+where
+    TA: TrackAttributes<TA, FA>,
+    M: ObservationMetric<TA, FA>,
+    FA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    #[allow(clippy::type_complexity)]
+    guard: RwLockWriteGuard<'a, Arc<HashMap<TrackId, Track<TA, M, FA, N>>>>,
+    shard: &'a Shard<TA, M, FA, N>,
+    len: usize,
+}
+
+impl<'a, TA, M, FA, N> StoreMutexGuard<'a, TA, M, FA, N>
+where
+    TA: TrackAttributes<TA, FA>,
+    M: ObservationMetric<TA, FA>,
+    FA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    pub(crate) fn lock(shard: &'a Shard<TA, M, FA, N>) -> Self {
+        let started = Instant::now();
+        let guard = shard.tracks.write().unwrap();
+        shard.counters.lock_waits.record(started.elapsed());
+        let len = guard.len();
+        Self { guard, shard, len }
+    }
+
+    /// Inserts the track into the shard, the track is ordered by its last update for the eviction of the
+    /// least recently updated tracks, look at [set_capacity](TrackStore::set_capacity)
+    ///
+    pub fn insert(
+        &mut self,
+        track_id: TrackId,
+        track: Track<TA, M, FA, N>,
+    ) -> Option<Track<TA, M, FA, N>> {
+        let previous = self.deref_mut().insert(track_id, track);
+        self.order(track_id);
+        previous
+    }
+
+    /// Orders the track of the shard by its last update
+    ///
+    pub(crate) fn order(&self, track_id: TrackId) {
+        let update = match self.guard.get(&track_id) {
+            Some(track) => track.get_last_update().instant,
+            None => return,
+        };
+        let mut updates = self.shard.updates.lock().unwrap();
+        // the entries of the removed and the updated tracks are dropped
+        if updates.len() > 2 * self.guard.len() + 16 {
+            *updates = Self::ordered(&self.guard);
+        } else {
+            updates.push(Reverse((update, track_id)));
+        }
+    }
+
+    /// The least recently updated track of the shard except `skip`
+    ///
+    /// The stale entries of the updated tracks are moved to their last updates, the entries of the removed
+    /// tracks are dropped.
+    ///
+    fn oldest(&self, skip: TrackId) -> Option<(Instant, TrackId)> {
+        let mut updates = self.shard.updates.lock().unwrap();
+        let mut skipped = Vec::new();
+        let mut oldest = None;
+        while let Some(Reverse((update, track_id))) = updates.pop() {
+            let last_update = match self.guard.get(&track_id) {
+                Some(track) => track.get_last_update().instant,
+                None => continue,
+            };
+            if last_update != update {
+                updates.push(Reverse((last_update, track_id)));
+            } else if track_id == skip {
+                skipped.push(Reverse((update, track_id)));
+            } else {
+                updates.push(Reverse((update, track_id)));
+                oldest = Some((update, track_id));
+                break;
+            }
+        }
+        updates.extend(skipped);
+        oldest
+    }
+
+    /// Orders all the tracks of the shard again, e.g. when they are inserted bypassing [insert](Self::insert)
+    ///
+    fn reorder(&self) {
+        *self.shard.updates.lock().unwrap() = Self::ordered(&self.guard);
+    }
+
+    fn ordered(
+        tracks: &HashMap<TrackId, Track<TA, M, FA, N>>,
+    ) -> BinaryHeap<Reverse<(Instant, TrackId)>> {
+        tracks
+            .values()
+            .map(|t| Reverse((t.get_last_update().instant, t.track_id)))
+            .collect()
+    }
+}
+
+impl<TA, M, FA, N> Drop for StoreMutexGuard<'_, TA, M, FA, N>
+where
+    TA: TrackAttributes<TA, FA>,
+    M: ObservationMetric<TA, FA>,
+    FA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    fn drop(&mut self) {
+        let len = self.guard.len();
+        if len > self.len {
+            self.shard.count.fetch_add(len - self.len, Ordering::SeqCst);
+        } else {
+            self.shard.count.fetch_sub(self.len - len, Ordering::SeqCst);
+        }
+    }
+}
+
+impl<TA, M, FA, N> Deref for StoreMutexGuard<'_, TA, M, FA, N>
+where
+    TA: TrackAttributes<TA, FA>,
+    M: ObservationMetric<TA, FA>,
+    FA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    type Target = HashMap<TrackId, Track<TA, M, FA, N>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<TA, M, FA, N> DerefMut for StoreMutexGuard<'_, TA, M, FA, N>
+where
+    TA: TrackAttributes<TA, FA>,
+    M: ObservationMetric<TA, FA>,
+    FA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.shard.changed.store(true, Ordering::Relaxed);
+        Arc::make_mut(&mut self.guard)
+    }
+}
 
 /// The type that provides the initial track that was in the store before it was merged into
 /// target track
 ///
+/// The observation of the batch: the track id, the feature class, the feature attribute, the feature and
+/// the update of the track attributes
+///
+pub type BatchObservation<TA, OA> = (
+    TrackId,
+    u64,
+    Option<OA>,
+    Option<Feature>,
+    Option<<TA as TrackAttributes<TA, OA>>::Update>,
+);
+
 pub type OwnedMergeResult<TA, M, FA, N> = Result<Option<Track<TA, M, FA, N>>>;
 
+/// Defines how the store admits the new tracks when it is full, look at [set_capacity](TrackStore::set_capacity)
+///
+/// The policy is applied to the observations and the tracks that create the new tracks, the observations of
+/// the tracks in the store are always added.
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CapacityPolicy {
+    /// the new track is added and the least recently updated tracks are evicted
+    #[default]
+    EvictOldest,
+    /// the new track is rejected with `Errors::StoreFull`
+    Reject,
+    /// the new track is parked in the overflow queue of the size and added when the store has the space, the
+    /// later observations of the parked track are added to it, the tracks that don't fit the queue are rejected
+    /// with `Errors::StoreFull`
+    Overflow(usize),
+}
+
+enum Admission {
+    Admit,
+    Park,
+}
+
+/// The outcome of the observation added with [add_bounded](TrackStore::add_bounded), look at
+/// [CapacityPolicy](CapacityPolicy)
+///
+pub enum Insertion<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// the observation is added, the tracks evicted over the capacity are returned
+    Added(Vec<Track<TA, M, OA, N>>),
+    /// the observation is parked in the overflow queue
+    Parked,
+}
+
+impl<TA, M, OA, N> Insertion<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// The evicted tracks, none when the observation is parked
+    ///
+    pub fn evicted(self) -> Vec<Track<TA, M, OA, N>> {
+        match self {
+            Insertion::Added(evicted) => evicted,
+            Insertion::Parked => Vec::default(),
+        }
+    }
+
+    /// Returns `true` when the observation is parked in the overflow queue
+    ///
+    pub fn is_parked(&self) -> bool {
+        matches!(self, Insertion::Parked)
+    }
+}
+
+/// The new tracks parked until the store has the space, with the indices of their buckets
+///
+pub(crate) type Overflow<TA, M, OA, N> = Mutex<VecDeque<(usize, Track<TA, M, OA, N>)>>;
+
+/// Defines when the tracks that are not updated are evicted from the store
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackTtl {
+    /// the track is evicted when it is not updated for longer than the duration
+    Duration(Duration),
+    /// the track is evicted when it is not updated for more than the number of the store epochs,
+    /// see [advance_epoch](TrackStore::advance_epoch)
+    Epochs(usize),
+}
+
+impl TrackTtl {
+    /// Checks if the track is expired at the epoch of the store
+    ///
+    pub fn expired<TA, M, OA, N>(&self, track: &Track<TA, M, OA, N>, epoch: usize) -> bool
+    where
+        TA: TrackAttributes<TA, OA>,
+        M: ObservationMetric<TA, OA>,
+        OA: ObservationAttributes,
+        N: ChangeNotifier,
+    {
+        let update = track.get_last_update();
+        match self {
+            TrackTtl::Duration(ttl) => update.instant.elapsed() > *ttl,
+            TrackTtl::Epochs(ttl) => epoch.saturating_sub(update.epoch) > *ttl,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Results<OA>
 where
@@ -61,7 +450,11 @@ where
 {
     DistanceOk(Vec<ObservationMetricOk<OA>>),
     DistanceErr(Vec<ObservationMetricErr<OA>>),
-    BakedStatus(Vec<(u64, Result<TrackStatus>)>),
+    #[allow(clippy::type_complexity)]
+    BatchDistances(Vec<(Vec<ObservationMetricOk<OA>>, Vec<ObservationMetricErr<OA>>)>),
+    BakedStatus(Vec<(TrackId, Result<TrackStatus>)>),
+    Found(Vec<TrackId>),
+    TopK(Vec<ObservationMetricOk<OA>>, Vec<ObservationMetricErr<OA>>),
     Dropped,
     MergeResult(Result<()>),
 }
@@ -121,8 +514,36 @@ where
     metric: M,
     notifier: N,
     num_shards: usize,
+    buckets: usize,
+    track_count: Arc<AtomicUsize>,
+    routes: HashMap<TrackId, usize>,
+    router: Option<Arc<ShardRouter>>,
+    distance_backend: Arc<dyn DistanceBackend>,
+    non_finite_policy: NonFinitePolicy,
+    projections: HashMap<u64, Arc<FeatureProjection>>,
+    feature_dimensions: HashMap<u64, usize>,
+    #[allow(clippy::type_complexity)]
+    translations: HashMap<(u64, u64), Arc<dyn DynObservationMetric<TA, OA>>>,
+    ttl: Option<TrackTtl>,
+    lazy_eviction: bool,
+    capacity: Option<usize>,
+    capacity_policy: CapacityPolicy,
+    #[allow(clippy::type_complexity)]
+    overflow: Arc<Overflow<TA, M, OA, N>>,
+    history_caps: HashMap<u64, HistoryCap<OA>>,
+    deduplications: HashMap<u64, Deduplication>,
+    attribute_history_cap: usize,
+    fusions: HashMap<u64, FeatureFusion>,
+    default_fusion: Option<FeatureFusion>,
+    epoch: Arc<AtomicUsize>,
+    events: EventBus<TA>,
+    #[allow(clippy::type_complexity)]
+    evicted: Arc<Mutex<Vec<Track<TA, M, OA, N>>>>,
+    external_ids: Arc<Mutex<ExternalIds>>,
+    maintenance: Option<MaintenanceWorker>,
+    counters: Arc<QueryCounters>,
     #[allow(clippy::type_complexity)]
-    stores: Arc<Vec<Mutex<HashMap<u64, Track<TA, M, OA, N>>>>>,
+    stores: Arc<Vec<Shard<TA, M, OA, N>>>,
     // receiver: Receiver<Results<FA>>,
     #[allow(clippy::type_complexity)]
     executors: Vec<(Sender<Commands<TA, M, OA, N>>, JoinHandle<()>)>,
@@ -136,6 +557,7 @@ where
     N: ChangeNotifier,
 {
     fn drop(&mut self) {
+        self.stop_maintenance();
         let executors = mem::take(&mut self.executors);
         let (results_sender, results_receiver) = crossbeam::channel::unbounded();
         for (s, j) in executors {
@@ -165,11 +587,20 @@ where
 {
     #[allow(clippy::type_complexity)]
     fn handle_store_ops(
-        stores: Arc<Vec<Mutex<HashMap<u64, Track<TA, M, OA, N>>>>>,
+        stores: Arc<Vec<Shard<TA, M, OA, N>>>,
         store_id: usize,
+        buckets: usize,
+        epoch: Arc<AtomicUsize>,
+        events: EventBus<TA>,
         commands_receiver: Receiver<Commands<TA, M, OA, N>>,
     ) {
-        let store = stores.get(store_id).unwrap();
+        let shard = &stores[store_id * buckets..(store_id + 1) * buckets];
+        let read = || {
+            shard
+                .iter()
+                .map(|bucket| bucket.read().unwrap())
+                .collect::<Vec<_>>()
+        };
         while let Ok(c) = commands_receiver.recv() {
             match c {
                 Commands::Drop(channel) => {
@@ -177,10 +608,9 @@ where
                     return;
                 }
                 Commands::FindBaked(channel) => {
-                    let baked = store
-                        .lock()
-                        .unwrap()
+                    let baked = read()
                         .iter()
+                        .flat_map(|bucket| bucket.iter())
                         .flat_map(|(track_id, track)| {
                             match track.get_attributes().baked(&track.observations) {
                                 Ok(status) => match status {
@@ -196,85 +626,141 @@ where
                         return;
                     }
                 }
-                Commands::Distances(track, feature_class, only_baked, channel_ok, channel_err) => {
-                    let mut capacity = 0;
-                    let res = store
-                        .lock()
-                        .unwrap()
-                        .iter()
-                        .flat_map(|(_, other)| {
-                            if track.track_id == other.track_id {
-                                return None;
-                            }
+                Commands::Distances(
+                    track,
+                    feature_class,
+                    only_baked,
+                    backend,
+                    policy,
+                    channel_ok,
+                    channel_err,
+                ) => {
+                    let dists = Self::shard_distances(
+                        read().iter().flat_map(|bucket| bucket.values()),
+                        &track,
+                        feature_class,
+                        only_baked,
+                        backend.as_ref(),
+                    );
 
-                            if !only_baked {
-                                let dists = track.distances(other, feature_class);
-                                match dists {
-                                    Ok(dists) => {
-                                        capacity += dists.len();
-                                        Some(Ok(track.metric.postprocess_distances(dists)))
-                                    }
-                                    Err(e) => match e.downcast_ref::<Errors>() {
-                                        Some(Errors::IncompatibleAttributes) => None,
-                                        _ => Some(Err(e)),
-                                    },
-                                }
-                            } else {
-                                match other.get_attributes().baked(&other.observations) {
-                                    Ok(TrackStatus::Ready) => {
-                                        let dists = track.distances(other, feature_class);
-                                        match dists {
-                                            Ok(dists) => {
-                                                capacity += dists.len();
-                                                Some(Ok(track.metric.postprocess_distances(dists)))
-                                            }
-                                            Err(e) => match e.downcast_ref::<Errors>() {
-                                                Some(Errors::IncompatibleAttributes) => None,
-                                                _ => Some(Err(e)),
-                                            },
-                                        }
-                                    }
-                                    _ => None,
-                                }
-                            }
+                    Self::send_distances(
+                        dists,
+                        |dists| {
+                            track
+                                .metric
+                                .postprocess_class_distances(feature_class, dists)
+                        },
+                        track.metric.polarity(),
+                        policy,
+                        channel_ok,
+                        channel_err,
+                    );
+                }
+                Commands::CrossClassDistances(
+                    track,
+                    (query_class, gallery_class),
+                    only_baked,
+                    translation,
+                    policy,
+                    channel_ok,
+                    channel_err,
+                ) => {
+                    let dists = read()
+                        .iter()
+                        .flat_map(|bucket| bucket.values())
+                        .filter(|other| track.track_id != other.track_id)
+                        .filter(|other| {
+                            !only_baked
+                                || matches!(
+                                    other.get_attributes().baked(&other.observations),
+                                    Ok(TrackStatus::Ready)
+                                )
                         })
-                        .collect::<Vec<_>>();
-
-                    let mut distances = Vec::with_capacity(capacity);
-                    let mut errors = Vec::new();
+                        .map(|other| {
+                            track.cross_class_distances(
+                                other,
+                                query_class,
+                                gallery_class,
+                                translation.as_ref(),
+                            )
+                        })
+                        .collect();
 
-                    for r in res {
-                        match r {
-                            Ok(dists) => {
-                                distances.extend_from_slice(&dists);
-                            }
-                            e => errors.push(e),
-                        }
-                    }
+                    Self::send_distances(
+                        dists,
+                        |dists| translation.dyn_postprocess_class_distances(gallery_class, dists),
+                        translation.dyn_polarity(),
+                        policy,
+                        channel_ok,
+                        channel_err,
+                    );
+                }
+                Commands::BatchDistances(
+                    tracks,
+                    feature_class,
+                    only_baked,
+                    backend,
+                    policy,
+                    channel,
+                ) => {
+                    let dists = {
+                        let store = read();
+                        tracks
+                            .iter()
+                            .map(|track| {
+                                Self::shard_distances(
+                                    store.iter().flat_map(|bucket| bucket.values()),
+                                    track,
+                                    feature_class,
+                                    only_baked,
+                                    backend.as_ref(),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    };
 
-                    let r = channel_ok.send(Results::DistanceOk(distances));
-                    if let Err(e) = r {
-                        warn!("Unable to send data back to caller. Channel error: {:?}", e);
-                    }
+                    let res = tracks
+                        .iter()
+                        .zip(dists)
+                        .map(|(track, dists)| {
+                            Self::collect_distances(
+                                dists,
+                                |dists| {
+                                    track
+                                        .metric
+                                        .postprocess_class_distances(feature_class, dists)
+                                },
+                                track.metric.polarity(),
+                                policy,
+                            )
+                        })
+                        .collect();
 
-                    let r = channel_err.send(Results::DistanceErr(errors));
-                    if let Err(e) = r {
+                    if let Err(e) = channel.send(Results::BatchDistances(res)) {
                         warn!("Unable to send data back to caller. Channel error: {:?}", e);
                     }
                 }
-                Commands::Merge(dest_id, src, classes, merge_history, channel_opt) => {
-                    let mut store = store.lock().unwrap();
+                Commands::Merge(dest_id, bucket, src, classes, merge_history, channel_opt) => {
+                    let mut store = StoreMutexGuard::lock(&stores[bucket]);
                     let dest = store.get_mut(&dest_id);
 
                     let res = match dest {
                         Some(dest) => {
-                            if dest_id == src.track_id {
+                            let res = if dest_id == src.track_id {
                                 Err(Errors::SameTrackCalculation(dest_id).into())
                             } else if !classes.is_empty() {
                                 dest.merge(&src, &classes, merge_history)
                             } else {
                                 dest.merge(&src, &src.get_feature_classes(), merge_history)
+                            };
+                            if res.is_ok() {
+                                dest.stamp(epoch.load(Ordering::SeqCst));
+                                events.publish(|| TrackEvent::Merged {
+                                    track: TrackView::new(dest),
+                                    sources: vec![src.track_id],
+                                });
                             }
+                            res
                         }
 
                         None => Err(Errors::TrackNotFound(dest_id).into()),
@@ -287,10 +773,10 @@ where
                     }
                 }
                 Commands::Lookup(q, channel) => {
-                    let store = store.lock().unwrap();
                     let res = channel.send(Results::BakedStatus(
-                        store
-                            .values()
+                        read()
+                            .iter()
+                            .flat_map(|bucket| bucket.values())
                             .filter(|x| x.lookup(&q))
                             .map(|x| (x.track_id, x.get_attributes().baked(&x.observations)))
                             .collect(),
@@ -300,8 +786,155 @@ where
                         warn!("Receiver channel was dropped before the data sent into it. Error is: {:?}", send_res);
                     }
                 }
+                Commands::TopK(track, feature_class, k, only_baked, policy, channel) => {
+                    let mut top = TopK::new(k, track.metric.polarity());
+                    let mut errors = Vec::new();
+                    for other in read()
+                        .iter()
+                        .flat_map(|bucket| bucket.values())
+                        .filter(|other| k > 0 && Self::is_candidate(&track, other, only_baked))
+                    {
+                        // the candidates that are not closer than the k-th closest one are skipped
+                        match track.closest_distance(other, feature_class, top.bound(), policy) {
+                            Ok(Some(closest)) => track
+                                .metric
+                                .postprocess_class_distances(feature_class, vec![closest])
+                                .into_iter()
+                                .for_each(|closest| top.push(closest)),
+                            Ok(None) => {}
+                            Err(e) => match e.downcast_ref::<Errors>() {
+                                Some(Errors::IncompatibleAttributes) => {}
+                                _ => errors.push(Err(e)),
+                            },
+                        }
+                    }
+
+                    if let Err(e) = channel.send(Results::TopK(top.into_sorted_vec(), errors)) {
+                        warn!("Unable to send data back to caller. Channel error: {:?}", e);
+                    }
+                }
+                Commands::Find(predicate, channel) => {
+                    let res = channel.send(Results::Found(
+                        read()
+                            .iter()
+                            .flat_map(|bucket| bucket.values())
+                            .filter(|x| predicate(x))
+                            .map(|x| x.track_id)
+                            .collect(),
+                    ));
+
+                    if let Err(send_res) = res {
+                        warn!("Receiver channel was dropped before the data sent into it. Error is: {:?}", send_res);
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn is_candidate(
+        track: &Track<TA, M, OA, N>,
+        other: &Track<TA, M, OA, N>,
+        only_baked: bool,
+    ) -> bool {
+        track.track_id != other.track_id
+            && (!only_baked
+                || matches!(
+                    other.get_attributes().baked(&other.observations),
+                    Ok(TrackStatus::Ready)
+                ))
+    }
+
+    fn shard_distances<'a>(
+        shard: impl Iterator<Item = &'a Track<TA, M, OA, N>>,
+        track: &Track<TA, M, OA, N>,
+        feature_class: u64,
+        only_baked: bool,
+        backend: &dyn DistanceBackend,
+    ) -> Vec<Result<Vec<ObservationMetricOk<OA>>>> {
+        let candidates = shard
+            .filter(|other| Self::is_candidate(track, other, only_baked))
+            .collect::<Vec<_>>();
+        Self::candidate_distances(&candidates, track, feature_class, backend)
+    }
+
+    pub(crate) fn candidate_distances(
+        candidates: &[&Track<TA, M, OA, N>],
+        track: &Track<TA, M, OA, N>,
+        feature_class: u64,
+        backend: &dyn DistanceBackend,
+    ) -> Vec<Result<Vec<ObservationMetricOk<OA>>>> {
+        match track.metric.batch_distance() {
+            Some(distance) if candidates.len() >= backend.min_candidates() => {
+                track.batch_distances(candidates, feature_class, distance, backend)
+            }
+            _ => candidates
+                .iter()
+                .map(|other| track.distances(other, feature_class))
+                .collect(),
+        }
+    }
+
+    fn send_distances<P>(
+        dists: Vec<Result<Vec<ObservationMetricOk<OA>>>>,
+        postprocess: P,
+        polarity: MetricPolarity,
+        policy: NonFinitePolicy,
+        channel_ok: Sender<Results<OA>>,
+        channel_err: Sender<Results<OA>>,
+    ) where
+        P: Fn(Vec<ObservationMetricOk<OA>>) -> Vec<ObservationMetricOk<OA>>,
+    {
+        let (distances, errors) = Self::collect_distances(dists, postprocess, polarity, policy);
+
+        let r = channel_ok.send(Results::DistanceOk(distances));
+        if let Err(e) = r {
+            warn!("Unable to send data back to caller. Channel error: {:?}", e);
+        }
+
+        let r = channel_err.send(Results::DistanceErr(errors));
+        if let Err(e) = r {
+            warn!("Unable to send data back to caller. Channel error: {:?}", e);
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn collect_distances<P>(
+        dists: Vec<Result<Vec<ObservationMetricOk<OA>>>>,
+        postprocess: P,
+        polarity: MetricPolarity,
+        policy: NonFinitePolicy,
+    ) -> (Vec<ObservationMetricOk<OA>>, Vec<ObservationMetricErr<OA>>)
+    where
+        P: Fn(Vec<ObservationMetricOk<OA>>) -> Vec<ObservationMetricOk<OA>>,
+    {
+        let mut capacity = 0;
+        let res = dists
+            .into_iter()
+            .flat_map(|dists| match dists {
+                Ok(dists) => Some(policy.apply(postprocess(dists), polarity).map(|dists| {
+                    capacity += dists.len();
+                    dists
+                })),
+                Err(e) => match e.downcast_ref::<Errors>() {
+                    Some(Errors::IncompatibleAttributes) => None,
+                    _ => Some(Err(e)),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let mut distances = Vec::with_capacity(capacity);
+        let mut errors = Vec::new();
+
+        for r in res {
+            match r {
+                Ok(dists) => {
+                    distances.extend_from_slice(&dists);
+                }
+                e => errors.push(e),
             }
         }
+
+        (distances, errors)
     }
 
     /// Constructor method
@@ -316,16 +949,63 @@ where
     /// If `None` is passed, `Default` initializers are used.
     ///
     pub fn new(metric: M, default_attributes: TA, notifier: N, shards: usize) -> Self {
+        Self::with_buckets(metric, default_attributes, notifier, shards, 1)
+    }
+
+    /// Constructor method for the store with the shards split into the lock buckets
+    ///
+    /// Every shard is still served by its own executor thread, but the tracks of the shard are spread over
+    /// the buckets by their ids and every bucket has its own lock, so the ingestion threads adding to the
+    /// different tracks of the shard contend only when the tracks fall into the same bucket. The queries read
+    /// all the buckets of the shard. Look at [new](TrackStore::new) for the other arguments.
+    ///
+    pub fn with_buckets(
+        metric: M,
+        default_attributes: TA,
+        notifier: N,
+        shards: usize,
+        buckets: usize,
+    ) -> Self {
+        assert!(buckets > 0, "The shard must have at least one bucket");
+        let track_count = Arc::new(AtomicUsize::new(0));
+        let counters = Arc::new(QueryCounters::default());
         let stores = Arc::new(
-            (0..shards)
-                .map(|_| Mutex::new(HashMap::default()))
+            (0..shards * buckets)
+                .map(|_| Shard::new(track_count.clone(), counters.clone()))
                 .collect::<Vec<_>>(),
         );
         let my_stores = stores.clone();
+        let epoch = Arc::new(AtomicUsize::new(0));
+        let events = EventBus::default();
 
         Self {
             //receiver: results_receiver,
             num_shards: shards,
+            buckets,
+            track_count,
+            routes: HashMap::default(),
+            router: None,
+            distance_backend: Arc::new(CpuBackend),
+            non_finite_policy: NonFinitePolicy::default(),
+            projections: HashMap::default(),
+            feature_dimensions: HashMap::default(),
+            translations: HashMap::default(),
+            ttl: None,
+            lazy_eviction: true,
+            capacity: None,
+            capacity_policy: CapacityPolicy::default(),
+            overflow: Arc::default(),
+            history_caps: HashMap::default(),
+            deduplications: HashMap::default(),
+            attribute_history_cap: 0,
+            fusions: HashMap::default(),
+            default_fusion: None,
+            epoch: epoch.clone(),
+            events: events.clone(),
+            evicted: Arc::new(Mutex::new(Vec::default())),
+            external_ids: Arc::new(Mutex::new(ExternalIds::default())),
+            maintenance: None,
+            counters,
             notifier,
             default_attributes,
             metric,
@@ -335,8 +1015,17 @@ where
                     .map(|s| {
                         let (commands_sender, commands_receiver) = crossbeam::channel::unbounded();
                         let stores = stores.clone();
+                        let epoch = epoch.clone();
+                        let events = events.clone();
                         let thread = thread::spawn(move || {
-                            Self::handle_store_ops(stores, s, commands_receiver);
+                            Self::handle_store_ops(
+                                stores,
+                                s,
+                                buckets,
+                                epoch,
+                                events,
+                                commands_receiver,
+                            );
                         });
                         (commands_sender, thread)
                     })
@@ -352,8 +1041,10 @@ where
     /// * `TrackStatus::Wasted` or
     /// * `Err(e)`
     ///
-    pub fn find_usable(&mut self) -> Vec<(u64, Result<TrackStatus>)> {
-        let mut results = Vec::with_capacity(self.shard_stats().iter().sum());
+    pub fn find_usable(&mut self) -> Vec<(TrackId, Result<TrackStatus>)> {
+        QueryCounters::record(&self.counters.lookups);
+        self.evict_lazily();
+        let mut results = Vec::with_capacity(self.tracks_count());
         let (results_sender, results_receiver) = crossbeam::channel::unbounded();
         for (cmd, _) in &mut self.executors {
             cmd.send(Commands::FindBaked(results_sender.clone()))
@@ -376,19 +1067,40 @@ where
     /// Counts of objects per every store shard
     ///
     pub fn shard_stats(&self) -> Vec<usize> {
-        let mut result = Vec::new();
-        for s in self.stores.iter() {
-            result.push(s.lock().unwrap().len());
-        }
-        result
+        self.stores
+            .chunks(self.buckets)
+            .map(|shard| shard.iter().map(|b| b.read().unwrap().len()).sum())
+            .collect()
+    }
+
+    /// The number of the tracks in the store
+    ///
+    pub fn tracks_count(&self) -> usize {
+        self.track_count.load(Ordering::SeqCst)
+    }
+
+    /// The number of the lock buckets of every shard, look at [with_buckets](TrackStore::with_buckets)
+    ///
+    pub fn buckets(&self) -> usize {
+        self.buckets
     }
 
     /// Pulls (and removes) requested tracks from the store.
     ///
-    pub fn fetch_tracks(&mut self, tracks: &[u64]) -> Vec<Track<TA, M, OA, N>> {
+    /// The external ids of the tracks are unmapped.
+    ///
+    pub fn fetch_tracks(&mut self, tracks: &[TrackId]) -> Vec<Track<TA, M, OA, N>> {
+        let res = self.take_tracks(tracks);
+        self.unmap_tracks(res.iter().map(|t| t.track_id));
+        res
+    }
+
+    /// Removes the tracks from the store to put them back later, the external ids stay mapped
+    ///
+    fn take_tracks(&mut self, tracks: &[TrackId]) -> Vec<Track<TA, M, OA, N>> {
         let mut res = Vec::default();
         for track_id in tracks {
-            let mut tracks_shard = self.get_store(*track_id as usize);
+            let mut tracks_shard = self.get_store(*track_id);
             if let Some(t) = tracks_shard.remove(track_id) {
                 res.push(t);
             }
@@ -396,37 +1108,868 @@ where
         res
     }
 
-    /// Returns track builder object that can build new track compatible with the storage.
+    /// Sets the time-to-live policy of the tracks, `None` disables the eviction
     ///
-    /// Attributes, metric, notifier are cloned from store
+    pub fn set_ttl(&mut self, ttl: Option<TrackTtl>) {
+        self.ttl = ttl;
+    }
+
+    /// The time-to-live policy of the tracks
     ///
-    pub fn new_track(&self, track_id: u64) -> TrackBuilder<TA, M, OA, N> {
-        TrackBuilder::new(track_id)
-            .metric(self.metric.clone())
-            .attributes(self.default_attributes.clone())
-            .notifier(self.notifier.clone())
+    pub fn ttl(&self) -> Option<TrackTtl> {
+        self.ttl
     }
 
-    /// Returns track builder object that can build new track compatible with the storage.
+    /// Configures whether the expired tracks are evicted lazily before the queries, enabled by default
     ///
-    /// Attributes, metric, notifier are cloned from store
+    /// The tracks evicted lazily are kept by the store until [purge](TrackStore::purge) is called.
     ///
-    pub fn new_track_random_id(&self) -> TrackBuilder<TA, M, OA, N> {
-        TrackBuilder::default()
-            .metric(self.metric.clone())
-            .attributes(self.default_attributes.clone())
-            .notifier(self.notifier.clone())
+    pub fn set_lazy_eviction(&mut self, lazy: bool) {
+        self.lazy_eviction = lazy;
     }
 
-    /// Calculates distances for external track (not in track store) to all tracks in DB which are
-    /// allowed.
+    /// The current epoch of the store
     ///
-    /// # Arguments
-    /// * `tracks` - batch external tracks that is used as distance subjects
-    /// * `feature_class` - what feature to use for distance calculation
-    /// * `only_baked` - calculate distances only across the tracks that have `TrackBakingStatus::Ready` status
+    pub fn epoch(&self) -> usize {
+        self.epoch.load(Ordering::SeqCst)
+    }
+
+    /// Advances the epoch of the store, e.g. once per frame, returns the new epoch
     ///
-    pub fn foreign_track_distances(
+    /// The epochs count the time for [TrackTtl::Epochs](TrackTtl::Epochs).
+    ///
+    pub fn advance_epoch(&self) -> usize {
+        self.epoch.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    fn evict(&self) -> Vec<Track<TA, M, OA, N>> {
+        let ttl = match self.ttl {
+            Some(ttl) => ttl,
+            None => return Vec::default(),
+        };
+        let evicted = Self::evict_expired(&self.stores, ttl, self.epoch());
+        self.publish_evicted(&evicted);
+        evicted
+    }
+
+    fn evict_expired(
+        stores: &[Shard<TA, M, OA, N>],
+        ttl: TrackTtl,
+        epoch: usize,
+    ) -> Vec<Track<TA, M, OA, N>> {
+        let mut evicted = Vec::default();
+        for store in stores {
+            let mut store = StoreMutexGuard::lock(store);
+            let expired = store
+                .values()
+                .filter(|t| ttl.expired(t, epoch))
+                .map(|t| t.track_id)
+                .collect::<Vec<_>>();
+            evicted.extend(expired.iter().flat_map(|track_id| store.remove(track_id)));
+        }
+        evicted
+    }
+
+    fn publish_evicted(&self, evicted: &[Track<TA, M, OA, N>]) {
+        Self::release_evicted(&self.events, &self.external_ids, &self.counters, evicted);
+    }
+
+    fn release_evicted(
+        events: &EventBus<TA>,
+        external_ids: &Mutex<ExternalIds>,
+        counters: &QueryCounters,
+        evicted: &[Track<TA, M, OA, N>],
+    ) {
+        QueryCounters::record_many(&counters.evictions, evicted.len());
+        let mut ids = external_ids.lock().unwrap();
+        for track in evicted {
+            ids.remove(track.track_id);
+        }
+        drop(ids);
+        for track in evicted {
+            events.publish(|| TrackEvent::Evicted(TrackView::new(track)));
+        }
+    }
+
+    fn evict_lazily(&self) {
+        if self.lazy_eviction && self.ttl.is_some() {
+            let evicted = self.evict();
+            self.evicted.lock().unwrap().extend(evicted);
+        }
+    }
+
+    /// Subscribes to the lifecycle events of the tracks, look at [TrackEvent](TrackEvent)
+    ///
+    /// The events are sent when the tracks are changed through the store, the changes made to the tracks
+    /// accessed with [get_store](TrackStore::get_store) are not reported.
+    ///
+    pub fn subscribe(&self) -> Receiver<TrackEvent<TA>> {
+        self.events.subscribe()
+    }
+
+    /// Sets the maximum number of the tracks in the store, `None` removes the limit
+    ///
+    /// When the insert makes the store exceed the capacity, the store applies the capacity policy, look at
+    /// [set_capacity_policy](TrackStore::set_capacity_policy). The store is not shrunk by the method, the tracks
+    /// over the new capacity are evicted by the next insert when the policy evicts the tracks.
+    ///
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+    }
+
+    /// The maximum number of the tracks in the store
+    ///
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Sets how the store admits the new tracks when it is full, look at [CapacityPolicy](CapacityPolicy)
+    ///
+    /// The parked tracks are added by the background maintenance or by the next insert after the space is freed,
+    /// or by [drain_overflow](TrackStore::drain_overflow). The parked tracks are kept when the policy is changed.
+    ///
+    pub fn set_capacity_policy(&mut self, policy: CapacityPolicy) {
+        self.capacity_policy = policy;
+    }
+
+    /// How the store admits the new tracks when it is full
+    ///
+    pub fn capacity_policy(&self) -> CapacityPolicy {
+        self.capacity_policy
+    }
+
+    /// The number of the new tracks parked in the overflow queue
+    ///
+    pub fn overflow_len(&self) -> usize {
+        self.overflow.lock().unwrap().len()
+    }
+
+    /// Adds the parked tracks while the store has the space, the rest are kept in order
+    ///
+    /// # Returns
+    /// The results of the added tracks in order of the queue
+    ///
+    pub fn drain_overflow(&mut self) -> Vec<Result<()>> {
+        Self::release_parked(
+            &self.stores,
+            &self.overflow,
+            self.capacity,
+            self.epoch(),
+            &self.events,
+        )
+    }
+
+    fn drain_parked(&mut self) {
+        if self.overflow_len() == 0 {
+            return;
+        }
+        for res in self.drain_overflow() {
+            if let Err(e) = res {
+                warn!("Unable to add the parked track: {:?}", e);
+            }
+        }
+    }
+
+    pub(crate) fn release_parked(
+        stores: &[Shard<TA, M, OA, N>],
+        overflow: &Overflow<TA, M, OA, N>,
+        capacity: Option<usize>,
+        epoch: usize,
+        events: &EventBus<TA>,
+    ) -> Vec<Result<()>> {
+        let mut overflow = overflow.lock().unwrap();
+        let mut results = Vec::default();
+        while let Some(&(bucket, _)) = overflow.front() {
+            let shard = &stores[bucket];
+            if capacity.map_or(false, |capacity| {
+                shard.count.load(Ordering::SeqCst) >= capacity
+            }) {
+                break;
+            }
+            let (_, mut track) = overflow.pop_front().unwrap();
+            let track_id = track.track_id;
+            let mut tracks = StoreMutexGuard::lock(shard);
+            if tracks.contains_key(&track_id) {
+                results.push(Err(Errors::DuplicateTrackId(track_id).into()));
+                continue;
+            }
+            track.stamp(epoch);
+            events.publish(|| TrackEvent::Created(TrackView::new(&track)));
+            tracks.insert(track_id, track);
+            results.push(Ok(()));
+        }
+        results
+    }
+
+    /// Parks the observation of the new track, the observation of the parked track is added to it
+    ///
+    fn park(&self, observation: BatchObservation<TA, OA>) -> Result<()> {
+        let track_id = observation.0;
+        let mut overflow = self.overflow.lock().unwrap();
+        let mut tracks = HashMap::default();
+        let position = overflow.iter().position(|(_, t)| t.track_id == track_id);
+        if let Some(position) = position {
+            let (_, track) = overflow.remove(position).unwrap();
+            tracks.insert(track_id, track);
+        }
+        let res = self.apply_observation(&mut tracks, self.epoch(), observation);
+        if let Some(track) = tracks.remove(&track_id) {
+            let parked = (self.get_bucket(track_id), track);
+            match position {
+                Some(position) => overflow.insert(position, parked),
+                None => overflow.push_back(parked),
+            }
+        }
+        res.map(|_| ())
+    }
+
+    /// Moves the parked tracks to their buckets after the tracks are routed again
+    ///
+    pub(crate) fn reroute_parked(&self) {
+        for (bucket, track) in self.overflow.lock().unwrap().iter_mut() {
+            *bucket = self.get_bucket(track.track_id);
+        }
+    }
+
+    /// Decides if the track is admitted, `admitted` are the new tracks admitted but not added yet
+    ///
+    fn admit(&self, track_id: TrackId, admitted: &mut HashSet<TrackId>) -> Result<Admission> {
+        let parked = || {
+            self.overflow
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|(_, t)| t.track_id == track_id)
+        };
+        if self.overflow_len() > 0 && parked() {
+            return Ok(Admission::Park);
+        }
+        let capacity = match (self.capacity, self.capacity_policy) {
+            (Some(capacity), CapacityPolicy::Reject | CapacityPolicy::Overflow(_)) => capacity,
+            _ => return Ok(Admission::Admit),
+        };
+        if admitted.contains(&track_id)
+            || self.stores[self.get_bucket(track_id)]
+                .read()
+                .unwrap()
+                .contains_key(&track_id)
+        {
+            return Ok(Admission::Admit);
+        }
+        if self.tracks_count() + admitted.len() < capacity {
+            admitted.insert(track_id);
+            return Ok(Admission::Admit);
+        }
+        match self.capacity_policy {
+            CapacityPolicy::Overflow(size) if self.overflow_len() < size => Ok(Admission::Park),
+            _ => Err(Errors::StoreFull(track_id, capacity).into()),
+        }
+    }
+
+    /// Sets the policy limiting the number of the observations of the feature class kept by every track
+    ///
+    /// The cap is applied when the observation is added through the store, after the metric optimized the
+    /// observations, so the metric still sees the observation being added. The observations added by the merges
+    /// and to the tracks accessed with [get_store](TrackStore::get_store) are capped by the next addition.
+    ///
+    pub fn set_history_cap(&mut self, feature_class: u64, cap: HistoryCap<OA>) {
+        self.history_caps.insert(feature_class, cap);
+    }
+
+    /// Removes the history cap of the feature class, the observations are kept as the metric decides
+    ///
+    pub fn remove_history_cap(&mut self, feature_class: u64) -> Option<HistoryCap<OA>> {
+        self.history_caps.remove(&feature_class)
+    }
+
+    /// The history cap of the feature class
+    ///
+    pub fn history_cap(&self, feature_class: u64) -> Option<&HistoryCap<OA>> {
+        self.history_caps.get(&feature_class)
+    }
+
+    /// Sets the number of the attribute versions kept by every track, `0` disables the history, look at
+    /// [attribute_history](Track::attribute_history)
+    ///
+    /// The cap is set for the tracks in the store and for the tracks created by the store later, the tracks added
+    /// with [add_track](TrackStore::add_track) get the cap unless they have the own one.
+    ///
+    pub fn set_attribute_history_cap(&mut self, cap: usize) {
+        self.attribute_history_cap = cap;
+        for store in self.stores.iter() {
+            let mut store = StoreMutexGuard::lock(store);
+            for track in store.values_mut() {
+                track.set_attribute_history_cap(cap);
+            }
+        }
+    }
+
+    /// Returns the number of the attribute versions kept by every track
+    ///
+    pub fn attribute_history_cap(&self) -> usize {
+        self.attribute_history_cap
+    }
+
+    /// Sets the deduplication of the features of the feature class, look at [Deduplication](Deduplication)
+    ///
+    /// The deduplication is applied when the observation is added through the store to the existing track. The
+    /// track attributes are updated by the dropped or merged observations as by the added ones.
+    ///
+    pub fn set_deduplication(&mut self, feature_class: u64, deduplication: Deduplication) {
+        self.deduplications.insert(feature_class, deduplication);
+    }
+
+    /// Removes the deduplication of the feature class, all the features are added again
+    ///
+    pub fn remove_deduplication(&mut self, feature_class: u64) -> Option<Deduplication> {
+        self.deduplications.remove(&feature_class)
+    }
+
+    /// The deduplication of the feature class
+    ///
+    pub fn deduplication(&self, feature_class: u64) -> Option<&Deduplication> {
+        self.deduplications.get(&feature_class)
+    }
+
+    /// Sets the fusion of the observations of the feature class, every track keeps the single fused observation
+    /// of the class, look at [FeatureFusion](FeatureFusion)
+    ///
+    /// The fusion is applied when the observation is added through the store, after the metric optimized the
+    /// observations and before the history cap. The observation with the feature of the dimensions other than
+    /// the fused feature has is rejected with `Errors::FeatureDimensionsMismatch` before it is added.
+    ///
+    pub fn set_feature_fusion(&mut self, feature_class: u64, fusion: FeatureFusion) {
+        self.fusions.insert(feature_class, fusion);
+    }
+
+    /// Removes the fusion of the feature class, the new observations are appended again
+    ///
+    pub fn remove_feature_fusion(&mut self, feature_class: u64) -> Option<FeatureFusion> {
+        self.fusions.remove(&feature_class)
+    }
+
+    /// Sets the fusion of the feature classes without the own fusion, `None` keeps the observations of such
+    /// classes as the metric decides
+    ///
+    /// `Some(FeatureFusion::Centroid)` is the compact mode of the store: the tracks keep only the centroid and the
+    /// number of the fused features of every class, e.g. for the large galleries, the distances are calculated
+    /// between the centroids.
+    ///
+    pub fn set_default_feature_fusion(&mut self, fusion: Option<FeatureFusion>) {
+        self.default_fusion = fusion;
+    }
+
+    /// The fusion applied to the feature class, the own one or the default one
+    ///
+    pub fn feature_fusion(&self, feature_class: u64) -> Option<&FeatureFusion> {
+        self.fusions
+            .get(&feature_class)
+            .or(self.default_fusion.as_ref())
+    }
+
+    fn check_fused_dimensions(
+        track: &Track<TA, M, OA, N>,
+        feature_class: u64,
+        feature: &Option<Feature>,
+    ) -> Result<()> {
+        let fused = track
+            .get_observations(feature_class)
+            .into_iter()
+            .flatten()
+            .find_map(|o| o.feature().as_ref());
+        match (fused, feature) {
+            (Some(fused), Some(feature)) if fused.len() != feature.len() => {
+                Err(Errors::FeatureDimensionsMismatch(
+                    feature_class,
+                    fused.len() * FEATURE_LANES_SIZE,
+                    feature.len() * FEATURE_LANES_SIZE,
+                )
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn shape_history(&self, track: &mut Track<TA, M, OA, N>, feature_class: u64) -> Result<()> {
+        let pool = &self.stores[self.get_bucket(track.track_id)].pool;
+        if let Some(fusion) = self.feature_fusion(feature_class) {
+            pool.release(track.fuse_observations(feature_class, fusion)?);
+        }
+        if let Some(cap) = self.history_caps.get(&feature_class) {
+            pool.release(track.cap_history(feature_class, cap));
+        }
+        Ok(())
+    }
+
+    fn evict_over_capacity(&self, inserted: TrackId) -> Vec<Track<TA, M, OA, N>> {
+        let capacity = match (self.capacity, self.capacity_policy) {
+            (Some(capacity), CapacityPolicy::EvictOldest) => capacity,
+            _ => return Vec::default(),
+        };
+        let count = self.tracks_count();
+        if count <= capacity {
+            return Vec::default();
+        }
+
+        let mut shards = self
+            .stores
+            .iter()
+            .map(StoreMutexGuard::lock)
+            .collect::<Vec<_>>();
+        let mut evicted = Vec::with_capacity(count - capacity);
+        let mut reordered = false;
+        while evicted.len() < count - capacity {
+            let oldest = shards
+                .iter()
+                .enumerate()
+                .filter_map(|(shard, tracks)| tracks.oldest(inserted).map(|o| (o, shard)))
+                .min();
+            match oldest {
+                Some(((_, track_id), shard)) => {
+                    evicted.push(shards[shard].remove(&track_id).unwrap());
+                }
+                // the tracks inserted bypassing the guard are not ordered yet
+                None if !reordered => {
+                    shards.iter().for_each(|tracks| tracks.reorder());
+                    reordered = true;
+                }
+                None => break,
+            }
+        }
+        drop(shards);
+        self.publish_evicted(&evicted);
+        evicted
+    }
+
+    /// Evicts the expired tracks from the store
+    ///
+    /// # Returns
+    /// The tracks evicted now and the tracks evicted since the last call lazily before the queries or by the
+    /// inserts over the capacity
+    ///
+    pub fn purge(&self) -> Vec<Track<TA, M, OA, N>> {
+        let mut evicted = mem::take(&mut *self.evicted.lock().unwrap());
+        evicted.extend(self.evict());
+        evicted
+    }
+
+    /// Finds the tracks that are not updated for more than `max_idle_epochs` epochs of the store
+    ///
+    /// The shards are scanned one by one and the tracks are left in the store, so the caller decides which of
+    /// them to archive or to remove with [fetch_tracks](TrackStore::fetch_tracks). The ids are sorted.
+    ///
+    pub fn stale_tracks(&self, max_idle_epochs: usize) -> Vec<TrackId> {
+        let ttl = TrackTtl::Epochs(max_idle_epochs);
+        let epoch = self.epoch();
+        let mut stale = Vec::default();
+        for store in self.stores.iter() {
+            let store = store.read().unwrap();
+            stale.extend(
+                store
+                    .values()
+                    .filter(|track| ttl.expired(track, epoch))
+                    .map(|track| track.track_id),
+            );
+        }
+        stale.sort_unstable();
+        stale
+    }
+
+    /// Sets the backend that calculates the batched feature distances, [CpuBackend](CpuBackend) is used by default
+    ///
+    pub fn set_distance_backend<B>(&mut self, backend: B)
+    where
+        B: DistanceBackend + 'static,
+    {
+        self.distance_backend = Arc::new(backend);
+    }
+
+    /// The backend that calculates the batched feature distances
+    ///
+    pub fn distance_backend(&self) -> &dyn DistanceBackend {
+        self.distance_backend.as_ref()
+    }
+
+    /// Sets the policy for the NaN and infinite feature distances, the policy is applied to the distances
+    /// calculated by the store, [NonFinitePolicy::Skip](NonFinitePolicy::Skip) is used by default
+    ///
+    pub fn set_non_finite_policy(&mut self, policy: NonFinitePolicy) {
+        self.non_finite_policy = policy;
+    }
+
+    /// The policy for the NaN and infinite feature distances
+    ///
+    pub fn non_finite_policy(&self) -> NonFinitePolicy {
+        self.non_finite_policy
+    }
+
+    /// Registers the expected dimensions of the features of the feature class
+    ///
+    /// The features added with [add](TrackStore::add) and the features of the query tracks passed to
+    /// [foreign_track_distances](TrackStore::foreign_track_distances) are checked before they are projected,
+    /// the feature with the wrong dimensions results in `Errors::FeatureDimensionsMismatch`. The features are
+    /// stored in the lanes padded with zeros, so the feature which trailing values are zeros is not distinguished
+    /// from the shorter one.
+    ///
+    pub fn set_feature_dimensions(&mut self, feature_class: u64, dimensions: usize) {
+        self.feature_dimensions.insert(feature_class, dimensions);
+    }
+
+    /// Removes the expected dimensions of the feature class
+    ///
+    pub fn remove_feature_dimensions(&mut self, feature_class: u64) {
+        self.feature_dimensions.remove(&feature_class);
+    }
+
+    /// The expected dimensions of the features of the feature class
+    ///
+    pub fn feature_dimensions(&self, feature_class: u64) -> Option<usize> {
+        self.feature_dimensions.get(&feature_class).copied()
+    }
+
+    fn check_dimensions(&self, feature_class: u64, feature: &Feature) -> Result<()> {
+        let dimensions = match self.feature_dimensions.get(&feature_class) {
+            Some(dimensions) => *dimensions,
+            None => return Ok(()),
+        };
+        let lanes =
+            dimensions / FEATURE_LANES_SIZE + usize::from(dimensions % FEATURE_LANES_SIZE > 0);
+        let values = Vec::<f32>::from_vec(feature);
+        let actual = if feature.len() != lanes {
+            values.len()
+        } else {
+            values.iter().rposition(|v| *v != 0.0).map_or(0, |p| p + 1)
+        };
+        if feature.len() != lanes || actual > dimensions {
+            return Err(
+                Errors::FeatureDimensionsMismatch(feature_class, dimensions, actual).into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets the linear projection of the features of the feature class
+    ///
+    /// The projection is applied to the features added with [add](TrackStore::add) and to the features of the
+    /// query tracks passed to [foreign_track_distances](TrackStore::foreign_track_distances). The tracks added with
+    /// [add_track](TrackStore::add_track) or merged with [merge_external](TrackStore::merge_external) are used as is,
+    /// project them with [project_track](TrackStore::project_track).
+    ///
+    /// The features already stored are not projected, so the projection should be set before the features are added.
+    ///
+    pub fn set_projection(&mut self, feature_class: u64, projection: FeatureProjection) {
+        self.projections.insert(feature_class, Arc::new(projection));
+    }
+
+    /// Fits the PCA projection of the feature class from the sample features and sets it
+    ///
+    /// # Arguments
+    /// * `feature_class` - the feature class which features are projected
+    /// * `samples` - the sample features, at least two
+    /// * `dimensions` - the number of feature dimensions that participate
+    /// * `components` - the number of principal components to keep
+    /// * `whiten` - scale the components to unit variance
+    ///
+    pub fn fit_projection(
+        &mut self,
+        feature_class: u64,
+        samples: &[Feature],
+        dimensions: usize,
+        components: usize,
+        whiten: bool,
+    ) -> Result<()> {
+        let projection = FeatureProjection::fit_pca(samples, dimensions, components, whiten)?;
+        self.set_projection(feature_class, projection);
+        Ok(())
+    }
+
+    /// Removes the projection of the feature class
+    ///
+    pub fn remove_projection(&mut self, feature_class: u64) {
+        self.projections.remove(&feature_class);
+    }
+
+    /// The projection of the feature class
+    ///
+    pub fn projection(&self, feature_class: u64) -> Option<&FeatureProjection> {
+        self.projections.get(&feature_class).map(|p| p.as_ref())
+    }
+
+    fn project(
+        &self,
+        track_id: TrackId,
+        feature_class: u64,
+        feature: Option<Feature>,
+    ) -> Result<Option<Feature>> {
+        if let Some(feature) = &feature {
+            self.check_dimensions(feature_class, feature)?;
+        }
+        match (self.projections.get(&feature_class), feature) {
+            (Some(projection), Some(feature)) => {
+                let projected = projection.project(&feature)?;
+                self.stores[self.get_bucket(track_id)]
+                    .pool
+                    .release(Some(feature));
+                Ok(Some(projected))
+            }
+            (_, feature) => Ok(feature),
+        }
+    }
+
+    /// Checks the dimensions of the features of the track and projects them with the projections of the store
+    ///
+    /// The track is not changed when one of the features has the wrong dimensions or cannot be projected.
+    ///
+    pub fn project_track(&self, track: &mut Track<TA, M, OA, N>) -> Result<()> {
+        for (feature_class, observations) in &track.observations {
+            for f in observations.iter().flat_map(|o| o.1.as_ref()) {
+                self.check_dimensions(*feature_class, f)?;
+            }
+        }
+
+        let mut projected = Vec::new();
+        for (feature_class, observations) in &track.observations {
+            if let Some(projection) = self.projections.get(feature_class) {
+                for (i, o) in observations.iter().enumerate() {
+                    if let Some(f) = &o.1 {
+                        projected.push((*feature_class, i, projection.project(f)?));
+                    }
+                }
+            }
+        }
+        for (feature_class, i, f) in projected {
+            track.observations.get_mut(&feature_class).unwrap()[i].1 = Some(f);
+        }
+        Ok(())
+    }
+
+    /// Polarity of the distances calculated by the store metric, the voting engines that process the
+    /// distances must be configured with the same polarity
+    ///
+    pub fn metric_polarity(&self) -> MetricPolarity {
+        self.metric.polarity()
+    }
+
+    /// Returns track builder object that can build new track compatible with the storage.
+    ///
+    /// Attributes, metric, notifier are cloned from store, the observations are stamped with the current epoch
+    /// of the store
+    ///
+    pub fn new_track(&self, track_id: TrackId) -> TrackBuilder<TA, M, OA, N> {
+        TrackBuilder::new(track_id)
+            .metric(self.metric.clone())
+            .attributes(self.default_attributes.clone())
+            .notifier(self.notifier.clone())
+            .epoch(self.epoch())
+    }
+
+    /// Returns track builder object that can build new track compatible with the storage.
+    ///
+    /// Attributes, metric, notifier are cloned from store, the observations are stamped with the current epoch
+    /// of the store
+    ///
+    pub fn new_track_random_id(&self) -> TrackBuilder<TA, M, OA, N> {
+        TrackBuilder::default()
+            .metric(self.metric.clone())
+            .attributes(self.default_attributes.clone())
+            .notifier(self.notifier.clone())
+            .epoch(self.epoch())
+    }
+
+    /// Calculates distances for external track (not in track store) to all tracks in DB which are
+    /// allowed.
+    ///
+    /// The features of the tracks are checked and projected with [project_track](TrackStore::project_track),
+    /// the tracks which features have the wrong dimensions or cannot be projected are skipped.
+    ///
+    /// # Arguments
+    /// * `tracks` - batch external tracks that is used as distance subjects
+    /// * `feature_class` - what feature to use for distance calculation
+    /// * `only_baked` - calculate distances only across the tracks that have `TrackBakingStatus::Ready` status
+    ///
+    pub fn foreign_track_distances(
+        &mut self,
+        tracks: Vec<Track<TA, M, OA, N>>,
+        feature_class: u64,
+        only_baked: bool,
+    ) -> (TrackDistanceOk<OA>, TrackDistanceErr<OA>) {
+        QueryCounters::record(&self.counters.distance_queries);
+        self.evict_lazily();
+        let tracks = self.compatible_tracks(tracks);
+        self.track_distances(tracks, feature_class, only_baked)
+    }
+
+    /// Finds the `k` tracks in DB closest to the external track
+    ///
+    /// Every track in DB is ranked by its closest feature distance to the track, the shards keep only their
+    /// `k` closest tracks and skip the tracks that are not closer than their `k`-th closest one, so the distances
+    /// of the pairs are not collected. The postprocessing of the metric is applied to the closest distances. The
+    /// track is checked and projected like in [foreign_track_distances](TrackStore::foreign_track_distances).
+    ///
+    /// # Arguments
+    /// * `track` - the external track that is used as distance subject
+    /// * `feature_class` - what feature to use for distance calculation
+    /// * `k` - the number of the closest tracks
+    /// * `only_baked` - calculate distances only across the tracks that have `TrackBakingStatus::Ready` status
+    ///
+    /// # Returns
+    /// The closest distances of the `k` closest tracks from the closest one in the polarity sense and the errors,
+    /// nothing when the track is skipped
+    ///
+    pub fn top_k_distances(
+        &mut self,
+        track: Track<TA, M, OA, N>,
+        feature_class: u64,
+        k: usize,
+        only_baked: bool,
+    ) -> (Vec<ObservationMetricOk<OA>>, Vec<ObservationMetricErr<OA>>) {
+        QueryCounters::record(&self.counters.distance_queries);
+        self.evict_lazily();
+        let track = match self.compatible_tracks(vec![track]).pop() {
+            Some(track) => Arc::new(track),
+            None => return (Vec::default(), Vec::default()),
+        };
+        self.compatible_top_k_distances(track, feature_class, k, only_baked)
+    }
+
+    fn compatible_top_k_distances(
+        &self,
+        track: Arc<Track<TA, M, OA, N>>,
+        feature_class: u64,
+        k: usize,
+        only_baked: bool,
+    ) -> (Vec<ObservationMetricOk<OA>>, Vec<ObservationMetricErr<OA>>) {
+        let (results_sender, results_receiver) = crossbeam::channel::unbounded();
+        for (cmd, _) in &self.executors {
+            cmd.send(Commands::TopK(
+                track.clone(),
+                feature_class,
+                k,
+                only_baked,
+                self.non_finite_policy,
+                results_sender.clone(),
+            ))
+            .unwrap();
+        }
+
+        let mut top = TopK::new(k, track.metric.polarity());
+        let mut errors = Vec::new();
+        for (_, _) in &self.executors {
+            match results_receiver.recv().unwrap() {
+                Results::TopK(dists, errs) => {
+                    dists.into_iter().for_each(|d| top.push(d));
+                    errors.extend(errs);
+                }
+                _ => unreachable!(),
+            }
+        }
+        (top.into_sorted_vec(), errors)
+    }
+
+    /// Streams the distances between the external track and the tracks in DB by the chunks, look at
+    /// [stream_distances](StoreSnapshot::stream_distances)
+    ///
+    /// The distances are calculated in the calling thread over the snapshot of the store, so the store is not
+    /// blocked by the scan. The track is checked and projected like in
+    /// [foreign_track_distances](TrackStore::foreign_track_distances), nothing is streamed when the track
+    /// is skipped.
+    ///
+    pub fn stream_distances<F>(
+        &mut self,
+        track: Track<TA, M, OA, N>,
+        feature_class: u64,
+        only_baked: bool,
+        chunk_size: usize,
+        sink: F,
+    ) where
+        F: FnMut(Vec<ObservationMetricOk<OA>>, Vec<ObservationMetricErr<OA>>) -> ControlFlow<()>,
+    {
+        QueryCounters::record(&self.counters.distance_queries);
+        self.evict_lazily();
+        if let Some(track) = self.compatible_tracks(vec![track]).pop() {
+            self.snapshot()
+                .stream_distances(&track, feature_class, only_baked, chunk_size, sink);
+        }
+    }
+
+    /// Calculates distances between the batch of external tracks and the tracks in DB in a single pass over the shards
+    ///
+    /// Every shard is locked once for the whole batch, unlike [foreign_track_distances](TrackStore::foreign_track_distances)
+    /// that locks the shards for every track. The tracks are checked and projected like in
+    /// [foreign_track_distances](TrackStore::foreign_track_distances), the skipped tracks have no results.
+    ///
+    /// # Arguments
+    /// * `tracks` - batch external tracks that is used as distance subjects
+    /// * `feature_class` - what feature to use for distance calculation
+    /// * `only_baked` - calculate distances only across the tracks that have `TrackBakingStatus::Ready` status
+    ///
+    /// # Returns
+    /// The distances and the errors of every track in the order of the batch
+    ///
+    pub fn batch_foreign_track_distances(
+        &mut self,
+        tracks: Vec<Track<TA, M, OA, N>>,
+        feature_class: u64,
+        only_baked: bool,
+    ) -> Vec<CandidateDistances<OA>> {
+        QueryCounters::record(&self.counters.distance_queries);
+        self.evict_lazily();
+        let tracks = Arc::new(self.compatible_tracks(tracks));
+        let (results_sender, results_receiver) = crossbeam::channel::unbounded();
+        for (cmd, _) in &self.executors {
+            cmd.send(Commands::BatchDistances(
+                tracks.clone(),
+                feature_class,
+                only_baked,
+                self.distance_backend.clone(),
+                self.non_finite_policy,
+                results_sender.clone(),
+            ))
+            .unwrap();
+        }
+
+        let mut results = tracks
+            .iter()
+            .map(|t| CandidateDistances {
+                track_id: t.track_id,
+                distances: Vec::default(),
+                errors: Vec::default(),
+            })
+            .collect::<Vec<_>>();
+
+        for _ in &self.executors {
+            match results_receiver.recv().unwrap() {
+                Results::BatchDistances(shard) => {
+                    for (res, (distances, errors)) in results.iter_mut().zip(shard) {
+                        res.distances.extend(distances);
+                        res.errors.extend(errors);
+                    }
+                }
+                _ => {
+                    unreachable!();
+                }
+            }
+        }
+        results
+    }
+
+    fn compatible_tracks(&self, tracks: Vec<Track<TA, M, OA, N>>) -> Vec<Track<TA, M, OA, N>> {
+        tracks
+            .into_iter()
+            .filter_map(|mut track| match self.project_track(&mut track) {
+                Ok(_) => Some(track),
+                Err(e) => {
+                    warn!(
+                        "Track={} is skipped, its features are not compatible with the store. Error is: {:?}",
+                        track.track_id, e
+                    );
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn track_distances(
         &mut self,
         tracks: Vec<Track<TA, M, OA, N>>,
         feature_class: u64,
@@ -444,6 +1987,8 @@ where
                     track.clone(),
                     feature_class,
                     only_baked,
+                    self.distance_backend.clone(),
+                    self.non_finite_policy,
                     results_ok_sender.clone(),
                     results_err_sender.clone(),
                 ))
@@ -459,6 +2004,86 @@ where
         )
     }
 
+    /// Registers the translation metric that compares the features of the query class with the features
+    /// of the gallery class, replaces the translation registered before
+    ///
+    /// The translation is used by [cross_class_track_distances](TrackStore::cross_class_track_distances), e.g. to query
+    /// the image embeddings of the stored tracks with the text embeddings.
+    ///
+    pub fn register_translation<T>(&mut self, query_class: u64, gallery_class: u64, translation: T)
+    where
+        T: ObservationMetric<TA, OA>,
+    {
+        self.translations
+            .insert((query_class, gallery_class), Arc::new(translation));
+    }
+
+    /// Removes the translation metric between the feature classes
+    ///
+    pub fn remove_translation(&mut self, query_class: u64, gallery_class: u64) {
+        self.translations.remove(&(query_class, gallery_class));
+    }
+
+    /// Calculates distances between the features of the query class of external tracks and the features of
+    /// the gallery class of the tracks in DB with the registered translation metric
+    ///
+    /// The distances are postprocessed and oriented with the translation metric, the tracks are checked and
+    /// projected like in [foreign_track_distances](TrackStore::foreign_track_distances).
+    ///
+    /// # Arguments
+    /// * `tracks` - batch external tracks that is used as distance subjects
+    /// * `query_class` - the feature class of the external tracks
+    /// * `gallery_class` - the feature class of the tracks in DB
+    /// * `only_baked` - calculate distances only across the tracks that have `TrackBakingStatus::Ready` status
+    ///
+    /// # Returns
+    /// * `Err(Errors::TranslationNotFound)` when the translation between the classes is not registered
+    ///
+    pub fn cross_class_track_distances(
+        &mut self,
+        tracks: Vec<Track<TA, M, OA, N>>,
+        query_class: u64,
+        gallery_class: u64,
+        only_baked: bool,
+    ) -> Result<(TrackDistanceOk<OA>, TrackDistanceErr<OA>)> {
+        let translation = self
+            .translations
+            .get(&(query_class, gallery_class))
+            .ok_or(Errors::TranslationNotFound(query_class, gallery_class))?
+            .clone();
+
+        QueryCounters::record(&self.counters.distance_queries);
+        self.evict_lazily();
+        let tracks = self.compatible_tracks(tracks);
+        let tracks_count = tracks.len();
+
+        let (results_ok_sender, results_ok_receiver) = crossbeam::channel::unbounded();
+        let (results_err_sender, results_err_receiver) = crossbeam::channel::unbounded();
+
+        for track in tracks {
+            let track = Arc::new(track);
+            for (cmd, _) in &mut self.executors {
+                cmd.send(Commands::CrossClassDistances(
+                    track.clone(),
+                    (query_class, gallery_class),
+                    only_baked,
+                    translation.clone(),
+                    self.non_finite_policy,
+                    results_ok_sender.clone(),
+                    results_err_sender.clone(),
+                ))
+                .unwrap();
+            }
+        }
+
+        let count = self.executors.len() * tracks_count;
+
+        Ok((
+            TrackDistanceOk::new(count, results_ok_receiver),
+            TrackDistanceErr::new(count, results_err_receiver),
+        ))
+    }
+
     /// Calculates track distances for a track within the store
     ///
     /// The distances for (self, self) are not calculated.
@@ -470,16 +2095,18 @@ where
     ///
     pub fn owned_track_distances(
         &mut self,
-        tracks: &[u64],
+        tracks: &[TrackId],
         feature_class: u64,
         only_baked: bool,
     ) -> (TrackDistanceOk<OA>, TrackDistanceErr<OA>) {
-        let tracks_vec = self.fetch_tracks(tracks);
+        QueryCounters::record(&self.counters.distance_queries);
+        self.evict_lazily();
+        let tracks_vec = self.take_tracks(tracks);
 
-        let res = self.foreign_track_distances(tracks_vec.clone(), feature_class, only_baked);
+        let res = self.track_distances(tracks_vec.clone(), feature_class, only_baked);
 
         for t in tracks_vec {
-            self.add_track(t).unwrap();
+            self.insert_track(t).unwrap();
         }
 
         res
@@ -487,19 +2114,74 @@ where
 
     /// returns the store shard for id
     ///
-    pub fn get_store(&self, id: usize) -> StoreMutexGuard<'_, TA, M, OA, N> {
-        let store_id = id % self.num_shards;
-        self.stores.as_ref().get(store_id).unwrap().lock().unwrap()
+    /// When the shards are split into the buckets, only the bucket of the shard that holds the id is returned.
+    ///
+    pub fn get_store(&self, id: TrackId) -> StoreMutexGuard<'_, TA, M, OA, N> {
+        StoreMutexGuard::lock(&self.stores[self.get_bucket(id)])
     }
 
     /// returns the store shard for id
     ///
-    pub fn get_executor(&self, id: usize) -> usize {
-        id % self.num_shards
+    /// The tracks are routed by the shard router, the tracks moved by [compact](TrackStore::compact) are routed
+    /// to their new shards.
+    ///
+    pub fn get_executor(&self, id: TrackId) -> usize {
+        self.get_bucket(id) / self.buckets
+    }
+
+    /// The index of the bucket of the track across the buckets of all the shards
+    ///
+    pub(crate) fn get_bucket(&self, track_id: TrackId) -> usize {
+        self.routes
+            .get(&track_id)
+            .copied()
+            .unwrap_or_else(|| self.home_bucket(track_id))
+    }
+
+    pub(crate) fn home_bucket(&self, track_id: TrackId) -> usize {
+        // the ids are mixed, so the ids of the shard don't fall into the same bucket
+        let bucket =
+            (folded_id(track_id).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as usize % self.buckets;
+        self.home_shard(track_id) * self.buckets + bucket
+    }
+
+    pub(crate) fn home_shard(&self, track_id: TrackId) -> usize {
+        match &self.router {
+            Some(router) => router(track_id) % self.num_shards,
+            None => folded_id(track_id) as usize % self.num_shards,
+        }
+    }
+
+    /// Sets the function that selects the shard of the track by its id, `None` routes the tracks by their ids
+    ///
+    /// The router co-locates the related tracks, e.g. the tracks of the camera encoded in the upper bits of
+    /// the track id, so the camera-local queries work with the shard from [get_store](TrackStore::get_store)
+    /// instead of all the shards. The tracks in the store are moved to their new shards, the routes set by
+    /// [compact](TrackStore::compact) are dropped.
+    ///
+    pub fn set_shard_router(&mut self, router: Option<Arc<ShardRouter>>) {
+        self.router = router;
+        self.routes.clear();
+        let mut shards = self
+            .stores
+            .iter()
+            .map(StoreMutexGuard::lock)
+            .collect::<Vec<_>>();
+        let tracks = shards
+            .iter_mut()
+            .flat_map(|shard| shard.drain().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        for (track_id, track) in tracks {
+            shards[self.home_bucket(track_id)].insert(track_id, track);
+        }
+        drop(shards);
+        self.reroute_parked();
     }
 
     /// Adds external track into storage
     ///
+    /// The track is stamped as updated at the current epoch of the store.
+    ///
     /// # Arguments
     /// * `track` - track compatible with the storage.
     ///
@@ -507,9 +2189,47 @@ where
     /// * `Ok(track_id)` if added
     /// * `Err(Errors::DuplicateTrackId(track_id))` if failed to add
     ///
-    pub fn add_track(&mut self, track: Track<TA, M, OA, N>) -> Result<u64> {
+    /// The tracks evicted when the store exceeds the capacity are kept by the store until
+    /// [purge](TrackStore::purge) is called.
+    ///
+    pub fn add_track(&mut self, track: Track<TA, M, OA, N>) -> Result<TrackId> {
+        let track_id = track.track_id;
+        let evicted = self.add_track_bounded(track)?;
+        self.evicted.lock().unwrap().extend(evicted);
+        Ok(track_id)
+    }
+
+    /// Adds external track into storage and evicts the least recently updated tracks when the store exceeds
+    /// the capacity
+    ///
+    /// # Returns
+    /// * `Ok(evicted)` - the track is added, the evicted tracks are returned
+    /// * `Err(Errors::DuplicateTrackId(track_id))` if failed to add
+    ///
+    pub fn add_track_bounded(
+        &mut self,
+        mut track: Track<TA, M, OA, N>,
+    ) -> Result<Vec<Track<TA, M, OA, N>>> {
+        if let Admission::Park = self.admit(track.track_id, &mut HashSet::default())? {
+            let capacity = self.capacity.unwrap();
+            return Err(Errors::StoreFull(track.track_id, capacity).into());
+        }
+        if track.attribute_history_cap == 0 {
+            track.set_attribute_history_cap(self.attribute_history_cap);
+        }
+        track.stamp(self.epoch());
+        let track_id = self.insert_track(track)?;
+        self.events.publish(|| {
+            TrackEvent::Created(TrackView::new(
+                self.get_store(track_id).get(&track_id).unwrap(),
+            ))
+        });
+        Ok(self.evict_over_capacity(track_id))
+    }
+
+    fn insert_track(&mut self, track: Track<TA, M, OA, N>) -> Result<TrackId> {
         let track_id = track.track_id;
-        let mut store = self.get_store(track_id as usize);
+        let mut store = self.get_store(track_id);
         if store.get(&track_id).is_none() {
             store.insert(track_id, track);
             Ok(track_id)
@@ -527,15 +2247,155 @@ where
     /// * `feature` - feature observation
     /// * `attributes_update` - the update to be applied to attributes upon the feature insert
     ///
+    /// The tracks evicted when the store exceeds the capacity are kept by the store until
+    /// [purge](TrackStore::purge) is called.
+    ///
     pub fn add(
         &mut self,
-        track_id: u64,
+        track_id: TrackId,
+        feature_class: u64,
+        feature_attribute: Option<OA>,
+        feature: Option<Feature>,
+        attributes_update: Option<TA::Update>,
+    ) -> Result<()> {
+        let evicted = self
+            .add_bounded(
+                track_id,
+                feature_class,
+                feature_attribute,
+                feature,
+                attributes_update,
+            )?
+            .evicted();
+        self.evicted.lock().unwrap().extend(evicted);
+        Ok(())
+    }
+
+    /// Injects new feature observation for feature class into track and evicts the least recently updated tracks
+    /// when the store exceeds the capacity
+    ///
+    /// The arguments are the same as for [add](TrackStore::add).
+    ///
+    /// # Returns
+    /// * `Ok(Insertion::Added(evicted))` - the observation is added, the evicted tracks are returned
+    /// * `Ok(Insertion::Parked)` - the observation is parked in the overflow queue
+    /// * `Err(e)` - the observation is not added
+    ///
+    pub fn add_bounded(
+        &mut self,
+        track_id: TrackId,
         feature_class: u64,
         feature_attribute: Option<OA>,
         feature: Option<Feature>,
         attributes_update: Option<TA::Update>,
+    ) -> Result<Insertion<TA, M, OA, N>> {
+        self.drain_parked();
+        let observation = (
+            track_id,
+            feature_class,
+            feature_attribute,
+            feature,
+            attributes_update,
+        );
+        if let Admission::Park = self.admit(track_id, &mut HashSet::default())? {
+            self.park(observation)?;
+            return Ok(Insertion::Parked);
+        }
+        let epoch = self.epoch();
+        let mut tracks = self.get_store(track_id);
+        self.add_to_shard(&mut tracks, epoch, observation)?;
+        drop(tracks);
+        Ok(Insertion::Added(self.evict_over_capacity(track_id)))
+    }
+
+    /// Injects the batch of the observations, the shards are locked once for all the observations they receive
+    ///
+    /// The observations are added in order of the batch, the failed observations don't stop the batch. The tracks
+    /// evicted when the store exceeds the capacity are kept by the store until [purge](TrackStore::purge)
+    /// is called, so the added observations have `Ok(Insertion::Added)` results without the evicted tracks.
+    ///
+    /// # Arguments
+    /// * `batch` - the observations, the elements are the same as the arguments of [add](TrackStore::add)
+    ///
+    /// # Returns
+    /// The results of the observations in order of the batch
+    ///
+    pub fn add_observations_batch(
+        &mut self,
+        batch: Vec<BatchObservation<TA, OA>>,
+    ) -> Vec<Result<Insertion<TA, M, OA, N>>> {
+        self.drain_parked();
+        let epoch = self.epoch();
+        let mut results = (0..batch.len())
+            .map(|_| Ok(Insertion::Added(Vec::default())))
+            .collect::<Vec<_>>();
+        let last = batch.last().map(|observation| observation.0);
+        let mut shards = (0..self.stores.len())
+            .map(|_| Vec::default())
+            .collect::<Vec<_>>();
+        let mut admitted = HashSet::default();
+        for (index, observation) in batch.into_iter().enumerate() {
+            match self.admit(observation.0, &mut admitted) {
+                Ok(Admission::Admit) => {
+                    shards[self.get_bucket(observation.0)].push((index, observation))
+                }
+                Ok(Admission::Park) => {
+                    results[index] = self.park(observation).map(|_| Insertion::Parked)
+                }
+                Err(e) => results[index] = Err(e),
+            }
+        }
+        for (shard, observations) in self.stores.iter().zip(shards) {
+            if observations.is_empty() {
+                continue;
+            }
+            let mut tracks = StoreMutexGuard::lock(shard);
+            for (index, observation) in observations {
+                results[index] = self
+                    .add_to_shard(&mut tracks, epoch, observation)
+                    .map(|_| Insertion::Added(Vec::default()));
+            }
+        }
+        if let Some(last) = last {
+            let evicted = self.evict_over_capacity(last);
+            self.evicted.lock().unwrap().extend(evicted);
+        }
+        results
+    }
+
+    fn add_to_shard(
+        &self,
+        tracks: &mut StoreMutexGuard<'_, TA, M, OA, N>,
+        epoch: usize,
+        observation: BatchObservation<TA, OA>,
     ) -> Result<()> {
-        let mut tracks = self.get_store(track_id as usize);
+        let track_id = observation.0;
+        let created = self.apply_observation(tracks, epoch, observation)?;
+        if created {
+            tracks.order(track_id);
+        }
+        let track = tracks.get(&track_id).unwrap();
+        self.events.publish(|| {
+            if created {
+                TrackEvent::Created(TrackView::new(track))
+            } else {
+                TrackEvent::Updated(TrackView::new(track))
+            }
+        });
+        Ok(())
+    }
+
+    /// Adds the observation to the tracks without publishing the event, returns `true` when the track is created
+    ///
+    pub(crate) fn apply_observation(
+        &self,
+        tracks: &mut HashMap<TrackId, Track<TA, M, OA, N>>,
+        epoch: usize,
+        observation: BatchObservation<TA, OA>,
+    ) -> Result<bool> {
+        let (track_id, feature_class, feature_attribute, feature, attributes_update) = observation;
+        QueryCounters::record(&self.counters.adds);
+        let feature = self.project(track_id, feature_class, feature)?;
         #[allow(clippy::significant_drop_in_scrutinee)]
         match tracks.get_mut(&track_id) {
             None => {
@@ -545,27 +2405,68 @@ where
                     track_id,
                     observations: HashMap::from([(
                         feature_class,
-                        vec![Observation(feature_attribute, feature)],
+                        vec![Observation(
+                            feature_attribute,
+                            feature,
+                            ObservationStamp::new(epoch),
+                        )],
                     )]),
                     metric: self.metric.clone(),
                     merge_history: vec![track_id],
+                    last_update: TrackUpdate::default(),
+                    metadata: Metadata::default(),
+                    observation_totals: HashMap::from([(feature_class, 1)]),
+                    fused_counts: HashMap::default(),
+                    attribute_history: VecDeque::default(),
+                    attribute_history_cap: self.attribute_history_cap,
                 };
                 if let Some(attributes_update) = &attributes_update {
                     t.update_attributes(attributes_update)?;
                 }
 
+                // the first observation is optimized like the observations added later
+                let observations = t.observations.get_mut(&feature_class).unwrap();
+                t.metric.optimize(
+                    feature_class,
+                    &t.merge_history,
+                    &mut t.attributes,
+                    observations,
+                    0,
+                    false,
+                )?;
+                self.shape_history(&mut t, feature_class)?;
+
+                t.stamp(epoch);
                 tracks.insert(track_id, t);
+                Ok(true)
             }
             Some(track) => {
-                track.add_observation(
-                    feature_class,
-                    feature_attribute,
-                    feature,
-                    attributes_update,
-                )?;
+                if self.feature_fusion(feature_class).is_some() {
+                    Self::check_fused_dimensions(track, feature_class, &feature)?;
+                }
+                let duplicate = match (self.deduplications.get(&feature_class), &feature) {
+                    (Some(deduplication), Some(feature)) => {
+                        track.deduplicate_feature(feature_class, feature, deduplication)
+                    }
+                    _ => false,
+                };
+                if duplicate {
+                    // only the attributes are updated
+                    track.add_observation(feature_class, None, None, attributes_update)?;
+                } else {
+                    track.add_stamped_observation(
+                        feature_class,
+                        feature_attribute,
+                        feature,
+                        attributes_update,
+                        ObservationStamp::new(epoch),
+                    )?;
+                    self.shape_history(track, feature_class)?;
+                }
+                track.stamp(epoch);
+                Ok(false)
             }
         }
-        Ok(())
     }
 
     /// Merge store owned tracks
@@ -583,13 +2484,13 @@ where
     ///
     pub fn merge_owned(
         &mut self,
-        dest_id: u64,
-        src_id: u64,
+        dest_id: TrackId,
+        src_id: TrackId,
         classes: Option<&[u64]>,
         remove_src_if_ok: bool,
         merge_history: bool,
     ) -> OwnedMergeResult<TA, M, OA, N> {
-        let mut src = self.fetch_tracks(&[src_id]);
+        let mut src = self.take_tracks(&[src_id]);
         if src.is_empty() {
             return Err(Errors::TrackNotFound(src_id).into());
         }
@@ -597,13 +2498,14 @@ where
         match self.merge_external(dest_id, &src, classes, merge_history) {
             Ok(_) => {
                 if !remove_src_if_ok {
-                    self.add_track(src).unwrap();
+                    self.insert_track(src).unwrap();
                     return Ok(None);
                 }
+                self.unmap_tracks(std::iter::once(src_id));
                 Ok(Some(src))
             }
             err => {
-                self.add_track(src).unwrap();
+                self.insert_track(src).unwrap();
                 err?;
                 unreachable!();
             }
@@ -624,17 +2526,20 @@ where
     ///
     pub fn merge_external_noblock(
         &mut self,
-        dest_id: u64,
+        dest_id: TrackId,
         src: Track<TA, M, OA, N>,
         classes: Option<&[u64]>,
         merge_history: bool,
     ) -> Result<FutureMergeResponse<OA>> {
+        QueryCounters::record(&self.counters.merges);
         let (results_sender, results_receiver) = crossbeam::channel::bounded(1);
-        let executor_id = self.get_executor(dest_id as usize);
+        let bucket = self.get_bucket(dest_id);
+        let executor_id = bucket / self.buckets;
         let (cmd, _) = self.executors.get_mut(executor_id).unwrap();
 
         let command = Commands::Merge(
             dest_id,
+            bucket,
             src,
             if let Some(c) = classes {
                 c.to_vec()
@@ -676,7 +2581,7 @@ where
     ///
     pub fn merge_external(
         &mut self,
-        dest_id: u64,
+        dest_id: TrackId,
         src: &Track<TA, M, OA, N>,
         classes: Option<&[u64]>,
         merge_history: bool,
@@ -690,12 +2595,277 @@ where
         }
     }
 
+    /// Merges several store owned tracks into the destination track and removes them from the store
+    ///
+    /// The merge is all-or-nothing: when any of the tracks is missing or any of the merges fails, the store
+    /// is left unchanged. The sources are merged in the order of the slice, all their feature classes are merged
+    /// and the merge history is built.
+    ///
+    /// # Arguments
+    /// * `dest_id` - identifier of destination track
+    /// * `src_ids` - identifiers of source tracks
+    /// * `policy` - how the attributes of the sources are combined with the destination attributes
+    ///
+    /// # Return
+    /// * `Ok(sources)` - the merge was successful, the removed source tracks are returned
+    /// * `Err(Errors::TrackNotFound(track_id))` - the track is not in the store
+    /// * `Err(Errors::SameTrackCalculation(dest_id))` - the destination is also the source
+    /// * `Err(e)` - the merge met problems
+    ///
+    pub fn merge_tracks(
+        &mut self,
+        dest_id: TrackId,
+        src_ids: &[TrackId],
+        policy: &AttributeMergePolicy<TA>,
+    ) -> Result<Vec<Track<TA, M, OA, N>>> {
+        QueryCounters::record(&self.counters.merges);
+        if src_ids.contains(&dest_id) {
+            return Err(Errors::SameTrackCalculation(dest_id).into());
+        }
+
+        let mut tracks = self.take_tracks(&[dest_id]);
+        let found = src_ids.iter().unique().all(|src_id| {
+            let mut src = self.take_tracks(&[*src_id]);
+            let found = !src.is_empty();
+            tracks.append(&mut src);
+            found
+        });
+
+        let res = match tracks.first() {
+            Some(dest) if found && dest.track_id == dest_id => {
+                let mut merged = dest.clone();
+                tracks[1..]
+                    .iter()
+                    .try_for_each(|src| {
+                        merged.merge_with_policy(src, &src.get_feature_classes(), true, policy)
+                    })
+                    .map(|_| merged)
+            }
+            _ => {
+                let missing = std::iter::once(&dest_id)
+                    .chain(src_ids)
+                    .find(|id| tracks.iter().all(|t| t.track_id != **id))
+                    .unwrap();
+                Err(Errors::TrackNotFound(*missing).into())
+            }
+        };
+
+        match res {
+            Ok(mut merged) => {
+                let sources = tracks.split_off(1);
+                merged.stamp(self.epoch());
+                self.events.publish(|| TrackEvent::Merged {
+                    track: TrackView::new(&merged),
+                    sources: sources.iter().map(|t| t.track_id).collect(),
+                });
+                self.insert_track(merged)?;
+                self.unmap_tracks(sources.iter().map(|t| t.track_id));
+                Ok(sources)
+            }
+            Err(e) => {
+                for track in tracks {
+                    self.insert_track(track).unwrap();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Splits the store owned track in two, the observations selected by the split point are moved to the new
+    /// tail track with the id `tail_id`, look at [Track::split](Track::split)
+    ///
+    /// # Return
+    /// * `Ok(tail_id)` - the tail track is added to the store
+    /// * `Err(Errors::TrackNotFound(track_id))` - the track is not in the store
+    /// * `Err(Errors::DuplicateTrackId(tail_id))` - the store already has the track with the tail id
+    /// * `Err(Errors::StoreFull(tail_id, capacity))` - the capacity policy doesn't admit the tail track, the
+    ///   source track is left intact
+    ///
+    pub fn split_track(
+        &mut self,
+        track_id: TrackId,
+        tail_id: TrackId,
+        point: &SplitPoint<OA>,
+    ) -> Result<TrackId> {
+        if self.get_store(tail_id).contains_key(&tail_id) {
+            return Err(Errors::DuplicateTrackId(tail_id).into());
+        }
+        if !self.get_store(track_id).contains_key(&track_id) {
+            return Err(Errors::TrackNotFound(track_id).into());
+        }
+        if let Admission::Park = self.admit(tail_id, &mut HashSet::default())? {
+            let capacity = self.capacity.unwrap();
+            return Err(Errors::StoreFull(tail_id, capacity).into());
+        }
+        let epoch = self.epoch();
+        let tail = match self.get_store(track_id).get_mut(&track_id) {
+            Some(track) => {
+                let tail = track.split(tail_id, point);
+                track.stamp(epoch);
+                self.events
+                    .publish(|| TrackEvent::Updated(TrackView::new(track)));
+                tail
+            }
+            None => return Err(Errors::TrackNotFound(track_id).into()),
+        };
+        self.add_track(tail)
+    }
+
+    /// Deep-copies the store owned track under the new random id, e.g. to fork the gallery identity
+    ///
+    /// # Return
+    /// * `Ok(new_id)` - the copy is added to the store
+    /// * `Err(Errors::TrackNotFound(src_id))` - the track is not in the store
+    ///
+    pub fn clone_track(&mut self, src_id: TrackId) -> Result<TrackId> {
+        let mut rng = rand::thread_rng();
+        loop {
+            let new_id = rng.gen::<TrackId>();
+            match self.clone_track_with_id(src_id, new_id) {
+                Err(e)
+                    if matches!(
+                        e.downcast_ref::<Errors>(),
+                        Some(Errors::DuplicateTrackId(_))
+                    ) => {}
+                res => return res,
+            }
+        }
+    }
+
+    /// Deep-copies the store owned track under the id
+    ///
+    /// The copy gets the observations, the attributes and the metadata of the track, its merge history starts
+    /// with the new id and the external id of the track is not copied. The copy is added as with
+    /// [add_track](TrackStore::add_track).
+    ///
+    /// # Return
+    /// * `Ok(new_id)` - the copy is added to the store
+    /// * `Err(Errors::TrackNotFound(src_id))` - the track is not in the store
+    /// * `Err(Errors::DuplicateTrackId(new_id))` - the store already has the track with the new id
+    ///
+    pub fn clone_track_with_id(&mut self, src_id: TrackId, new_id: TrackId) -> Result<TrackId> {
+        let mut copy = match self.get_store(src_id).get(&src_id) {
+            Some(track) => track.clone(),
+            None => return Err(Errors::TrackNotFound(src_id).into()),
+        };
+        copy.track_id = new_id;
+        copy.merge_history = vec![new_id];
+        copy.notifier.send(new_id);
+        self.add_track(copy)
+    }
+
+    /// Removes the observations of the store owned track selected by the predicate, look at
+    /// [Track::remove_observations](Track::remove_observations)
+    ///
+    /// The last update of the track is not changed, so the removals don't extend the time-to-live of the track.
+    ///
+    /// # Return
+    /// * `Ok(removed)` - the number of the removed observations
+    /// * `Err(Errors::TrackNotFound(track_id))` - the track is not in the store
+    /// * `Err(e)` - the metric failed to optimize the kept observations, the track is left unchanged
+    ///
+    pub fn remove_track_observations<F>(&self, track_id: TrackId, predicate: F) -> Result<usize>
+    where
+        F: Fn(u64, &Observation<OA>) -> bool,
+    {
+        match self.get_store(track_id).get_mut(&track_id) {
+            Some(track) => {
+                let removed = track.remove_observations(predicate)?;
+                if removed > 0 {
+                    self.events
+                        .publish(|| TrackEvent::Updated(TrackView::new(track)));
+                }
+                Ok(removed)
+            }
+            None => Err(Errors::TrackNotFound(track_id).into()),
+        }
+    }
+
+    /// Removes all the observations of the feature class from the store owned track
+    ///
+    pub fn remove_track_feature_class(
+        &self,
+        track_id: TrackId,
+        feature_class: u64,
+    ) -> Result<usize> {
+        self.remove_track_observations(track_id, |class, _| class == feature_class)
+    }
+
+    /// Sets the metadata value of the store owned track, returns the previous value
+    ///
+    /// # Return
+    /// * `Err(Errors::TrackNotFound(track_id))` - the track is not in the store
+    ///
+    pub fn set_track_metadata<V: Into<MetadataValue>>(
+        &self,
+        track_id: TrackId,
+        key: &str,
+        value: V,
+    ) -> Result<Option<MetadataValue>> {
+        match self.get_store(track_id).get_mut(&track_id) {
+            Some(track) => Ok(track.set_metadata(key, value)),
+            None => Err(Errors::TrackNotFound(track_id).into()),
+        }
+    }
+
+    /// Removes the metadata value of the store owned track, returns the removed value
+    ///
+    /// # Return
+    /// * `Err(Errors::TrackNotFound(track_id))` - the track is not in the store
+    ///
+    pub fn remove_track_metadata(
+        &self,
+        track_id: TrackId,
+        key: &str,
+    ) -> Result<Option<MetadataValue>> {
+        match self.get_store(track_id).get_mut(&track_id) {
+            Some(track) => Ok(track.remove_metadata(key)),
+            None => Err(Errors::TrackNotFound(track_id).into()),
+        }
+    }
+
+    /// Returns the metadata value of the store owned track
+    ///
+    /// # Return
+    /// * `Err(Errors::TrackNotFound(track_id))` - the track is not in the store
+    ///
+    pub fn get_track_metadata(
+        &self,
+        track_id: TrackId,
+        key: &str,
+    ) -> Result<Option<MetadataValue>> {
+        match self.get_store(track_id).get(&track_id) {
+            Some(track) => Ok(track.get_metadata(key).cloned()),
+            None => Err(Errors::TrackNotFound(track_id).into()),
+        }
+    }
+
+    /// Finds the tracks that have the metadata value
+    ///
+    pub fn find_by_metadata(&self, key: &str, value: &MetadataValue) -> Vec<TrackId> {
+        QueryCounters::record(&self.counters.lookups);
+        let mut tracks = Vec::new();
+        for store in self.stores.iter() {
+            tracks.extend(
+                store
+                    .read()
+                    .unwrap()
+                    .values()
+                    .filter(|t| t.get_metadata(key) == Some(value))
+                    .map(|t| t.track_id),
+            );
+        }
+        tracks
+    }
+
     /// Method is used to find tracks that match lookup query.
     ///
     /// The search is parallelized with Rayon. The results returned for tracks with their statuses.
     ///
-    pub fn lookup(&self, q: TA::Lookup) -> Vec<(u64, Result<TrackStatus>)> {
-        let mut results = Vec::with_capacity(self.shard_stats().iter().sum());
+    pub fn lookup(&self, q: TA::Lookup) -> Vec<(TrackId, Result<TrackStatus>)> {
+        QueryCounters::record(&self.counters.lookups);
+        self.evict_lazily();
+        let mut results = Vec::with_capacity(self.tracks_count());
         let (results_sender, results_receiver) = crossbeam::channel::unbounded();
         for (cmd, _) in &self.executors {
             cmd.send(Commands::Lookup(q.clone(), results_sender.clone()))
@@ -715,12 +2885,54 @@ where
         results
     }
 
+    /// Finds the tracks that satisfy the predicate, e.g. by the attributes and the last update of the tracks
+    ///
+    /// The predicate is evaluated by the shard executors in parallel, the tracks are left in the store.
+    /// The ids are sorted.
+    ///
+    pub fn find_tracks<F>(&self, predicate: F) -> Vec<TrackId>
+    where
+        F: Fn(&Track<TA, M, OA, N>) -> bool + Send + Sync + 'static,
+    {
+        QueryCounters::record(&self.counters.lookups);
+        self.evict_lazily();
+        let predicate: Arc<TrackPredicate<TA, M, OA, N>> = Arc::new(predicate);
+        let (results_sender, results_receiver) = crossbeam::channel::unbounded();
+        for (cmd, _) in &self.executors {
+            cmd.send(Commands::Find(predicate.clone(), results_sender.clone()))
+                .unwrap();
+        }
+        let mut results = Vec::default();
+        for (_, _) in &self.executors {
+            match results_receiver.recv().unwrap() {
+                Results::Found(r) => results.extend(r),
+                _ => unreachable!(),
+            }
+        }
+        results.sort_unstable();
+        results
+    }
+
+    /// Finds the tracks with the observations added within the range of the epochs, e.g. for the forensic lookups
+    /// combined with the attributes, look at [observations_in_range](Track::observations_in_range)
+    ///
+    /// The ids are sorted.
+    ///
+    pub fn tracks_active_in_range<R>(&self, epochs: R) -> Vec<TrackId>
+    where
+        R: RangeBounds<usize>,
+    {
+        let epochs = (epochs.start_bound().cloned(), epochs.end_bound().cloned());
+        self.find_tracks(move |track| track.active_in_range(&epochs))
+    }
+
     /// clears all the tracks from the store
     ///
     pub fn clear(&self) {
         for s in self.stores.as_ref() {
-            let mut lock = s.lock().unwrap();
+            let mut lock = StoreMutexGuard::lock(s);
             lock.clear();
         }
+        self.external_ids.lock().unwrap().clear();
     }
 }
@@ -0,0 +1,231 @@
+use crate::store::{ObservationMetricErr, TrackStore};
+use crate::track::notify::ChangeNotifier;
+use crate::track::{
+    Feature, ObservationAttributes, ObservationMetric, ObservationMetricOk, Track, TrackAttributes,
+    TrackId, TrackStatus,
+};
+use anyhow::Result;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+struct Completion<T> {
+    result: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// The future of the store operation that runs on the thread pool
+///
+/// The future doesn't depend on the async runtime, it is woken when the operation is complete.
+///
+pub struct StoreFuture<T> {
+    completion: Arc<Mutex<Completion<T>>>,
+}
+
+impl<T> Future for StoreFuture<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut completion = self.completion.lock().unwrap();
+        match completion.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                completion.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The distances calculated by [foreign_track_distances](AsyncTrackStore::foreign_track_distances)
+///
+pub type AsyncTrackDistances<OA> = (Vec<ObservationMetricOk<OA>>, Vec<ObservationMetricErr<OA>>);
+
+/// Asynchronous access to the track store
+///
+/// The operations run on the thread pool, so the async services don't block their reactors while the store
+/// is scanned. The operations lock the store, so they are executed one after another, the distances are
+/// collected after the store is unlocked.
+///
+pub struct AsyncTrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    store: Arc<Mutex<TrackStore<TA, M, OA, N>>>,
+    pool: Arc<ThreadPool>,
+}
+
+impl<TA, M, OA, N> Clone for AsyncTrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl<TA, M, OA, N> AsyncTrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Creates the asynchronous store with the own thread pool
+    ///
+    /// # Arguments
+    /// * `store` - the store to access
+    /// * `threads` - the number of threads of the pool
+    ///
+    pub fn new(store: TrackStore<TA, M, OA, N>, threads: usize) -> Result<Self> {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+        Ok(Self::with_pool(store, Arc::new(pool)))
+    }
+
+    /// Creates the asynchronous store that runs the operations on the shared thread pool
+    ///
+    pub fn with_pool(store: TrackStore<TA, M, OA, N>, pool: Arc<ThreadPool>) -> Self {
+        Self {
+            store: Arc::new(Mutex::new(store)),
+            pool,
+        }
+    }
+
+    /// The store, e.g. for the synchronous operations
+    ///
+    pub fn store(&self) -> Arc<Mutex<TrackStore<TA, M, OA, N>>> {
+        self.store.clone()
+    }
+
+    fn spawn<T, F>(&self, operation: F) -> StoreFuture<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Mutex<TrackStore<TA, M, OA, N>>) -> T + Send + 'static,
+    {
+        let completion = Arc::new(Mutex::new(Completion {
+            result: None,
+            waker: None,
+        }));
+        let (store, done) = (self.store.clone(), completion.clone());
+        self.pool.spawn(move || {
+            let result = operation(&store);
+            let mut done = done.lock().unwrap();
+            done.result = Some(result);
+            if let Some(waker) = done.waker.take() {
+                waker.wake();
+            }
+        });
+        StoreFuture { completion }
+    }
+
+    /// Asynchronous [add](TrackStore::add)
+    ///
+    pub fn add(
+        &self,
+        track_id: TrackId,
+        feature_class: u64,
+        feature_attribute: Option<OA>,
+        feature: Option<Feature>,
+        attributes_update: Option<TA::Update>,
+    ) -> StoreFuture<Result<()>> {
+        self.spawn(move |store| {
+            store.lock().unwrap().add(
+                track_id,
+                feature_class,
+                feature_attribute,
+                feature,
+                attributes_update,
+            )
+        })
+    }
+
+    /// Asynchronous [lookup](TrackStore::lookup)
+    ///
+    pub fn lookup(&self, q: TA::Lookup) -> StoreFuture<Vec<(TrackId, Result<TrackStatus>)>> {
+        self.spawn(move |store| store.lock().unwrap().lookup(q))
+    }
+
+    /// Asynchronous [foreign_track_distances](TrackStore::foreign_track_distances), the future resolves to all
+    /// the calculated distances
+    ///
+    pub fn foreign_track_distances(
+        &self,
+        tracks: Vec<Track<TA, M, OA, N>>,
+        feature_class: u64,
+        only_baked: bool,
+    ) -> StoreFuture<AsyncTrackDistances<OA>> {
+        self.spawn(move |store| {
+            let (dists, errs) =
+                store
+                    .lock()
+                    .unwrap()
+                    .foreign_track_distances(tracks, feature_class, only_baked);
+            (dists.all(), errs.all())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::async_store::AsyncTrackStore;
+    use crate::store::TrackStore;
+    use crate::track::notify::NoopNotifier;
+    use crate::track::{NoopLookup, Track};
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(result) => return result,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn async_operations() {
+        let store = AsyncTrackStore::new(
+            TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2),
+            2,
+        )
+        .unwrap();
+
+        block_on(store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)).unwrap();
+        block_on(store.add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)).unwrap();
+        assert!(block_on(store.lookup(NoopLookup::default())).is_empty());
+
+        let mut query = Track::new(3, UnboundMetric, UnboundAttrs, NoopNotifier);
+        query
+            .add_observation(0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+            .unwrap();
+        let (dists, errs) = block_on(store.foreign_track_distances(vec![query], 0, false));
+        assert_eq!(dists.len(), 2);
+        assert!(errs.is_empty());
+    }
+}
@@ -1,7 +1,14 @@
-use crate::store::TrackStore;
+use crate::distance::backend::DistanceBackend;
+use crate::store::{CapacityPolicy, ShardRouter, TrackStore, TrackTtl};
 use crate::track::notify::{ChangeNotifier, NoopNotifier};
-use crate::track::{ObservationAttributes, ObservationMetric, TrackAttributes};
+use crate::track::projection::FeatureProjection;
+use crate::track::{
+    Deduplication, FeatureFusion, HistoryCap, NonFinitePolicy, ObservationAttributes,
+    ObservationMetric, TrackAttributes,
+};
+use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 /// Builder for TrackStore
 ///
@@ -16,6 +23,22 @@ where
     default_attributes: Option<TA>,
     notifier: Option<N>,
     shards: usize,
+    buckets: usize,
+    distance_backend: Option<Arc<dyn DistanceBackend>>,
+    non_finite_policy: NonFinitePolicy,
+    projections: HashMap<u64, FeatureProjection>,
+    feature_dimensions: HashMap<u64, usize>,
+    ttl: Option<TrackTtl>,
+    lazy_eviction: bool,
+    capacity: Option<usize>,
+    capacity_policy: CapacityPolicy,
+    shard_router: Option<Arc<ShardRouter>>,
+    history_caps: HashMap<u64, HistoryCap<OA>>,
+    deduplications: HashMap<u64, Deduplication>,
+    attribute_history_cap: usize,
+    feature_pool: usize,
+    fusions: HashMap<u64, FeatureFusion>,
+    default_fusion: Option<FeatureFusion>,
     _phantom_oa: PhantomData<OA>,
 }
 
@@ -49,13 +72,36 @@ where
     pub fn new(shards: usize) -> Self {
         TrackStoreBuilder {
             shards,
+            buckets: 1,
             metric: None,
             default_attributes: None,
             notifier: None,
+            distance_backend: None,
+            non_finite_policy: NonFinitePolicy::default(),
+            projections: HashMap::default(),
+            feature_dimensions: HashMap::default(),
+            ttl: None,
+            lazy_eviction: true,
+            capacity: None,
+            capacity_policy: CapacityPolicy::default(),
+            shard_router: None,
+            history_caps: HashMap::default(),
+            deduplications: HashMap::default(),
+            attribute_history_cap: 0,
+            feature_pool: 0,
+            fusions: HashMap::default(),
+            default_fusion: None,
             _phantom_oa: PhantomData,
         }
     }
 
+    /// Splits every shard into the lock buckets, look at [with_buckets](TrackStore::with_buckets)
+    ///
+    pub fn buckets(mut self, buckets: usize) -> Self {
+        self.buckets = buckets;
+        self
+    }
+
     /// Sets the metric object to use
     ///
     pub fn metric(mut self, metric: M) -> Self {
@@ -89,14 +135,152 @@ where
         self
     }
 
+    /// Sets the backend that calculates the batched feature distances
+    ///
+    pub fn distance_backend<B>(mut self, backend: B) -> Self
+    where
+        B: DistanceBackend + 'static,
+    {
+        self.distance_backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// Sets the policy for the NaN and infinite feature distances
+    ///
+    pub fn non_finite_policy(mut self, policy: NonFinitePolicy) -> Self {
+        self.non_finite_policy = policy;
+        self
+    }
+
+    /// Sets the linear projection of the features of the feature class
+    ///
+    pub fn projection(mut self, feature_class: u64, projection: FeatureProjection) -> Self {
+        self.projections.insert(feature_class, projection);
+        self
+    }
+
+    /// Registers the expected dimensions of the features of the feature class
+    ///
+    pub fn feature_dimensions(mut self, feature_class: u64, dimensions: usize) -> Self {
+        self.feature_dimensions.insert(feature_class, dimensions);
+        self
+    }
+
+    /// Sets the time-to-live policy of the tracks
+    ///
+    pub fn ttl(mut self, ttl: TrackTtl) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Configures whether the expired tracks are evicted lazily before the queries
+    ///
+    pub fn lazy_eviction(mut self, lazy: bool) -> Self {
+        self.lazy_eviction = lazy;
+        self
+    }
+
+    /// Sets the maximum number of the tracks in the store
+    ///
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Sets the function that selects the shard of the track by its id
+    ///
+    pub fn shard_router(mut self, router: Arc<ShardRouter>) -> Self {
+        self.shard_router = Some(router);
+        self
+    }
+
+    /// Sets how the store admits the new tracks when it is full
+    ///
+    pub fn capacity_policy(mut self, policy: CapacityPolicy) -> Self {
+        self.capacity_policy = policy;
+        self
+    }
+
+    /// Sets the history cap of the feature class
+    ///
+    pub fn history_cap(mut self, feature_class: u64, cap: HistoryCap<OA>) -> Self {
+        self.history_caps.insert(feature_class, cap);
+        self
+    }
+
+    /// Sets the number of the attribute versions kept by every track
+    ///
+    pub fn attribute_history_cap(mut self, cap: usize) -> Self {
+        self.attribute_history_cap = cap;
+        self
+    }
+
+    /// Sets the number of the feature buffers recycled by every bucket of the shards, look at
+    /// [set_feature_pool](TrackStore::set_feature_pool)
+    ///
+    pub fn feature_pool(mut self, limit: usize) -> Self {
+        self.feature_pool = limit;
+        self
+    }
+
+    /// Sets the deduplication of the features of the feature class
+    ///
+    pub fn deduplication(mut self, feature_class: u64, deduplication: Deduplication) -> Self {
+        self.deduplications.insert(feature_class, deduplication);
+        self
+    }
+
+    /// Sets the fusion of the observations of the feature class
+    ///
+    pub fn feature_fusion(mut self, feature_class: u64, fusion: FeatureFusion) -> Self {
+        self.fusions.insert(feature_class, fusion);
+        self
+    }
+
+    /// Sets the fusion of the feature classes without the own fusion
+    ///
+    pub fn default_feature_fusion(mut self, fusion: FeatureFusion) -> Self {
+        self.default_fusion = Some(fusion);
+        self
+    }
+
     /// Builds the TrackStore
     ///
     pub fn build(self) -> TrackStore<TA, M, OA, N> {
-        TrackStore::new(
+        let mut store = TrackStore::with_buckets(
             self.metric.unwrap(),
             self.default_attributes.unwrap(),
             self.notifier.unwrap(),
             self.shards,
-        )
+            self.buckets,
+        );
+        store.set_non_finite_policy(self.non_finite_policy);
+        store.set_ttl(self.ttl);
+        store.set_lazy_eviction(self.lazy_eviction);
+        store.set_capacity(self.capacity);
+        store.set_capacity_policy(self.capacity_policy);
+        store.set_shard_router(self.shard_router);
+        store.set_attribute_history_cap(self.attribute_history_cap);
+        store.set_feature_pool(self.feature_pool);
+        store.set_default_feature_fusion(self.default_fusion);
+        for (feature_class, fusion) in self.fusions {
+            store.set_feature_fusion(feature_class, fusion);
+        }
+        for (feature_class, cap) in self.history_caps {
+            store.set_history_cap(feature_class, cap);
+        }
+        for (feature_class, deduplication) in self.deduplications {
+            store.set_deduplication(feature_class, deduplication);
+        }
+        for (feature_class, dimensions) in self.feature_dimensions {
+            store.set_feature_dimensions(feature_class, dimensions);
+        }
+        for (feature_class, projection) in self.projections {
+            store.set_projection(feature_class, projection);
+        }
+        if let Some(backend) = self.distance_backend {
+            store.distance_backend = backend;
+        }
+        store
     }
 }
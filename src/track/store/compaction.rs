@@ -0,0 +1,101 @@
+use crate::store::{StoreMutexGuard, TrackStore};
+use crate::track::notify::ChangeNotifier;
+use crate::track::{ObservationAttributes, ObservationMetric, TrackAttributes};
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Rebalances the tracks across the shards and shrinks the over-allocated buffers
+    ///
+    /// The tracks of the shards over the even share are moved to the shards under it, e.g. when the eviction
+    /// has emptied some shards, the moved tracks are routed to their new shards by
+    /// [get_executor](TrackStore::get_executor). The shards, the observations and the merge histories of the
+    /// tracks are shrunk to fit. All the shards are locked while the store is compacted.
+    ///
+    /// # Returns
+    /// The number of the moved tracks
+    ///
+    pub fn compact(&mut self) -> usize {
+        let mut shards = self
+            .stores
+            .iter()
+            .map(StoreMutexGuard::lock)
+            .collect::<Vec<_>>();
+        let total = shards.iter().map(|s| s.len()).sum::<usize>();
+        let share = total / shards.len() + usize::from(total % shards.len() > 0);
+
+        let mut moved = 0;
+        for from in 0..shards.len() {
+            while shards[from].len() > share {
+                let to = match (0..shards.len()).find(|s| shards[*s].len() < share) {
+                    Some(to) => to,
+                    None => break,
+                };
+                let track_id = *shards[from].keys().next().unwrap();
+                let track = shards[from].remove(&track_id).unwrap();
+                shards[to].insert(track_id, track);
+                moved += 1;
+            }
+        }
+
+        // the routes of the removed tracks are dropped
+        self.routes.clear();
+        for (bucket, shard) in shards.iter_mut().enumerate() {
+            shard.shrink_to_fit();
+            for (track_id, track) in shard.iter_mut() {
+                if self.home_bucket(*track_id) != bucket {
+                    self.routes.insert(*track_id, bucket);
+                }
+                track.observations.values_mut().for_each(Vec::shrink_to_fit);
+                track.observations.shrink_to_fit();
+                track.merge_history.shrink_to_fit();
+            }
+        }
+        self.routes.shrink_to_fit();
+        drop(shards);
+        self.reroute_parked();
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::TrackStore;
+    use crate::track::notify::NoopNotifier;
+
+    #[test]
+    fn compact() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 3);
+        for track_id in (0..27).step_by(3) {
+            store
+                .add(track_id, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+                .unwrap();
+        }
+        assert_eq!(store.shard_stats(), vec![9, 0, 0]);
+
+        assert_eq!(store.compact(), 6);
+        assert_eq!(store.shard_stats(), vec![3, 3, 3]);
+        assert_eq!(store.compact(), 0);
+
+        for track_id in (0..27).step_by(3) {
+            store
+                .add(track_id, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)
+                .unwrap();
+        }
+        assert_eq!(store.shard_stats(), vec![3, 3, 3]);
+        let snapshot = store.snapshot();
+        assert!((0..27).step_by(3).all(|id| snapshot.get(id).is_some()));
+        drop(snapshot);
+
+        let tracks = store.fetch_tracks(&(0..27).step_by(3).collect::<Vec<_>>());
+        assert_eq!(tracks.len(), 9);
+        assert!(tracks
+            .iter()
+            .all(|t| t.get_observations(0).unwrap().len() == 2));
+    }
+}
@@ -0,0 +1,221 @@
+use crate::distance::backend::DistanceBackend;
+use crate::store::{ObservationMetricErr, TrackStore};
+use crate::track::notify::ChangeNotifier;
+use crate::track::{
+    NonFinitePolicy, ObservationAttributes, ObservationMetric, ObservationMetricOk, Track,
+    TrackAttributes, TrackId,
+};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+/// Consistent read-only copy of the tracks of the store, look at [snapshot](TrackStore::snapshot)
+///
+/// The snapshot shares the shards with the store, the shard is copied by the store when it is changed for
+/// the first time after the snapshot is taken. The snapshot is not changed by the store, so the long queries
+/// over the snapshot don't block the updates of the store.
+///
+pub struct StoreSnapshot<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    #[allow(clippy::type_complexity)]
+    shards: Vec<Arc<HashMap<TrackId, Track<TA, M, OA, N>>>>,
+    epoch: usize,
+    distance_backend: Arc<dyn DistanceBackend>,
+    non_finite_policy: NonFinitePolicy,
+}
+
+impl<TA, M, OA, N> StoreSnapshot<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// The epoch of the store when the snapshot was taken
+    ///
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    /// The number of the tracks in the snapshot
+    ///
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.len()).sum()
+    }
+
+    /// Returns `true` when the snapshot has no tracks
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| s.is_empty())
+    }
+
+    /// The track of the snapshot
+    ///
+    pub fn get(&self, track_id: TrackId) -> Option<&Track<TA, M, OA, N>> {
+        self.shards.iter().find_map(|s| s.get(&track_id))
+    }
+
+    /// Iterates over the tracks of the snapshot shard by shard
+    ///
+    pub fn iter(&self) -> impl Iterator<Item = &Track<TA, M, OA, N>> {
+        self.shards.iter().flat_map(|s| s.values())
+    }
+
+    /// Calculates the distances between the track and the tracks of the snapshot in the calling thread
+    ///
+    /// The distances are calculated like in [foreign_track_distances](TrackStore::foreign_track_distances) with
+    /// the distance backend and the non-finite policy of the store, the track is not projected.
+    ///
+    pub fn track_distances(
+        &self,
+        track: &Track<TA, M, OA, N>,
+        feature_class: u64,
+        only_baked: bool,
+    ) -> (Vec<ObservationMetricOk<OA>>, Vec<ObservationMetricErr<OA>>) {
+        let dists = self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                TrackStore::shard_distances(
+                    shard.values(),
+                    track,
+                    feature_class,
+                    only_baked,
+                    self.distance_backend.as_ref(),
+                )
+            })
+            .collect();
+        TrackStore::<TA, M, OA, N>::collect_distances(
+            dists,
+            |dists| {
+                track
+                    .metric
+                    .postprocess_class_distances(feature_class, dists)
+            },
+            track.metric.polarity(),
+            self.non_finite_policy,
+        )
+    }
+
+    /// Streams the distances between the track and the tracks of the snapshot to the sink in the calling thread
+    ///
+    /// The tracks of every shard are processed by the chunks of `chunk_size` tracks, the sink gets the distances
+    /// and the errors of the chunk, so only the distances of one chunk are kept in memory. The sink stops the
+    /// stream with `ControlFlow::Break`. The distances are calculated like in
+    /// [track_distances](StoreSnapshot::track_distances).
+    ///
+    pub fn stream_distances<F>(
+        &self,
+        track: &Track<TA, M, OA, N>,
+        feature_class: u64,
+        only_baked: bool,
+        chunk_size: usize,
+        mut sink: F,
+    ) where
+        F: FnMut(Vec<ObservationMetricOk<OA>>, Vec<ObservationMetricErr<OA>>) -> ControlFlow<()>,
+    {
+        for shard in &self.shards {
+            let candidates = shard
+                .values()
+                .filter(|other| TrackStore::is_candidate(track, other, only_baked))
+                .collect::<Vec<_>>();
+            for chunk in candidates.chunks(chunk_size.max(1)) {
+                let dists = TrackStore::candidate_distances(
+                    chunk,
+                    track,
+                    feature_class,
+                    self.distance_backend.as_ref(),
+                );
+                let (distances, errors) = TrackStore::<TA, M, OA, N>::collect_distances(
+                    dists,
+                    |dists| {
+                        track
+                            .metric
+                            .postprocess_class_distances(feature_class, dists)
+                    },
+                    track.metric.polarity(),
+                    self.non_finite_policy,
+                );
+                if sink(distances, errors).is_break() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Takes the consistent copy-on-write snapshot of the tracks, look at [StoreSnapshot](StoreSnapshot)
+    ///
+    /// All the shards are locked while the snapshot is taken, so the snapshot doesn't see the partial changes
+    /// of the operations that span the shards. Taking the snapshot doesn't copy the tracks, the first change of
+    /// every shard after the snapshot copies the tracks of the shard.
+    ///
+    pub fn snapshot(&self) -> StoreSnapshot<TA, M, OA, N> {
+        let shards = self
+            .stores
+            .iter()
+            .map(|s| s.read().unwrap())
+            .collect::<Vec<_>>();
+        StoreSnapshot {
+            shards: shards.iter().map(|s| Arc::clone(s)).collect(),
+            epoch: self.epoch(),
+            distance_backend: self.distance_backend.clone(),
+            non_finite_policy: self.non_finite_policy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::TrackStore;
+    use crate::track::notify::NoopNotifier;
+    use crate::track::Track;
+
+    #[test]
+    fn snapshot_isolation() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        for track_id in 0..4 {
+            store
+                .add(track_id, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+                .unwrap();
+        }
+        let snapshot = store.snapshot();
+
+        // the changes after the snapshot are not visible in it
+        store
+            .add(1, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)
+            .unwrap();
+        store
+            .add(4, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)
+            .unwrap();
+        store.fetch_tracks(&[2]);
+        assert_eq!(snapshot.len(), 4);
+        assert_eq!(
+            snapshot.get(1).unwrap().get_observations(0).unwrap().len(),
+            1
+        );
+        assert!(snapshot.get(2).is_some() && snapshot.get(4).is_none());
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 4);
+
+        let mut query = Track::new(10, UnboundMetric, UnboundAttrs, NoopNotifier);
+        query
+            .add_observation(0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+            .unwrap();
+        let (dists, errs) = snapshot.track_distances(&query, 0, false);
+        assert_eq!(dists.len(), 4);
+        assert!(errs.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::store::view::TrackView;
+use crate::track::TrackId;
+use crossbeam::channel::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// The lifecycle event of the track in the store
+///
+/// The events carry the views of the tracks after the change, so the subscribers can mirror the state of
+/// the store without querying it.
+///
+#[derive(Debug, Clone)]
+pub enum TrackEvent<TA> {
+    /// the track is added to the store
+    Created(TrackView<TA>),
+    /// the observations are added to the track or the track is split
+    Updated(TrackView<TA>),
+    /// the source tracks are merged into the track
+    Merged {
+        track: TrackView<TA>,
+        sources: Vec<TrackId>,
+    },
+    /// the track is evicted by the time-to-live or the capacity policy
+    Evicted(TrackView<TA>),
+}
+
+impl<TA> TrackEvent<TA> {
+    /// The id of the track the event is about
+    ///
+    pub fn track_id(&self) -> TrackId {
+        match self {
+            TrackEvent::Created(t)
+            | TrackEvent::Updated(t)
+            | TrackEvent::Merged { track: t, .. }
+            | TrackEvent::Evicted(t) => t.track_id,
+        }
+    }
+}
+
+/// Delivers the track events to the subscribers
+///
+/// The subscribers that dropped their receivers are removed when the next event is published.
+///
+pub struct EventBus<TA> {
+    subscribers: Arc<Mutex<Vec<Sender<TrackEvent<TA>>>>>,
+}
+
+impl<TA> Clone for EventBus<TA> {
+    fn clone(&self) -> Self {
+        Self {
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+impl<TA> Default for EventBus<TA> {
+    fn default() -> Self {
+        Self {
+            subscribers: Arc::new(Mutex::new(Vec::default())),
+        }
+    }
+}
+
+impl<TA: Clone> EventBus<TA> {
+    /// Subscribes to the events, the events published after the call are received
+    ///
+    pub fn subscribe(&self) -> Receiver<TrackEvent<TA>> {
+        let (sender, receiver) = crossbeam::channel::unbounded();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Publishes the event, the event is built only when there are subscribers
+    ///
+    pub fn publish<F>(&self, event: F)
+    where
+        F: FnOnce() -> TrackEvent<TA>,
+    {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if subscribers.is_empty() {
+            return;
+        }
+        let event = event();
+        subscribers.retain(|s| s.send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::events::TrackEvent;
+    use crate::store::{TrackStore, TrackTtl};
+    use crate::track::notify::NoopNotifier;
+    use crate::track::AttributeMergePolicy;
+
+    #[test]
+    fn lifecycle_events() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        let events = store.subscribe();
+
+        store
+            .add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+            .unwrap();
+        store
+            .add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+            .unwrap();
+        store
+            .add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)
+            .unwrap();
+        store
+            .merge_tracks(1, &[2], &AttributeMergePolicy::Merge)
+            .unwrap();
+        store.set_ttl(Some(TrackTtl::Epochs(0)));
+        store.advance_epoch();
+        store.purge();
+
+        let events = events.try_iter().collect::<Vec<_>>();
+        assert_eq!(events.len(), 5);
+        assert!(matches!(&events[0], TrackEvent::Created(t) if t.track_id == 1));
+        assert!(matches!(&events[1], TrackEvent::Updated(t) if t.observation_counts[&0] == 2));
+        assert!(matches!(&events[2], TrackEvent::Created(t) if t.track_id == 2));
+        assert!(matches!(
+            &events[3],
+            TrackEvent::Merged { track, sources } if track.observation_counts[&0] == 3 && sources == &vec![2]
+        ));
+        assert!(matches!(&events[4], TrackEvent::Evicted(t) if t.track_id == 1));
+    }
+}
@@ -0,0 +1,295 @@
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::utils::FromVec;
+use crate::track::{ObservationAttributes, ObservationMetric, TrackAttributes, TrackId};
+use anyhow::Result;
+use arrow::array::{
+    make_builder, ArrayBuilder, ArrayRef, FixedSizeBinaryBuilder, Float32Builder, ListBuilder,
+    UInt32Builder, UInt64Builder,
+};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The attributes exported as the columns of the record batches
+///
+/// The tracks are exported with the columns of the track attributes, the observations are exported with the
+/// columns of the observation attributes. The builders of the columns are created from the fields with
+/// [make_builder](arrow::array::make_builder), so [append](ArrowColumns::append) downcasts them with
+/// `as_any_mut`, e.g. to [Float32Builder](arrow::array::Float32Builder) for [DataType::Float32].
+///
+pub trait ArrowColumns {
+    /// The columns of the attributes
+    ///
+    fn fields() -> Vec<Field>;
+
+    /// Appends the attributes to the builders of the columns, in the order of [fields](ArrowColumns::fields),
+    /// the values are null when the observation doesn't have the attributes
+    ///
+    fn append(value: Option<&Self>, columns: &mut [Box<dyn ArrayBuilder>]);
+}
+
+impl ArrowColumns for f32 {
+    fn fields() -> Vec<Field> {
+        vec![Field::new("value", DataType::Float32, true)]
+    }
+
+    fn append(value: Option<&Self>, columns: &mut [Box<dyn ArrayBuilder>]) {
+        columns[0]
+            .as_any_mut()
+            .downcast_mut::<Float32Builder>()
+            .unwrap()
+            .append_option(value.copied());
+    }
+}
+
+/// The track ids are exported as 16 big-endian bytes, the layout of the UUIDs
+///
+fn track_id_field() -> Field {
+    Field::new("track_id", DataType::FixedSizeBinary(16), false)
+}
+
+fn append_track_id(builder: &mut FixedSizeBinaryBuilder, track_id: TrackId) {
+    builder.append_value(track_id.to_be_bytes()).unwrap();
+}
+
+fn builders(fields: &[Field]) -> Vec<Box<dyn ArrayBuilder>> {
+    fields
+        .iter()
+        .map(|f| make_builder(f.data_type(), 0))
+        .collect()
+}
+
+fn batch(fields: Vec<Field>, columns: Vec<ArrayRef>) -> Result<RecordBatch> {
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+fn write_parquet<P: AsRef<Path>>(path: P, batch: &RecordBatch) -> Result<()> {
+    let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), None)?;
+    writer.write(batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA> + ArrowColumns,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes + ArrowColumns,
+    N: ChangeNotifier,
+{
+    /// Exports the tracks of the store to the record batch
+    ///
+    /// The batch has the row per track with the `track_id` column and the columns of the track attributes,
+    /// look at [ArrowColumns](ArrowColumns). The track ids are 16 big-endian bytes, e.g. `uuid.UUID(bytes=id)`
+    /// or `int.from_bytes(id, "big")` in Python.
+    ///
+    pub fn export_tracks(&self) -> Result<RecordBatch> {
+        let attribute_fields = TA::fields();
+        let mut track_ids = FixedSizeBinaryBuilder::new(16);
+        let mut attributes = builders(&attribute_fields);
+        self.visit_tracks(|_, track| {
+            append_track_id(&mut track_ids, track.get_track_id());
+            TA::append(Some(track.get_attributes()), &mut attributes);
+        });
+
+        let mut fields = vec![track_id_field()];
+        fields.extend(attribute_fields);
+        let mut columns: Vec<ArrayRef> = vec![Arc::new(track_ids.finish())];
+        columns.extend(attributes.iter_mut().map(|b| b.finish()));
+        batch(fields, columns)
+    }
+
+    /// Exports the observations of the tracks of the store to the record batch
+    ///
+    /// The batch has the row per observation with the columns:
+    /// * `track_id` - the track of the observation;
+    /// * `feature_class` - the feature class of the observation;
+    /// * `observation` - the index of the observation in the feature class of the track;
+    /// * `epoch` - the epoch the observation was added at;
+    /// * the columns of the observation attributes, look at [ArrowColumns](ArrowColumns);
+    /// * `feature` - the feature vector as the list of `f32`, null when the observation doesn't have it.
+    ///
+    /// The feature vectors are truncated to the [feature_dimensions](TrackStore::feature_dimensions) of their
+    /// classes, the vectors of the classes without the dimensions are exported with the padding to the blocks of
+    /// 8 values.
+    ///
+    pub fn export_observations(&self) -> Result<RecordBatch> {
+        let attribute_fields = OA::fields();
+        let mut track_ids = FixedSizeBinaryBuilder::new(16);
+        let mut feature_classes = UInt64Builder::new();
+        let mut indexes = UInt32Builder::new();
+        let mut epochs = UInt64Builder::new();
+        let mut attributes = builders(&attribute_fields);
+        let mut features = ListBuilder::new(Float32Builder::new());
+        self.visit_tracks(|_, track| {
+            for (feature_class, observations) in &track.observations {
+                let dimensions = self.feature_dimensions(*feature_class);
+                for (index, observation) in observations.iter().enumerate() {
+                    append_track_id(&mut track_ids, track.get_track_id());
+                    feature_classes.append_value(*feature_class);
+                    indexes.append_value(index as u32);
+                    epochs.append_value(observation.2.epoch as u64);
+                    OA::append(observation.0.as_ref(), &mut attributes);
+                    match &observation.1 {
+                        Some(feature) => {
+                            let mut values = Vec::<f32>::from_vec(feature);
+                            if let Some(dimensions) = dimensions {
+                                values.truncate(dimensions);
+                            }
+                            features.values().append_slice(&values);
+                            features.append(true);
+                        }
+                        None => features.append(false),
+                    }
+                }
+            }
+        });
+
+        let mut fields = vec![
+            track_id_field(),
+            Field::new("feature_class", DataType::UInt64, false),
+            Field::new("observation", DataType::UInt32, false),
+            Field::new("epoch", DataType::UInt64, false),
+        ];
+        fields.extend(attribute_fields);
+        fields.push(Field::new(
+            "feature",
+            DataType::new_list(DataType::Float32, true),
+            true,
+        ));
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(track_ids.finish()),
+            Arc::new(feature_classes.finish()),
+            Arc::new(indexes.finish()),
+            Arc::new(epochs.finish()),
+        ];
+        columns.extend(attributes.iter_mut().map(|b| b.finish()));
+        columns.push(Arc::new(features.finish()));
+        batch(fields, columns)
+    }
+
+    /// Writes the tracks and the observations of the store to the Parquet files
+    ///
+    /// # Arguments
+    /// * `tracks` - the file of [export_tracks](TrackStore::export_tracks), replaced if exists
+    /// * `observations` - the file of [export_observations](TrackStore::export_observations), replaced if exists
+    ///
+    /// The files are joined by `track_id`, e.g. with `pandas.read_parquet(...).merge(...)`.
+    ///
+    pub fn write_parquet<P: AsRef<Path>>(&self, tracks: P, observations: P) -> Result<()> {
+        write_parquet(tracks, &self.export_tracks()?)?;
+        write_parquet(observations, &self.export_observations()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::export::ArrowColumns;
+    use crate::store::TrackStore;
+    use crate::track::notify::NoopNotifier;
+    use anyhow::Result;
+    use arrow::array::{
+        Array, ArrayBuilder, BooleanBuilder, FixedSizeBinaryArray, Float32Array, ListArray,
+        UInt32Array,
+    };
+    use arrow::datatypes::{DataType, Field};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::fs::File;
+
+    impl ArrowColumns for UnboundAttrs {
+        fn fields() -> Vec<Field> {
+            vec![Field::new("unbound", DataType::Boolean, false)]
+        }
+
+        fn append(_value: Option<&Self>, columns: &mut [Box<dyn ArrayBuilder>]) {
+            columns[0]
+                .as_any_mut()
+                .downcast_mut::<BooleanBuilder>()
+                .unwrap()
+                .append_value(true);
+        }
+    }
+
+    fn store() -> Result<TrackStore<UnboundAttrs, UnboundMetric, f32>> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        store.set_feature_dimensions(0, 2);
+        store.add(1, 0, Some(0.5), Some(vec2(1.0, 0.0)), None)?;
+        store.add(1, 0, None, Some(vec2(0.0, 1.0)), None)?;
+        store.add(2, 1, Some(1.0), None, None)?;
+        Ok(store)
+    }
+
+    #[test]
+    fn export() -> Result<()> {
+        let store = store()?;
+        let tracks = store.export_tracks()?;
+        assert_eq!(tracks.num_rows(), 2);
+        assert_eq!(tracks.schema().field(1).name(), "unbound");
+
+        let observations = store.export_observations()?;
+        assert_eq!(observations.num_rows(), 3);
+        let column = |name: &str| observations.column_by_name(name).unwrap().clone();
+        let track_ids = column("track_id");
+        let track_ids = track_ids
+            .as_any()
+            .downcast_ref::<FixedSizeBinaryArray>()
+            .unwrap();
+        let track_id = |row: usize| u128::from_be_bytes(track_ids.value(row).try_into().unwrap());
+        let row = (0..3).find(|r| track_id(*r) == 1).unwrap();
+
+        let indexes = column("observation");
+        let indexes = indexes.as_any().downcast_ref::<UInt32Array>().unwrap();
+        assert_eq!(indexes.value(row), 0);
+        let values = column("value");
+        let values = values.as_any().downcast_ref::<Float32Array>().unwrap();
+        assert_eq!(values.value(row), 0.5);
+        assert!(values.is_null(row + 1));
+
+        let features = column("feature");
+        let features = features.as_any().downcast_ref::<ListArray>().unwrap();
+        // truncated to the dimensions of the class
+        assert_eq!(
+            features
+                .value(row + 1)
+                .as_any()
+                .downcast_ref::<Float32Array>()
+                .unwrap()
+                .values()
+                .to_vec(),
+            vec![0.0, 1.0]
+        );
+        let other = (0..3).find(|r| track_id(*r) == 2).unwrap();
+        assert!(features.is_null(other));
+        Ok(())
+    }
+
+    #[test]
+    fn parquet() -> Result<()> {
+        let store = store()?;
+        let dir = std::env::temp_dir().join(format!("similari-export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let (tracks, observations) = (dir.join("tracks.parquet"), dir.join("observations.parquet"));
+        store.write_parquet(&tracks, &observations)?;
+
+        let rows = |path| -> Result<usize> {
+            let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()?;
+            let mut rows = 0;
+            for batch in reader {
+                rows += batch?.num_rows();
+            }
+            Ok(rows)
+        };
+        assert_eq!(rows(&tracks)?, 2);
+        assert_eq!(rows(&observations)?, 3);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
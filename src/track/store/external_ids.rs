@@ -0,0 +1,167 @@
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::{ObservationAttributes, ObservationMetric, TrackAttributes, TrackId};
+use crate::Errors;
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Bidirectional mapping between the track ids and the external ids, e.g. the database keys
+///
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExternalIds {
+    externals: HashMap<TrackId, String>,
+    tracks: HashMap<String, TrackId>,
+}
+
+impl ExternalIds {
+    pub(crate) fn insert(
+        &mut self,
+        track_id: TrackId,
+        external_id: String,
+    ) -> Result<Option<String>> {
+        match self.tracks.get(&external_id) {
+            Some(&owner) if owner == track_id => return Ok(Some(external_id)),
+            Some(&owner) => return Err(Errors::ExternalIdCollision(external_id, owner).into()),
+            None => {}
+        }
+        let previous = self.remove(track_id);
+        self.tracks.insert(external_id.clone(), track_id);
+        self.externals.insert(track_id, external_id);
+        Ok(previous)
+    }
+
+    pub(crate) fn remove(&mut self, track_id: TrackId) -> Option<String> {
+        let external_id = self.externals.remove(&track_id)?;
+        self.tracks.remove(&external_id);
+        Some(external_id)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.externals.clear();
+        self.tracks.clear();
+    }
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Maps the track to the external id
+    ///
+    /// The mapping is maintained by the store: it is removed when the track is evicted, fetched, merged into
+    /// the other track with [merge_tracks](TrackStore::merge_tracks) or removed by
+    /// [merge_owned](TrackStore::merge_owned), or when the store is cleared.
+    ///
+    /// # Returns
+    /// * `Ok(previous)` - the track is mapped, the previous external id of the track is returned
+    /// * `Err(Errors::TrackNotFound(track_id))` - the track is not in the store
+    /// * `Err(Errors::ExternalIdCollision(external_id, owner))` - the external id is mapped to the other track
+    ///
+    pub fn map_external_id<S: Into<String>>(
+        &self,
+        track_id: TrackId,
+        external_id: S,
+    ) -> Result<Option<String>> {
+        // the shard is locked while the mapping is changed, so the track cannot be evicted meanwhile
+        let shard = self.get_store(track_id);
+        if !shard.contains_key(&track_id) {
+            return Err(Errors::TrackNotFound(track_id).into());
+        }
+        self.external_ids
+            .lock()
+            .unwrap()
+            .insert(track_id, external_id.into())
+    }
+
+    /// Removes the mapping of the track, returns the external id of the track
+    ///
+    pub fn unmap_external_id(&self, track_id: TrackId) -> Option<String> {
+        self.external_ids.lock().unwrap().remove(track_id)
+    }
+
+    /// The track mapped to the external id
+    ///
+    pub fn track_id_of(&self, external_id: &str) -> Option<TrackId> {
+        self.external_ids
+            .lock()
+            .unwrap()
+            .tracks
+            .get(external_id)
+            .copied()
+    }
+
+    /// The external id of the track
+    ///
+    pub fn external_id_of(&self, track_id: TrackId) -> Option<String> {
+        self.external_ids
+            .lock()
+            .unwrap()
+            .externals
+            .get(&track_id)
+            .cloned()
+    }
+
+    pub(crate) fn unmap_tracks<I: IntoIterator<Item = TrackId>>(&self, track_ids: I) {
+        let mut external_ids = self.external_ids.lock().unwrap();
+        for track_id in track_ids {
+            external_ids.remove(track_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::{TrackStore, TrackTtl};
+    use crate::track::notify::NoopNotifier;
+    use crate::track::AttributeMergePolicy;
+    use crate::Errors;
+
+    #[test]
+    fn external_ids() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        for track_id in 1..=4 {
+            store
+                .add(track_id, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+                .unwrap();
+        }
+        assert!(store.map_external_id(1, "a").unwrap().is_none());
+        store.map_external_id(2, "b").unwrap();
+        store.map_external_id(3, "c").unwrap();
+        store.map_external_id(4, "d").unwrap();
+        assert!(matches!(
+            store
+                .map_external_id(5, "e")
+                .unwrap_err()
+                .downcast::<Errors>()
+                .unwrap(),
+            Errors::TrackNotFound(5)
+        ));
+        assert!(matches!(
+            store.map_external_id(2, "a").unwrap_err().downcast::<Errors>().unwrap(),
+            Errors::ExternalIdCollision(id, 1) if id == "a"
+        ));
+        assert_eq!(store.map_external_id(2, "bb").unwrap(), Some("b".into()));
+        assert_eq!(store.track_id_of("bb"), Some(2));
+        assert_eq!(store.track_id_of("b"), None);
+        assert_eq!(store.external_id_of(1), Some("a".into()));
+
+        // the mappings follow the tracks leaving the store
+        store
+            .merge_tracks(1, &[2], &AttributeMergePolicy::Merge)
+            .unwrap();
+        assert_eq!(store.track_id_of("bb"), None);
+        store.fetch_tracks(&[3]);
+        assert_eq!(store.track_id_of("c"), None);
+        store.add(1, 0, Some(1.0), None, None).unwrap();
+        store.set_ttl(Some(TrackTtl::Epochs(0)));
+        store.advance_epoch();
+        store.add(1, 0, Some(1.0), None, None).unwrap();
+        store.purge();
+        assert_eq!(store.track_id_of("d"), None);
+        assert_eq!(store.track_id_of("a"), Some(1));
+    }
+}
@@ -0,0 +1,364 @@
+use crate::store::events::EventBus;
+use crate::store::external_ids::ExternalIds;
+use crate::store::pool::FeaturePool;
+use crate::store::stats::QueryCounters;
+use crate::store::{Overflow, Shard, StoreMutexGuard, TrackStore, TrackTtl};
+use crate::track::notify::ChangeNotifier;
+use crate::track::{
+    FeatureFusion, HistoryCap, ObservationAttributes, ObservationMetric, Track, TrackAttributes,
+};
+use crossbeam::channel::{RecvTimeoutError, Sender};
+use log::warn;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Defines what the background maintenance of the store does and how often
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceSchedule {
+    /// the pause between the maintenance runs
+    pub interval: Duration,
+    /// evict the expired tracks, they are kept by the store until [purge](TrackStore::purge) is called
+    pub purge: bool,
+    /// apply the feature fusions and the history caps to the tracks of the changed shards, e.g. after the merges,
+    /// and shrink the observation buffers which capacity exceeds the length twice
+    pub compact_history: bool,
+}
+
+impl MaintenanceSchedule {
+    /// The schedule that runs all the maintenance every `interval`
+    ///
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            purge: true,
+            compact_history: true,
+        }
+    }
+}
+
+impl Default for MaintenanceSchedule {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1))
+    }
+}
+
+pub(crate) struct MaintenanceWorker {
+    stop: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+struct Maintenance<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    schedule: MaintenanceSchedule,
+    stores: Arc<Vec<Shard<TA, M, OA, N>>>,
+    epoch: Arc<AtomicUsize>,
+    events: EventBus<TA>,
+    #[allow(clippy::type_complexity)]
+    evicted: Arc<Mutex<Vec<Track<TA, M, OA, N>>>>,
+    external_ids: Arc<Mutex<ExternalIds>>,
+    counters: Arc<QueryCounters>,
+    #[allow(clippy::type_complexity)]
+    overflow: Arc<Overflow<TA, M, OA, N>>,
+    capacity: Option<usize>,
+    ttl: Option<TrackTtl>,
+    history_caps: HashMap<u64, HistoryCap<OA>>,
+    fusions: HashMap<u64, FeatureFusion>,
+    default_fusion: Option<FeatureFusion>,
+}
+
+impl<TA, M, OA, N> Maintenance<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    fn run(&self) {
+        if let (true, Some(ttl)) = (self.schedule.purge, self.ttl) {
+            let evicted =
+                TrackStore::evict_expired(&self.stores, ttl, self.epoch.load(Ordering::SeqCst));
+            TrackStore::release_evicted(&self.events, &self.external_ids, &self.counters, &evicted);
+            self.evicted.lock().unwrap().extend(evicted);
+        }
+        // the parked tracks take the space freed by the eviction
+        let parked = TrackStore::release_parked(
+            &self.stores,
+            &self.overflow,
+            self.capacity,
+            self.epoch.load(Ordering::SeqCst),
+            &self.events,
+        );
+        for res in parked {
+            if let Err(e) = res {
+                warn!("Unable to add the parked track: {:?}", e);
+            }
+        }
+        if self.schedule.compact_history {
+            for store in self.stores.iter() {
+                if !store.changed.swap(false, Ordering::Relaxed) {
+                    continue;
+                }
+                let stale = store
+                    .read()
+                    .unwrap()
+                    .values()
+                    .filter(|track| self.needs_compaction(track))
+                    .map(|track| track.track_id)
+                    .collect::<Vec<_>>();
+                if stale.is_empty() {
+                    continue;
+                }
+                let mut tracks = StoreMutexGuard::lock(store);
+                for track_id in stale {
+                    if let Some(track) = tracks.get_mut(&track_id) {
+                        self.compact(track, &store.pool);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks if the fusion or the history cap changes the observations of the track, or the observation
+    /// buffers are over-allocated
+    ///
+    fn needs_compaction(&self, track: &Track<TA, M, OA, N>) -> bool {
+        track
+            .observations
+            .iter()
+            .any(|(feature_class, observations)| {
+                let fused = self
+                    .fusions
+                    .get(feature_class)
+                    .or(self.default_fusion.as_ref())
+                    .is_some()
+                    && observations.len() > 1;
+                let capped = match self.history_caps.get(feature_class) {
+                    Some(
+                        HistoryCap::KeepLast(n)
+                        | HistoryCap::KeepBest(n, _)
+                        | HistoryCap::Reservoir(n),
+                    ) => observations.len() > *n,
+                    None => false,
+                };
+                fused || capped || over_allocated(observations)
+            })
+    }
+
+    fn compact(&self, track: &mut Track<TA, M, OA, N>, pool: &FeaturePool) {
+        for feature_class in track.get_feature_classes() {
+            let fusion = self
+                .fusions
+                .get(&feature_class)
+                .or(self.default_fusion.as_ref());
+            if let Some(fusion) = fusion {
+                match track.fuse_observations(feature_class, fusion) {
+                    Ok(fused) => pool.release(fused),
+                    Err(e) => warn!(
+                        "Unable to fuse the observations of track={}, class={}: {:?}",
+                        track.track_id, feature_class, e
+                    ),
+                }
+            }
+            if let Some(cap) = self.history_caps.get(&feature_class) {
+                pool.release(track.cap_history(feature_class, cap));
+            }
+        }
+        track
+            .observations
+            .values_mut()
+            .filter(|observations| over_allocated(observations))
+            .for_each(Vec::shrink_to_fit);
+    }
+}
+
+/// The buffer is shrunk by the maintenance when its capacity exceeds the length by the ratio
+///
+const SHRINK_RATIO: usize = 2;
+
+fn over_allocated<T>(buffer: &Vec<T>) -> bool {
+    buffer.capacity() > SHRINK_RATIO * buffer.len().max(1)
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Starts the background worker that maintains the store by the schedule, the running worker is stopped
+    ///
+    /// The worker uses the time-to-live, the capacity, the fusions and the history caps set when it is started,
+    /// restart the worker to apply the changed policies. Every run adds the tracks parked in the overflow queue
+    /// while the store has the space, look at [CapacityPolicy](crate::store::CapacityPolicy). The worker is stopped when the store is dropped.
+    ///
+    pub fn start_maintenance(&mut self, schedule: MaintenanceSchedule) {
+        self.stop_maintenance();
+        let maintenance = Maintenance {
+            schedule,
+            stores: self.stores.clone(),
+            epoch: self.epoch.clone(),
+            events: self.events.clone(),
+            evicted: self.evicted.clone(),
+            external_ids: self.external_ids.clone(),
+            counters: self.counters.clone(),
+            overflow: self.overflow.clone(),
+            capacity: self.capacity,
+            ttl: self.ttl,
+            history_caps: self.history_caps.clone(),
+            fusions: self.fusions.clone(),
+            default_fusion: self.default_fusion,
+        };
+        let (stop, stop_receiver) = crossbeam::channel::bounded(1);
+        let handle = thread::spawn(move || loop {
+            match stop_receiver.recv_timeout(maintenance.schedule.interval) {
+                Err(RecvTimeoutError::Timeout) => maintenance.run(),
+                _ => return,
+            }
+        });
+        self.maintenance = Some(MaintenanceWorker { stop, handle });
+    }
+
+    /// Stops the background maintenance worker, waits for the running maintenance to complete
+    ///
+    /// # Returns
+    /// `true` when the worker was running
+    ///
+    pub fn stop_maintenance(&mut self) -> bool {
+        match self.maintenance.take() {
+            Some(worker) => {
+                let _ = worker.stop.send(());
+                worker.handle.join().unwrap();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `true` when the background maintenance worker is running
+    ///
+    pub fn maintenance_running(&self) -> bool {
+        self.maintenance.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::maintenance::MaintenanceSchedule;
+    use crate::store::{CapacityPolicy, TrackStore, TrackTtl};
+    use crate::track::notify::NoopNotifier;
+    use crate::track::{HistoryCap, Track, TrackId};
+    use std::collections::HashMap;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn background_maintenance() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        store.set_ttl(Some(TrackTtl::Epochs(1)));
+        store.set_lazy_eviction(false);
+        store.set_history_cap(0, HistoryCap::KeepLast(1));
+        for track_id in 0..2 {
+            store
+                .add(track_id, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+                .unwrap();
+        }
+        // the merged observations are not capped by the merge
+        let src = store.fetch_tracks(&[1]).pop().unwrap();
+        store.merge_external(0, &src, None, false).unwrap();
+        store.add(2, 0, Some(1.0), None, None).unwrap();
+        store.advance_epoch();
+        store.advance_epoch();
+        store.add(0, 1, Some(1.0), None, None).unwrap();
+
+        store.start_maintenance(MaintenanceSchedule::new(Duration::from_millis(10)));
+        assert!(store.maintenance_running());
+        thread::sleep(Duration::from_millis(200));
+        assert!(store.stop_maintenance());
+        assert!(!store.stop_maintenance());
+
+        let evicted = store.purge();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].get_track_id(), 2);
+        let track = store.fetch_tracks(&[0]).pop().unwrap();
+        assert_eq!(track.get_observations(0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn overflow_drain() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        store.set_ttl(Some(TrackTtl::Epochs(1)));
+        store.set_lazy_eviction(false);
+        store.set_capacity(Some(1));
+        store.set_capacity_policy(CapacityPolicy::Overflow(1));
+        store
+            .add(0, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+            .unwrap();
+        assert!(store
+            .add_bounded(1, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)
+            .unwrap()
+            .is_parked());
+        store.advance_epoch();
+        store.advance_epoch();
+
+        // the idle store gets the parked track when the expired one is evicted
+        store.start_maintenance(MaintenanceSchedule::new(Duration::from_millis(10)));
+        thread::sleep(Duration::from_millis(200));
+        store.stop_maintenance();
+        assert_eq!(store.overflow_len(), 0);
+        assert_eq!(store.purge()[0].get_track_id(), 0);
+        assert!(store.get_store(1).contains_key(&1));
+    }
+
+    #[test]
+    fn compaction_skips_unchanged_shards() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        for track_id in 0..2 {
+            store
+                .add(track_id, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+                .unwrap();
+        }
+        let reserve = |shard: &mut HashMap<TrackId, Track<_, _, _, _>>, track_id| {
+            let observations = shard
+                .get_mut(&track_id)
+                .unwrap()
+                .observations
+                .get_mut(&0)
+                .unwrap();
+            observations.reserve(64);
+        };
+        reserve(&mut store.get_store(0), 0);
+        // the change that bypasses the guard leaves the shard unchanged for the maintenance
+        reserve(
+            Arc::make_mut(&mut store.stores[1].tracks.write().unwrap()),
+            1,
+        );
+        store.stores[1].changed.store(false, Ordering::Relaxed);
+
+        store.start_maintenance(MaintenanceSchedule::new(Duration::from_millis(10)));
+        thread::sleep(Duration::from_millis(100));
+        store.stop_maintenance();
+        assert!(store
+            .stores
+            .iter()
+            .all(|shard| !shard.changed.load(Ordering::Relaxed)));
+        let capacity = |track_id: usize| {
+            store.stores[track_id].read().unwrap()[&(track_id as TrackId)].observations[&0]
+                .capacity()
+        };
+        assert!(capacity(0) < 64);
+        assert!(capacity(1) >= 64);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::{
+    Feature, ObservationAttributes, ObservationMetric, Track, TrackAttributes, TrackId,
+    FEATURE_LANES_SIZE,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use ultraviolet::f32x8;
+
+/// Recycled feature buffers of the bucket of the shard
+///
+#[derive(Debug, Default)]
+pub(crate) struct FeaturePool {
+    buffers: Mutex<Vec<Feature>>,
+    limit: AtomicUsize,
+}
+
+impl FeaturePool {
+    fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::Relaxed);
+        self.buffers.lock().unwrap().truncate(limit);
+    }
+
+    fn len(&self) -> usize {
+        self.buffers.lock().unwrap().len()
+    }
+
+    /// Takes the empty buffer for the feature of the blocks, the buffer is allocated when the pool doesn't have
+    /// the large enough one
+    ///
+    fn take(&self, blocks: usize) -> Feature {
+        let mut buffers = self.buffers.lock().unwrap();
+        match buffers.iter().rposition(|b| b.capacity() >= blocks) {
+            Some(position) => {
+                let mut buffer = buffers.swap_remove(position);
+                buffer.clear();
+                buffer
+            }
+            None => Feature::with_capacity(blocks),
+        }
+    }
+
+    /// Keeps the buffers of the features while the pool is not full, the rest are dropped
+    ///
+    pub(crate) fn release(&self, features: impl IntoIterator<Item = Feature>) {
+        let limit = self.limit.load(Ordering::Relaxed);
+        if limit == 0 {
+            return;
+        }
+        let mut buffers = self.buffers.lock().unwrap();
+        let free = limit.saturating_sub(buffers.len());
+        buffers.extend(features.into_iter().take(free));
+    }
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Sets the number of the feature buffers recycled by every bucket of the shards, `0` disables the recycling
+    ///
+    /// The features of the observations dropped by the history caps, the fusions and the projections are kept in
+    /// the pool of the bucket of their track, as well as the features of the tracks returned with
+    /// [recycle](TrackStore::recycle), e.g. the evicted ones. [feature](TrackStore::feature) builds the features of
+    /// the new observations in the kept buffers, so the ingestion under the churn of the tracks doesn't allocate
+    /// the feature vectors. The recycling is disabled by default.
+    ///
+    pub fn set_feature_pool(&mut self, limit: usize) {
+        self.stores
+            .iter()
+            .for_each(|shard| shard.pool.set_limit(limit));
+    }
+
+    /// The number of the feature buffers recycled by every bucket of the shards
+    ///
+    pub fn feature_pool(&self) -> usize {
+        self.stores[0].pool.limit.load(Ordering::Relaxed)
+    }
+
+    /// The number of the feature buffers kept by the pools of the buckets
+    ///
+    pub fn pooled_features(&self) -> usize {
+        self.stores.iter().map(|shard| shard.pool.len()).sum()
+    }
+
+    /// Builds the feature of the observation of the track in the buffer recycled by the bucket of the track, look
+    /// at [set_feature_pool](TrackStore::set_feature_pool)
+    ///
+    pub fn feature(&self, track_id: TrackId, values: &[f32]) -> Feature {
+        let blocks = (values.len() + FEATURE_LANES_SIZE - 1) / FEATURE_LANES_SIZE;
+        let mut feature = self.stores[self.get_bucket(track_id)].pool.take(blocks);
+        for chunk in values.chunks(FEATURE_LANES_SIZE) {
+            let mut block = [0.0; FEATURE_LANES_SIZE];
+            block[..chunk.len()].copy_from_slice(chunk);
+            feature.push(f32x8::new(block));
+        }
+        feature
+    }
+
+    /// Keeps the feature buffers of the tracks which are not used anymore, e.g. the evicted ones, in the pools of
+    /// their buckets
+    ///
+    pub fn recycle(&self, tracks: impl IntoIterator<Item = Track<TA, M, OA, N>>) {
+        for track in tracks {
+            self.stores[self.get_bucket(track.track_id)]
+                .pool
+                .release(track.observations.into_values().flatten().flat_map(|o| o.1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::prelude::{NoopNotifier, TrackStoreBuilder};
+    use crate::store::TrackTtl;
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, HistoryCap};
+    use anyhow::Result;
+
+    #[test]
+    fn feature_pool() -> Result<()> {
+        let mut store = TrackStoreBuilder::new(2)
+            .default_attributes(UnboundAttrs)
+            .metric(UnboundMetric)
+            .notifier(NoopNotifier)
+            .history_cap(0, HistoryCap::KeepLast(1))
+            .build();
+        store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        store.add(1, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        // the pool is disabled
+        assert_eq!(store.pooled_features(), 0);
+
+        store.set_feature_pool(2);
+        assert_eq!(store.feature_pool(), 2);
+        for i in 0..3 {
+            store.add(1, 0, Some(1.0), Some(vec2(i as f32, 0.0)), None)?;
+        }
+        // the pool is full
+        assert_eq!(store.pooled_features(), 2);
+
+        let feature = store.feature(1, &[1.0, 2.0, 3.0]);
+        assert_eq!(store.pooled_features(), 1);
+        assert_eq!(feature, Feature::from_vec(vec![1.0, 2.0, 3.0]));
+        // the other bucket doesn't have the buffers
+        let _ = store.feature(2, &[1.0]);
+        assert_eq!(store.pooled_features(), 1);
+
+        store.set_ttl(Some(TrackTtl::Epochs(0)));
+        store.advance_epoch();
+        let evicted = store.purge();
+        assert_eq!(evicted.len(), 1);
+        store.set_feature_pool(4);
+        store.recycle(evicted);
+        assert_eq!(store.pooled_features(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use crate::metrics::rerank::PairScorer;
+use crate::store::{ObservationMetricErr, TrackStore};
+use crate::track::notify::ChangeNotifier;
+use crate::track::{
+    ObservationAttributes, ObservationMetric, ObservationMetricOk, Track, TrackAttributes, TrackId,
+};
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Finds the `k` tracks in DB closest to the external track with the store metric and re-ranks them with
+    /// the learned similarity
+    ///
+    /// The store metric is the cheap first pass, the scorer runs only for the observations of the `k` closest
+    /// tracks found like in [top_k_distances](TrackStore::top_k_distances). The feature distance of the track is
+    /// `1 - learned_similarity` of its closest scored observation pair, it is `None` when the scorer can't score
+    /// any pair of the track. The attribute metrics come from the first pass.
+    ///
+    /// # Arguments
+    /// * `track` - the external track that is used as distance subject
+    /// * `feature_class` - what feature to use for distance calculation
+    /// * `k` - the number of the re-ranked tracks
+    /// * `only_baked` - calculate distances only across the tracks that have `TrackBakingStatus::Ready` status
+    /// * `scorer` - the model that calculates the learned similarity
+    ///
+    /// # Returns
+    /// The re-ranked distances from the closest one, the tracks without the learned distance go last, and the
+    /// errors of the first pass
+    ///
+    pub fn rerank_top_k<S>(
+        &mut self,
+        track: Track<TA, M, OA, N>,
+        feature_class: u64,
+        k: usize,
+        only_baked: bool,
+        scorer: &S,
+    ) -> (Vec<ObservationMetricOk<OA>>, Vec<ObservationMetricErr<OA>>)
+    where
+        S: PairScorer,
+    {
+        let (track, first_pass, errors) = match self.compatible_tracks(vec![track]).pop() {
+            Some(track) => {
+                let track = Arc::new(track);
+                let (dists, errors) =
+                    self.compatible_top_k_distances(track.clone(), feature_class, k, only_baked);
+                (track, dists, errors)
+            }
+            None => return (Vec::default(), Vec::default()),
+        };
+
+        let mut reranked = first_pass
+            .into_iter()
+            .map(|d| ObservationMetricOk {
+                feature_distance: self.learned_distance(&track, d.to, feature_class, scorer),
+                ..d
+            })
+            .collect::<Vec<_>>();
+        reranked.sort_by(|l, r| match (l.feature_distance, r.feature_distance) {
+            (Some(l), Some(r)) => l.total_cmp(&r),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        (reranked, errors)
+    }
+
+    fn learned_distance<S>(
+        &self,
+        track: &Track<TA, M, OA, N>,
+        other: TrackId,
+        feature_class: u64,
+        scorer: &S,
+    ) -> Option<f32>
+    where
+        S: PairScorer,
+    {
+        let store = self.get_store(other);
+        let other = store.get(&other)?;
+        let (left, right) = (
+            track.get_observations(feature_class)?,
+            other.get_observations(feature_class)?,
+        );
+        left.iter()
+            .flat_map(|l| right.iter().map(move |r| (l, r)))
+            .filter_map(
+                |(l, r)| match (l.feature().as_ref(), r.feature().as_ref()) {
+                    (Some(l), Some(r)) => scorer.score(l, r).map(|s| 1.0 - s),
+                    _ => None,
+                },
+            )
+            .min_by(|l, r| l.total_cmp(r))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs};
+    use crate::metrics::minkowski::MinkowskiMetric;
+    use crate::metrics::rerank::PairScorer;
+    use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+    use crate::track::Feature;
+    use crate::EPS;
+
+    /// The similarity is ten times the second lane of the features, the pairs with the negative lane are not scored
+    #[derive(Clone)]
+    struct SecondLaneScorer;
+
+    impl PairScorer for SecondLaneScorer {
+        fn score(&self, _left: &Feature, right: &Feature) -> Option<f32> {
+            let s = right[0].as_array_ref()[1] * 10.0;
+            (s >= 0.0).then_some(s)
+        }
+    }
+
+    #[test]
+    fn rerank_top_k() {
+        let mut store = TrackStoreBuilder::new(2)
+            .default_attributes(UnboundAttrs)
+            .metric(MinkowskiMetric::chebyshev())
+            .notifier(NoopNotifier)
+            .build();
+        // the first pass ranks the tracks by the first lane, the scorer by the second one
+        for (id, first, learned) in [
+            (1, 0.1, 0.02),
+            (2, 0.2, 0.09),
+            (3, 0.3, -0.1),
+            (4, 0.9, 0.1),
+        ] {
+            store
+                .add(id, 0, None, Some(vec2(first, learned)), None)
+                .unwrap();
+        }
+
+        let query = store
+            .new_track(10)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation(vec2(0.0, 0.0))
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        let (dists, errs) = store.rerank_top_k(query, 0, 3, false, &SecondLaneScorer);
+        assert!(errs.is_empty());
+        // the track 4 is the best for the scorer, but it is not in the top 3 of the first pass
+        assert_eq!(
+            dists.iter().map(|d| d.to).collect::<Vec<_>>(),
+            vec![2, 1, 3]
+        );
+        assert!((dists[0].feature_distance.unwrap() - 0.1).abs() < EPS);
+        assert!((dists[1].feature_distance.unwrap() - 0.8).abs() < EPS);
+        // the scorer can't score the track 3, it has no learned distance instead of the first-pass one
+        assert_eq!(dists[2].feature_distance, None);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::serialization::TrackState;
+use crate::track::{ObservationAttributes, ObservationMetric, TrackAttributes};
+use crate::Errors;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The store is serialized as the sequence of its tracks ordered by the track id
+///
+impl<TA, M, OA, N> Serialize for TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA> + Serialize,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes + Serialize,
+    N: ChangeNotifier,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let shards = self
+            .stores
+            .iter()
+            .map(|s| s.read().unwrap())
+            .collect::<Vec<_>>();
+        let mut tracks = shards.iter().flat_map(|s| s.values()).collect::<Vec<_>>();
+        tracks.sort_by_key(|t| t.track_id);
+        serializer.collect_seq(tracks)
+    }
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA> + DeserializeOwned,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes + DeserializeOwned,
+    N: ChangeNotifier,
+{
+    /// Restores the tracks of the serialized store, the tracks get the metric and the notifier of the store
+    ///
+    /// # Returns
+    /// * `Ok(count)` - the number of the restored tracks
+    /// * `Err(Errors::DuplicateTrackId(track_id))` - the store already has the track, the tracks before it are restored
+    ///
+    pub fn restore<'de, D>(&mut self, deserializer: D) -> Result<usize>
+    where
+        D: Deserializer<'de>,
+    {
+        let states = Vec::<TrackState<TA, OA>>::deserialize(deserializer)
+            .map_err(|e| Errors::RestoreFailed(e.to_string()))?;
+        self.restore_states(states)
+    }
+
+    /// Restores the tracks from the states, the tracks keep the epochs of their last updates when the states
+    /// have them, otherwise they are stamped with the current epoch of the store
+    ///
+    pub(crate) fn restore_states(&mut self, states: Vec<TrackState<TA, OA>>) -> Result<usize> {
+        let count = states.len();
+        for state in states {
+            let last_update_epoch = state.last_update_epoch;
+            let track = state.into_track(self.metric.clone(), self.notifier.clone());
+            let track_id = self.add_track(track)?;
+            if let Some(epoch) = last_update_epoch {
+                if let Some(track) = self.get_store(track_id).get_mut(&track_id) {
+                    track.last_update.epoch = epoch;
+                }
+            }
+        }
+        Ok(count)
+    }
+}
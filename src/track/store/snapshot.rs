@@ -0,0 +1,320 @@
+mod codec;
+
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::{ObservationAttributes, ObservationMetric, TrackAttributes};
+use crate::Errors;
+use anyhow::Result;
+use codec::{Decoder, Encoder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+/// The bytes the snapshot file starts with
+///
+pub const SNAPSHOT_MAGIC: [u8; 8] = *b"SIMSNAP\0";
+
+/// The version of the snapshot format written by the store
+///
+/// The version is incremented when the layout of the payload changes, the snapshots of the newer versions
+/// are rejected with `Errors::UnsupportedSnapshotVersion` instead of being misread. The version 2 widens the
+/// track ids to `u128`, the ids of the version 1 snapshots are read as `u64`.
+///
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+const NARROW_IDS_VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = SNAPSHOT_MAGIC.len() + 4 + 8;
+
+/// The information about the loaded snapshot
+///
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotInfo {
+    /// The version of the snapshot format
+    pub version: u32,
+    /// The number of the restored tracks
+    pub tracks: usize,
+    /// The epochs saved with the snapshot
+    pub epochs: HashMap<u64, usize>,
+    /// The epoch of the store saved with the snapshot
+    pub store_epoch: usize,
+}
+
+fn invalid<E: ToString>(e: E) -> Errors {
+    Errors::InvalidSnapshot(e.to_string())
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA> + Serialize + DeserializeOwned,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes + Serialize + DeserializeOwned,
+    N: ChangeNotifier,
+{
+    /// Saves the tracks of the store to the snapshot file
+    ///
+    /// The snapshot keeps the ids, the attributes, the observations with the feature vectors, the merge history
+    /// and the epochs of the last updates of the tracks together with the epoch of the store, so the
+    /// [TrackTtl::Epochs](crate::store::TrackTtl::Epochs) expiration continues after the load. Use
+    /// [save_with_epochs](TrackStore::save_with_epochs) to save the epochs of the tracker scenes with the tracks.
+    ///
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.save_with_epochs(path, &HashMap::default())
+    }
+
+    /// Saves the tracks of the store and the epochs to the snapshot file
+    ///
+    /// # Arguments
+    /// * `path` - the snapshot file, replaced if exists
+    /// * `epochs` - the epochs of the scenes, returned by [load](TrackStore::load)
+    ///
+    /// The file is `SNAPSHOT_MAGIC`, `SNAPSHOT_VERSION` as `u32`, the payload length as `u64` and the payload,
+    /// the numbers are little-endian. The payload is the epochs, the epoch of the store and the tracks.
+    ///
+    /// The snapshot is written to the temporary file next to the target one and renamed over it when it is
+    /// complete, so the crash during the save leaves the previous snapshot intact.
+    ///
+    pub fn save_with_epochs<P: AsRef<Path>>(
+        &self,
+        path: P,
+        epochs: &HashMap<u64, usize>,
+    ) -> Result<()> {
+        let mut payload = Vec::new();
+        let mut encoder = Encoder::new(&mut payload);
+        epochs.serialize(&mut encoder).map_err(invalid)?;
+        self.epoch().serialize(&mut encoder).map_err(invalid)?;
+        self.serialize(&mut encoder).map_err(invalid)?;
+
+        let mut data = Vec::with_capacity(HEADER_SIZE + payload.len());
+        data.extend_from_slice(&SNAPSHOT_MAGIC);
+        data.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        data.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        data.extend_from_slice(&payload);
+
+        let path = path.as_ref();
+        let mut temp_name = path.file_name().map(OsString::from).unwrap_or_default();
+        temp_name.push(".tmp");
+        let temp = path.with_file_name(temp_name);
+        let written = fs::File::create(&temp).and_then(|mut file| {
+            file.write_all(&data)?;
+            file.sync_all()
+        });
+        if let Err(e) = written.and_then(|_| fs::rename(&temp, path)) {
+            let _ = fs::remove_file(&temp);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Loads the tracks from the snapshot file into the store
+    ///
+    /// The tracks get the metric and the notifier of the store and are added as with
+    /// [add_track](TrackStore::add_track), so the projections and the dimension checks of the store are not applied
+    /// to them again. The tracks keep the epochs of their last updates and the epoch of the store is advanced to the
+    /// saved one if it is behind.
+    ///
+    /// # Returns
+    /// * `Ok(info)` - the number of the restored tracks and the saved epochs
+    /// * `Err(Errors::InvalidSnapshot(reason))` - the file is not the snapshot or is damaged, nothing is restored
+    /// * `Err(Errors::UnsupportedSnapshotVersion(found, supported))` - the snapshot is written by the newer format
+    /// * `Err(Errors::DuplicateTrackId(track_id))` - the store already has the track, the tracks before it are restored
+    ///
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<SnapshotInfo> {
+        let data = fs::read(path)?;
+        if data.len() < HEADER_SIZE || data[..SNAPSHOT_MAGIC.len()] != SNAPSHOT_MAGIC {
+            return Err(invalid("the file is not the track store snapshot").into());
+        }
+
+        let (version, length) = data[SNAPSHOT_MAGIC.len()..HEADER_SIZE].split_at(4);
+        let version = u32::from_le_bytes(version.try_into()?);
+        if version > SNAPSHOT_VERSION {
+            return Err(Errors::UnsupportedSnapshotVersion(version, SNAPSHOT_VERSION).into());
+        }
+
+        let length = u64::from_le_bytes(length.try_into()?) as usize;
+        let payload = &data[HEADER_SIZE..];
+        if payload.len() != length {
+            return Err(invalid(format!(
+                "the payload length is {}, expected {}",
+                payload.len(),
+                length
+            ))
+            .into());
+        }
+
+        let mut reader = payload;
+        let mut decoder = if version == NARROW_IDS_VERSION {
+            Decoder::narrowing_u128(&mut reader)
+        } else {
+            Decoder::new(&mut reader)
+        };
+        let epochs = HashMap::<u64, usize>::deserialize(&mut decoder).map_err(invalid)?;
+        let store_epoch = usize::deserialize(&mut decoder).map_err(invalid)?;
+        self.epoch.fetch_max(store_epoch, Ordering::SeqCst);
+        let tracks = self.restore(&mut decoder)?;
+        if !reader.is_empty() {
+            return Err(invalid("unexpected data after the tracks").into());
+        }
+
+        Ok(SnapshotInfo {
+            version,
+            tracks,
+            epochs,
+            store_epoch,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{SimpleAttrs, SimpleMetric};
+    use crate::store::snapshot::{SNAPSHOT_MAGIC, SNAPSHOT_VERSION};
+    use crate::store::TrackStore;
+    use crate::track::notify::NoopNotifier;
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, Observation};
+    use crate::Errors;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn new_store() -> TrackStore<SimpleAttrs, SimpleMetric, f32> {
+        TrackStore::new(SimpleMetric, SimpleAttrs::default(), NoopNotifier, 2)
+    }
+
+    #[test]
+    fn save_load() {
+        let dir = std::env::temp_dir().join(format!("similari-snapshot-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("store.snapshot");
+
+        let mut store = new_store();
+        store
+            .add(
+                1,
+                0,
+                Some(0.5),
+                Some(Feature::from_vec(vec![1.0, 2.0, 3.0])),
+                None,
+            )
+            .unwrap();
+        store
+            .add(
+                2,
+                1,
+                Some(0.7),
+                Some(Feature::from_vec(vec![0.0; 10])),
+                None,
+            )
+            .unwrap();
+        let epochs = HashMap::from([(0, 5), (3, 7)]);
+        store.set_track_metadata(1, "camera", "north").unwrap();
+        store.save_with_epochs(&path, &epochs).unwrap();
+        assert!(!dir.join("store.snapshot.tmp").exists());
+
+        // the failed save keeps the previous snapshot
+        fs::create_dir(dir.join("store.snapshot.tmp")).unwrap();
+        assert!(new_store().save(&path).is_err());
+        fs::remove_dir(dir.join("store.snapshot.tmp")).unwrap();
+
+        let mut restored = new_store();
+        let info = restored.load(&path).unwrap();
+        assert_eq!((info.version, info.tracks), (SNAPSHOT_VERSION, 2));
+        assert_eq!(info.epochs, epochs);
+        // the tracks are already in the store
+        assert!(restored.load(&path).is_err());
+
+        let track = restored.fetch_tracks(&[1]).pop().unwrap();
+        let obs = &track.get_observations(0).unwrap()[0];
+        assert_eq!(obs.attr(), &Some(0.5));
+        assert_eq!(track.get_metadata("camera"), Some(&"north".into()));
+        assert_eq!(
+            Vec::<f32>::from_vec(obs.feature().as_ref().unwrap())[..3],
+            [1.0, 2.0, 3.0]
+        );
+
+        // the snapshot of the newer version is rejected
+        let mut data = fs::read(&path).unwrap();
+        data[SNAPSHOT_MAGIC.len()..SNAPSHOT_MAGIC.len() + 4]
+            .copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        fs::write(&path, &data).unwrap();
+        let mut empty = new_store();
+        assert!(matches!(
+            empty.load(&path).unwrap_err().downcast::<Errors>().unwrap(),
+            Errors::UnsupportedSnapshotVersion(v, SNAPSHOT_VERSION) if v == SNAPSHOT_VERSION + 1
+        ));
+
+        // the truncated snapshot is rejected
+        let mut data = fs::read(&path).unwrap();
+        data[SNAPSHOT_MAGIC.len()..SNAPSHOT_MAGIC.len() + 4]
+            .copy_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        fs::write(&path, &data[..data.len() - 1]).unwrap();
+        assert!(matches!(
+            empty.load(&path).unwrap_err().downcast::<Errors>().unwrap(),
+            Errors::InvalidSnapshot(_)
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_load_stamps() {
+        let dir = std::env::temp_dir().join(format!("similari-stamps-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("store.snapshot");
+
+        let mut store = new_store();
+        for epoch in 1..=3 {
+            assert_eq!(store.advance_epoch(), epoch);
+            store.add(1, 0, Some(epoch as f32), None, None).unwrap();
+        }
+        store.save(&path).unwrap();
+
+        let mut restored = new_store();
+        restored.load(&path).unwrap();
+        let track = restored.fetch_tracks(&[1]).pop().unwrap();
+        let attrs = |observations: Vec<&Observation<f32>>| {
+            observations
+                .into_iter()
+                .map(|o| o.attr().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(attrs(track.observations_in_range(0, 2..)), vec![2.0, 3.0]);
+        assert_eq!(attrs(track.observations_in_range(0, ..2)), vec![1.0]);
+        assert!(!track.active_in_range(&(4..)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_load_track_epochs() {
+        let dir = std::env::temp_dir().join(format!("similari-epochs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("store.snapshot");
+
+        let mut store = new_store();
+        store.add(1, 0, Some(0.1), None, None).unwrap();
+        for _ in 0..5 {
+            store.advance_epoch();
+        }
+        store.add(2, 0, Some(0.2), None, None).unwrap();
+        store.advance_epoch();
+        store.save(&path).unwrap();
+
+        let mut restored = new_store();
+        let info = restored.load(&path).unwrap();
+        assert_eq!(info.store_epoch, 6);
+        assert_eq!(restored.epoch(), 6);
+        // only the track not updated for more than 2 epochs is stale after the load
+        assert_eq!(restored.stale_tracks(2), vec![1]);
+        let epochs = restored
+            .fetch_tracks(&[1, 2])
+            .into_iter()
+            .map(|t| (t.get_track_id(), t.get_last_update().epoch))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(epochs, HashMap::from([(1, 0), (2, 5)]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,595 @@
+use serde::de::{
+    DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::{ser, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+
+/// Error of the snapshot encoding
+///
+#[derive(Debug)]
+pub struct CodecError(String);
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl ser::Error for CodecError {
+    fn custom<T: Display>(msg: T) -> Self {
+        CodecError(msg.to_string())
+    }
+}
+
+impl serde::de::Error for CodecError {
+    fn custom<T: Display>(msg: T) -> Self {
+        CodecError(msg.to_string())
+    }
+}
+
+impl From<std::io::Error> for CodecError {
+    fn from(e: std::io::Error) -> Self {
+        CodecError(e.to_string())
+    }
+}
+
+type CodecResult<T> = Result<T, CodecError>;
+
+/// Compact binary encoding of the serde data model
+///
+/// The encoding is not self-describing: the numbers are little-endian with the fixed width, the lengths of
+/// the strings, sequences and maps are `u64`, the options are prefixed with the `u8` tag, the enum variants
+/// are encoded with their `u32` index, the structs and tuples are encoded as the sequences of their fields.
+///
+pub struct Encoder<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    fn len(&mut self, len: Option<usize>) -> CodecResult<()> {
+        let len = len.ok_or_else(|| CodecError("The length must be known".into()))?;
+        self.writer.write_all(&(len as u64).to_le_bytes())?;
+        Ok(())
+    }
+}
+
+macro_rules! encode_number {
+    ($method:ident, $t:ty) => {
+        fn $method(self, v: $t) -> CodecResult<()> {
+            self.writer.write_all(&v.to_le_bytes())?;
+            Ok(())
+        }
+    };
+}
+
+impl<W: Write> ser::Serializer for &mut Encoder<W> {
+    type Ok = ();
+    type Error = CodecError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> CodecResult<()> {
+        self.serialize_u8(u8::from(v))
+    }
+
+    encode_number!(serialize_i8, i8);
+    encode_number!(serialize_i16, i16);
+    encode_number!(serialize_i32, i32);
+    encode_number!(serialize_i64, i64);
+    encode_number!(serialize_i128, i128);
+    encode_number!(serialize_u8, u8);
+    encode_number!(serialize_u16, u16);
+    encode_number!(serialize_u32, u32);
+    encode_number!(serialize_u64, u64);
+    encode_number!(serialize_u128, u128);
+    encode_number!(serialize_f32, f32);
+    encode_number!(serialize_f64, f64);
+
+    fn serialize_char(self, v: char) -> CodecResult<()> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> CodecResult<()> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> CodecResult<()> {
+        self.len(Some(v.len()))?;
+        self.writer.write_all(v)?;
+        Ok(())
+    }
+
+    fn serialize_none(self) -> CodecResult<()> {
+        self.serialize_u8(0)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> CodecResult<()> {
+        self.serialize_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> CodecResult<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> CodecResult<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> CodecResult<()> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> CodecResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> CodecResult<()> {
+        self.serialize_u32(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> CodecResult<Self> {
+        self.len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> CodecResult<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> CodecResult<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> CodecResult<Self> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> CodecResult<Self> {
+        self.len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> CodecResult<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> CodecResult<Self> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! encode_compound {
+    ($trait:ident, $method:ident) => {
+        impl<W: Write> ser::$trait for &mut Encoder<W> {
+            type Ok = ();
+            type Error = CodecError;
+
+            fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> CodecResult<()> {
+                value.serialize(&mut **self)
+            }
+
+            fn end(self) -> CodecResult<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+encode_compound!(SerializeSeq, serialize_element);
+encode_compound!(SerializeTuple, serialize_element);
+encode_compound!(SerializeTupleStruct, serialize_field);
+encode_compound!(SerializeTupleVariant, serialize_field);
+
+impl<W: Write> ser::SerializeMap for &mut Encoder<W> {
+    type Ok = ();
+    type Error = CodecError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> CodecResult<()> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> CodecResult<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> CodecResult<()> {
+        Ok(())
+    }
+}
+
+macro_rules! encode_struct {
+    ($trait:ident) => {
+        impl<W: Write> ser::$trait for &mut Encoder<W> {
+            type Ok = ();
+            type Error = CodecError;
+
+            fn serialize_field<T: Serialize + ?Sized>(
+                &mut self,
+                _key: &'static str,
+                value: &T,
+            ) -> CodecResult<()> {
+                value.serialize(&mut **self)
+            }
+
+            fn end(self) -> CodecResult<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+encode_struct!(SerializeStruct);
+encode_struct!(SerializeStructVariant);
+
+/// Deserializer of the encoding
+///
+pub struct Decoder<R: Read> {
+    reader: R,
+    narrow_u128: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            narrow_u128: false,
+        }
+    }
+
+    /// The decoder of the data written when the track ids were `u64`, the `u128` values are read as `u64`
+    ///
+    pub fn narrowing_u128(reader: R) -> Self {
+        Self {
+            reader,
+            narrow_u128: true,
+        }
+    }
+
+    fn bytes<const B: usize>(&mut self) -> CodecResult<[u8; B]> {
+        let mut buf = [0u8; B];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn len(&mut self) -> CodecResult<usize> {
+        Ok(u64::from_le_bytes(self.bytes()?) as usize)
+    }
+
+    fn byte_buf(&mut self) -> CodecResult<Vec<u8>> {
+        let len = self.len()?;
+        let mut buf = Vec::new();
+        (&mut self.reader).take(len as u64).read_to_end(&mut buf)?;
+        if buf.len() != len {
+            return Err(CodecError("Unexpected end of data".into()));
+        }
+        Ok(buf)
+    }
+}
+
+macro_rules! decode_number {
+    ($method:ident, $visit:ident, $t:ty) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+            visitor.$visit(<$t>::from_le_bytes(self.bytes()?))
+        }
+    };
+}
+
+impl<'de, R: Read> serde::Deserializer<'de> for &mut Decoder<R> {
+    type Error = CodecError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> CodecResult<V::Value> {
+        Err(CodecError(
+            "The encoding is not self-describing, the type must be known".into(),
+        ))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        match self.bytes::<1>()?[0] {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            v => Err(CodecError(format!("Invalid bool value {}", v))),
+        }
+    }
+
+    decode_number!(deserialize_i8, visit_i8, i8);
+    decode_number!(deserialize_i16, visit_i16, i16);
+    decode_number!(deserialize_i32, visit_i32, i32);
+    decode_number!(deserialize_i64, visit_i64, i64);
+    decode_number!(deserialize_i128, visit_i128, i128);
+    decode_number!(deserialize_u8, visit_u8, u8);
+    decode_number!(deserialize_u16, visit_u16, u16);
+    decode_number!(deserialize_u32, visit_u32, u32);
+    decode_number!(deserialize_u64, visit_u64, u64);
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        if self.narrow_u128 {
+            visitor.visit_u128(u64::from_le_bytes(self.bytes()?).into())
+        } else {
+            visitor.visit_u128(u128::from_le_bytes(self.bytes()?))
+        }
+    }
+
+    decode_number!(deserialize_f32, visit_f32, f32);
+    decode_number!(deserialize_f64, visit_f64, f64);
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        let code = u32::from_le_bytes(self.bytes()?);
+        visitor.visit_char(
+            char::from_u32(code).ok_or_else(|| CodecError(format!("Invalid char {}", code)))?,
+        )
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        let buf = self.byte_buf()?;
+        visitor.visit_string(String::from_utf8(buf).map_err(|e| CodecError(e.to_string()))?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        visitor.visit_byte_buf(self.byte_buf()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        match self.bytes::<1>()?[0] {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            v => Err(CodecError(format!("Invalid option tag {}", v))),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> CodecResult<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> CodecResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        let len = self.len()?;
+        visitor.visit_seq(Elements { decoder: self, len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> CodecResult<V::Value> {
+        visitor.visit_seq(Elements { decoder: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> CodecResult<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> CodecResult<V::Value> {
+        let len = self.len()?;
+        visitor.visit_map(Elements { decoder: self, len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> CodecResult<V::Value> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> CodecResult<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> CodecResult<V::Value> {
+        Err(CodecError("Identifiers are not encoded".into()))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> CodecResult<V::Value> {
+        Err(CodecError("Ignored values are not supported".into()))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+struct Elements<'a, R: Read> {
+    decoder: &'a mut Decoder<R>,
+    len: usize,
+}
+
+impl<'de, 'a, R: Read> SeqAccess<'de> for Elements<'a, R> {
+    type Error = CodecError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> CodecResult<Option<T::Value>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, 'a, R: Read> MapAccess<'de> for Elements<'a, R> {
+    type Error = CodecError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> CodecResult<Option<K::Value>> {
+        self.next_element_seed(seed)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> CodecResult<V::Value> {
+        seed.deserialize(&mut *self.decoder)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, R: Read> EnumAccess<'de> for &mut Decoder<R> {
+    type Error = CodecError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> CodecResult<(V::Value, Self)> {
+        let index = u32::from_le_bytes(self.bytes()?);
+        let value = seed.deserialize(IntoDeserializer::<CodecError>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, R: Read> VariantAccess<'de> for &mut Decoder<R> {
+    type Error = CodecError;
+
+    fn unit_variant(self) -> CodecResult<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> CodecResult<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> CodecResult<V::Value> {
+        serde::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> CodecResult<V::Value> {
+        serde::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::store::snapshot::codec::{Decoder, Encoder};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Kind {
+        Empty,
+        Scaled(f32),
+        Named { name: String, id: u64 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Record {
+        flag: bool,
+        kinds: Vec<Kind>,
+        index: HashMap<u64, Option<(i32, char)>>,
+    }
+
+    #[test]
+    fn roundtrip() {
+        let record = Record {
+            flag: true,
+            kinds: vec![
+                Kind::Empty,
+                Kind::Scaled(0.5),
+                Kind::Named {
+                    name: "track".into(),
+                    id: 7,
+                },
+            ],
+            index: HashMap::from([(1, Some((-3, 'x'))), (2, None)]),
+        };
+
+        let mut buf = Vec::new();
+        record.serialize(&mut Encoder::new(&mut buf)).unwrap();
+        let decoded = Record::deserialize(&mut Decoder::new(buf.as_slice())).unwrap();
+        assert_eq!(decoded, record);
+        assert!(Record::deserialize(&mut Decoder::new(&buf[..buf.len() - 1])).is_err());
+    }
+
+    #[test]
+    fn narrowing_u128() {
+        let mut buf = Vec::new();
+        (7u64, vec![u64::MAX])
+            .serialize(&mut Encoder::new(&mut buf))
+            .unwrap();
+        let decoded =
+            <(u128, Vec<u128>)>::deserialize(&mut Decoder::narrowing_u128(buf.as_slice())).unwrap();
+        assert_eq!(decoded, (7, vec![u64::MAX as u128]));
+    }
+}
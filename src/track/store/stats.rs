@@ -0,0 +1,348 @@
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::{
+    Observation, ObservationAttributes, ObservationMetric, Track, TrackAttributes, TrackId,
+};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use ultraviolet::f32x8;
+
+#[cfg(feature = "prometheus")]
+pub mod collector;
+
+/// The upper bounds of the buckets of the lock wait histogram in seconds
+///
+pub const LOCK_WAIT_BUCKETS: [f64; 7] = [0.000_001, 0.000_01, 0.000_1, 0.001, 0.01, 0.1, 1.0];
+
+/// The numbers of the operations served by the store since it was created
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    /// the observations added
+    pub adds: u64,
+    /// the distance queries, every batch of the query tracks counts as one query
+    pub distance_queries: u64,
+    /// the lookups and the predicate queries
+    pub lookups: u64,
+    /// the merges of the tracks
+    pub merges: u64,
+    /// the tracks evicted by the time-to-live or the capacity policy
+    pub evictions: u64,
+}
+
+impl QueryStats {
+    /// The names, the descriptions and the values of the counters
+    ///
+    pub(crate) fn counters(&self) -> [(&'static str, &'static str, u64); 5] {
+        [
+            ("adds_total", "The observations added.", self.adds),
+            (
+                "distance_queries_total",
+                "The distance queries.",
+                self.distance_queries,
+            ),
+            (
+                "lookups_total",
+                "The lookups and the predicate queries.",
+                self.lookups,
+            ),
+            ("merges_total", "The merges of the tracks.", self.merges),
+            ("evictions_total", "The evicted tracks.", self.evictions),
+        ]
+    }
+}
+
+/// The histogram of the waits for the write locks of the shards
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LockWaitStats {
+    /// the upper bounds of the buckets in seconds with the numbers of the waits not longer than the bounds,
+    /// look at [LOCK_WAIT_BUCKETS](LOCK_WAIT_BUCKETS)
+    pub buckets: Vec<(f64, u64)>,
+    /// the number of the waits
+    pub count: u64,
+    /// the total wait in seconds
+    pub sum: f64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct LockWaits {
+    buckets: [AtomicU64; LOCK_WAIT_BUCKETS.len()],
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl LockWaits {
+    pub(crate) fn record(&self, wait: Duration) {
+        let seconds = wait.as_secs_f64();
+        if let Some(bucket) = LOCK_WAIT_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos
+            .fetch_add(wait.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> LockWaitStats {
+        let mut count = 0;
+        LockWaitStats {
+            buckets: LOCK_WAIT_BUCKETS
+                .iter()
+                .zip(&self.buckets)
+                .map(|(bound, waits)| {
+                    count += waits.load(Ordering::Relaxed);
+                    (*bound, count)
+                })
+                .collect(),
+            count: self.count.load(Ordering::Relaxed),
+            sum: self.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct QueryCounters {
+    pub(crate) adds: AtomicU64,
+    pub(crate) distance_queries: AtomicU64,
+    pub(crate) lookups: AtomicU64,
+    pub(crate) merges: AtomicU64,
+    pub(crate) evictions: AtomicU64,
+    pub(crate) lock_waits: LockWaits,
+}
+
+impl QueryCounters {
+    pub(crate) fn record(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_many(counter: &AtomicU64, count: usize) {
+        counter.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> QueryStats {
+        QueryStats {
+            adds: self.adds.load(Ordering::Relaxed),
+            distance_queries: self.distance_queries.load(Ordering::Relaxed),
+            lookups: self.lookups.load(Ordering::Relaxed),
+            merges: self.merges.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The statistics of the store for the capacity planning
+///
+#[derive(Debug, Clone, Default)]
+pub struct StoreStats {
+    /// the number of the tracks of every shard
+    pub shard_tracks: Vec<usize>,
+    /// the number of the observations of every feature class
+    pub observations: HashMap<u64, usize>,
+    /// the estimated number of the bytes held by the tracks
+    pub estimated_memory: usize,
+    /// the numbers of the served operations
+    pub queries: QueryStats,
+    /// the waits for the write locks of the shards
+    pub lock_waits: LockWaitStats,
+}
+
+impl StoreStats {
+    /// The number of the tracks in the store
+    ///
+    pub fn tracks(&self) -> usize {
+        self.shard_tracks.iter().sum()
+    }
+
+    /// Renders the statistics in the Prometheus text exposition format, e.g. for the `/metrics` endpoint
+    ///
+    /// The names of the metrics start with the prefix. The operations are exposed as the counters, the rates of
+    /// the operations are calculated by Prometheus. The waits for the write locks of the shards are exposed as the
+    /// histogram.
+    ///
+    pub fn prometheus(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, f64)>| {
+            writeln!(out, "# HELP {}_{} {}", prefix, name, help).unwrap();
+            writeln!(out, "# TYPE {}_{} {}", prefix, name, kind).unwrap();
+            for (labels, value) in samples {
+                writeln!(out, "{}_{}{} {}", prefix, name, labels, value).unwrap();
+            }
+        };
+        metric(
+            "tracks",
+            "gauge",
+            "The number of the tracks of the shard.",
+            self.shard_tracks
+                .iter()
+                .enumerate()
+                .map(|(shard, tracks)| (format!("{{shard=\"{}\"}}", shard), *tracks as f64))
+                .collect(),
+        );
+        metric(
+            "observations",
+            "gauge",
+            "The number of the observations of the feature class.",
+            self.observations
+                .iter()
+                .sorted()
+                .map(|(feature_class, observations)| {
+                    (
+                        format!("{{feature_class=\"{}\"}}", feature_class),
+                        *observations as f64,
+                    )
+                })
+                .collect(),
+        );
+        metric(
+            "estimated_memory_bytes",
+            "gauge",
+            "The estimated number of the bytes held by the tracks.",
+            vec![(String::new(), self.estimated_memory as f64)],
+        );
+        for (name, help, value) in self.queries.counters() {
+            metric(name, "counter", help, vec![(String::new(), value as f64)]);
+        }
+        let waits = &self.lock_waits;
+        metric(
+            "shard_lock_wait_seconds",
+            "histogram",
+            "The waits for the write locks of the shards.",
+            waits
+                .buckets
+                .iter()
+                .map(|(bound, count)| (format!("_bucket{{le=\"{}\"}}", bound), *count as f64))
+                .chain([
+                    ("_bucket{le=\"+Inf\"}".to_string(), waits.count as f64),
+                    ("_sum".to_string(), waits.sum),
+                    ("_count".to_string(), waits.count as f64),
+                ])
+                .collect(),
+        );
+        out
+    }
+}
+
+fn estimated_memory<TA, M, OA, N>(track: &Track<TA, M, OA, N>) -> usize
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    let observations = track
+        .observations
+        .values()
+        .map(|observations| {
+            size_of::<(u64, Vec<Observation<OA>>)>()
+                + observations.capacity() * size_of::<Observation<OA>>()
+                + observations
+                    .iter()
+                    .flat_map(|o| o.feature().as_ref())
+                    .map(|f| f.capacity() * size_of::<f32x8>())
+                    .sum::<usize>()
+        })
+        .sum::<usize>();
+    size_of::<(TrackId, Track<TA, M, OA, N>)>()
+        + track.merge_history.capacity() * size_of::<TrackId>()
+        + observations
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Collects the statistics of the store
+    ///
+    /// The shards are scanned one by one. The memory is estimated from the sizes of the tracks, the observations
+    /// and the feature vectors, the memory held by the attributes, the metadata and the hash tables beyond their
+    /// entries is not counted.
+    ///
+    pub fn stats(&self) -> StoreStats {
+        let mut stats = StoreStats {
+            queries: self.counters.stats(),
+            lock_waits: self.counters.lock_waits.stats(),
+            ..Default::default()
+        };
+        stats.shard_tracks = vec![0; self.num_shards];
+        for (bucket, shard) in self.stores.iter().enumerate() {
+            let shard = shard.read().unwrap();
+            stats.shard_tracks[bucket / self.buckets] += shard.len();
+            for track in shard.values() {
+                for (feature_class, observations) in &track.observations {
+                    *stats.observations.entry(*feature_class).or_default() += observations.len();
+                }
+                stats.estimated_memory += estimated_memory(track);
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::stats::LOCK_WAIT_BUCKETS;
+    use crate::store::{TrackStore, TrackTtl};
+    use crate::track::notify::NoopNotifier;
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, NoopLookup};
+
+    #[test]
+    fn stats() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        store
+            .add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+            .unwrap();
+        store
+            .add(1, 1, Some(1.0), Some(vec2(1.0, 0.0)), None)
+            .unwrap();
+        store
+            .add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)
+            .unwrap();
+        store.lookup(NoopLookup::default());
+        store.owned_track_distances(&[1], 0, false);
+        let small = store.stats();
+
+        assert_eq!(small.shard_tracks, vec![1, 1]);
+        assert_eq!(small.tracks(), 2);
+        assert_eq!((small.observations[&0], small.observations[&1]), (2, 1));
+        assert_eq!(small.queries.adds, 3);
+        assert_eq!(small.queries.lookups, 1);
+        assert_eq!(small.queries.distance_queries, 1);
+
+        store
+            .add(
+                3,
+                0,
+                Some(1.0),
+                Some(Feature::from_vec(vec![0.0; 512])),
+                None,
+            )
+            .unwrap();
+        assert!(store.stats().estimated_memory > small.estimated_memory + 512 * 4);
+
+        store.set_ttl(Some(TrackTtl::Epochs(0)));
+        store.advance_epoch();
+        store.purge();
+        let metrics = store.stats().prometheus("similari");
+        assert!(metrics.contains("# TYPE similari_tracks gauge\n"));
+        assert!(metrics.contains("similari_tracks{shard=\"1\"} 0\n"));
+        assert!(metrics.contains("similari_adds_total 4\n"));
+        assert!(metrics.contains("similari_evictions_total 3\n"));
+
+        let waits = store.stats().lock_waits;
+        assert!(waits.count >= 4);
+        assert_eq!(waits.buckets.len(), LOCK_WAIT_BUCKETS.len());
+        assert!(waits.buckets.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert!(waits.buckets.last().unwrap().1 <= waits.count);
+        assert!(metrics.contains("# TYPE similari_shard_lock_wait_seconds histogram\n"));
+        assert!(metrics.contains("similari_shard_lock_wait_seconds_bucket{le=\"0.001\"} "));
+    }
+}
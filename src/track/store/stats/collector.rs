@@ -0,0 +1,197 @@
+use crate::store::stats::QueryCounters;
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::{ObservationAttributes, ObservationMetric, TrackAttributes};
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::{Bucket, Counter, Gauge, Histogram, Metric, MetricFamily, MetricType};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Exposes the counters of the store to a [prometheus](https://docs.rs/prometheus) registry
+///
+/// The collector is detached from the store, it reads the counters shared with the store when the registry is
+/// gathered, so the shards are not scanned. The metrics are the number of the tracks, the counters of
+/// [QueryStats](crate::store::stats::QueryStats) and the histogram of the waits for the write locks of the
+/// shards, their names start with the prefix.
+///
+/// ```
+/// use prometheus::Registry;
+/// use similari::examples::{UnboundAttrs, UnboundMetric};
+/// use similari::store::TrackStore;
+/// use similari::track::notify::NoopNotifier;
+///
+/// let store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+/// let registry = Registry::new();
+/// registry.register(Box::new(store.prometheus_collector("similari"))).unwrap();
+/// assert_eq!(registry.gather().len(), 7);
+/// ```
+///
+pub struct StoreCollector {
+    prefix: String,
+    counters: Arc<QueryCounters>,
+    track_count: Arc<AtomicUsize>,
+    descs: Vec<Desc>,
+}
+
+impl StoreCollector {
+    fn family(&self, name: &str, help: &str, kind: MetricType, metric: Metric) -> MetricFamily {
+        let mut family = MetricFamily::default();
+        family.set_name(format!("{}_{}", self.prefix, name));
+        family.set_help(help.to_string());
+        family.set_field_type(kind);
+        family.mut_metric().push(metric);
+        family
+    }
+}
+
+impl Collector for StoreCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.descs.iter().collect()
+    }
+
+    // the buckets are converted for the protobuf model of prometheus when it is enabled by the other crates
+    #[allow(clippy::useless_conversion)]
+    fn collect(&self) -> Vec<MetricFamily> {
+        let mut tracks = Gauge::default();
+        tracks.set_value(self.track_count.load(Ordering::SeqCst) as f64);
+        let mut metric = Metric::default();
+        metric.set_gauge(tracks);
+        let mut families = vec![self.family(
+            "tracks",
+            "The number of the tracks.",
+            MetricType::GAUGE,
+            metric,
+        )];
+
+        for (name, help, value) in self.counters.stats().counters() {
+            let mut counter = Counter::default();
+            counter.set_value(value as f64);
+            let mut metric = Metric::default();
+            metric.set_counter(counter);
+            families.push(self.family(name, help, MetricType::COUNTER, metric));
+        }
+
+        let waits = self.counters.lock_waits.stats();
+        let mut histogram = Histogram::default();
+        histogram.set_sample_count(waits.count);
+        histogram.set_sample_sum(waits.sum);
+        histogram.set_bucket(
+            waits
+                .buckets
+                .iter()
+                .map(|(bound, count)| {
+                    let mut bucket = Bucket::default();
+                    bucket.set_upper_bound(*bound);
+                    bucket.set_cumulative_count(*count);
+                    bucket
+                })
+                .collect::<Vec<_>>()
+                .into(),
+        );
+        let mut metric = Metric::default();
+        metric.set_histogram(histogram);
+        families.push(self.family(
+            "shard_lock_wait_seconds",
+            "The waits for the write locks of the shards.",
+            MetricType::HISTOGRAM,
+            metric,
+        ));
+        families
+    }
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Creates the collector of the metrics of the store for a prometheus registry, look at
+    /// [StoreCollector](StoreCollector)
+    ///
+    /// # Arguments
+    /// * `prefix` - the prefix of the names of the metrics
+    ///
+    pub fn prometheus_collector(&self, prefix: &str) -> StoreCollector {
+        let counters = self.counters.stats().counters();
+        let descs = [
+            ("tracks", "The number of the tracks."),
+            (
+                "shard_lock_wait_seconds",
+                "The waits for the write locks of the shards.",
+            ),
+        ]
+        .into_iter()
+        .chain(counters.iter().map(|(name, help, _)| (*name, *help)))
+        .map(|(name, help)| {
+            Desc::new(
+                format!("{}_{}", prefix, name),
+                help.to_string(),
+                vec![],
+                HashMap::default(),
+            )
+            .unwrap()
+        })
+        .collect();
+        StoreCollector {
+            prefix: prefix.to_string(),
+            counters: self.counters.clone(),
+            track_count: self.track_count.clone(),
+            descs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::TrackStore;
+    use crate::track::notify::NoopNotifier;
+    use prometheus::proto::MetricType;
+    use prometheus::{Encoder, Registry, TextEncoder};
+
+    #[test]
+    fn collector() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        let registry = Registry::new();
+        registry
+            .register(Box::new(store.prometheus_collector("similari")))
+            .unwrap();
+        store
+            .add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+            .unwrap();
+        store
+            .add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)
+            .unwrap();
+
+        let families = registry.gather();
+        let family = |name: &str| {
+            families
+                .iter()
+                .find(|family| family.get_name() == name)
+                .unwrap()
+        };
+        assert_eq!(
+            family("similari_tracks").get_metric()[0]
+                .get_gauge()
+                .get_value(),
+            2.0
+        );
+        assert_eq!(
+            family("similari_adds_total").get_metric()[0]
+                .get_counter()
+                .get_value(),
+            2.0
+        );
+        let waits = family("similari_shard_lock_wait_seconds");
+        assert_eq!(waits.get_field_type(), MetricType::HISTOGRAM);
+        assert!(waits.get_metric()[0].get_histogram().get_sample_count() >= 2);
+
+        let mut text = Vec::new();
+        TextEncoder::new().encode(&families, &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("similari_shard_lock_wait_seconds_bucket{le=\"+Inf\"}"));
+    }
+}
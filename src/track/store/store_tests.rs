@@ -1,17 +1,28 @@
 #[cfg(test)]
 mod tests {
-    use crate::distance::euclidean;
-    use crate::examples::{current_time_ms, vec2};
-    use crate::prelude::TrackStoreBuilder;
-    use crate::track::store::TrackStore;
-    use crate::track::utils::feature_attributes_sort_dec;
+    use crate::distance::matrix::GEMM_MIN_CANDIDATES;
+    use crate::distance::{cosine, euclidean};
+    use crate::examples::{current_time_ms, vec2, UnboundAttrs, UnboundMetric};
+    use crate::metrics::cosine::CosineMetric;
+    use crate::metrics::minkowski::MinkowskiMetric;
+    use crate::prelude::{ObservationBuilder, TrackStoreBuilder};
+    use crate::track::metadata::MetadataValue;
+    use crate::track::projection::FeatureProjection;
+    use crate::track::store::{CapacityPolicy, TrackStore, TrackTtl};
+    use crate::track::utils::{feature_attributes_sort_dec, FromVec};
     use crate::track::{
-        LookupRequest, MetricOutput, MetricQuery, NoopLookup, NoopNotifier, Observation,
-        ObservationAttributes, ObservationMetric, ObservationsDb, Track, TrackAttributes,
-        TrackAttributesUpdate, TrackStatus,
+        AttributeMergePolicy, Deduplication, Feature, FeatureFusion, HistoryCap, LookupRequest,
+        MetricOutput, MetricQuery, NonFinitePolicy, NoopLookup, NoopNotifier, Observation,
+        ObservationAttributes, ObservationMetric, ObservationsDb, SplitPoint, Track,
+        TrackAttributes, TrackAttributesUpdate, TrackId, TrackStatus,
     };
-    use crate::EPS;
+    use crate::voting::topn::TopNVoting;
+    use crate::voting::Voting;
+    use crate::{Errors, EPS};
     use anyhow::Result;
+    use nalgebra::{DMatrix, DVector};
+    use std::ops::ControlFlow;
+    use std::sync::Arc;
     use std::thread;
     use std::time::Duration;
 
@@ -80,7 +91,7 @@ mod tests {
         fn optimize(
             &mut self,
             _feature_class: u64,
-            _merge_history: &[u64],
+            _merge_history: &[TrackId],
             _attrs: &mut TimeAttrs,
             features: &mut Vec<Observation<f32>>,
             _prev_length: usize,
@@ -619,7 +630,7 @@ mod tests {
                 &self,
                 _attributes: &LookupAttrs,
                 _observations: &ObservationsDb<f32>,
-                _merge_history: &[u64],
+                _merge_history: &[TrackId],
             ) -> bool {
                 true
             }
@@ -672,7 +683,7 @@ mod tests {
             fn optimize(
                 &mut self,
                 _feature_class: u64,
-                _merge_history: &[u64],
+                _merge_history: &[TrackId],
                 _attrs: &mut LookupAttrs,
                 _features: &mut Vec<Observation<f32>>,
                 _prev_length: usize,
@@ -695,4 +706,1272 @@ mod tests {
         let res = store.lookup(Lookup);
         assert_eq!(res.len(), N);
     }
+
+    #[test]
+    fn batch_distances() -> Result<()> {
+        let feature = |i: usize| {
+            Feature::from_vec(
+                (0..12)
+                    .map(|j| ((i * 5 + j * 3) % 7) as f32 - 2.0)
+                    .collect::<Vec<f32>>(),
+            )
+        };
+
+        let mut store = TrackStoreBuilder::new(1)
+            .default_attributes(UnboundAttrs)
+            .metric(CosineMetric::new())
+            .notifier(NoopNotifier)
+            .build();
+        let tracks = GEMM_MIN_CANDIDATES as TrackId + 5;
+        for id in 0..tracks {
+            store.add(id, 0, Some(0.0), Some(feature(id as usize)), None)?;
+        }
+        // the observation without the feature gets no feature distance
+        store.add(0, 0, Some(0.0), None, None)?;
+
+        let query = store
+            .new_track(1000)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation_attributes(0.0)
+                    .observation(feature(100))
+                    .build(),
+            )
+            .build()?;
+
+        let (dists, errs) = store.foreign_track_distances(vec![query], 0, false);
+        assert!(errs.all().is_empty());
+        let dists = dists.all();
+        assert_eq!(dists.len() as TrackId, tracks + 1);
+        assert_eq!(
+            dists
+                .iter()
+                .filter(|d| d.feature_distance.is_none())
+                .count(),
+            1
+        );
+        for d in dists.iter().filter(|d| d.feature_distance.is_some()) {
+            let expected = 1.0 - cosine(&feature(100), &feature(d.to as usize));
+            assert!((d.feature_distance.unwrap() - expected).abs() < 1e-4);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite_policy() -> Result<()> {
+        for (policy, distances, errors) in [
+            // the attribute metric of the skipped distance is kept
+            (NonFinitePolicy::Skip, 2, 0),
+            (NonFinitePolicy::TreatAsMax, 2, 0),
+            // only the distances to the track with the non-finite distance are rejected
+            (NonFinitePolicy::Reject, 1, 1),
+        ] {
+            let mut store = TrackStoreBuilder::new(1)
+                .default_attributes(UnboundAttrs)
+                .metric(CosineMetric::new())
+                .notifier(NoopNotifier)
+                .non_finite_policy(policy)
+                .build();
+            // the zero-norm feature gives NaN cosine distance
+            store.add(
+                1,
+                0,
+                Some(0.0),
+                Some(Feature::from_vec(vec![0.0, 0.0])),
+                None,
+            )?;
+            store.add(
+                2,
+                0,
+                Some(0.0),
+                Some(Feature::from_vec(vec![1.0, 0.0])),
+                None,
+            )?;
+
+            let query = store
+                .new_track(10)
+                .observation(
+                    ObservationBuilder::new(0)
+                        .observation_attributes(0.0)
+                        .observation(Feature::from_vec(vec![1.0, 1.0]))
+                        .build(),
+                )
+                .build()?;
+            let (dists, errs) = store.foreign_track_distances(vec![query], 0, false);
+            let dists = dists.all();
+            assert_eq!(dists.len(), distances);
+            assert!(dists
+                .iter()
+                .all(|d| !matches!(d.feature_distance, Some(d) if d.is_nan())));
+            assert!(dists.iter().all(|d| d.attribute_metric == Some(0.0)));
+            assert_eq!(errs.all().len(), errors);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn feature_projection() -> Result<()> {
+        // keeps the first of nine dimensions
+        let mut first = DMatrix::zeros(1, 9);
+        first[(0, 0)] = 1.0;
+        let projection = FeatureProjection::new(DVector::zeros(9), first)?;
+        let feature =
+            |x: f32, y: f32| Feature::from_vec(vec![x, y, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+
+        let mut store = TrackStoreBuilder::new(1)
+            .default_attributes(UnboundAttrs)
+            .metric(MinkowskiMetric::new(2.0))
+            .notifier(NoopNotifier)
+            .projection(0, projection)
+            .build();
+
+        store.add(1, 0, Some(0.0), Some(feature(1.0, 5.0)), None)?;
+        store.add(2, 0, Some(0.0), Some(feature(3.0, 1.0)), None)?;
+        assert!(store
+            .add(3, 0, Some(0.0), Some(Feature::from_vec(vec![1.0])), None)
+            .is_err());
+        {
+            let shard = store.get_store(1);
+            let stored = shard.get(&1).unwrap().observations.get(&0).unwrap()[0]
+                .feature()
+                .as_ref()
+                .unwrap();
+            assert_eq!(stored.len(), 1);
+            assert_eq!(Vec::<f32>::from_vec(stored)[0], 1.0);
+        }
+
+        let query = |f: Feature| {
+            store
+                .new_track(10)
+                .observation(
+                    ObservationBuilder::new(0)
+                        .observation_attributes(0.0)
+                        .observation(f)
+                        .build(),
+                )
+                .build()
+        };
+        let (q1, q2) = (
+            query(feature(1.0, -5.0))?,
+            query(Feature::from_vec(vec![1.0]))?,
+        );
+
+        let (dists, _) = store.foreign_track_distances(vec![q1], 0, false);
+        let mut dists = dists
+            .all()
+            .into_iter()
+            .map(|d| (d.to, d.feature_distance.unwrap()))
+            .collect::<Vec<_>>();
+        dists.sort_by_key(|(to, _)| *to);
+        assert_eq!(dists, vec![(1, 0.0), (2, 2.0)]);
+
+        // the query which feature cannot be projected is skipped
+        let (dists, errs) = store.foreign_track_distances(vec![q2], 0, false);
+        assert!(dists.all().is_empty() && errs.all().is_empty());
+
+        // the fitted projection replaces the explicit one
+        let samples = (0..10)
+            .map(|i| Feature::from_vec(vec![i as f32, 2.0 * i as f32, 0.0]))
+            .collect::<Vec<_>>();
+        store.fit_projection(0, &samples, 3, 2, true)?;
+        assert_eq!(store.projection(0).unwrap().output_dimensions(), 2);
+        store.remove_projection(0);
+        assert!(store.projection(0).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn feature_dimensions() -> Result<()> {
+        let mut store = TrackStoreBuilder::new(1)
+            .default_attributes(UnboundAttrs)
+            .metric(MinkowskiMetric::new(2.0))
+            .notifier(NoopNotifier)
+            .feature_dimensions(0, 3)
+            .build();
+        assert_eq!(store.feature_dimensions(0), Some(3));
+
+        store.add(1, 0, Some(0.0), Some(vec2(1.0, 0.0)), None)?;
+        store.add(
+            1,
+            0,
+            Some(0.0),
+            Some(Feature::from_vec(vec![1.0, 0.0, 1.0])),
+            None,
+        )?;
+        // other classes are not checked
+        store.add(
+            1,
+            1,
+            Some(0.0),
+            Some(Feature::from_vec(vec![1.0; 12])),
+            None,
+        )?;
+
+        let res = store.add(2, 0, Some(0.0), Some(Feature::from_vec(vec![1.0; 4])), None);
+        assert!(matches!(
+            res.unwrap_err().downcast_ref::<Errors>(),
+            Some(Errors::FeatureDimensionsMismatch(0, 3, 4))
+        ));
+        let res = store.add(
+            2,
+            0,
+            Some(0.0),
+            Some(Feature::from_vec(vec![1.0; 12])),
+            None,
+        );
+        assert!(matches!(
+            res.unwrap_err().downcast_ref::<Errors>(),
+            Some(Errors::FeatureDimensionsMismatch(0, 3, 16))
+        ));
+        assert_eq!(store.shard_stats(), vec![1]);
+
+        let query = store
+            .new_track(10)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation_attributes(0.0)
+                    .observation(Feature::from_vec(vec![1.0; 5]))
+                    .build(),
+            )
+            .build()?;
+        let (dists, errs) = store.foreign_track_distances(vec![query], 0, false);
+        assert!(dists.all().is_empty() && errs.all().is_empty());
+
+        store.remove_feature_dimensions(0);
+        store.add(2, 0, Some(0.0), Some(Feature::from_vec(vec![1.0; 4])), None)?;
+        Ok(())
+    }
+
+    #[test]
+    fn cross_class_distances() -> Result<()> {
+        let mut store = TrackStoreBuilder::new(2)
+            .default_attributes(UnboundAttrs)
+            .metric(CosineMetric::new())
+            .notifier(NoopNotifier)
+            .build();
+
+        // class 0 - image embeddings of the gallery tracks
+        store.add(1, 0, Some(0.0), Some(vec2(1.0, 0.0)), None)?;
+        store.add(2, 0, Some(0.0), Some(vec2(0.0, 3.0)), None)?;
+
+        // class 1 - text embedding of the query
+        let query = || {
+            store
+                .new_track(10)
+                .observation(
+                    ObservationBuilder::new(1)
+                        .observation_attributes(0.0)
+                        .observation(vec2(0.0, 1.0))
+                        .build(),
+                )
+                .build()
+        };
+        let (q1, q2, q3) = (query()?, query()?, query()?);
+
+        assert!(matches!(
+            store
+                .cross_class_track_distances(vec![q1.clone()], 1, 0, false)
+                .err()
+                .unwrap()
+                .downcast_ref::<Errors>(),
+            Some(Errors::TranslationNotFound(1, 0))
+        ));
+
+        store.register_translation(1, 0, MinkowskiMetric::new(2.0));
+        let (dists, errs) = store.cross_class_track_distances(vec![q1], 1, 0, false)?;
+        let mut dists = dists
+            .all()
+            .into_iter()
+            .map(|d| (d.to, d.feature_distance.unwrap()))
+            .collect::<Vec<_>>();
+        dists.sort_by_key(|(to, _)| *to);
+        assert_eq!(dists, vec![(1, 2.0_f32.sqrt()), (2, 2.0)]);
+        assert!(errs.all().is_empty());
+
+        // the same-class distances are not affected
+        let (dists, errs) = store.foreign_track_distances(vec![q2], 0, false);
+        assert!(dists.all().is_empty());
+        assert_eq!(errs.all().len(), 2);
+
+        store.remove_translation(1, 0);
+        assert!(store
+            .cross_class_track_distances(vec![q3], 1, 0, false)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ttl_eviction() -> Result<()> {
+        let mut store: TrackStore<UnboundAttrs, UnboundMetric, f32> = TrackStoreBuilder::new(2)
+            .default_attributes(UnboundAttrs)
+            .metric(UnboundMetric)
+            .notifier(NoopNotifier)
+            .ttl(TrackTtl::Epochs(1))
+            .build();
+
+        store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        store.add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        store.advance_epoch();
+        store.add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        assert!(store.purge().is_empty());
+
+        // the track 1 is not updated for two epochs and is evicted lazily by the query
+        store.advance_epoch();
+        let (dists, _) = store.owned_track_distances(&[2], 0, false);
+        assert!(dists.all().is_empty());
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 1);
+
+        let evicted = store.purge();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].get_track_id(), 1);
+        assert!(store.purge().is_empty());
+
+        // the queries don't refresh the tracks
+        store.advance_epoch();
+        store.set_lazy_eviction(false);
+        store.owned_track_distances(&[2], 0, false);
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 1);
+        assert_eq!(store.purge()[0].get_track_id(), 2);
+
+        store.set_ttl(Some(TrackTtl::Duration(Duration::from_millis(10))));
+        store.add(3, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        assert!(store.purge().is_empty());
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(store.purge().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn capacity_eviction() -> Result<()> {
+        let mut store: TrackStore<UnboundAttrs, UnboundMetric, f32> = TrackStoreBuilder::new(2)
+            .default_attributes(UnboundAttrs)
+            .metric(UnboundMetric)
+            .notifier(NoopNotifier)
+            .capacity(2)
+            .build();
+
+        store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        thread::sleep(Duration::from_millis(1));
+        store.add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        thread::sleep(Duration::from_millis(1));
+        // the track 1 becomes the most recently updated one
+        assert!(store
+            .add_bounded(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?
+            .evicted()
+            .is_empty());
+        thread::sleep(Duration::from_millis(1));
+
+        let evicted = store
+            .add_bounded(3, 0, Some(1.0), Some(vec2(1.0, 1.0)), None)?
+            .evicted();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].get_track_id(), 2);
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 2);
+
+        // the tracks evicted by the plain insert are returned by purge
+        store.add_track(Track::new(4, UnboundMetric, UnboundAttrs, NoopNotifier))?;
+        let evicted = store.purge();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].get_track_id(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn capacity_eviction_order() -> Result<()> {
+        let mut store: TrackStore<UnboundAttrs, UnboundMetric, f32> = TrackStoreBuilder::new(2)
+            .default_attributes(UnboundAttrs)
+            .metric(UnboundMetric)
+            .notifier(NoopNotifier)
+            .capacity(3)
+            .build();
+        for track_id in 1..=3 {
+            store.add(track_id, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+            thread::sleep(Duration::from_millis(1));
+        }
+        // the repeated updates don't grow the order of the shard over its tracks
+        for _ in 0..50 {
+            store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+            store.add(3, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        }
+        assert!(store
+            .stores
+            .iter()
+            .all(|s| s.updates.lock().unwrap().len() <= 20));
+        assert_eq!(store.tracks_count(), 3);
+        thread::sleep(Duration::from_millis(1));
+
+        let evicted = store
+            .add_bounded(4, 0, Some(1.0), Some(vec2(1.0, 1.0)), None)?
+            .evicted();
+        assert_eq!(evicted[0].get_track_id(), 2);
+
+        // the track inserted bypassing the guard is ordered when the ordered tracks are exhausted
+        store.get_store(6).entry(6).or_insert_with(|| {
+            let mut track = Track::new(6, UnboundMetric, UnboundAttrs, NoopNotifier);
+            track.stamp(0);
+            track
+        });
+        assert_eq!(store.tracks_count(), 4);
+        store.set_capacity(Some(1));
+        let evicted = store
+            .add_bounded(5, 0, Some(1.0), Some(vec2(1.0, 1.0)), None)?
+            .evicted();
+        assert_eq!(evicted.len(), 4);
+        assert_eq!(store.tracks_count(), 1);
+        assert!(store.get_store(5).contains_key(&5));
+        Ok(())
+    }
+
+    #[test]
+    fn batch_foreign_distances() -> Result<()> {
+        let mut store: TrackStore<UnboundAttrs, UnboundMetric, f32> = TrackStoreBuilder::new(3)
+            .default_attributes(UnboundAttrs)
+            .metric(UnboundMetric)
+            .notifier(NoopNotifier)
+            .build();
+        for i in 0..5 {
+            store.add(i, 0, Some(1.0), Some(vec2(i as f32, 1.0)), None)?;
+        }
+
+        let candidates = (10..13)
+            .map(|i| {
+                let mut t = Track::new(i, UnboundMetric, UnboundAttrs, NoopNotifier);
+                t.add_observation(0, Some(1.0), Some(vec2(1.0, i as f32)), None)
+                    .unwrap();
+                t
+            })
+            .collect::<Vec<_>>();
+
+        let results = store.batch_foreign_track_distances(candidates.clone(), 0, false);
+        assert_eq!(
+            results.iter().map(|r| r.track_id).collect::<Vec<_>>(),
+            vec![10, 11, 12]
+        );
+        for (res, candidate) in results.into_iter().zip(candidates) {
+            assert!(res.errors.is_empty());
+            let (dists, _) = store.foreign_track_distances(vec![candidate], 0, false);
+            let mut expected = dists.all();
+            let mut actual = res.distances;
+            assert_eq!(actual.len(), 5);
+            assert!(actual.iter().all(|d| d.from == res.track_id));
+            expected.sort_by_key(|d| d.to);
+            actual.sort_by_key(|d| d.to);
+            assert_eq!(
+                actual
+                    .iter()
+                    .map(|d| d.feature_distance)
+                    .collect::<Vec<_>>(),
+                expected
+                    .iter()
+                    .map(|d| d.feature_distance)
+                    .collect::<Vec<_>>()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn merge_tracks() -> Result<()> {
+        let mut store = TrackStore::new(
+            TimeMetric { max_length: 20 },
+            TimeAttrs {
+                baked_period: 10,
+                ..Default::default()
+            },
+            NoopNotifier,
+            2,
+        );
+        for (track_id, time) in [(1, 10), (2, 20), (3, 30)] {
+            store.add(
+                track_id,
+                0,
+                Some(1.0),
+                Some(vec2(track_id as f32, 0.0)),
+                Some(TimeAttrUpdates { time }),
+            )?;
+        }
+
+        // the missing source leaves the store unchanged
+        assert!(store
+            .merge_tracks(1, &[2, 4], &AttributeMergePolicy::Merge)
+            .is_err());
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 3);
+        assert!(store
+            .merge_tracks(1, &[1], &AttributeMergePolicy::Merge)
+            .is_err());
+
+        let sources = store.merge_tracks(1, &[2, 3], &AttributeMergePolicy::KeepDestination)?;
+        assert_eq!(
+            sources.iter().map(|t| t.get_track_id()).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        let merged = store.fetch_tracks(&[1]).pop().unwrap();
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 0);
+        assert_eq!(merged.get_observations(0).unwrap().len(), 3);
+        assert_eq!(merged.get_merge_history(), &vec![1, 2, 3]);
+        assert_eq!(merged.get_attributes().end_time, 10);
+
+        store.add_track(merged)?;
+        for track in sources {
+            store.add_track(track)?;
+        }
+        store.merge_tracks(1, &[2, 3], &AttributeMergePolicy::Merge)?;
+        let merged = store.fetch_tracks(&[1]).pop().unwrap();
+        assert_eq!(merged.get_attributes().end_time, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn split_track() -> Result<()> {
+        let mut store: TrackStore<UnboundAttrs, UnboundMetric, f32> = TrackStoreBuilder::new(2)
+            .default_attributes(UnboundAttrs)
+            .metric(UnboundMetric)
+            .notifier(NoopNotifier)
+            .build();
+        for i in 0..4 {
+            store.add(1, 0, Some(i as f32), Some(vec2(i as f32, 0.0)), None)?;
+        }
+        store.add(1, 1, Some(0.0), Some(vec2(0.0, 1.0)), None)?;
+
+        assert!(store.split_track(1, 1, &SplitPoint::Index(2)).is_err());
+        assert!(store.split_track(3, 2, &SplitPoint::Index(2)).is_err());
+
+        store.split_track(1, 2, &SplitPoint::Index(2))?;
+        let tracks = store.fetch_tracks(&[1, 2]);
+        let attrs = |t: &Track<UnboundAttrs, UnboundMetric, f32>| {
+            t.get_observations(0)
+                .unwrap()
+                .iter()
+                .map(|o| o.attr().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(attrs(&tracks[0]), vec![0.0, 1.0]);
+        assert_eq!(attrs(&tracks[1]), vec![2.0, 3.0]);
+        assert!(tracks[1].get_observations(1).is_none());
+        assert_eq!(tracks[1].get_merge_history(), &vec![2]);
+
+        for t in tracks {
+            store.add_track(t)?;
+        }
+        let odd = SplitPoint::Predicate(Arc::new(|_, o: &Observation<f32>| {
+            o.attr().unwrap() as u64 % 2 == 1
+        }));
+        store.split_track(2, 3, &odd)?;
+        let tracks = store.fetch_tracks(&[2, 3]);
+        assert_eq!(attrs(&tracks[0]), vec![2.0]);
+        assert_eq!(attrs(&tracks[1]), vec![3.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn split_track_at_epoch() -> Result<()> {
+        let mut store: TrackStore<UnboundAttrs, UnboundMetric, f32> = TrackStoreBuilder::new(2)
+            .default_attributes(UnboundAttrs)
+            .metric(UnboundMetric)
+            .notifier(NoopNotifier)
+            .build();
+        for i in 0..4 {
+            store.add(1, 0, Some(i as f32), Some(vec2(i as f32, 0.0)), None)?;
+            store.advance_epoch();
+        }
+        store.add(1, 1, Some(0.0), Some(vec2(0.0, 1.0)), None)?;
+
+        store.split_track(1, 2, &SplitPoint::Epoch(2))?;
+        let tracks = store.fetch_tracks(&[1, 2]);
+        let epochs = |t: &Track<UnboundAttrs, UnboundMetric, f32>, feature_class| {
+            t.get_observations(feature_class)
+                .map(|o| o.iter().map(|o| o.stamp().epoch).collect::<Vec<_>>())
+        };
+        assert_eq!(epochs(&tracks[0], 0), Some(vec![0, 1]));
+        assert_eq!(epochs(&tracks[1], 0), Some(vec![2, 3]));
+        // the observation of the class 1 is stamped with the epoch 4
+        assert_eq!(epochs(&tracks[0], 1), None);
+        assert_eq!(epochs(&tracks[1], 1), Some(vec![4]));
+        Ok(())
+    }
+
+    #[test]
+    fn split_track_at_capacity() -> Result<()> {
+        let mut store = TrackStoreBuilder::new(2)
+            .metric(UnboundMetric)
+            .default_attributes(UnboundAttrs)
+            .notifier(NoopNotifier)
+            .capacity(1)
+            .capacity_policy(CapacityPolicy::Reject)
+            .build();
+        for i in 0..4 {
+            store.add(1, 0, Some(i as f32), Some(vec2(i as f32, 0.0)), None)?;
+        }
+        assert!(matches!(
+            store
+                .split_track(1, 2, &SplitPoint::Index(2))
+                .err()
+                .unwrap()
+                .downcast_ref::<Errors>(),
+            Some(Errors::StoreFull(2, 1))
+        ));
+        let track = store.fetch_tracks(&[1]).pop().unwrap();
+        assert_eq!(track.get_observations(0).unwrap().len(), 4);
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn track_metadata() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        store.add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+
+        assert!(store.set_track_metadata(1, "camera", 3).is_ok());
+        assert!(store.set_track_metadata(3, "camera", 3).is_err());
+        store.set_track_metadata(2, "camera", 4)?;
+        store.set_track_metadata(2, "labels", vec!["car", "red"])?;
+        assert_eq!(
+            store.set_track_metadata(1, "camera", 4)?,
+            Some(MetadataValue::Int(3))
+        );
+
+        let mut found = store.find_by_metadata("camera", &MetadataValue::Int(4));
+        found.sort();
+        assert_eq!(found, vec![1, 2]);
+        assert_eq!(
+            store.get_track_metadata(2, "labels")?,
+            Some(MetadataValue::List(vec!["car".into(), "red".into()]))
+        );
+
+        // the metadata missing in the destination is copied from the source
+        store.merge_tracks(1, &[2], &AttributeMergePolicy::Merge)?;
+        assert_eq!(
+            store.get_track_metadata(1, "camera")?,
+            Some(MetadataValue::Int(4))
+        );
+        assert!(store.get_track_metadata(1, "labels")?.is_some());
+        assert_eq!(
+            store.remove_track_metadata(1, "labels")?,
+            Some(vec!["car", "red"].into())
+        );
+        assert!(store.get_track_metadata(1, "labels")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn history_cap() -> Result<()> {
+        let mut store = TrackStoreBuilder::new(2)
+            .metric(UnboundMetric)
+            .default_attributes(UnboundAttrs)
+            .notifier(NoopNotifier)
+            .history_cap(0, HistoryCap::KeepLast(2))
+            .history_cap(
+                1,
+                HistoryCap::KeepBest(2, Arc::new(|o: &Observation<f32>| o.attr().unwrap())),
+            )
+            .history_cap(2, HistoryCap::Reservoir(3))
+            .build();
+        for i in 0..10 {
+            let q = [0.5, 0.9, 0.1, 0.7, 0.3, 0.2, 0.8, 0.4, 0.6, 0.0][i];
+            for feature_class in 0..3 {
+                store.add(1, feature_class, Some(q), Some(vec2(i as f32, 0.0)), None)?;
+            }
+        }
+        store.add(2, 3, Some(1.0), Some(vec2(0.0, 0.0)), None)?;
+        store.add(2, 3, Some(1.0), Some(vec2(0.0, 0.0)), None)?;
+
+        let track = store.fetch_tracks(&[1]).pop().unwrap();
+        let attrs = |class| -> Vec<f32> {
+            track
+                .get_observations(class)
+                .unwrap()
+                .iter()
+                .map(|o| o.attr().unwrap())
+                .collect()
+        };
+        assert_eq!(attrs(0), vec![0.6, 0.0]);
+        assert_eq!(attrs(1), vec![0.9, 0.8]);
+        assert_eq!(attrs(2).len(), 3);
+        assert_eq!(track.observation_total(2), 10);
+
+        // the classes without the cap keep all the observations
+        let track = store.fetch_tracks(&[2]).pop().unwrap();
+        assert_eq!(track.get_observations(3).unwrap().len(), 2);
+        assert!(store.remove_history_cap(0).is_some());
+        assert!(store.history_cap(0).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn feature_fusion() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        store.set_feature_fusion(
+            0,
+            FeatureFusion::Ema {
+                alpha: 0.75,
+                normalize: false,
+            },
+        );
+        store.add(1, 0, Some(0.1), Some(vec2(1.0, 0.0)), None)?;
+        store.add(1, 0, Some(0.2), Some(vec2(0.0, 1.0)), None)?;
+        store.add(1, 0, Some(0.3), None, None)?;
+        store.add(1, 1, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        store.add(1, 1, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        assert!(store
+            .add(
+                1,
+                0,
+                Some(0.4),
+                Some(Feature::from_vec(vec![1.0; 10])),
+                None
+            )
+            .is_err());
+
+        let track = store.fetch_tracks(&[1]).pop().unwrap();
+        let observations = track.get_observations(0).unwrap();
+        assert_eq!(observations.len(), 1);
+        assert_eq!(observations[0].attr(), &Some(0.3));
+        let fused = Vec::<f32>::from_vec(observations[0].feature().as_ref().unwrap());
+        assert!((fused[0] - 0.75).abs() < EPS && (fused[1] - 0.25).abs() < EPS);
+        // the classes without the fusion keep all the observations
+        assert_eq!(track.get_observations(1).unwrap().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn centroid_mode() -> Result<()> {
+        let mut store = TrackStoreBuilder::new(2)
+            .metric(UnboundMetric)
+            .default_attributes(UnboundAttrs)
+            .notifier(NoopNotifier)
+            .default_feature_fusion(FeatureFusion::Centroid)
+            .build();
+        for (x, y) in [(3.0, 0.0), (0.0, 3.0), (0.0, 0.0)] {
+            store.add(1, 0, Some(1.0), Some(vec2(x, y)), None)?;
+        }
+        store.add(1, 0, Some(1.0), None, None)?;
+        store.add(2, 0, Some(1.0), Some(vec2(1.0, 1.0)), None)?;
+
+        let track = store.fetch_tracks(&[1]).pop().unwrap();
+        assert_eq!(track.fused_count(0), 3);
+        let observations = track.get_observations(0).unwrap();
+        assert_eq!(observations.len(), 1);
+        let centroid = Vec::<f32>::from_vec(observations[0].feature().as_ref().unwrap());
+        assert!((centroid[0] - 1.0).abs() < EPS && (centroid[1] - 1.0).abs() < EPS);
+
+        // the distances are calculated between the centroids
+        let (dists, _) = store.foreign_track_distances(vec![track], 0, false);
+        let dists = dists.all();
+        assert_eq!(dists.len(), 1);
+        assert!(dists[0].feature_distance.unwrap().abs() < EPS);
+        Ok(())
+    }
+
+    #[test]
+    fn stale_tracks() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        store.add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        store.advance_epoch();
+        store.add(3, 0, Some(1.0), Some(vec2(1.0, 1.0)), None)?;
+        store.advance_epoch();
+        store.add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+
+        assert!(store.stale_tracks(2).is_empty());
+        assert_eq!(store.stale_tracks(1), vec![1]);
+        assert_eq!(store.stale_tracks(0), vec![1, 3]);
+        // the stale tracks are left in the store
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn find_tracks() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 3);
+        for track_id in 1..=6 {
+            store.add(track_id, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+            if track_id % 2 == 0 {
+                store.add(track_id, 1, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+            }
+            store.advance_epoch();
+        }
+
+        let since = store.epoch() - 3;
+        let found = store.find_tracks(move |t| {
+            t.get_feature_classes().contains(&1) && t.get_last_update().epoch >= since
+        });
+        assert_eq!(found, vec![4, 6]);
+        assert!(store.find_tracks(|_| false).is_empty());
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_observations() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        for q in [0.2, 0.9, 0.4] {
+            store.add(1, 0, Some(q), Some(vec2(1.0, 0.0)), None)?;
+            store.add(1, 1, Some(q), Some(vec2(0.0, 1.0)), None)?;
+        }
+        store.add(1, 2, Some(1.0), Some(vec2(1.0, 1.0)), None)?;
+
+        // the low quality observations of all the classes are pruned
+        assert_eq!(
+            store.remove_track_observations(1, |_, o| o.attr().unwrap() < 0.5)?,
+            4
+        );
+        assert_eq!(store.remove_track_feature_class(1, 2)?, 1);
+        assert_eq!(store.remove_track_feature_class(1, 2)?, 0);
+        assert!(store.remove_track_feature_class(2, 0).is_err());
+
+        let track = store.fetch_tracks(&[1]).pop().unwrap();
+        let mut classes = track.get_feature_classes();
+        classes.sort();
+        assert_eq!(classes, vec![0, 1]);
+        assert_eq!(track.get_observations(0).unwrap().len(), 1);
+        assert_eq!(track.get_observations(1).unwrap()[0].attr(), &Some(0.9));
+        Ok(())
+    }
+
+    #[test]
+    fn clone_track() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        store.add(1, 1, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        store.set_track_metadata(1, "camera", 1)?;
+
+        let new_id = store.clone_track(1)?;
+        assert_ne!(new_id, 1);
+        assert_eq!(store.clone_track_with_id(1, 5)?, 5);
+        assert!(store.clone_track_with_id(1, 5).is_err());
+        assert!(store.clone_track(7).is_err());
+
+        // the copy is independent of the source track
+        store.add(new_id, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        let mut tracks = store.fetch_tracks(&[1, new_id]);
+        let copy = tracks.pop().unwrap();
+        assert_eq!(copy.get_track_id(), new_id);
+        assert_eq!(copy.get_merge_history(), &vec![new_id]);
+        assert_eq!(copy.get_observations(0).unwrap().len(), 2);
+        assert_eq!(tracks[0].get_observations(0).unwrap().len(), 1);
+        assert_eq!(copy.get_metadata("camera"), Some(&MetadataValue::Int(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn shared_reads() -> Result<()> {
+        #[derive(Clone, Default)]
+        struct SlowMetric;
+
+        impl ObservationMetric<UnboundAttrs, f32> for SlowMetric {
+            fn metric(&self, _mq: &MetricQuery<UnboundAttrs, f32>) -> MetricOutput<f32> {
+                thread::sleep(Duration::from_millis(300));
+                Some((None, Some(0.0)))
+            }
+
+            fn optimize(
+                &mut self,
+                _feature_class: u64,
+                _merge_history: &[TrackId],
+                _attrs: &mut UnboundAttrs,
+                _features: &mut Vec<Observation<f32>>,
+                _prev_length: usize,
+                _is_merge: bool,
+            ) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut store = TrackStoreBuilder::new(1)
+            .metric(SlowMetric)
+            .default_attributes(UnboundAttrs)
+            .notifier(NoopNotifier)
+            .build();
+        store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+
+        let mut query = Track::new(10, SlowMetric, UnboundAttrs, NoopNotifier);
+        query.add_observation(0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        let (dists, _) = store.foreign_track_distances(vec![query], 0, false);
+
+        // the reads of the shard don't wait for the query reading it
+        thread::sleep(Duration::from_millis(50));
+        let started = std::time::Instant::now();
+        assert_eq!(store.shard_stats(), vec![1]);
+        assert!(started.elapsed() < Duration::from_millis(150));
+        assert_eq!(dists.all().len(), 1);
+
+        // the query doesn't hold the tracks of the shard, so the insert doesn't copy them
+        store.add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        assert_eq!(Arc::strong_count(&store.stores[0].read().unwrap()), 1);
+        assert_eq!(store.shard_stats(), vec![2]);
+        Ok(())
+    }
+
+    #[test]
+    fn top_k_distances() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 3);
+        for track_id in 1..=10 {
+            store.add(
+                track_id,
+                0,
+                Some(1.0),
+                Some(vec2(track_id as f32, 0.0)),
+                None,
+            )?;
+            store.add(
+                track_id,
+                0,
+                Some(1.0),
+                Some(vec2(track_id as f32 + 0.5, 0.0)),
+                None,
+            )?;
+        }
+
+        let mut query = Track::new(100, UnboundMetric, UnboundAttrs, NoopNotifier);
+        query.add_observation(0, Some(1.0), Some(vec2(4.2, 0.0)), None)?;
+        let (dists, errs) = store.top_k_distances(query.clone(), 0, 3, false);
+        assert!(errs.is_empty());
+        assert_eq!(
+            dists.iter().map(|d| d.to).collect::<Vec<_>>(),
+            vec![4, 3, 5]
+        );
+        assert!((dists[0].feature_distance.unwrap() - 0.2).abs() < EPS);
+
+        assert_eq!(
+            store.top_k_distances(query.clone(), 0, 20, false).0.len(),
+            10
+        );
+        assert!(store.top_k_distances(query, 0, 0, false).0.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn observations_batch() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 3);
+        store.set_feature_dimensions(0, 2);
+        let results = store.add_observations_batch(vec![
+            (1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None),
+            (2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None),
+            (1, 0, Some(1.0), Some(vec2(1.0, 1.0)), None),
+            (
+                1,
+                0,
+                Some(1.0),
+                Some(Feature::from_vec(vec![1.0; 16])),
+                None,
+            ),
+            (3, 1, Some(1.0), None, None),
+        ]);
+        assert_eq!(results.len(), 5);
+        assert!(results[..3].iter().all(|r| r.is_ok()));
+        assert!(results[3].is_err());
+        assert!(results[4].is_ok());
+
+        let tracks = store.fetch_tracks(&[1, 2, 3]);
+        assert_eq!(tracks.len(), 3);
+        let track = tracks.iter().find(|t| t.get_track_id() == 1).unwrap();
+        assert_eq!(track.get_observations(0).unwrap().len(), 2);
+        assert_eq!(
+            track.get_observations(0).unwrap()[1].feature(),
+            &Some(vec2(1.0, 1.0))
+        );
+        assert_eq!(store.stats().queries.adds, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn deduplication() -> Result<()> {
+        let mut store = TrackStoreBuilder::new(2)
+            .metric(UnboundMetric)
+            .default_attributes(UnboundAttrs)
+            .notifier(NoopNotifier)
+            .deduplication(0, Deduplication::Drop(0.1))
+            .deduplication(1, Deduplication::Merge(0.1))
+            .build();
+        for class in 0..2 {
+            store.add(1, class, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+            store.add(1, class, Some(1.0), Some(vec2(1.0, 0.05)), None)?;
+            store.add(1, class, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        }
+        assert_eq!(store.deduplication(0), Some(&Deduplication::Drop(0.1)));
+
+        let track = store.fetch_tracks(&[1]).pop().unwrap();
+        let dropped = track.get_observations(0).unwrap();
+        assert_eq!(dropped.len(), 2);
+        assert_eq!(dropped[0].feature(), &Some(vec2(1.0, 0.0)));
+        let merged = track.get_observations(1).unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].feature(), &Some(vec2(1.0, 0.025)));
+
+        store.remove_deduplication(0);
+        store.add(2, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        store.add(2, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        let track = store.fetch_tracks(&[2]).pop().unwrap();
+        assert_eq!(track.get_observations(0).unwrap().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn attribute_history() -> Result<()> {
+        let mut store = TrackStoreBuilder::new(2)
+            .metric(TimeMetric { max_length: 20 })
+            .default_attributes(TimeAttrs::default())
+            .notifier(NoopNotifier)
+            .attribute_history_cap(2)
+            .build();
+        for time in 1..=3 {
+            store.add(
+                1,
+                0,
+                Some(1.0),
+                Some(vec2(1.0, 0.0)),
+                Some(TimeAttrUpdates { time }),
+            )?;
+            store.advance_epoch();
+        }
+
+        let track = store.fetch_tracks(&[1]).pop().unwrap();
+        let history = track.attribute_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            history
+                .iter()
+                .map(|v| (v.epoch, v.attributes.end_time))
+                .collect::<Vec<_>>(),
+            vec![(1, 2), (2, 3)]
+        );
+        assert!(history.iter().all(|v| v.attributes.start_time == 1));
+
+        let mut track = track;
+        track.set_attribute_history_cap(0);
+        assert!(track.attribute_history().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn epoch_range_queries() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        for epoch in 0..4 {
+            store.add(1, 0, Some(1.0), Some(vec2(epoch as f32, 0.0)), None)?;
+            if epoch == 2 {
+                store.add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+            }
+            store.advance_epoch();
+        }
+
+        assert_eq!(store.tracks_active_in_range(1..=2), vec![1, 2]);
+        assert_eq!(store.tracks_active_in_range(3..), vec![1]);
+        assert!(store.tracks_active_in_range(10..).is_empty());
+
+        let track = store.fetch_tracks(&[1]).pop().unwrap();
+        let observations = track.observations_in_range(0, 1..3);
+        assert_eq!(
+            observations
+                .iter()
+                .map(|o| o.stamp().epoch)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(observations[0].feature(), &Some(vec2(1.0, 0.0)));
+        Ok(())
+    }
+
+    #[test]
+    fn observation_stamps() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        store.advance_epoch();
+        thread::sleep(Duration::from_millis(20));
+        store.add(2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+
+        let (dists, errs) = store.owned_track_distances(&[1], 0, false);
+        assert!(errs.all().is_empty());
+        let dists = dists.all();
+        assert_eq!(dists.len(), 1);
+        assert_eq!(dists[0].stamp.epoch, 1);
+
+        let mut tracks = store.fetch_tracks(&[1, 2]);
+        tracks.sort_by_key(|t| t.get_track_id());
+        let (first, second) = (
+            tracks[0].get_observations(0).unwrap()[0].stamp(),
+            tracks[1].get_observations(0).unwrap()[0].stamp(),
+        );
+        assert_eq!(second, &dists[0].stamp);
+        assert!(second.instant.duration_since(first.instant) >= Duration::from_millis(20));
+        assert!(first.age() >= Duration::from_millis(20));
+        Ok(())
+    }
+
+    #[test]
+    fn capacity_policies() -> Result<()> {
+        let mut store = TrackStoreBuilder::new(2)
+            .metric(UnboundMetric)
+            .default_attributes(UnboundAttrs)
+            .notifier(NoopNotifier)
+            .capacity(2)
+            .capacity_policy(CapacityPolicy::Reject)
+            .build();
+        store.add(1, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        store.add(2, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        assert!(matches!(
+            store
+                .add(3, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+                .err()
+                .unwrap()
+                .downcast_ref::<Errors>(),
+            Some(Errors::StoreFull(3, 2))
+        ));
+        store.add(1, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        let results = store.add_observations_batch(vec![
+            (2, 0, Some(1.0), Some(vec2(0.0, 1.0)), None),
+            (4, 0, Some(1.0), Some(vec2(0.0, 1.0)), None),
+        ]);
+        assert!(results[0].is_ok() && results[1].is_err());
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 2);
+
+        store.set_capacity_policy(CapacityPolicy::Overflow(2));
+        assert!(store
+            .add_bounded(3, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?
+            .is_parked());
+        // the observation of the parked track is added to it
+        store.add(3, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        let results = store.add_observations_batch(vec![
+            (4, 0, Some(1.0), Some(vec2(0.0, 1.0)), None),
+            (5, 0, Some(1.0), Some(vec2(0.0, 1.0)), None),
+        ]);
+        assert!(results[0].as_ref().unwrap().is_parked() && results[1].is_err());
+        assert_eq!(store.overflow_len(), 2);
+        assert!(store.drain_overflow().is_empty());
+
+        store.fetch_tracks(&[2]);
+        assert_eq!(store.drain_overflow().len(), 1);
+        assert_eq!(store.overflow_len(), 1);
+        let track = store.fetch_tracks(&[3]).pop().unwrap();
+        assert_eq!(track.get_observations(0).unwrap().len(), 2);
+        assert_eq!(store.drain_overflow().len(), 1);
+        store.fetch_tracks(&[4]);
+
+        store.set_capacity_policy(CapacityPolicy::EvictOldest);
+        store.add(5, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        store.add(6, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        assert_eq!(store.purge().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn shard_router() -> Result<()> {
+        let camera_track = |camera: TrackId, track: TrackId| camera << 32 | track;
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 4);
+        for track in 0..3 {
+            store.add(camera_track(1, track), 0, Some(1.0), None, None)?;
+        }
+        assert_eq!(store.shard_stats(), vec![1, 1, 1, 0]);
+
+        store.set_shard_router(Some(Arc::new(|track_id| (track_id >> 32) as usize)));
+        assert_eq!(store.shard_stats(), vec![0, 3, 0, 0]);
+        store.add(camera_track(2, 0), 0, Some(1.0), None, None)?;
+        store.add(camera_track(1, 0), 0, Some(1.0), None, None)?;
+        assert_eq!(store.shard_stats(), vec![0, 3, 1, 0]);
+        assert_eq!(store.get_store(camera_track(1, 7)).len(), 3);
+        assert_eq!(
+            store
+                .fetch_tracks(&[camera_track(1, 0)])
+                .pop()
+                .unwrap()
+                .get_observations(0)
+                .unwrap()
+                .len(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn wide_track_ids() -> Result<()> {
+        // the ids differ only in the upper half, e.g. the UUIDs
+        let (first, second): (TrackId, TrackId) = (1 << 64 | 7, 2 << 64 | 7);
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 4);
+        store.add(first, 0, Some(1.0), Some(vec2(0.0, 0.0)), None)?;
+        store.add(second, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)?;
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 2);
+
+        let mut query = Track::new(u128::MAX, UnboundMetric, UnboundAttrs, NoopNotifier);
+        query.add_observation(0, Some(1.0), Some(vec2(0.1, 0.0)), None)?;
+        let (dists, _) = store.foreign_track_distances(vec![query], 0, false);
+        let winners = TopNVoting::new(1, 1.0, 1).winners(dists.all());
+        assert_eq!(winners[&u128::MAX][0].winner_track, first);
+
+        let track = store.fetch_tracks(&[second]).pop().unwrap();
+        assert_eq!(track.get_track_id(), second);
+        assert!(store.get_store(first).contains_key(&first));
+        Ok(())
+    }
+
+    #[test]
+    fn bucketed_shards() -> Result<()> {
+        let mut store = TrackStore::with_buckets(UnboundMetric, UnboundAttrs, NoopNotifier, 2, 4);
+        assert_eq!(store.buckets(), 4);
+        for track_id in 0..40 {
+            store.add(
+                track_id,
+                0,
+                Some(1.0),
+                Some(vec2(track_id as f32, 0.0)),
+                None,
+            )?;
+        }
+        assert_eq!(store.shard_stats(), vec![20, 20]);
+        // the tracks of the shard are spread over its buckets
+        assert!(store.get_store(0).len() < 20);
+        assert!((0..40).all(|track_id| store.get_store(track_id).contains_key(&track_id)));
+
+        let mut query = Track::new(100, UnboundMetric, UnboundAttrs, NoopNotifier);
+        query.add_observation(0, Some(1.0), Some(vec2(0.0, 0.0)), None)?;
+        let (dists, errs) = store.foreign_track_distances(vec![query], 0, false);
+        assert_eq!(dists.all().len(), 40);
+        assert!(errs.all().is_empty());
+
+        let src = store.fetch_tracks(&[3]).pop().unwrap();
+        store.merge_external(5, &src, None, false)?;
+        assert_eq!(
+            store
+                .get_store(5)
+                .get(&5)
+                .unwrap()
+                .get_observations(0)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 39);
+        Ok(())
+    }
+
+    #[test]
+    fn stream_distances() -> Result<()> {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        for track_id in 0..10 {
+            store.add(
+                track_id,
+                0,
+                Some(1.0),
+                Some(vec2(track_id as f32, 0.0)),
+                None,
+            )?;
+        }
+        let mut query = Track::new(100, UnboundMetric, UnboundAttrs, NoopNotifier);
+        query.add_observation(0, Some(1.0), Some(vec2(0.0, 0.0)), None)?;
+
+        let mut chunks = Vec::new();
+        store.stream_distances(query.clone(), 0, false, 2, |dists, errs| {
+            assert!(errs.is_empty());
+            chunks.push(dists.len());
+            ControlFlow::Continue(())
+        });
+        assert_eq!(chunks, vec![2, 2, 1, 2, 2, 1]);
+
+        let mut streamed = 0;
+        store.stream_distances(query, 0, false, 3, |dists, _| {
+            streamed += dists.len();
+            ControlFlow::Break(())
+        });
+        assert_eq!(streamed, 3);
+        Ok(())
+    }
 }
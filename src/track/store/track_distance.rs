@@ -1,6 +1,8 @@
 use crate::store::{ObservationMetricErr, Results};
-use crate::track::{ObservationAttributes, ObservationMetricOk};
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
 use crossbeam::channel::Receiver;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::vec::IntoIter;
 
 /// Represents the response from the track distance computation.
@@ -25,6 +27,21 @@ where
     fn channel(&self) -> &Receiver<Results<OA>>;
 }
 
+/// The distances of the external track calculated by the batch query
+///
+#[derive(Debug)]
+pub struct CandidateDistances<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// the external track ID
+    pub track_id: TrackId,
+    /// the distances to the tracks in DB
+    pub distances: Vec<ObservationMetricOk<OA>>,
+    /// the errors of the distance calculation
+    pub errors: Vec<ObservationMetricErr<OA>>,
+}
+
 /// Represents the ok response from the track distance computation.
 ///
 pub struct TrackDistanceOk<OA>
@@ -230,6 +247,89 @@ where
     }
 }
 
+struct Ranked<OA>(f32, ObservationMetricOk<OA>)
+where
+    OA: ObservationAttributes;
+
+impl<OA: ObservationAttributes> PartialEq for Ranked<OA> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<OA: ObservationAttributes> Eq for Ranked<OA> {}
+
+impl<OA: ObservationAttributes> PartialOrd for Ranked<OA> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<OA: ObservationAttributes> Ord for Ranked<OA> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Keeps the `k` closest distances in the polarity sense, the farthest kept distance is on the top of the heap
+///
+pub(crate) struct TopK<OA>
+where
+    OA: ObservationAttributes,
+{
+    k: usize,
+    polarity: MetricPolarity,
+    heap: BinaryHeap<Ranked<OA>>,
+}
+
+impl<OA> TopK<OA>
+where
+    OA: ObservationAttributes,
+{
+    pub(crate) fn new(k: usize, polarity: MetricPolarity) -> Self {
+        Self {
+            k,
+            polarity,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    pub(crate) fn push(&mut self, distance: ObservationMetricOk<OA>) {
+        let rank = match distance.feature_distance {
+            Some(d) => self.polarity.orient(d),
+            None => return,
+        };
+        if self.k == 0
+            || matches!(self.heap.peek(), Some(top) if self.heap.len() == self.k && rank >= top.0)
+        {
+            return;
+        }
+        self.heap.push(Ranked(rank, distance));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    /// The rank of the `k`-th closest distance when `k` distances are kept, the farther distances are not kept
+    ///
+    pub(crate) fn bound(&self) -> Option<f32> {
+        match self.heap.peek() {
+            Some(top) if self.heap.len() == self.k => Some(top.0),
+            _ => None,
+        }
+    }
+
+    /// The kept distances from the closest to the farthest
+    ///
+    pub(crate) fn into_sorted_vec(self) -> Vec<ObservationMetricOk<OA>> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|r| r.1)
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::distance::euclidean;
@@ -237,7 +337,7 @@ mod tests {
     use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
     use crate::track::{
         MetricOutput, MetricQuery, NoopLookup, Observation, ObservationAttributes,
-        ObservationMetric, ObservationsDb, Track, TrackAttributes, TrackAttributesUpdate,
+        ObservationMetric, ObservationsDb, Track, TrackAttributes, TrackAttributesUpdate, TrackId,
         TrackStatus,
     };
     use anyhow::Result;
@@ -289,7 +389,7 @@ mod tests {
         fn optimize(
             &mut self,
             _feature_class: u64,
-            _merge_history: &[u64],
+            _merge_history: &[TrackId],
             _attrs: &mut MockAttrs,
             _features: &mut Vec<Observation<f32>>,
             _prev_length: usize,
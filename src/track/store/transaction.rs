@@ -0,0 +1,251 @@
+use crate::store::events::TrackEvent;
+use crate::store::view::TrackView;
+use crate::store::{Admission, BatchObservation, StoreMutexGuard, TrackStore};
+use crate::track::notify::ChangeNotifier;
+use crate::track::{
+    AttributeMergePolicy, ObservationAttributes, ObservationMetric, Track, TrackAttributes, TrackId,
+};
+use crate::Errors;
+use anyhow::Result;
+use itertools::Itertools;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// The operation of the transaction, look at [apply_transaction](TrackStore::apply_transaction)
+///
+pub enum TransactionOp<TA, OA>
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+{
+    /// adds the observation like [add](TrackStore::add)
+    Add(BatchObservation<TA, OA>),
+    /// merges all the feature classes of the source track into the destination track with the merge history,
+    /// the source track is removed
+    Merge {
+        dest: TrackId,
+        src: TrackId,
+        policy: AttributeMergePolicy<TA>,
+    },
+    /// removes the track
+    Delete(TrackId),
+}
+
+impl<TA, OA> TransactionOp<TA, OA>
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+{
+    fn track_ids(&self) -> Vec<TrackId> {
+        match self {
+            TransactionOp::Add(observation) => vec![observation.0],
+            TransactionOp::Merge { dest, src, .. } => vec![*dest, *src],
+            TransactionOp::Delete(track_id) => vec![*track_id],
+        }
+    }
+}
+
+enum Change {
+    Created(TrackId),
+    Updated(TrackId),
+    Merged(TrackId, TrackId),
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Applies the operations to the tracks atomically
+    ///
+    /// The shards of the involved tracks are locked for the whole transaction, the operations are applied in order
+    /// to the copies of the tracks, which replace the tracks when all the operations succeed. So the queries never
+    /// observe the partially applied transaction, and the store is left unchanged when any of the operations
+    /// fails. The events are published when the transaction is applied, the tracks evicted when the store exceeds
+    /// the capacity are kept by the store until [purge](TrackStore::purge) is called. The new tracks that don't fit
+    /// the store fail the transaction unless the capacity policy evicts the tracks.
+    ///
+    /// # Returns
+    /// * `Ok(removed)` - the transaction is applied, the deleted and the merged source tracks are returned
+    /// * `Err(Errors::TrackNotFound(track_id))` - the merged or deleted track is not in the store
+    /// * `Err(Errors::SameTrackCalculation(track_id))` - the track is merged into itself
+    /// * `Err(Errors::StoreFull(track_id, capacity))` - the new track doesn't fit the store
+    /// * `Err(e)` - the operation met problems
+    ///
+    pub fn apply_transaction(
+        &mut self,
+        ops: Vec<TransactionOp<TA, OA>>,
+    ) -> Result<Vec<Track<TA, M, OA, N>>> {
+        // the transaction is atomic, so the new tracks are not parked
+        let mut admitted = HashSet::default();
+        for op in &ops {
+            if let TransactionOp::Add(observation) = op {
+                if let Admission::Park = self.admit(observation.0, &mut admitted)? {
+                    let capacity = self.capacity.unwrap();
+                    return Err(Errors::StoreFull(observation.0, capacity).into());
+                }
+            }
+        }
+        let track_ids = ops
+            .iter()
+            .flat_map(|op| op.track_ids())
+            .unique()
+            .collect::<Vec<_>>();
+        // the shards are locked in order of the indices
+        let mut shards = track_ids
+            .iter()
+            .map(|track_id| self.get_bucket(*track_id))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|shard_id| (shard_id, StoreMutexGuard::lock(&self.stores[shard_id])))
+            .collect::<BTreeMap<_, _>>();
+        let mut tracks = track_ids
+            .iter()
+            .filter_map(|track_id| {
+                shards[&self.get_bucket(*track_id)]
+                    .get(track_id)
+                    .map(|track| (*track_id, track.clone()))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let epoch = self.epoch();
+        let mut removed = Vec::new();
+        let mut changes = Vec::new();
+        let mut last_added = None;
+        for op in ops {
+            match op {
+                TransactionOp::Add(observation) => {
+                    let track_id = observation.0;
+                    if self.apply_observation(&mut tracks, epoch, observation)? {
+                        changes.push(Change::Created(track_id));
+                    } else {
+                        changes.push(Change::Updated(track_id));
+                    }
+                    last_added = Some(track_id);
+                }
+                TransactionOp::Merge { dest, src, policy } => {
+                    if dest == src {
+                        return Err(Errors::SameTrackCalculation(dest).into());
+                    }
+                    let source = tracks.remove(&src).ok_or(Errors::TrackNotFound(src))?;
+                    let track = tracks.get_mut(&dest).ok_or(Errors::TrackNotFound(dest))?;
+                    track.merge_with_policy(
+                        &source,
+                        &source.get_feature_classes(),
+                        true,
+                        &policy,
+                    )?;
+                    track.stamp(epoch);
+                    changes.push(Change::Merged(dest, src));
+                    removed.push(source);
+                }
+                TransactionOp::Delete(track_id) => {
+                    removed.push(
+                        tracks
+                            .remove(&track_id)
+                            .ok_or(Errors::TrackNotFound(track_id))?,
+                    );
+                }
+            }
+        }
+
+        for track_id in &track_ids {
+            let shard = shards.get_mut(&self.get_bucket(*track_id)).unwrap();
+            match tracks.remove(track_id) {
+                Some(track) => shard.insert(*track_id, track),
+                None => shard.remove(track_id),
+            };
+        }
+        for change in changes {
+            let track_id = match change {
+                Change::Created(track_id)
+                | Change::Updated(track_id)
+                | Change::Merged(track_id, _) => track_id,
+            };
+            // the tracks removed later in the transaction have no events
+            let track = match shards[&self.get_bucket(track_id)].get(&track_id) {
+                Some(track) => track,
+                None => continue,
+            };
+            self.events.publish(|| match change {
+                Change::Created(_) => TrackEvent::Created(TrackView::new(track)),
+                Change::Updated(_) => TrackEvent::Updated(TrackView::new(track)),
+                Change::Merged(_, src) => TrackEvent::Merged {
+                    track: TrackView::new(track),
+                    sources: vec![src],
+                },
+            });
+        }
+        drop(shards);
+
+        self.unmap_tracks(removed.iter().map(|track| track.track_id));
+        if let Some(last_added) = last_added {
+            let evicted = self.evict_over_capacity(last_added);
+            self.evicted.lock().unwrap().extend(evicted);
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::events::TrackEvent;
+    use crate::store::transaction::TransactionOp;
+    use crate::store::TrackStore;
+    use crate::track::notify::NoopNotifier;
+    use crate::track::AttributeMergePolicy;
+    use crate::Errors;
+
+    #[test]
+    fn transaction() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 2);
+        for track_id in 1..=3 {
+            store
+                .add(track_id, 0, Some(1.0), Some(vec2(1.0, 0.0)), None)
+                .unwrap();
+        }
+        let events = store.subscribe();
+
+        let res = store.apply_transaction(vec![
+            TransactionOp::Add((1, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)),
+            TransactionOp::Delete(4),
+        ]);
+        assert!(matches!(
+            res.err().unwrap().downcast_ref::<Errors>(),
+            Some(Errors::TrackNotFound(4))
+        ));
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 3);
+        assert!(events.try_recv().is_err());
+
+        let removed = store
+            .apply_transaction(vec![
+                TransactionOp::Add((1, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)),
+                TransactionOp::Merge {
+                    dest: 1,
+                    src: 2,
+                    policy: AttributeMergePolicy::Merge,
+                },
+                TransactionOp::Delete(3),
+                TransactionOp::Add((5, 0, Some(1.0), Some(vec2(0.0, 1.0)), None)),
+            ])
+            .unwrap();
+        assert_eq!(
+            removed.iter().map(|t| t.get_track_id()).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        let events = events.try_iter().collect::<Vec<_>>();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[1], TrackEvent::Merged { sources, .. } if sources == &vec![2]));
+
+        let mut tracks = store.fetch_tracks(&[1, 2, 3, 5]);
+        tracks.sort_by_key(|t| t.get_track_id());
+        assert_eq!(
+            tracks.iter().map(|t| t.get_track_id()).collect::<Vec<_>>(),
+            vec![1, 5]
+        );
+        assert_eq!(tracks[0].get_observations(0).unwrap().len(), 3);
+        assert_eq!(tracks[0].get_merge_history(), &vec![1, 2]);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::{
+    ObservationAttributes, ObservationMetric, Track, TrackAttributes, TrackId, TrackUpdate,
+};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::vec::IntoIter;
+
+/// Read-only copy of the track state without the observations
+///
+#[derive(Debug, Clone)]
+pub struct TrackView<TA> {
+    pub track_id: TrackId,
+    pub attributes: TA,
+    /// the number of the observations of every feature class
+    pub observation_counts: HashMap<u64, usize>,
+    pub last_update: TrackUpdate,
+}
+
+impl<TA> TrackView<TA> {
+    pub(crate) fn new<M, OA, N>(track: &Track<TA, M, OA, N>) -> Self
+    where
+        TA: TrackAttributes<TA, OA>,
+        M: ObservationMetric<TA, OA>,
+        OA: ObservationAttributes,
+        N: ChangeNotifier,
+    {
+        Self {
+            track_id: track.track_id,
+            attributes: track.attributes.clone(),
+            observation_counts: track
+                .observations
+                .iter()
+                .map(|(feature_class, observations)| (*feature_class, observations.len()))
+                .collect(),
+            last_update: *track.get_last_update(),
+        }
+    }
+}
+
+/// Iterator over the views of the tracks in the store
+///
+/// The shards are locked one by one when the iterator reaches them, the views of the shard are copied and
+/// the shard is released, so the iterator doesn't block the store while the views are processed.
+///
+pub struct TrackViews<'a, TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    store: &'a TrackStore<TA, M, OA, N>,
+    shard: usize,
+    current: IntoIter<TrackView<TA>>,
+    _phantom: PhantomData<(M, OA, N)>,
+}
+
+impl<'a, TA, M, OA, N> Iterator for TrackViews<'a, TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    type Item = TrackView<TA>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(view) = self.current.next() {
+                return Some(view);
+            }
+            let shard = self.store.stores.get(self.shard)?;
+            self.shard += 1;
+            self.current = shard
+                .read()
+                .unwrap()
+                .values()
+                .map(TrackView::new)
+                .collect::<Vec<_>>()
+                .into_iter();
+        }
+    }
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Visits all the tracks of the store without removing them
+    ///
+    /// The visitor gets the shard index and the track, the shard is locked while its tracks are visited.
+    ///
+    pub fn visit_tracks<F>(&self, mut visitor: F)
+    where
+        F: FnMut(usize, &Track<TA, M, OA, N>),
+    {
+        for (bucket, shard) in self.stores.iter().enumerate() {
+            for track in shard.read().unwrap().values() {
+                visitor(bucket / self.buckets, track);
+            }
+        }
+    }
+
+    /// Iterates over the views of the tracks of the store, look at [TrackViews](TrackViews)
+    ///
+    pub fn track_views(&self) -> TrackViews<'_, TA, M, OA, N> {
+        TrackViews {
+            store: self,
+            shard: 0,
+            current: Vec::default().into_iter(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::store::TrackStore;
+    use crate::track::notify::NoopNotifier;
+
+    #[test]
+    fn views() {
+        let mut store = TrackStore::new(UnboundMetric, UnboundAttrs, NoopNotifier, 3);
+        for i in 0..5 {
+            store
+                .add(i, 0, Some(1.0), Some(vec2(i as f32, 0.0)), None)
+                .unwrap();
+        }
+        store
+            .add(2, 1, Some(1.0), Some(vec2(0.0, 0.0)), None)
+            .unwrap();
+
+        let mut views = store.track_views().collect::<Vec<_>>();
+        views.sort_by_key(|v| v.track_id);
+        assert_eq!(views.len(), 5);
+        assert_eq!(views[2].observation_counts.len(), 2);
+        assert_eq!(views[3].observation_counts[&0], 1);
+
+        let mut visited = Vec::new();
+        store.visit_tracks(|shard, track| {
+            assert_eq!(store.get_executor(track.get_track_id()), shard);
+            visited.push(track.get_track_id());
+        });
+        visited.sort();
+        assert_eq!(visited, vec![0, 1, 2, 3, 4]);
+        assert_eq!(store.shard_stats().iter().sum::<usize>(), 5);
+    }
+}
@@ -1,14 +1,44 @@
+pub mod auction;
 pub mod best;
+pub mod borda;
+pub mod bytetrack;
+pub mod cascade;
+pub mod channel;
+pub mod class_aware;
+pub mod composite;
+pub mod diagnostics;
+pub mod exclusive;
+pub mod gating;
+pub mod greedy;
+pub mod hungarian;
+pub mod median;
+pub mod nearest;
+pub mod reciprocal;
+pub mod registry;
+pub mod rrf;
+pub mod softmax;
 pub mod topn;
+pub mod weighted;
 
-use crate::track::{ObservationAttributes, ObservationMetricOk};
-use std::collections::HashMap;
+use crate::track::{
+    MetricPolarity, NonFinitePolicy, ObservationAttributes, ObservationMetricOk, TrackId,
+};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
 
 /// Trait to implement distance voting engines.
 ///
 /// Distance voting engine is used to select winning tracks among distances
 /// resulted from the distance calculation.
 ///
+/// NaN distances are treated as missing ones: they never vote and never panic the engine. Engines
+/// order winners with equal weights deterministically, the lowest winner track id goes first.
+///
+/// The engines that compare the distances accept the [polarity](crate::track::ObservationMetric::polarity) of the
+/// metric with `with_polarity`, so the similarities are voted with larger values as better ones. The engines
+/// combining other engines (e.g. [CompositeVoting](composite::CompositeVoting)) rely on the polarity of the
+/// engines they combine.
+///
 pub trait Voting<OA>
 where
     OA: ObservationAttributes,
@@ -23,7 +53,173 @@ where
     /// # Return
     /// Map of track_ids -> Vec<Result>
     ///
-    fn winners<T>(&self, distances: T) -> HashMap<u64, Vec<Self::WinnerObject>>
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<Self::WinnerObject>>
     where
         T: IntoIterator<Item = ObservationMetricOk<OA>>;
+
+    /// Method that selects winning tracks among the tracks that are not excluded
+    ///
+    /// The distances to the excluded tracks (e.g. already matched in the frame or known false positives)
+    /// are skipped lazily, before the engine sees them.
+    ///
+    /// # Arguments
+    /// * `distances` - distances resulted from the distance calculation.
+    /// * `excluded` - ids of tracks that cannot win.
+    ///
+    /// # Return
+    /// Map of track_ids -> Vec<Result>
+    ///
+    fn winners_excluding<T>(
+        &self,
+        distances: T,
+        excluded: &HashSet<TrackId>,
+    ) -> HashMap<TrackId, Vec<Self::WinnerObject>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        self.winners(distances.into_iter().filter(|e| !excluded.contains(&e.to)))
+    }
+
+    /// Method that selects winning tracks after the NaN and infinite distances are handled with the policy
+    ///
+    /// # Arguments
+    /// * `distances` - distances resulted from the distance calculation.
+    /// * `policy` - the policy for the non-finite distances
+    /// * `polarity` - the polarity of the distances
+    ///
+    /// # Return
+    /// Map of track_ids -> Vec<Result> or [Errors::NonFiniteDistance](crate::Errors::NonFiniteDistance) when the
+    /// policy rejects the distances
+    ///
+    fn winners_with_policy<T>(
+        &self,
+        distances: T,
+        policy: NonFinitePolicy,
+        polarity: MetricPolarity,
+    ) -> Result<HashMap<TrackId, Vec<Self::WinnerObject>>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let distances = policy.apply(distances.into_iter().collect(), polarity)?;
+        Ok(self.winners(distances))
+    }
+}
+
+/// Maps the feature distances to the smaller-is-better scale of the polarity
+///
+/// The engines supporting [MetricPolarity::Similarity](MetricPolarity::Similarity) vote over the oriented
+/// distances with the oriented `max_distance`, so the same logic serves the distances and the similarities.
+///
+pub(crate) fn oriented<OA, T>(
+    distances: T,
+    polarity: MetricPolarity,
+) -> impl Iterator<Item = ObservationMetricOk<OA>>
+where
+    OA: ObservationAttributes,
+    T: IntoIterator<Item = ObservationMetricOk<OA>>,
+{
+    distances.into_iter().map(move |mut e| {
+        e.feature_distance = e.feature_distance.map(|d| polarity.orient(d));
+        e
+    })
+}
+
+/// Trait to implement voting engines that process batches of candidates.
+///
+/// Every batch element holds the candidate id and the distances calculated for the candidate,
+/// so the engine knows which candidate produced which distances even when `from` fields of the distances
+/// are not unique across the batch. The default implementation attributes the distances to their
+/// candidates and votes over the whole batch at once, so assignment engines (e.g. [HungarianVoting](hungarian::HungarianVoting))
+/// solve the joint problem.
+///
+pub trait BatchVoting<OA>: Voting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Method that selects winning tracks for the batch of candidates
+    ///
+    /// # Arguments
+    /// * `batch` - pairs of candidate id and distances calculated for the candidate.
+    ///
+    /// # Return
+    /// Map of candidate_ids -> Vec<Result>
+    ///
+    fn winners_batch(
+        &self,
+        batch: &[(TrackId, &[ObservationMetricOk<OA>])],
+    ) -> HashMap<TrackId, Vec<Self::WinnerObject>> {
+        self.winners(batch.iter().flat_map(|(candidate, distances)| {
+            distances.iter().map(|d| ObservationMetricOk {
+                from: *candidate,
+                ..d.clone()
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::{MetricPolarity, NonFinitePolicy, ObservationMetricOk, TrackId};
+    use crate::voting::topn::TopNVoting;
+    use crate::voting::Voting;
+    use std::collections::HashSet;
+
+    #[test]
+    fn winners_with_policy() {
+        let v: TopNVoting<()> = TopNVoting::new(1, f32::MAX, 1);
+        let distances = || {
+            [
+                ObservationMetricOk::new(0, 1, None, Some(f32::NAN)),
+                ObservationMetricOk::new(0, 2, None, Some(0.5)),
+                ObservationMetricOk::new(3, 4, None, Some(f32::INFINITY)),
+            ]
+        };
+        let polarity = MetricPolarity::Distance;
+
+        assert!(v
+            .winners_with_policy(distances(), NonFinitePolicy::Reject, polarity)
+            .is_err());
+
+        let res = v
+            .winners_with_policy(distances(), NonFinitePolicy::Skip, polarity)
+            .unwrap();
+        assert_eq!(res[&0][0].winner_track, 2);
+        assert!(!res.contains_key(&3));
+
+        // the worst distance still passes the unbounded threshold, but loses to the finite one
+        let res = v
+            .winners_with_policy(distances(), NonFinitePolicy::TreatAsMax, polarity)
+            .unwrap();
+        assert_eq!(res[&3][0].winner_track, 4);
+
+        let treated = NonFinitePolicy::TreatAsMax
+            .apply(distances().to_vec(), MetricPolarity::Similarity)
+            .unwrap();
+        assert_eq!(treated[0].feature_distance, Some(f32::MIN));
+        assert_eq!(treated[1].feature_distance, Some(0.5));
+    }
+
+    #[test]
+    fn winners_excluding() {
+        let v: TopNVoting<()> = TopNVoting::new(1, 1.0, 1);
+        let distances = || {
+            [
+                ObservationMetricOk::new(0, 1, None, Some(0.1)),
+                ObservationMetricOk::new(0, 2, None, Some(0.5)),
+                ObservationMetricOk::new(0, 3, None, Some(0.9)),
+            ]
+        };
+
+        let winner = |excluded: HashSet<TrackId>| {
+            v.winners_excluding(distances(), &excluded)
+                .remove(&0)
+                .unwrap()[0]
+                .winner_track
+        };
+        assert_eq!(winner(HashSet::default()), 1);
+        assert_eq!(winner(HashSet::from([1])), 2);
+        assert!(v
+            .winners_excluding(distances(), &HashSet::from([1, 2, 3]))
+            .is_empty());
+    }
 }
@@ -0,0 +1,378 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{oriented, BatchVoting, Voting};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Default final epsilon of the auction
+///
+pub const DEFAULT_AUCTION_EPSILON: f32 = 0.0001;
+
+/// Default epsilon scaling factor of the auction
+///
+pub const DEFAULT_AUCTION_SCALING: f32 = 4.0;
+
+/// Max number of epsilon scaling phases, the largest epsilons are dropped when the scaling needs more phases
+///
+pub const MAX_AUCTION_PHASES: usize = 64;
+
+/// Bertsekas' auction algorithm assignment voting engine.
+///
+/// An alternative to [HungarianVoting](crate::voting::hungarian::HungarianVoting) for very large sparse
+/// bipartite problems. Query tracks bid for candidate tracks, the price of the candidate grows with every bid,
+/// so every candidate track wins for at most one query track. A query track stays unmatched when no candidate
+/// gives it a positive profit.
+///
+/// The total weight of the result is within `queries * epsilon` from the optimal one. Epsilon scaling starts the
+/// auction with a large epsilon and divides it by the scaling factor until the final epsilon is reached, larger final
+/// epsilon values give faster, but less optimal results.
+///
+/// It calculates winners as:
+/// 1. removes all distances that are greater than threshold
+/// 2. takes the minimal distance for every (query, candidate) pair as the pair distance
+/// 3. runs the auction with the benefits `max_distance - distance` and returns the assigned pairs
+///
+/// Queries that are not matched with any candidate are not present in the result. The weight of the
+/// winner is `max_distance - distance`.
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities, `max_distance` is
+/// the min similarity permitted to participate, the benefits are `similarity - max_distance` and the weight of the
+/// winner is `similarity - max_distance`.
+///
+pub struct AuctionVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    max_distance: f32,
+    polarity: MetricPolarity,
+    epsilon: f32,
+    scaling: f32,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> AuctionVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine with the default epsilon [DEFAULT_AUCTION_EPSILON](DEFAULT_AUCTION_EPSILON) and
+    /// the default scaling factor [DEFAULT_AUCTION_SCALING](DEFAULT_AUCTION_SCALING)
+    ///
+    /// # Arguments
+    /// * `max_distance` - max distance permitted to participate
+    ///
+    pub fn new(max_distance: f32) -> Self {
+        Self::with_epsilon(
+            max_distance,
+            DEFAULT_AUCTION_EPSILON,
+            DEFAULT_AUCTION_SCALING,
+        )
+    }
+
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `max_distance` - max distance permitted to participate, must be finite
+    /// * `epsilon` - final bid increment, defines the optimality of the result, must be finite and positive
+    /// * `scaling` - epsilon scaling factor, values not greater than `1.0` disable the scaling, must be finite
+    ///
+    pub fn with_epsilon(max_distance: f32, epsilon: f32, scaling: f32) -> Self {
+        assert!(max_distance.is_finite(), "Max distance must be finite");
+        assert!(
+            epsilon.is_finite() && epsilon > 0.0,
+            "Epsilon must be finite and positive"
+        );
+        assert!(scaling.is_finite(), "Scaling must be finite");
+        Self {
+            max_distance,
+            polarity: MetricPolarity::default(),
+            epsilon,
+            scaling,
+            _phony: PhantomData,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Epsilons of the scaling phases in the decreasing order, the largest one is below the largest benefit
+    /// of the instance, at most [MAX_AUCTION_PHASES](MAX_AUCTION_PHASES) phases are returned
+    ///
+    fn epsilons(&self, max_benefit: f64) -> Vec<f64> {
+        let epsilon = self.epsilon as f64;
+        let scaling = self.scaling as f64;
+        let mut epsilons = vec![epsilon];
+        if scaling > 1.0 {
+            let mut current = epsilon * scaling;
+            while current < max_benefit && epsilons.len() < MAX_AUCTION_PHASES {
+                epsilons.push(current);
+                current *= scaling;
+            }
+            epsilons.reverse();
+        }
+        epsilons
+    }
+}
+
+impl<OA> Voting<OA> for AuctionVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut queries: Vec<TrackId> = Vec::new();
+        let mut queries_index: HashMap<TrackId, usize> = HashMap::new();
+        let mut tracks: Vec<TrackId> = Vec::new();
+        let mut tracks_index: HashMap<TrackId, usize> = HashMap::new();
+        let mut pairs: HashMap<(usize, usize), f32> = HashMap::new();
+        let max_distance = self.polarity.orient(self.max_distance);
+
+        for ObservationMetricOk {
+            from,
+            to,
+            attribute_metric: _,
+            feature_distance,
+            weight: _,
+            stamp: _,
+            query_observation: _,
+        } in oriented(distances, self.polarity)
+        {
+            let dist = match feature_distance {
+                Some(d) if d <= max_distance => d,
+                _ => continue,
+            };
+
+            let row = *queries_index.entry(from).or_insert_with(|| {
+                queries.push(from);
+                queries.len() - 1
+            });
+
+            let col = *tracks_index.entry(to).or_insert_with(|| {
+                tracks.push(to);
+                tracks.len() - 1
+            });
+
+            pairs
+                .entry((row, col))
+                .and_modify(|d| *d = d.min(dist))
+                .or_insert(dist);
+        }
+
+        if queries.is_empty() {
+            return HashMap::default();
+        }
+
+        let mut options: Vec<Vec<(usize, f64)>> = vec![Vec::new(); queries.len()];
+        let mut max_benefit = 0.0_f64;
+        for ((row, col), dist) in &pairs {
+            let benefit = (max_distance - dist) as f64;
+            max_benefit = max_benefit.max(benefit);
+            options[*row].push((*col, benefit));
+        }
+        for opts in options.iter_mut() {
+            opts.sort_by_key(|(col, _)| *col);
+        }
+
+        let epsilons = self.epsilons(max_benefit);
+        let final_epsilon = *epsilons.last().unwrap();
+        let mut prices = vec![0.0_f64; tracks.len()];
+        let mut assignment = Vec::default();
+        for epsilon in epsilons {
+            assignment = auction(&options, &mut prices, epsilon);
+        }
+
+        // prices carried from the previous scaling phases may keep unassigned tracks expensive,
+        // in that case the result is not guaranteed to be near-optimal and the auction is repeated
+        if !balanced(&assignment, &prices) {
+            prices.iter_mut().for_each(|p| *p = 0.0);
+            assignment = auction(&options, &mut prices, final_epsilon);
+        }
+
+        assignment
+            .into_iter()
+            .enumerate()
+            .flat_map(|(row, col)| {
+                let col = col?;
+                let dist = pairs.get(&(row, col))?;
+                let (query, winner) = (queries[row], tracks[col]);
+                Some((
+                    query,
+                    vec![TopNVotingElt::new(
+                        query,
+                        winner,
+                        (max_distance - dist) as f64,
+                    )],
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Runs a single auction phase, returns the track assigned to every query
+///
+fn auction(options: &[Vec<(usize, f64)>], prices: &mut [f64], epsilon: f64) -> Vec<Option<usize>> {
+    let mut owners: Vec<Option<usize>> = vec![None; prices.len()];
+    let mut assignment: Vec<Option<usize>> = vec![None; options.len()];
+    let mut unassigned = (0..options.len()).rev().collect::<Vec<_>>();
+
+    while let Some(row) = unassigned.pop() {
+        // "no match" is the outside option of zero value
+        let (mut best, mut best_value, mut second_value) = (None, 0.0_f64, 0.0_f64);
+        for (col, benefit) in &options[row] {
+            let value = benefit - prices[*col];
+            if value > best_value {
+                second_value = best_value;
+                best_value = value;
+                best = Some(*col);
+            } else if value > second_value {
+                second_value = value;
+            }
+        }
+
+        let best = match best {
+            Some(best) => best,
+            None => continue,
+        };
+
+        prices[best] += best_value - second_value + epsilon;
+        if let Some(previous) = owners[best].replace(row) {
+            assignment[previous] = None;
+            unassigned.push(previous);
+        }
+        assignment[row] = Some(best);
+    }
+
+    assignment
+}
+
+/// Checks that unassigned tracks are not more expensive than assigned ones
+///
+fn balanced(assignment: &[Option<usize>], prices: &[f64]) -> bool {
+    let mut assigned = vec![false; prices.len()];
+    let mut lambda = f64::INFINITY;
+    for a in assignment {
+        match a {
+            Some(col) => {
+                assigned[*col] = true;
+                lambda = lambda.min(prices[*col]);
+            }
+            None => lambda = lambda.min(0.0),
+        }
+    }
+    prices
+        .iter()
+        .zip(assigned)
+        .all(|(p, assigned)| assigned || *p <= lambda)
+}
+
+impl<OA> BatchVoting<OA> for AuctionVoting<OA> where OA: ObservationAttributes {}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::auction::{AuctionVoting, MAX_AUCTION_PHASES};
+    use crate::track::{MetricPolarity, ObservationMetricOk, TrackId};
+    use crate::voting::hungarian::HungarianVoting;
+    use crate::voting::topn::TopNVotingElt;
+    use crate::voting::Voting;
+    use std::collections::HashMap;
+
+    fn winners(res: &HashMap<TrackId, Vec<TopNVotingElt>>) -> Vec<(TrackId, TrackId)> {
+        let mut w = res
+            .values()
+            .flatten()
+            .map(|e| (e.query_track, e.winner_track))
+            .collect::<Vec<_>>();
+        w.sort();
+        w
+    }
+
+    #[test]
+    fn optimal_assignment() {
+        let v: AuctionVoting<()> = AuctionVoting::new(1.0);
+        let res = v.winners([
+            ObservationMetricOk::new(1, 20, None, Some(0.1)),
+            ObservationMetricOk::new(1, 30, None, Some(0.2)),
+            ObservationMetricOk::new(2, 20, None, Some(0.15)),
+            ObservationMetricOk::new(2, 30, None, Some(0.9)),
+            ObservationMetricOk::new(3, 40, None, Some(1.5)),
+        ]);
+        assert_eq!(winners(&res), vec![(1, 30), (2, 20)]);
+
+        let res = v.winners([]);
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn same_as_hungarian() {
+        let distances = (0..20)
+            .flat_map(|q| {
+                (0..15).map(move |t| {
+                    let d = ((q * 7 + t * 13) % 17) as f32 / 17.0;
+                    ObservationMetricOk::new(q, 100 + t, None, Some(d))
+                })
+            })
+            .collect::<Vec<ObservationMetricOk<()>>>();
+
+        let total = |res: HashMap<TrackId, Vec<TopNVotingElt>>| {
+            res.values().flatten().map(|e| e.weight).sum::<f64>()
+        };
+
+        let auction = total(AuctionVoting::new(0.8).winners(distances.clone()));
+        let hungarian = total(HungarianVoting::new(0.8).winners(distances.clone()));
+        assert!((auction - hungarian).abs() < 20.0 * 0.0001 + 1e-4);
+
+        let coarse = total(AuctionVoting::with_epsilon(0.8, 0.05, 1.0).winners(distances));
+        assert!(hungarian - coarse <= 20.0 * 0.05 + 1e-4);
+    }
+
+    #[test]
+    fn bounded_phases() {
+        let v: AuctionVoting<()> = AuctionVoting::with_epsilon(f32::MAX, 1e-6, 1.0001);
+        let epsilons = v.epsilons(f32::MAX as f64);
+        assert_eq!(epsilons.len(), MAX_AUCTION_PHASES);
+        assert_eq!(*epsilons.last().unwrap(), 1e-6_f32 as f64);
+
+        let res = v.winners([
+            ObservationMetricOk::new(1, 20, None, Some(0.1)),
+            ObservationMetricOk::new(2, 20, None, Some(0.2)),
+        ]);
+        assert_eq!(res.len(), 1);
+
+        // the scaling starts from the largest benefit of the instance, not from the max distance
+        let v: AuctionVoting<()> = AuctionVoting::new(f32::MAX);
+        assert_eq!(v.epsilons(1.0).len(), 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "Max distance must be finite")]
+    fn infinite_max_distance() {
+        let _: AuctionVoting<()> = AuctionVoting::new(f32::INFINITY);
+    }
+
+    #[test]
+    #[should_panic(expected = "Epsilon must be finite and positive")]
+    fn nan_epsilon() {
+        let _: AuctionVoting<()> = AuctionVoting::with_epsilon(1.0, f32::NAN, 4.0);
+    }
+
+    #[test]
+    fn similarity_assignment() {
+        let v: AuctionVoting<()> =
+            AuctionVoting::new(0.5).with_polarity(MetricPolarity::Similarity);
+        let res = v.winners([
+            ObservationMetricOk::new(1, 20, None, Some(0.9)),
+            ObservationMetricOk::new(1, 30, None, Some(0.8)),
+            ObservationMetricOk::new(2, 20, None, Some(0.85)),
+            ObservationMetricOk::new(2, 30, None, Some(0.1)),
+            ObservationMetricOk::new(3, 30, None, Some(0.4)),
+        ]);
+        assert_eq!(winners(&res), vec![(1, 30), (2, 20)]);
+    }
+}
@@ -1,6 +1,6 @@
-use crate::track::{ObservationAttributes, ObservationMetricOk};
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
 use crate::voting::topn::TopNVotingElt;
-use crate::voting::Voting;
+use crate::voting::{oriented, BatchVoting, Voting};
 use itertools::Itertools;
 use log::debug;
 use std::collections::{HashMap, HashSet};
@@ -15,11 +15,15 @@ use std::marker::PhantomData;
 /// 4. sorts groups by frequency decreasingly
 /// 5. returns TopN
 ///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities, `max_distance` is
+/// the min similarity permitted to participate and the more similar votes weigh more.
+///
 pub struct BestFitVoting<OA>
 where
     OA: ObservationAttributes,
 {
     max_distance: f32,
+    polarity: MetricPolarity,
     min_votes: usize,
     _phony: PhantomData<OA>,
 }
@@ -37,10 +41,18 @@ where
     pub fn new(max_distance: f32, min_votes: usize) -> Self {
         Self {
             max_distance,
+            polarity: MetricPolarity::default(),
             min_votes,
             _phony: PhantomData,
         }
     }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
 }
 
 impl<OA> Voting<OA> for BestFitVoting<OA>
@@ -49,19 +61,22 @@ where
 {
     type WinnerObject = TopNVotingElt;
 
-    fn winners<T>(&self, distances: T) -> HashMap<u64, Vec<TopNVotingElt>>
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
     where
         T: IntoIterator<Item = ObservationMetricOk<OA>>,
     {
-        let mut max_dist = -1.0_f32;
-        let mut candidates: Vec<_> = distances
-            .into_iter()
+        let max_distance = self.polarity.orient(self.max_distance);
+        let mut max_dist = f32::MIN;
+        let mut candidates: Vec<_> = oriented(distances, self.polarity)
             .filter(
                 |ObservationMetricOk {
                      from: q,
                      to: w,
                      attribute_metric: _f_attr_dist,
                      feature_distance: feat_dist,
+                     weight: _,
+                     stamp: _,
+                     query_observation: _,
                  }| {
                     debug!(
                         "Raw | Src: {:#?}, Dst: {:#?}, Metric: {:#?}",
@@ -72,7 +87,7 @@ where
                             if max_dist < *e {
                                 max_dist = *e;
                             }
-                            *e <= self.max_distance
+                            *e <= max_distance
                         }
                         _ => false,
                     }
@@ -84,7 +99,10 @@ where
                      to: dest_track,
                      attribute_metric: _,
                      feature_distance: dist,
-                 }| { ((src_track, dest_track), dist.unwrap()) },
+                     weight,
+                     stamp: _,
+                     query_observation: _,
+                 }| { ((src_track, dest_track), (dist.unwrap(), weight)) },
             )
             .into_group_map()
             .into_iter()
@@ -94,7 +112,10 @@ where
                     "Group | Src: {:#?}, Dst: {:#?}, Dist: {:#?}",
                     src_track, dest_track, &dists
                 );
-                let weight = dists.into_iter().map(|d| (max_dist - d) as f64).sum();
+                let weight = dists
+                    .into_iter()
+                    .map(|(d, w)| (max_dist - d) as f64 * w as f64)
+                    .sum();
                 TopNVotingElt {
                     query_track: src_track,
                     winner_track: dest_track,
@@ -103,11 +124,16 @@ where
             })
             .collect::<Vec<_>>();
 
-        candidates.sort_by(|e1, e2| e2.weight.partial_cmp(&e1.weight).unwrap());
+        candidates.sort_by(|e1, e2| {
+            e2.weight
+                .total_cmp(&e1.weight)
+                .then(e1.query_track.cmp(&e2.query_track))
+                .then(e1.winner_track.cmp(&e2.winner_track))
+        });
 
         debug!("Candidates: {:#?}", &candidates);
 
-        let mut results: HashSet<u64> = HashSet::new();
+        let mut results: HashSet<TrackId> = HashSet::new();
 
         for c in &mut candidates {
             let key = c.query_track;
@@ -127,3 +153,5 @@ where
         res
     }
 }
+
+impl<OA> BatchVoting<OA> for BestFitVoting<OA> where OA: ObservationAttributes {}
@@ -0,0 +1,266 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{oriented, Voting};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Ranks candidates for every observation of the query tracks within a single ballot (distance set).
+///
+/// For every (query observation, candidate) pair the closest distance passing `max_distance` in the polarity sense
+/// is used, candidates are sorted from the closest one. Returns `(query, query observation) -> [candidate]` ordered
+/// by rank.
+///
+pub(crate) fn rank_candidates<OA, T>(
+    distances: T,
+    max_distance: f32,
+    polarity: MetricPolarity,
+) -> HashMap<(TrackId, usize), Vec<TrackId>>
+where
+    OA: ObservationAttributes,
+    T: IntoIterator<Item = ObservationMetricOk<OA>>,
+{
+    let mut pairs: HashMap<(TrackId, usize), HashMap<TrackId, f32>> = HashMap::new();
+    let max_distance = polarity.orient(max_distance);
+    for ObservationMetricOk {
+        from,
+        to,
+        attribute_metric: _,
+        feature_distance,
+        weight: _,
+        stamp: _,
+        query_observation,
+    } in oriented(distances, polarity)
+    {
+        if let Some(dist) = feature_distance.filter(|d| *d <= max_distance) {
+            pairs
+                .entry((from, query_observation))
+                .or_default()
+                .entry(to)
+                .and_modify(|d| *d = d.min(dist))
+                .or_insert(dist);
+        }
+    }
+
+    pairs
+        .into_iter()
+        .map(|(query, candidates)| {
+            let mut candidates = candidates.into_iter().collect::<Vec<_>>();
+            candidates.sort_by(|(lt, ld), (rt, rd)| ld.total_cmp(rd).then(lt.cmp(rt)));
+            (query, candidates.into_iter().map(|(t, _)| t).collect())
+        })
+        .collect()
+}
+
+/// Sorts accumulated scores decreasingly and keeps TopN for every query track
+///
+pub(crate) fn top_scores(
+    scores: HashMap<TrackId, HashMap<TrackId, f64>>,
+    topn: usize,
+) -> HashMap<TrackId, Vec<TopNVotingElt>> {
+    scores
+        .into_iter()
+        .map(|(query, candidates)| {
+            let mut winners = candidates
+                .into_iter()
+                .map(|(w, score)| TopNVotingElt::new(query, w, score))
+                .collect::<Vec<_>>();
+            winners.sort_by(|l, r| {
+                r.weight
+                    .total_cmp(&l.weight)
+                    .then(l.winner_track.cmp(&r.winner_track))
+            });
+            winners.truncate(topn);
+            (query, winners)
+        })
+        .collect()
+}
+
+/// Borda-count rank aggregation voting engine.
+///
+/// Every observation of a query track ranks the candidates by the distances calculated for it, look at
+/// [query_observation](ObservationMetricOk::query_observation). The candidate ranked `r` (zero-based) among `n`
+/// candidates receives `n - r` points. Points are summed across the observations and TopN winners by total
+/// score are returned. Since only ranks are used, observations with very different distance scales can be
+/// combined safely.
+///
+/// [Voting::winners](Voting::winners) ranks the candidates for every query observation of the passed distances, use
+/// [BordaVoting::winners_ballots](BordaVoting::winners_ballots) to aggregate the ranks across several distance sets
+/// as well, e.g. calculated for different feature classes.
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities, `max_distance` is
+/// the min similarity permitted to participate and the most similar candidates get the top ranks.
+///
+pub struct BordaVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    topn: usize,
+    max_distance: f32,
+    polarity: MetricPolarity,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> BordaVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance permitted to participate
+    ///
+    pub fn new(topn: usize, max_distance: f32) -> Self {
+        Self {
+            topn,
+            max_distance,
+            polarity: MetricPolarity::default(),
+            _phony: PhantomData,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Aggregates ranks across several ballots
+    ///
+    /// # Arguments
+    /// * `ballots` - distance sets, the candidates are ranked for every query observation of every set independently
+    ///
+    pub fn winners_ballots<B, T>(&self, ballots: B) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        B: IntoIterator<Item = T>,
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut scores: HashMap<TrackId, HashMap<TrackId, f64>> = HashMap::new();
+        for ballot in ballots {
+            for ((query, _), ranked) in rank_candidates(ballot, self.max_distance, self.polarity) {
+                let n = ranked.len();
+                let query_scores = scores.entry(query).or_default();
+                for (rank, w) in ranked.into_iter().enumerate() {
+                    *query_scores.entry(w).or_default() += (n - rank) as f64;
+                }
+            }
+        }
+        top_scores(scores, self.topn)
+    }
+}
+
+impl<OA> Voting<OA> for BordaVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        self.winners_ballots([distances])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::borda::BordaVoting;
+    use crate::track::{MetricPolarity, ObservationMetricOk};
+    use crate::voting::topn::TopNVotingElt;
+    use crate::voting::Voting;
+    use std::collections::HashMap;
+
+    #[test]
+    fn single_ballot() {
+        let v: BordaVoting<()> = BordaVoting::new(2, 1.0);
+        let res = v.winners([
+            ObservationMetricOk::new(0, 1, None, Some(0.3)),
+            ObservationMetricOk::new(0, 2, None, Some(0.1)),
+            ObservationMetricOk::new(0, 2, None, Some(0.9)),
+            ObservationMetricOk::new(0, 3, None, Some(0.2)),
+            ObservationMetricOk::new(0, 4, None, Some(1.2)),
+        ]);
+        assert_eq!(
+            res,
+            HashMap::from([(
+                0,
+                vec![TopNVotingElt::new(0, 2, 3.0), TopNVotingElt::new(0, 3, 2.0)]
+            )])
+        );
+    }
+
+    #[test]
+    fn query_observations() {
+        let v: BordaVoting<()> = BordaVoting::new(3, 1.0);
+        // the closest candidate of the every observation is not the winner
+        let res = v.winners([
+            ObservationMetricOk::new(0, 1, None, Some(0.1)),
+            ObservationMetricOk::new(0, 2, None, Some(0.2)),
+            ObservationMetricOk::new(0, 3, None, Some(0.3)),
+            ObservationMetricOk::new(0, 1, None, Some(0.3)).with_query_observation(1),
+            ObservationMetricOk::new(0, 2, None, Some(0.1)).with_query_observation(1),
+            ObservationMetricOk::new(0, 3, None, Some(0.2)).with_query_observation(1),
+            ObservationMetricOk::new(0, 1, None, Some(0.9)).with_query_observation(2),
+            ObservationMetricOk::new(0, 2, None, Some(0.5)).with_query_observation(2),
+            ObservationMetricOk::new(0, 3, None, Some(0.1)).with_query_observation(2),
+        ]);
+        assert_eq!(
+            res,
+            HashMap::from([(
+                0,
+                vec![
+                    TopNVotingElt::new(0, 2, 7.0),
+                    TopNVotingElt::new(0, 3, 6.0),
+                    TopNVotingElt::new(0, 1, 5.0)
+                ]
+            )])
+        );
+    }
+
+    #[test]
+    fn ballots_with_different_scales() {
+        let v: BordaVoting<()> = BordaVoting::new(3, 1000.0);
+        let res = v.winners_ballots([
+            vec![
+                ObservationMetricOk::new(0, 1, None, Some(0.1)),
+                ObservationMetricOk::new(0, 2, None, Some(0.2)),
+                ObservationMetricOk::new(0, 3, None, Some(0.3)),
+            ],
+            vec![
+                ObservationMetricOk::new(0, 1, None, Some(300.0)),
+                ObservationMetricOk::new(0, 2, None, Some(100.0)),
+                ObservationMetricOk::new(0, 3, None, Some(200.0)),
+            ],
+        ]);
+        assert_eq!(
+            res,
+            HashMap::from([(
+                0,
+                vec![
+                    TopNVotingElt::new(0, 2, 5.0),
+                    TopNVotingElt::new(0, 1, 4.0),
+                    TopNVotingElt::new(0, 3, 3.0)
+                ]
+            )])
+        );
+    }
+
+    #[test]
+    fn similarity_ranks() {
+        let v: BordaVoting<()> = BordaVoting::new(3, 0.5).with_polarity(MetricPolarity::Similarity);
+        let res = v.winners([
+            ObservationMetricOk::new(0, 1, None, Some(0.9)),
+            ObservationMetricOk::new(0, 2, None, Some(0.6)),
+            ObservationMetricOk::new(0, 3, None, Some(0.1)),
+        ]);
+        assert_eq!(
+            res,
+            HashMap::from([(
+                0,
+                vec![TopNVotingElt::new(0, 1, 2.0), TopNVotingElt::new(0, 2, 1.0)]
+            )])
+        );
+    }
+}
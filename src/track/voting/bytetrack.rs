@@ -0,0 +1,192 @@
+use crate::track::{ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::Voting;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+/// Two-stage ByteTrack-style association voting engine.
+///
+/// The query tracks are detections, their confidences are provided with the callback. It calculates winners as:
+/// 1. drops the distances of the detections with the confidence lower than `low_confidence`
+/// 2. associates the detections with the confidence not lower than `high_confidence` with the `first` engine
+/// 3. associates the remaining low-confidence detections with the `second` engine over the tracks that were
+///    not matched in the first stage
+///
+/// The matching thresholds of the stages are the thresholds of the `first` and `second` engines.
+///
+pub struct ByteTrackVoting<OA, F, S, C>
+where
+    OA: ObservationAttributes,
+    F: Voting<OA, WinnerObject = TopNVotingElt>,
+    S: Voting<OA, WinnerObject = TopNVotingElt>,
+    C: Fn(TrackId) -> f32,
+{
+    first: F,
+    second: S,
+    high_confidence: f32,
+    low_confidence: f32,
+    confidence: C,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA, F, S, C> ByteTrackVoting<OA, F, S, C>
+where
+    OA: ObservationAttributes,
+    F: Voting<OA, WinnerObject = TopNVotingElt>,
+    S: Voting<OA, WinnerObject = TopNVotingElt>,
+    C: Fn(TrackId) -> f32,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `first` - the engine that associates high-confidence detections
+    /// * `second` - the engine that associates low-confidence detections with the remaining tracks
+    /// * `high_confidence` - min confidence of detections that participate in the first stage
+    /// * `low_confidence` - min confidence of detections that participate in the second stage
+    /// * `confidence` - the callback that returns the confidence of the detection (query track)
+    ///
+    pub fn new(
+        first: F,
+        second: S,
+        high_confidence: f32,
+        low_confidence: f32,
+        confidence: C,
+    ) -> Self {
+        assert!(
+            low_confidence <= high_confidence,
+            "Low confidence threshold must not exceed high confidence threshold"
+        );
+        Self {
+            first,
+            second,
+            high_confidence,
+            low_confidence,
+            confidence,
+            _phony: PhantomData,
+        }
+    }
+
+    /// Selects winning tracks for both stages separately
+    ///
+    /// # Arguments
+    /// * `distances` - distances resulted from the distance calculation.
+    ///
+    /// # Return
+    /// Winners of the first stage and winners of the second stage
+    ///
+    pub fn winners_stages<T>(
+        &self,
+        distances: T,
+    ) -> (
+        HashMap<TrackId, Vec<TopNVotingElt>>,
+        HashMap<TrackId, Vec<TopNVotingElt>>,
+    )
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut confidences: HashMap<TrackId, f32> = HashMap::new();
+        let (high, low): (Vec<_>, Vec<_>) = distances
+            .into_iter()
+            .map(|e| {
+                let confidence = *confidences
+                    .entry(e.from)
+                    .or_insert_with(|| (self.confidence)(e.from));
+                (confidence, e)
+            })
+            .filter(|(confidence, _)| *confidence >= self.low_confidence)
+            .partition(|(confidence, _)| *confidence >= self.high_confidence);
+
+        let mut first = self.first.winners(high.into_iter().map(|(_, e)| e));
+        first.retain(|_, winners| !winners.is_empty());
+
+        let matched_tracks = first
+            .values()
+            .flatten()
+            .map(|e| e.winner_track)
+            .collect::<HashSet<_>>();
+
+        let mut second = self.second.winners(
+            low.into_iter()
+                .map(|(_, e)| e)
+                .filter(|e| !matched_tracks.contains(&e.to)),
+        );
+        second.retain(|_, winners| !winners.is_empty());
+
+        (first, second)
+    }
+}
+
+impl<OA, F, S, C> Voting<OA> for ByteTrackVoting<OA, F, S, C>
+where
+    OA: ObservationAttributes,
+    F: Voting<OA, WinnerObject = TopNVotingElt>,
+    S: Voting<OA, WinnerObject = TopNVotingElt>,
+    C: Fn(TrackId) -> f32,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let (mut first, second) = self.winners_stages(distances);
+        first.extend(second);
+        first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::bytetrack::ByteTrackVoting;
+    use crate::track::ObservationMetricOk;
+    use crate::voting::hungarian::HungarianVoting;
+    use crate::voting::Voting;
+
+    #[test]
+    fn two_stages() {
+        let v = ByteTrackVoting::new(
+            HungarianVoting::<()>::new(0.5),
+            HungarianVoting::<()>::new(0.8),
+            0.6,
+            0.1,
+            |query| match query {
+                1 => 0.9,
+                2 => 0.3,
+                3 => 0.4,
+                _ => 0.05,
+            },
+        );
+
+        let distances = || {
+            [
+                ObservationMetricOk::new(1, 10, None, Some(0.2)),
+                ObservationMetricOk::new(2, 10, None, Some(0.1)),
+                ObservationMetricOk::new(3, 20, None, Some(0.7)),
+                ObservationMetricOk::new(4, 30, None, Some(0.1)),
+            ]
+        };
+
+        let (first, second) = v.winners_stages(distances());
+        assert_eq!(
+            first[&1].iter().map(|e| e.winner_track).collect::<Vec<_>>(),
+            vec![10]
+        );
+        assert_eq!(second.len(), 1);
+        assert_eq!(
+            second[&3]
+                .iter()
+                .map(|e| e.winner_track)
+                .collect::<Vec<_>>(),
+            vec![20]
+        );
+
+        let mut winners = v
+            .winners(distances())
+            .values()
+            .flatten()
+            .map(|e| (e.query_track, e.winner_track))
+            .collect::<Vec<_>>();
+        winners.sort();
+        assert_eq!(winners, vec![(1, 10), (3, 20)]);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::hungarian::HungarianVoting;
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{BatchVoting, Voting};
+use std::collections::HashMap;
+
+/// DeepSORT matching cascade voting engine.
+///
+/// Candidate tracks are associated in the order of increasing time since their last update, so the tracks
+/// updated recently have priority over the tracks that were lost for a while. The time since update is provided
+/// with the callback.
+///
+/// It calculates winners as:
+/// 1. removes candidates which time since update is greater than `max_age`
+/// 2. for every level `0..=max_age` solves the assignment between the unmatched query tracks and the candidates
+///    with the time since update equal to the level with [HungarianVoting](HungarianVoting)
+///
+/// The weight of the winner is `max_distance - distance`, the polarity is handled like
+/// [HungarianVoting](HungarianVoting) does.
+///
+pub struct MatchingCascadeVoting<OA, A>
+where
+    OA: ObservationAttributes,
+    A: Fn(TrackId) -> usize,
+{
+    max_age: usize,
+    time_since_update: A,
+    level_engine: HungarianVoting<OA>,
+}
+
+impl<OA, A> MatchingCascadeVoting<OA, A>
+where
+    OA: ObservationAttributes,
+    A: Fn(TrackId) -> usize,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `max_distance` - max distance permitted to participate
+    /// * `max_age` - max time since update of the candidate tracks that participate
+    /// * `time_since_update` - the callback that returns the time since update of the candidate track
+    ///
+    pub fn new(max_distance: f32, max_age: usize, time_since_update: A) -> Self {
+        Self {
+            max_age,
+            time_since_update,
+            level_engine: HungarianVoting::new(max_distance),
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.level_engine = self.level_engine.with_polarity(polarity);
+        self
+    }
+}
+
+impl<OA, A> Voting<OA> for MatchingCascadeVoting<OA, A>
+where
+    OA: ObservationAttributes,
+    A: Fn(TrackId) -> usize,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut ages: HashMap<TrackId, usize> = HashMap::new();
+        let mut levels: Vec<Vec<ObservationMetricOk<OA>>> =
+            (0..=self.max_age).map(|_| Vec::new()).collect();
+        for e in distances {
+            let age = *ages
+                .entry(e.to)
+                .or_insert_with(|| (self.time_since_update)(e.to));
+            if age <= self.max_age {
+                levels[age].push(e);
+            }
+        }
+
+        let mut results: HashMap<TrackId, Vec<TopNVotingElt>> = HashMap::new();
+        for level in levels {
+            let unmatched = level
+                .into_iter()
+                .filter(|e| !results.contains_key(&e.from))
+                .collect::<Vec<_>>();
+            if unmatched.is_empty() {
+                continue;
+            }
+            results.extend(self.level_engine.winners(unmatched));
+        }
+
+        results
+    }
+}
+
+impl<OA, A> BatchVoting<OA> for MatchingCascadeVoting<OA, A>
+where
+    OA: ObservationAttributes,
+    A: Fn(TrackId) -> usize,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::cascade::MatchingCascadeVoting;
+    use crate::track::{ObservationMetricOk, TrackId};
+    use crate::voting::hungarian::HungarianVoting;
+    use crate::voting::topn::TopNVotingElt;
+    use crate::voting::Voting;
+    use std::collections::HashMap;
+
+    fn winners(v: &impl Voting<(), WinnerObject = TopNVotingElt>) -> Vec<(TrackId, TrackId)> {
+        let mut w = v
+            .winners([
+                ObservationMetricOk::new(1, 10, None, Some(0.3)),
+                ObservationMetricOk::new(1, 20, None, Some(0.1)),
+                ObservationMetricOk::new(2, 10, None, Some(0.2)),
+                ObservationMetricOk::new(2, 30, None, Some(0.1)),
+            ])
+            .values()
+            .flatten()
+            .map(|e| (e.query_track, e.winner_track))
+            .collect::<Vec<_>>();
+        w.sort();
+        w
+    }
+
+    #[test]
+    fn cascade_priority() {
+        let ages = HashMap::from([(10, 0), (20, 1), (30, 5)]);
+        let time_since_update = |track| ages[&track];
+
+        // the recently updated track is matched first
+        let v = MatchingCascadeVoting::<(), _>::new(1.0, 0, time_since_update);
+        assert_eq!(winners(&v), vec![(2, 10)]);
+
+        let v = MatchingCascadeVoting::<(), _>::new(1.0, 5, time_since_update);
+        assert_eq!(winners(&v), vec![(1, 20), (2, 10)]);
+
+        // without the cascade the globally optimal assignment is selected
+        assert_eq!(
+            winners(&HungarianVoting::<()>::new(1.0)),
+            vec![(1, 20), (2, 30)]
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::Voting;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Defines which distance of [ObservationMetricOk](ObservationMetricOk) is used for voting
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DistanceChannel {
+    /// `feature_distance` is used
+    Feature,
+    /// `attribute_metric` is used
+    Attribute,
+    /// weighted sum `attribute_weight * attribute_metric + feature_weight * feature_distance` is used,
+    /// both values must be present
+    Combined {
+        attribute_weight: f32,
+        feature_weight: f32,
+    },
+}
+
+impl DistanceChannel {
+    fn distance(
+        &self,
+        attribute_metric: Option<f32>,
+        feature_distance: Option<f32>,
+    ) -> Option<f32> {
+        match self {
+            DistanceChannel::Feature => feature_distance,
+            DistanceChannel::Attribute => attribute_metric,
+            DistanceChannel::Combined {
+                attribute_weight,
+                feature_weight,
+            } => Some(attribute_weight * attribute_metric? + feature_weight * feature_distance?),
+        }
+    }
+}
+
+/// TopN voting engine that can vote over the attribute metric channel.
+///
+/// Works for the observation attributes which metric object is a distance expressed with `f32`
+/// (e.g. `1 - IoU` for bounding boxes). The engine works like [TopNVoting](crate::voting::topn::TopNVoting),
+/// but the distance is selected with [DistanceChannel](DistanceChannel).
+///
+/// It calculates winners as:
+/// 1. selects the distance according to the channel, drops votes without it
+/// 2. removes all distances that are greater than threshold
+/// 3. groups remaining distances by (query, winner) track pairs, removes groups with less than `min_votes` votes
+/// 4. calculates the group weight as `sum(max_observed_distance - distance)`
+/// 5. sorts groups by weight decreasingly and returns TopN
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values of the selected channel are
+/// similarities, `max_distance` is the min similarity permitted to participate and the more similar votes weigh more.
+///
+pub struct ChannelTopNVoting<OA>
+where
+    OA: ObservationAttributes<MetricObject = f32>,
+{
+    topn: usize,
+    max_distance: f32,
+    polarity: MetricPolarity,
+    min_votes: usize,
+    channel: DistanceChannel,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> ChannelTopNVoting<OA>
+where
+    OA: ObservationAttributes<MetricObject = f32>,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance (in the selected channel) permitted to participate
+    /// * `min_votes` - minimal amount of votes required the track to participate
+    /// * `channel` - the distance used for voting
+    ///
+    pub fn new(topn: usize, max_distance: f32, min_votes: usize, channel: DistanceChannel) -> Self {
+        Self {
+            topn,
+            max_distance,
+            polarity: MetricPolarity::default(),
+            min_votes,
+            channel,
+            _phony: PhantomData,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+}
+
+impl<OA> Voting<OA> for ChannelTopNVoting<OA>
+where
+    OA: ObservationAttributes<MetricObject = f32>,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let max_distance = self.polarity.orient(self.max_distance);
+        let mut max_dist = f32::MIN;
+        let mut results: HashMap<TrackId, Vec<TopNVotingElt>> = HashMap::new();
+
+        distances
+            .into_iter()
+            .flat_map(
+                |ObservationMetricOk {
+                     from,
+                     to,
+                     attribute_metric,
+                     feature_distance,
+                     weight,
+                     stamp: _,
+                     query_observation: _,
+                 }| {
+                    let dist = self
+                        .polarity
+                        .orient(self.channel.distance(attribute_metric, feature_distance)?);
+                    max_dist = max_dist.max(dist);
+                    if dist <= max_distance {
+                        Some(((from, to), (dist, weight)))
+                    } else {
+                        None
+                    }
+                },
+            )
+            .into_group_map()
+            .into_iter()
+            .filter(|(_, dists)| dists.len() >= self.min_votes)
+            .for_each(|((q, w), dists)| {
+                let weight = dists
+                    .into_iter()
+                    .map(|(d, w)| (max_dist - d) as f64 * w as f64)
+                    .sum();
+                results
+                    .entry(q)
+                    .or_default()
+                    .push(TopNVotingElt::new(q, w, weight));
+            });
+
+        for winners in results.values_mut() {
+            winners.sort_by(|l, r| {
+                r.weight
+                    .total_cmp(&l.weight)
+                    .then(l.winner_track.cmp(&r.winner_track))
+            });
+            winners.truncate(self.topn);
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::channel::{ChannelTopNVoting, DistanceChannel};
+    use crate::track::{MetricPolarity, ObservationMetricOk, TrackId};
+    use crate::voting::Voting;
+
+    fn distances() -> Vec<ObservationMetricOk<f32>> {
+        vec![
+            ObservationMetricOk::new(0, 1, Some(0.1), Some(0.6)),
+            ObservationMetricOk::new(0, 2, Some(0.5), Some(0.1)),
+            ObservationMetricOk::new(0, 3, None, Some(0.2)),
+            ObservationMetricOk::new(0, 4, Some(0.3), None),
+        ]
+    }
+
+    fn winners(channel: DistanceChannel) -> Vec<TrackId> {
+        let v: ChannelTopNVoting<f32> = ChannelTopNVoting::new(5, 0.45, 1, channel);
+        v.winners(distances())
+            .remove(&0)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.winner_track)
+            .collect()
+    }
+
+    #[test]
+    fn channels() {
+        assert_eq!(winners(DistanceChannel::Feature), vec![2, 3]);
+        assert_eq!(winners(DistanceChannel::Attribute), vec![1, 4]);
+        assert_eq!(
+            winners(DistanceChannel::Combined {
+                attribute_weight: 0.5,
+                feature_weight: 0.5
+            }),
+            vec![2, 1]
+        );
+    }
+
+    #[test]
+    fn similarity_channel() {
+        let v: ChannelTopNVoting<f32> =
+            ChannelTopNVoting::new(5, 0.45, 1, DistanceChannel::Attribute)
+                .with_polarity(MetricPolarity::Similarity);
+        let winners = v
+            .winners(distances())
+            .remove(&0)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.winner_track)
+            .collect::<Vec<_>>();
+        assert_eq!(winners, vec![2]);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{oriented, Voting};
+use itertools::Itertools;
+use std::collections::HashMap;
+
+/// Class-aware TopN voting engine.
+///
+/// The class consistency of the observation pair is determined from the attribute metric object
+/// calculated with [ObservationAttributes::calculate_metric_object](ObservationAttributes::calculate_metric_object)
+/// by the user-provided `same_class` function. Votes without the attribute metric object are treated as cross-class votes.
+///
+/// Cross-class votes are either dropped (when `cross_class_penalty` is `None`) or their distance is
+/// increased by the penalty before the threshold is applied.
+///
+/// It calculates winners as:
+/// 1. penalizes or removes cross-class votes
+/// 2. removes all distances that are greater than threshold
+/// 3. groups remaining distances by (query, winner) track pairs, removes groups with less than `min_votes` votes
+/// 4. calculates the group weight like [TopNVoting](crate::voting::topn::TopNVoting) does
+/// 5. sorts groups by weight decreasingly and returns TopN
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities, `max_distance` is
+/// the min similarity permitted to participate and the penalty is subtracted from the similarities of the cross-class
+/// votes.
+///
+pub struct ClassAwareVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    topn: usize,
+    max_distance: f32,
+    polarity: MetricPolarity,
+    min_votes: usize,
+    cross_class_penalty: Option<f32>,
+    same_class: fn(&OA::MetricObject) -> bool,
+}
+
+impl<OA> ClassAwareVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance permitted to participate
+    /// * `min_votes` - minimal amount of votes required the track to participate
+    /// * `cross_class_penalty` - the distance penalty for cross-class votes, `None` drops such votes
+    /// * `same_class` - function that decides whether the pair of observations belongs to the same class
+    ///
+    pub fn new(
+        topn: usize,
+        max_distance: f32,
+        min_votes: usize,
+        cross_class_penalty: Option<f32>,
+        same_class: fn(&OA::MetricObject) -> bool,
+    ) -> Self {
+        Self {
+            topn,
+            max_distance,
+            polarity: MetricPolarity::default(),
+            min_votes,
+            cross_class_penalty,
+            same_class,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+}
+
+impl<OA> Voting<OA> for ClassAwareVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let max_distance = self.polarity.orient(self.max_distance);
+        let mut max_dist = f32::MIN;
+        let mut results: HashMap<TrackId, Vec<TopNVotingElt>> = HashMap::new();
+
+        oriented(distances, self.polarity)
+            .flat_map(
+                |ObservationMetricOk {
+                     from,
+                     to,
+                     attribute_metric,
+                     feature_distance,
+                     weight,
+                     stamp: _,
+                     query_observation: _,
+                 }| {
+                    let same_class = attribute_metric
+                        .as_ref()
+                        .map(self.same_class)
+                        .unwrap_or(false);
+                    let dist = match (same_class, self.cross_class_penalty) {
+                        (true, _) => feature_distance?,
+                        (false, Some(penalty)) => feature_distance? + penalty,
+                        (false, None) => return None,
+                    };
+                    max_dist = max_dist.max(dist);
+                    if dist <= max_distance {
+                        Some(((from, to), (dist, weight)))
+                    } else {
+                        None
+                    }
+                },
+            )
+            .into_group_map()
+            .into_iter()
+            .filter(|(_, dists)| dists.len() >= self.min_votes)
+            .for_each(|((q, w), dists)| {
+                let weight = dists
+                    .into_iter()
+                    .map(|(d, w)| (max_dist - d) as f64 * w as f64)
+                    .sum();
+                results
+                    .entry(q)
+                    .or_default()
+                    .push(TopNVotingElt::new(q, w, weight));
+            });
+
+        for winners in results.values_mut() {
+            winners.sort_by(|l, r| {
+                r.weight
+                    .total_cmp(&l.weight)
+                    .then(l.winner_track.cmp(&r.winner_track))
+            });
+            winners.truncate(self.topn);
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::class_aware::ClassAwareVoting;
+    use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk};
+    use crate::voting::Voting;
+
+    #[derive(Clone)]
+    struct ClassAttrs(u64);
+
+    impl ObservationAttributes for ClassAttrs {
+        type MetricObject = bool;
+
+        fn calculate_metric_object(
+            l: &Option<&Self>,
+            r: &Option<&Self>,
+        ) -> Option<Self::MetricObject> {
+            match (l, r) {
+                (Some(l), Some(r)) => Some(l.0 == r.0),
+                _ => None,
+            }
+        }
+    }
+
+    fn same(l: u64, r: u64) -> Option<bool> {
+        ClassAttrs::calculate_metric_object(&Some(&ClassAttrs(l)), &Some(&ClassAttrs(r)))
+    }
+
+    #[test]
+    fn cross_class_dropped() {
+        let v: ClassAwareVoting<ClassAttrs> = ClassAwareVoting::new(5, 0.5, 1, None, |m| *m);
+        let res = v.winners([
+            ObservationMetricOk::new(0, 1, same(1, 1), Some(0.3)),
+            ObservationMetricOk::new(0, 2, same(1, 2), Some(0.1)),
+            ObservationMetricOk::new(0, 3, None, Some(0.1)),
+        ]);
+        let winners = res.get(&0).unwrap();
+        assert_eq!(winners.len(), 1);
+        assert_eq!(winners[0].winner_track, 1);
+    }
+
+    #[test]
+    fn cross_class_penalized() {
+        let v: ClassAwareVoting<ClassAttrs> = ClassAwareVoting::new(5, 0.5, 1, Some(0.3), |m| *m);
+        let res = v.winners([
+            ObservationMetricOk::new(0, 1, same(1, 1), Some(0.3)),
+            ObservationMetricOk::new(0, 2, same(1, 2), Some(0.1)),
+            ObservationMetricOk::new(0, 3, same(1, 2), Some(0.3)),
+        ]);
+        let winners = res.get(&0).unwrap();
+        assert_eq!(
+            winners.iter().map(|e| e.winner_track).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn cross_class_similarity_penalized() {
+        let v: ClassAwareVoting<ClassAttrs> = ClassAwareVoting::new(5, 0.5, 1, Some(0.3), |m| *m)
+            .with_polarity(MetricPolarity::Similarity);
+        let res = v.winners([
+            ObservationMetricOk::new(0, 1, same(1, 1), Some(0.7)),
+            ObservationMetricOk::new(0, 2, same(1, 2), Some(0.9)),
+            ObservationMetricOk::new(0, 3, same(1, 2), Some(0.7)),
+        ]);
+        let winners = res.get(&0).unwrap();
+        assert_eq!(
+            winners.iter().map(|e| e.winner_track).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use crate::track::{ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::Voting;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+/// Composition of two voting engines.
+///
+/// Runs the `first` engine over all distances, then runs the `second` engine over the distances of
+/// the query tracks that were not matched by the `first` engine. Winners of both engines are merged.
+/// Since `CompositeVoting` is a voting engine itself, more engines are composed by nesting.
+///
+/// Unlike [MatchingCascadeVoting](crate::voting::cascade::MatchingCascadeVoting), which orders the candidates by
+/// their age, the composition orders the engines.
+///
+/// E.g. IoU gating as the first engine and appearance TopN voting on the remainder.
+///
+pub struct CompositeVoting<OA, F, S>
+where
+    OA: ObservationAttributes,
+    F: Voting<OA, WinnerObject = TopNVotingElt>,
+    S: Voting<OA, WinnerObject = TopNVotingElt>,
+{
+    first: F,
+    second: S,
+    exclude_matched_tracks: bool,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA, F, S> CompositeVoting<OA, F, S>
+where
+    OA: ObservationAttributes,
+    F: Voting<OA, WinnerObject = TopNVotingElt>,
+    S: Voting<OA, WinnerObject = TopNVotingElt>,
+{
+    /// Constructs new combinator
+    ///
+    /// # Arguments
+    /// * `first` - the first engine
+    /// * `second` - the engine that processes the query tracks unmatched by the first engine
+    /// * `exclude_matched_tracks` - when `true`, tracks matched by the first engine don't participate in the second one
+    ///
+    pub fn new(first: F, second: S, exclude_matched_tracks: bool) -> Self {
+        Self {
+            first,
+            second,
+            exclude_matched_tracks,
+            _phony: PhantomData,
+        }
+    }
+}
+
+impl<OA, F, S> Voting<OA> for CompositeVoting<OA, F, S>
+where
+    OA: ObservationAttributes,
+    F: Voting<OA, WinnerObject = TopNVotingElt>,
+    S: Voting<OA, WinnerObject = TopNVotingElt>,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let distances = distances.into_iter().collect::<Vec<_>>();
+        let mut results = self.first.winners(distances.iter().cloned());
+        results.retain(|_, winners| !winners.is_empty());
+
+        let matched_tracks = if self.exclude_matched_tracks {
+            results
+                .values()
+                .flatten()
+                .map(|e| e.winner_track)
+                .collect::<HashSet<_>>()
+        } else {
+            HashSet::default()
+        };
+
+        let remainder = distances
+            .into_iter()
+            .filter(|e| !results.contains_key(&e.from) && !matched_tracks.contains(&e.to))
+            .collect::<Vec<_>>();
+
+        for (query, winners) in self.second.winners(remainder) {
+            if !winners.is_empty() {
+                results.insert(query, winners);
+            }
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::composite::CompositeVoting;
+    use crate::track::{ObservationMetricOk, TrackId};
+    use crate::voting::topn::{TopNVoting, TopNVotingElt};
+    use crate::voting::Voting;
+    use std::collections::HashMap;
+
+    fn distances() -> Vec<ObservationMetricOk<()>> {
+        vec![
+            ObservationMetricOk::new(1, 10, None, Some(0.1)),
+            ObservationMetricOk::new(2, 10, None, Some(0.3)),
+            ObservationMetricOk::new(2, 20, None, Some(0.4)),
+            ObservationMetricOk::new(3, 30, None, Some(0.9)),
+        ]
+    }
+
+    fn winners(res: HashMap<TrackId, Vec<TopNVotingElt>>) -> Vec<(TrackId, TrackId)> {
+        let mut w = res
+            .values()
+            .flatten()
+            .map(|e| (e.query_track, e.winner_track))
+            .collect::<Vec<_>>();
+        w.sort();
+        w
+    }
+
+    #[test]
+    fn composition() {
+        let v = CompositeVoting::new(
+            TopNVoting::<()>::new(1, 0.2, 1),
+            TopNVoting::<()>::new(1, 0.5, 1),
+            true,
+        );
+        assert_eq!(winners(v.winners(distances())), vec![(1, 10), (2, 20)]);
+
+        let v = CompositeVoting::new(
+            TopNVoting::<()>::new(1, 0.2, 1),
+            TopNVoting::<()>::new(1, 0.5, 1),
+            false,
+        );
+        assert_eq!(winners(v.winners(distances())), vec![(1, 10), (2, 10)]);
+    }
+
+    #[test]
+    fn nested_composition() {
+        let v = CompositeVoting::new(
+            CompositeVoting::new(
+                TopNVoting::<()>::new(1, 0.2, 1),
+                TopNVoting::<()>::new(1, 0.5, 1),
+                true,
+            ),
+            TopNVoting::<()>::new(1, 1.0, 1),
+            true,
+        );
+        assert_eq!(
+            winners(v.winners(distances())),
+            vec![(1, 10), (2, 20), (3, 30)]
+        );
+    }
+}
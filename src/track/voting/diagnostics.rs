@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+/// Diagnostics of a voting engine call
+///
+/// Helps to tune engine thresholds (like `max_distance`, `min_votes`) with the data observed in the scene.
+///
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct VotingDiagnostics {
+    /// amount of distances passed to the engine
+    pub distances: usize,
+    /// amount of distances without the feature distance
+    pub missing_distances: usize,
+    /// amount of distances that are greater than threshold
+    pub filtered_by_threshold: usize,
+    /// amount of (query, candidate) groups with less than `min_votes` votes
+    pub filtered_by_votes: usize,
+    /// amount of (query, candidate) groups by the amount of votes they gathered
+    pub vote_histogram: HashMap<usize, usize>,
+    /// weight margins between the best and the second best candidates of query tracks
+    /// having at least two candidates
+    pub margins: Vec<f64>,
+}
+
+impl VotingDiagnostics {
+    /// Minimal weight margin
+    ///
+    pub fn min_margin(&self) -> Option<f64> {
+        self.margins.iter().copied().reduce(f64::min)
+    }
+
+    /// Maximal weight margin
+    ///
+    pub fn max_margin(&self) -> Option<f64> {
+        self.margins.iter().copied().reduce(f64::max)
+    }
+
+    /// Mean weight margin
+    ///
+    pub fn mean_margin(&self) -> Option<f64> {
+        if self.margins.is_empty() {
+            None
+        } else {
+            Some(self.margins.iter().sum::<f64>() / self.margins.len() as f64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::diagnostics::VotingDiagnostics;
+
+    #[test]
+    fn margins() {
+        let d = VotingDiagnostics::default();
+        assert_eq!(d.mean_margin(), None);
+
+        let d = VotingDiagnostics {
+            margins: vec![0.5, 1.5, 1.0],
+            ..Default::default()
+        };
+        assert_eq!(d.min_margin(), Some(0.5));
+        assert_eq!(d.max_margin(), Some(1.5));
+        assert_eq!(d.mean_margin(), Some(1.0));
+    }
+}
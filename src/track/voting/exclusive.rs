@@ -0,0 +1,117 @@
+use crate::track::{ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{BatchVoting, Voting};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+/// Exclusive one-to-one winners voting engine for batched queries.
+///
+/// Wraps any voting engine which produces ranked [TopNVotingElt](TopNVotingElt) winners (e.g.
+/// [TopNVoting](crate::voting::topn::TopNVoting)) and resolves the conflicts between the query tracks:
+/// every store track is assigned to at most one query track and every query track gets at most one winner.
+///
+/// Winners of all query tracks are processed in the order of decreasing weight, a pair is accepted
+/// when neither the query track nor the winner track is already assigned.
+///
+pub struct ExclusiveVoting<OA, V>
+where
+    OA: ObservationAttributes,
+    V: Voting<OA, WinnerObject = TopNVotingElt>,
+{
+    engine: V,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA, V> ExclusiveVoting<OA, V>
+where
+    OA: ObservationAttributes,
+    V: Voting<OA, WinnerObject = TopNVotingElt>,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `engine` - the engine that produces ranked winners for every query track
+    ///
+    pub fn new(engine: V) -> Self {
+        Self {
+            engine,
+            _phony: PhantomData,
+        }
+    }
+}
+
+impl<OA, V> Voting<OA> for ExclusiveVoting<OA, V>
+where
+    OA: ObservationAttributes,
+    V: Voting<OA, WinnerObject = TopNVotingElt>,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut candidates = self
+            .engine
+            .winners(distances)
+            .into_values()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        candidates.sort_by(|l, r| {
+            r.weight
+                .total_cmp(&l.weight)
+                .then(l.query_track.cmp(&r.query_track))
+                .then(l.winner_track.cmp(&r.winner_track))
+        });
+
+        let mut assigned_tracks: HashSet<TrackId> = HashSet::new();
+        let mut results: HashMap<TrackId, Vec<TopNVotingElt>> = HashMap::new();
+
+        for c in candidates {
+            if results.contains_key(&c.query_track) || assigned_tracks.contains(&c.winner_track) {
+                continue;
+            }
+            assigned_tracks.insert(c.winner_track);
+            results.insert(c.query_track, vec![c]);
+        }
+
+        results
+    }
+}
+
+impl<OA, V> BatchVoting<OA> for ExclusiveVoting<OA, V>
+where
+    OA: ObservationAttributes,
+    V: Voting<OA, WinnerObject = TopNVotingElt>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::exclusive::ExclusiveVoting;
+    use crate::track::ObservationMetricOk;
+    use crate::voting::topn::TopNVoting;
+    use crate::voting::Voting;
+
+    #[test]
+    fn deduplicated_winners() {
+        let v = ExclusiveVoting::new(TopNVoting::<()>::new(3, 1.0, 1));
+        let res = v.winners([
+            ObservationMetricOk::new(1, 10, None, Some(0.1)),
+            ObservationMetricOk::new(1, 10, None, Some(0.1)),
+            ObservationMetricOk::new(1, 20, None, Some(0.5)),
+            ObservationMetricOk::new(2, 10, None, Some(0.2)),
+            ObservationMetricOk::new(2, 20, None, Some(0.3)),
+            ObservationMetricOk::new(3, 10, None, Some(0.4)),
+        ]);
+
+        let mut winners = res
+            .values()
+            .flatten()
+            .map(|e| (e.query_track, e.winner_track))
+            .collect::<Vec<_>>();
+        winners.sort();
+        assert_eq!(winners, vec![(1, 10), (2, 20)]);
+    }
+}
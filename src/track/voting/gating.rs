@@ -0,0 +1,120 @@
+use crate::track::{ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::{BatchVoting, Voting};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Gating stage for voting engines.
+///
+/// Wraps any voting engine and rejects the candidate tracks which gating distance exceeds the threshold
+/// before the votes are counted. The gating distance of a (query, candidate) pair is calculated with the
+/// callback once per pair. The standard DeepSORT gate uses the squared Mahalanobis distance of the motion
+/// model (e.g. [Universal2DBoxKalmanFilter::distance](crate::utils::kalman::kalman_2d_box::Universal2DBoxKalmanFilter::distance))
+/// with the chi-square threshold from [CHI2INV95](crate::utils::kalman::CHI2INV95).
+///
+pub struct GatedVoting<OA, V, G>
+where
+    OA: ObservationAttributes,
+    V: Voting<OA>,
+    G: Fn(&ObservationMetricOk<OA>) -> f32,
+{
+    engine: V,
+    threshold: f32,
+    gate: G,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA, V, G> GatedVoting<OA, V, G>
+where
+    OA: ObservationAttributes,
+    V: Voting<OA>,
+    G: Fn(&ObservationMetricOk<OA>) -> f32,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `engine` - the engine that votes over the distances passed the gate
+    /// * `threshold` - max gating distance permitted to participate
+    /// * `gate` - the callback that calculates the gating distance for the (query, candidate) pair of the distance
+    ///
+    pub fn new(engine: V, threshold: f32, gate: G) -> Self {
+        Self {
+            engine,
+            threshold,
+            gate,
+            _phony: PhantomData,
+        }
+    }
+}
+
+impl<OA, V, G> Voting<OA> for GatedVoting<OA, V, G>
+where
+    OA: ObservationAttributes,
+    V: Voting<OA>,
+    G: Fn(&ObservationMetricOk<OA>) -> f32,
+{
+    type WinnerObject = V::WinnerObject;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<V::WinnerObject>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut passed: HashMap<(TrackId, TrackId), bool> = HashMap::new();
+        let distances = distances
+            .into_iter()
+            .filter(|e| {
+                *passed
+                    .entry((e.from, e.to))
+                    .or_insert_with(|| (self.gate)(e) <= self.threshold)
+            })
+            .collect::<Vec<_>>();
+        self.engine.winners(distances)
+    }
+}
+
+impl<OA, V, G> BatchVoting<OA> for GatedVoting<OA, V, G>
+where
+    OA: ObservationAttributes,
+    V: Voting<OA>,
+    G: Fn(&ObservationMetricOk<OA>) -> f32,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::gating::GatedVoting;
+    use crate::track::ObservationMetricOk;
+    use crate::utils::kalman::CHI2INV95;
+    use crate::voting::topn::TopNVoting;
+    use crate::voting::Voting;
+    use std::cell::Cell;
+
+    #[test]
+    fn gated_candidates() {
+        let calls = Cell::new(0);
+        let v = GatedVoting::new(
+            TopNVoting::<()>::new(5, 1.0, 1),
+            CHI2INV95[3],
+            |e: &ObservationMetricOk<()>| {
+                calls.set(calls.get() + 1);
+                if e.to == 20 {
+                    50.0
+                } else {
+                    1.0
+                }
+            },
+        );
+
+        let res = v.winners([
+            ObservationMetricOk::new(1, 10, None, Some(0.5)),
+            ObservationMetricOk::new(1, 10, None, Some(0.5)),
+            ObservationMetricOk::new(1, 20, None, Some(0.1)),
+            ObservationMetricOk::new(1, 20, None, Some(0.1)),
+        ]);
+
+        assert_eq!(
+            res[&1].iter().map(|e| e.winner_track).collect::<Vec<_>>(),
+            vec![10]
+        );
+        assert_eq!(calls.get(), 2);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{oriented, BatchVoting, Voting};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+/// Greedy one-to-one assignment voting engine.
+///
+/// A cheaper alternative to [HungarianVoting](crate::voting::hungarian::HungarianVoting) for large
+/// candidate sets. The result is not guaranteed to be optimal, but every candidate track still wins
+/// for at most one query track.
+///
+/// It calculates winners as:
+/// 1. removes all distances that are greater than threshold
+/// 2. takes the minimal distance for every (query, candidate) pair as the pair distance
+/// 3. picks the pair with the globally minimal distance, assigns it and removes both the query and the candidate
+/// 4. repeats step 3 until no pairs left
+///
+/// The weight of the winner is `max_distance - distance`.
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities, `max_distance` is
+/// the min similarity permitted to participate, the pairs with the maximal similarity are assigned first and the
+/// weight of the winner is `similarity - max_distance`.
+///
+pub struct GreedyMutualVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    max_distance: f32,
+    polarity: MetricPolarity,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> GreedyMutualVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `max_distance` - max distance permitted to participate
+    ///
+    pub fn new(max_distance: f32) -> Self {
+        Self {
+            max_distance,
+            polarity: MetricPolarity::default(),
+            _phony: PhantomData,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+}
+
+impl<OA> Voting<OA> for GreedyMutualVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut pairs: HashMap<(TrackId, TrackId), f32> = HashMap::new();
+        let max_distance = self.polarity.orient(self.max_distance);
+        for ObservationMetricOk {
+            from,
+            to,
+            attribute_metric: _,
+            feature_distance,
+            weight: _,
+            stamp: _,
+            query_observation: _,
+        } in oriented(distances, self.polarity)
+        {
+            if let Some(dist) = feature_distance.filter(|d| *d <= max_distance) {
+                pairs
+                    .entry((from, to))
+                    .and_modify(|d| *d = d.min(dist))
+                    .or_insert(dist);
+            }
+        }
+
+        let mut pairs = pairs.into_iter().collect::<Vec<_>>();
+        pairs.sort_by(|(lp, ld), (rp, rd)| ld.total_cmp(rd).then(lp.cmp(rp)));
+
+        let mut assigned_tracks: HashSet<TrackId> = HashSet::new();
+        let mut results: HashMap<TrackId, Vec<TopNVotingElt>> = HashMap::new();
+
+        for ((query, winner), dist) in pairs {
+            if results.contains_key(&query) || assigned_tracks.contains(&winner) {
+                continue;
+            }
+            assigned_tracks.insert(winner);
+            results.insert(
+                query,
+                vec![TopNVotingElt::new(
+                    query,
+                    winner,
+                    (max_distance - dist) as f64,
+                )],
+            );
+        }
+
+        results
+    }
+}
+
+impl<OA> BatchVoting<OA> for GreedyMutualVoting<OA> where OA: ObservationAttributes {}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::greedy::GreedyMutualVoting;
+    use crate::track::{MetricPolarity, ObservationMetricOk};
+    use crate::voting::topn::TopNVotingElt;
+    use crate::voting::Voting;
+    use std::collections::HashMap;
+
+    #[test]
+    fn greedy_assignment() {
+        let v: GreedyMutualVoting<()> = GreedyMutualVoting::new(1.0);
+        let res = v.winners([
+            ObservationMetricOk::new(1, 20, None, Some(0.1)),
+            ObservationMetricOk::new(1, 30, None, Some(0.2)),
+            ObservationMetricOk::new(2, 20, None, Some(0.15)),
+            ObservationMetricOk::new(2, 30, None, Some(0.5)),
+            ObservationMetricOk::new(3, 20, None, Some(0.05)),
+            ObservationMetricOk::new(3, 40, None, Some(1.5)),
+        ]);
+
+        assert_eq!(
+            res,
+            HashMap::from([
+                (3, vec![TopNVotingElt::new(3, 20, 0.949999988079071)]),
+                (1, vec![TopNVotingElt::new(1, 30, 0.800000011920929)]),
+            ])
+        );
+    }
+
+    #[test]
+    fn greedy_by_similarity() {
+        let v: GreedyMutualVoting<()> =
+            GreedyMutualVoting::new(0.5).with_polarity(MetricPolarity::Similarity);
+        let res = v.winners([
+            ObservationMetricOk::new(1, 20, None, Some(0.9)),
+            ObservationMetricOk::new(1, 30, None, Some(0.75)),
+            ObservationMetricOk::new(2, 20, None, Some(1.0)),
+            ObservationMetricOk::new(3, 30, None, Some(0.25)),
+        ]);
+        assert_eq!(
+            res,
+            HashMap::from([
+                (1, vec![TopNVotingElt::new(1, 30, 0.25)]),
+                (2, vec![TopNVotingElt::new(2, 20, 0.5)]),
+            ])
+        );
+    }
+}
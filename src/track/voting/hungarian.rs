@@ -0,0 +1,313 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{oriented, BatchVoting, Voting};
+use pathfinding::kuhn_munkres::kuhn_munkres;
+use pathfinding::matrix::Matrix;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// The largest weight of the pair, the weights are scaled to `[0, MAX_WEIGHT]`, so the sums of the weights
+/// of the assignment don't overflow
+///
+const MAX_WEIGHT: f64 = 1e12;
+
+/// Optimal one-to-one assignment voting engine.
+///
+/// The engine solves the assignment problem between query tracks and candidate tracks with the
+/// Kuhn-Munkres (Hungarian) algorithm, so every candidate track wins for at most one query track.
+///
+/// It calculates winners as:
+/// 1. removes all distances that are greater than threshold
+/// 2. takes the minimal distance for every (query, candidate) pair as the pair distance
+/// 3. builds the weight matrix `max_distance - distance`, extended with a "no match" column for every query
+/// 4. solves the assignment and returns the matched pairs
+///
+/// Queries that are not matched with any candidate are not present in the result. The weight of the
+/// winner is `max_distance - distance`.
+///
+/// The assignment is solved with the integer weights, the weights are scaled to the bounded range before they are
+/// rounded, so the large and the infinite `max_distance` and distances don't overflow the weights and the distinct
+/// distances keep the distinct weights.
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities, `max_distance` is
+/// the min similarity permitted to participate, the assignment maximizes the similarities and the weight of the
+/// winner is `similarity - max_distance`.
+///
+pub struct HungarianVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    max_distance: f32,
+    polarity: MetricPolarity,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> HungarianVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `max_distance` - max distance permitted to participate
+    ///
+    pub fn new(max_distance: f32) -> Self {
+        Self {
+            max_distance,
+            polarity: MetricPolarity::default(),
+            _phony: PhantomData,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+}
+
+impl<OA> HungarianVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Scales the weights `max_distance - distance` of the pairs to `[0, MAX_WEIGHT]`
+    ///
+    /// The weight is split into the bonus `max_distance - farthest` every match gets and the gain
+    /// `farthest - distance`, where `farthest` is the largest distance of the pairs. The bonus larger than
+    /// `(rows + 1)` spans of the gains makes the solver maximize the number of the matches first, so the larger
+    /// bonuses don't change the assignment and the bonus is capped by it. The distances are clamped to the finite
+    /// `f32` range.
+    ///
+    fn scaled_weights(
+        &self,
+        pairs: &HashMap<(usize, usize), f32>,
+        rows: usize,
+        max_distance: f32,
+    ) -> HashMap<(usize, usize), i64> {
+        let clamp = |d: f32| f64::from(d).clamp(f64::from(f32::MIN), f64::from(f32::MAX));
+        let (nearest, farthest) = pairs.values().fold((f64::MAX, f64::MIN), |(n, f), d| {
+            (n.min(clamp(*d)), f.max(clamp(*d)))
+        });
+        let span = farthest - nearest;
+        let cap = if span > 0.0 {
+            (rows + 1) as f64 * span
+        } else {
+            1.0
+        };
+        let bonus = (clamp(max_distance) - farthest).clamp(0.0, cap);
+        let scale = match bonus + span {
+            max if max > 0.0 => MAX_WEIGHT / max,
+            _ => 0.0,
+        };
+
+        pairs
+            .iter()
+            .map(|(pair, d)| {
+                (
+                    *pair,
+                    ((bonus + farthest - clamp(*d)) * scale).round() as i64,
+                )
+            })
+            .collect()
+    }
+}
+
+impl<OA> Voting<OA> for HungarianVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut queries: Vec<TrackId> = Vec::new();
+        let mut queries_index: HashMap<TrackId, usize> = HashMap::new();
+        let mut tracks: Vec<TrackId> = Vec::new();
+        let mut tracks_index: HashMap<TrackId, usize> = HashMap::new();
+        let mut pairs: HashMap<(usize, usize), f32> = HashMap::new();
+        let max_distance = self.polarity.orient(self.max_distance);
+
+        for ObservationMetricOk {
+            from,
+            to,
+            attribute_metric: _,
+            feature_distance,
+            weight: _,
+            stamp: _,
+            query_observation: _,
+        } in oriented(distances, self.polarity)
+        {
+            let dist = match feature_distance {
+                Some(d) if d <= max_distance => d,
+                _ => continue,
+            };
+
+            let row = *queries_index.entry(from).or_insert_with(|| {
+                queries.push(from);
+                queries.len() - 1
+            });
+
+            let col = *tracks_index.entry(to).or_insert_with(|| {
+                tracks.push(to);
+                tracks.len() - 1
+            });
+
+            pairs
+                .entry((row, col))
+                .and_modify(|d| *d = d.min(dist))
+                .or_insert(dist);
+        }
+
+        if queries.is_empty() {
+            return HashMap::default();
+        }
+
+        // pairs without calculated distance get the weight lower than "no match" columns have
+        let mut weights = Matrix::new(queries.len(), tracks.len() + queries.len(), 0i64);
+        for row in 0..queries.len() {
+            for col in 0..tracks.len() {
+                *weights.get_mut((row, col)).unwrap() = -1;
+            }
+        }
+
+        for ((row, col), weight) in self.scaled_weights(&pairs, queries.len(), max_distance) {
+            *weights.get_mut((row, col)).unwrap() = weight;
+        }
+
+        let (_, solution) = kuhn_munkres(&weights);
+
+        solution
+            .into_iter()
+            .enumerate()
+            .flat_map(|(row, col)| {
+                let dist = pairs.get(&(row, col))?;
+                let (query, winner) = (queries[row], tracks[col]);
+                Some((
+                    query,
+                    vec![TopNVotingElt::new(
+                        query,
+                        winner,
+                        (max_distance - dist) as f64,
+                    )],
+                ))
+            })
+            .collect()
+    }
+}
+
+impl<OA> BatchVoting<OA> for HungarianVoting<OA> where OA: ObservationAttributes {}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::hungarian::HungarianVoting;
+    use crate::track::{MetricPolarity, ObservationMetricOk, TrackId};
+    use crate::voting::topn::TopNVotingElt;
+    use crate::voting::{BatchVoting, Voting};
+    use std::collections::HashMap;
+
+    fn winners(res: &HashMap<TrackId, Vec<TopNVotingElt>>) -> Vec<(TrackId, TrackId)> {
+        let mut w = res
+            .values()
+            .flatten()
+            .map(|e| (e.query_track, e.winner_track))
+            .collect::<Vec<_>>();
+        w.sort();
+        w
+    }
+
+    #[test]
+    fn optimal_assignment() {
+        let v: HungarianVoting<()> = HungarianVoting::new(1.0);
+        // greedy per-query choice gives track 20 to both queries
+        let res = v.winners([
+            ObservationMetricOk::new(1, 20, None, Some(0.1)),
+            ObservationMetricOk::new(1, 30, None, Some(0.2)),
+            ObservationMetricOk::new(2, 20, None, Some(0.15)),
+            ObservationMetricOk::new(2, 30, None, Some(0.9)),
+        ]);
+        assert_eq!(winners(&res), vec![(1, 30), (2, 20)]);
+    }
+
+    #[test]
+    fn similarity_assignment() {
+        let v: HungarianVoting<()> =
+            HungarianVoting::new(0.5).with_polarity(MetricPolarity::Similarity);
+        let res = v.winners([
+            ObservationMetricOk::new(1, 20, None, Some(0.9)),
+            ObservationMetricOk::new(1, 30, None, Some(0.8)),
+            ObservationMetricOk::new(2, 20, None, Some(0.85)),
+            ObservationMetricOk::new(2, 30, None, Some(0.1)),
+            ObservationMetricOk::new(3, 30, None, Some(0.4)),
+        ]);
+        assert_eq!(winners(&res), vec![(1, 30), (2, 20)]);
+        assert!((res[&1][0].weight - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unmatched_queries() {
+        let v: HungarianVoting<()> = HungarianVoting::new(0.5);
+        let res = v.winners([
+            ObservationMetricOk::new(1, 20, None, Some(0.1)),
+            ObservationMetricOk::new(1, 20, None, Some(0.3)),
+            ObservationMetricOk::new(2, 20, None, Some(0.2)),
+            ObservationMetricOk::new(3, 30, None, Some(0.7)),
+            ObservationMetricOk::new(4, 40, None, None),
+        ]);
+        assert_eq!(winners(&res), vec![(1, 20)]);
+        assert!((res.get(&1).unwrap()[0].weight - 0.4).abs() < 1e-6);
+
+        let res = v.winners([]);
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn unbounded_distances() {
+        let distances = |scale: f32| {
+            [
+                ObservationMetricOk::new(1, 20, None, Some(0.1 * scale)),
+                ObservationMetricOk::new(1, 30, None, Some(0.2 * scale)),
+                ObservationMetricOk::new(2, 20, None, Some(0.15 * scale)),
+                ObservationMetricOk::new(2, 30, None, Some(0.9 * scale)),
+            ]
+        };
+        for (max_distance, scale) in [
+            (f32::MAX, 1.0),
+            (f32::INFINITY, 1.0),
+            (f32::MAX, 1e37),
+            (f32::INFINITY, 1e-30),
+        ] {
+            let v: HungarianVoting<()> = HungarianVoting::new(max_distance);
+            assert_eq!(
+                winners(&v.winners(distances(scale))),
+                vec![(1, 30), (2, 20)]
+            );
+        }
+
+        // the match is preferred to no match when all the distances are the same
+        let v: HungarianVoting<()> = HungarianVoting::new(f32::INFINITY);
+        let res = v.winners([
+            ObservationMetricOk::new(1, 20, None, Some(f32::MAX)),
+            ObservationMetricOk::new(2, 30, None, Some(f32::INFINITY)),
+        ]);
+        assert_eq!(winners(&res), vec![(1, 20), (2, 30)]);
+    }
+
+    #[test]
+    fn batch_assignment() {
+        let v: HungarianVoting<()> = HungarianVoting::new(1.0);
+        // distances of both candidates are calculated with the same `from` id
+        let first = [
+            ObservationMetricOk::new(0, 20, None, Some(0.1)),
+            ObservationMetricOk::new(0, 30, None, Some(0.2)),
+        ];
+        let second = [
+            ObservationMetricOk::new(0, 20, None, Some(0.15)),
+            ObservationMetricOk::new(0, 30, None, Some(0.9)),
+        ];
+        let res = v.winners_batch(&[(1, &first), (2, &second)]);
+        assert_eq!(winners(&res), vec![(1, 30), (2, 20)]);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{oriented, BatchVoting, Voting};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Median distance voting engine.
+///
+/// Ranks the candidate tracks by the median distance of their matched observations instead of the
+/// amount of votes, so a few outlier observations within a track don't change the result.
+///
+/// It calculates winners as:
+/// 1. removes all distances that are greater than threshold
+/// 2. groups remaining distances by (query, winner) track pairs, removes groups with less than `min_votes` votes
+/// 3. calculates the median distance of every group
+/// 4. sorts groups by the median distance increasingly and returns TopN
+///
+/// The weight of the winner is `max_distance - median_distance`.
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities, `max_distance` is
+/// the min similarity permitted to participate, the groups are sorted by the median similarity decreasingly and the
+/// weight of the winner is `median_similarity - max_distance`.
+///
+pub struct MedianVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    topn: usize,
+    max_distance: f32,
+    polarity: MetricPolarity,
+    min_votes: usize,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> MedianVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance permitted to participate
+    /// * `min_votes` - minimal amount of votes required the track to participate
+    ///
+    pub fn new(topn: usize, max_distance: f32, min_votes: usize) -> Self {
+        Self {
+            topn,
+            max_distance,
+            polarity: MetricPolarity::default(),
+            min_votes,
+            _phony: PhantomData,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+}
+
+fn median(mut distances: Vec<f32>) -> f32 {
+    distances.sort_by(|l, r| l.total_cmp(r));
+    let mid = distances.len() / 2;
+    if distances.len() % 2 == 0 {
+        (distances[mid - 1] + distances[mid]) / 2.0
+    } else {
+        distances[mid]
+    }
+}
+
+impl<OA> Voting<OA> for MedianVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut results: HashMap<TrackId, Vec<(f32, TopNVotingElt)>> = HashMap::new();
+        let max_distance = self.polarity.orient(self.max_distance);
+
+        oriented(distances, self.polarity)
+            .flat_map(|e| {
+                let dist = e.feature_distance.filter(|d| *d <= max_distance)?;
+                Some(((e.from, e.to), dist))
+            })
+            .into_group_map()
+            .into_iter()
+            .filter(|(_, dists)| dists.len() >= self.min_votes)
+            .for_each(|((q, w), dists)| {
+                let median = median(dists);
+                results.entry(q).or_default().push((
+                    median,
+                    TopNVotingElt::new(q, w, (max_distance - median) as f64),
+                ));
+            });
+
+        results
+            .into_iter()
+            .map(|(q, mut winners)| {
+                winners.sort_by(|(ld, le), (rd, re)| {
+                    ld.total_cmp(rd).then(le.winner_track.cmp(&re.winner_track))
+                });
+                (
+                    q,
+                    winners
+                        .into_iter()
+                        .take(self.topn)
+                        .map(|(_, e)| e)
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+impl<OA> BatchVoting<OA> for MedianVoting<OA> where OA: ObservationAttributes {}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::median::{median, MedianVoting};
+    use crate::track::{MetricPolarity, ObservationMetricOk};
+    use crate::voting::topn::TopNVotingElt;
+    use crate::voting::Voting;
+    use std::collections::HashMap;
+
+    #[test]
+    fn median_values() {
+        assert_eq!(median(vec![0.5]), 0.5);
+        assert_eq!(median(vec![0.75, 0.25]), 0.5);
+        assert_eq!(median(vec![0.75, 0.0, 0.25]), 0.25);
+    }
+
+    #[test]
+    fn robust_to_outliers() {
+        let v: MedianVoting<()> = MedianVoting::new(2, 1.0, 2);
+        let res = v.winners([
+            // a single very close outlier observation
+            ObservationMetricOk::new(1, 10, None, Some(0.0)),
+            ObservationMetricOk::new(1, 10, None, Some(0.75)),
+            ObservationMetricOk::new(1, 10, None, Some(0.75)),
+            ObservationMetricOk::new(1, 20, None, Some(0.25)),
+            ObservationMetricOk::new(1, 20, None, Some(0.25)),
+            ObservationMetricOk::new(1, 20, None, Some(1.5)),
+            ObservationMetricOk::new(1, 30, None, Some(0.0)),
+        ]);
+
+        assert_eq!(
+            res,
+            HashMap::from([(
+                1,
+                vec![
+                    TopNVotingElt::new(1, 20, 0.75),
+                    TopNVotingElt::new(1, 10, 0.25)
+                ]
+            )])
+        );
+    }
+
+    #[test]
+    fn median_similarity() {
+        let v: MedianVoting<()> =
+            MedianVoting::new(2, 0.5, 1).with_polarity(MetricPolarity::Similarity);
+        let res = v.winners([
+            ObservationMetricOk::new(1, 10, None, Some(1.0)),
+            ObservationMetricOk::new(1, 10, None, Some(0.5)),
+            ObservationMetricOk::new(1, 10, None, Some(0.75)),
+            ObservationMetricOk::new(1, 20, None, Some(1.0)),
+            ObservationMetricOk::new(1, 30, None, Some(0.25)),
+        ]);
+        assert_eq!(
+            res,
+            HashMap::from([(
+                1,
+                vec![
+                    TopNVotingElt::new(1, 20, 0.5),
+                    TopNVotingElt::new(1, 10, 0.25)
+                ]
+            )])
+        );
+    }
+}
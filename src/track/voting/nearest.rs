@@ -0,0 +1,157 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{BatchVoting, Voting};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Nearest neighbor (1-NN) voting engine.
+///
+/// Selects for every query track the single track with the minimal feature distance, the amount
+/// of votes that the tracks gathered is ignored. Unlike `TopNVoting::new(1, max_distance, 1)`, which
+/// picks the winner by the votes, the engine picks the winner by closeness.
+///
+/// It calculates winners as:
+/// 1. removes all distances that are greater than threshold
+/// 2. selects the track with the minimal distance for every query track; on ties the lower track id wins
+///
+/// The weight of the winner is `max_distance - distance`. With [MetricPolarity::Similarity](MetricPolarity::Similarity)
+/// the values are similarities, `max_distance` is the min similarity permitted to participate, the track with
+/// the maximal similarity wins and the weight of the winner is `similarity - max_distance`.
+///
+pub struct NearestVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    max_distance: f32,
+    polarity: MetricPolarity,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> NearestVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `max_distance` - max distance permitted to participate
+    ///
+    pub fn new(max_distance: f32) -> Self {
+        Self {
+            max_distance,
+            polarity: MetricPolarity::default(),
+            _phony: PhantomData,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+}
+
+impl<OA> Voting<OA> for NearestVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut nearest: HashMap<TrackId, (TrackId, f32)> = HashMap::new();
+        for ObservationMetricOk {
+            from,
+            to,
+            attribute_metric: _,
+            feature_distance,
+            weight: _,
+            stamp: _,
+            query_observation: _,
+        } in distances
+        {
+            let dist = match feature_distance {
+                Some(d) if self.polarity.passes(d, self.max_distance) => d,
+                _ => continue,
+            };
+
+            nearest
+                .entry(from)
+                .and_modify(|(track, d)| {
+                    if self.polarity.is_closer(dist, *d) || (dist == *d && to < *track) {
+                        *track = to;
+                        *d = dist;
+                    }
+                })
+                .or_insert((to, dist));
+        }
+
+        nearest
+            .into_iter()
+            .map(|(query, (winner, dist))| {
+                (
+                    query,
+                    vec![TopNVotingElt::new(
+                        query,
+                        winner,
+                        (self.polarity.orient(self.max_distance) - self.polarity.orient(dist))
+                            as f64,
+                    )],
+                )
+            })
+            .collect()
+    }
+}
+
+impl<OA> BatchVoting<OA> for NearestVoting<OA> where OA: ObservationAttributes {}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::nearest::NearestVoting;
+    use crate::track::{MetricPolarity, ObservationMetricOk};
+    use crate::voting::topn::TopNVotingElt;
+    use crate::voting::Voting;
+    use std::collections::HashMap;
+
+    #[test]
+    fn nearest_wins_over_votes() {
+        let v: NearestVoting<()> = NearestVoting::new(1.0);
+        let res = v.winners([
+            ObservationMetricOk::new(1, 10, None, Some(0.5)),
+            ObservationMetricOk::new(1, 10, None, Some(0.5)),
+            ObservationMetricOk::new(1, 10, None, Some(0.5)),
+            ObservationMetricOk::new(1, 20, None, Some(0.25)),
+            ObservationMetricOk::new(2, 30, None, Some(0.5)),
+            ObservationMetricOk::new(2, 20, None, Some(0.5)),
+            ObservationMetricOk::new(3, 30, None, Some(1.5)),
+            ObservationMetricOk::new(4, 30, None, None),
+        ]);
+
+        assert_eq!(
+            res,
+            HashMap::from([
+                (1, vec![TopNVotingElt::new(1, 20, 0.75)]),
+                (2, vec![TopNVotingElt::new(2, 20, 0.5)]),
+            ])
+        );
+    }
+
+    #[test]
+    fn nearest_by_similarity() {
+        let v: NearestVoting<()> =
+            NearestVoting::new(0.5).with_polarity(MetricPolarity::Similarity);
+        let res = v.winners([
+            ObservationMetricOk::new(1, 10, None, Some(0.75)),
+            ObservationMetricOk::new(1, 20, None, Some(2.0)),
+            ObservationMetricOk::new(2, 30, None, Some(0.25)),
+        ]);
+
+        assert_eq!(
+            res,
+            HashMap::from([(1, vec![TopNVotingElt::new(1, 20, 1.5)])])
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{oriented, Voting};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+/// Default weight of the original distance in the re-ranked distance
+///
+pub const DEFAULT_KRECIPROCAL_LAMBDA: f32 = 0.3;
+
+/// K-reciprocal nearest neighbor re-ranking voting engine.
+///
+/// The engine re-ranks the candidates like the k-reciprocal encoding used in person ReID does. It works over the
+/// batch of query tracks: the query track `q` and the candidate track `t` are k-reciprocal neighbors when `t` is
+/// among `k` nearest tracks of `q` and `q` is among `k` nearest query tracks of `t`.
+///
+/// It calculates winners as:
+/// 1. takes the minimal distance for every (query, candidate) pair as the pair distance
+/// 2. encodes every query track as the vector `exp(-distance)` over its k-reciprocal candidates
+/// 3. encodes every candidate track as the mean of the vectors of its k-reciprocal query tracks
+/// 4. calculates the Jaccard distance of the query and candidate vectors
+/// 5. calculates the re-ranked distance `lambda * distance + (1 - lambda) * jaccard_distance`
+/// 6. removes pairs with the distance greater than threshold, sorts candidates by re-ranked distance and returns TopN
+///
+/// So the candidates that appear in each other's neighborhoods are boosted. The weight of the winner is
+/// `max_distance - reranked_distance`.
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities: the neighbors are
+/// the most similar tracks, the pairs are encoded with `exp(similarity)`, the re-ranked distance uses the negated
+/// similarity and `max_distance` is the min similarity permitted to participate.
+///
+pub struct KReciprocalVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    topn: usize,
+    max_distance: f32,
+    polarity: MetricPolarity,
+    k: usize,
+    lambda: f32,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> KReciprocalVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine with the default lambda [DEFAULT_KRECIPROCAL_LAMBDA](DEFAULT_KRECIPROCAL_LAMBDA)
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance permitted to participate
+    /// * `k` - the size of nearest neighbor sets
+    ///
+    pub fn new(topn: usize, max_distance: f32, k: usize) -> Self {
+        Self::with_lambda(topn, max_distance, k, DEFAULT_KRECIPROCAL_LAMBDA)
+    }
+
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance permitted to participate
+    /// * `k` - the size of nearest neighbor sets
+    /// * `lambda` - the weight of the original distance (`0.0..=1.0`)
+    ///
+    pub fn with_lambda(topn: usize, max_distance: f32, k: usize, lambda: f32) -> Self {
+        assert!(k > 0, "The size of neighbor sets must be positive");
+        assert!(
+            (0.0..=1.0).contains(&lambda),
+            "Lambda must be within 0.0..=1.0"
+        );
+        Self {
+            topn,
+            max_distance,
+            polarity: MetricPolarity::default(),
+            k,
+            lambda,
+            _phony: PhantomData,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    fn nearest(&self, mut neighbors: Vec<(TrackId, f32)>) -> HashSet<TrackId> {
+        neighbors.sort_by(|(li, ld), (ri, rd)| ld.total_cmp(rd).then(li.cmp(ri)));
+        neighbors
+            .into_iter()
+            .take(self.k)
+            .map(|(id, _)| id)
+            .collect()
+    }
+}
+
+impl<OA> Voting<OA> for KReciprocalVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let max_distance = self.polarity.orient(self.max_distance);
+        let mut pairs: HashMap<(TrackId, TrackId), f32> = HashMap::new();
+        for e in oriented(distances, self.polarity) {
+            if let Some(dist) = e.feature_distance.filter(|d| !d.is_nan()) {
+                pairs
+                    .entry((e.from, e.to))
+                    .and_modify(|d| *d = d.min(dist))
+                    .or_insert(dist);
+            }
+        }
+
+        let mut query_neighbors: HashMap<TrackId, Vec<(TrackId, f32)>> = HashMap::new();
+        let mut track_neighbors: HashMap<TrackId, Vec<(TrackId, f32)>> = HashMap::new();
+        for ((q, t), d) in &pairs {
+            query_neighbors.entry(*q).or_default().push((*t, *d));
+            track_neighbors.entry(*t).or_default().push((*q, *d));
+        }
+
+        let query_knn = query_neighbors
+            .into_iter()
+            .map(|(q, n)| (q, self.nearest(n)))
+            .collect::<HashMap<_, _>>();
+        let track_knn = track_neighbors
+            .into_iter()
+            .map(|(t, n)| (t, self.nearest(n)))
+            .collect::<HashMap<_, _>>();
+
+        let reciprocal = |q: &TrackId, t: &TrackId| {
+            query_knn.get(q).map(|n| n.contains(t)).unwrap_or(false)
+                && track_knn.get(t).map(|n| n.contains(q)).unwrap_or(false)
+        };
+
+        // query track encodings over the candidate tracks
+        let query_vectors = query_knn
+            .iter()
+            .map(|(q, knn)| {
+                let v = knn
+                    .iter()
+                    .filter(|t| reciprocal(q, t))
+                    .map(|t| (*t, (-pairs[&(*q, *t)]).exp()))
+                    .collect::<HashMap<_, _>>();
+                (*q, v)
+            })
+            .collect::<HashMap<_, _>>();
+
+        // candidate track encodings as means of their k-reciprocal query track encodings
+        let track_vectors = track_knn
+            .iter()
+            .map(|(t, knn)| {
+                let queries = knn.iter().filter(|q| reciprocal(q, t)).collect::<Vec<_>>();
+                let mut v: HashMap<TrackId, f32> = HashMap::new();
+                for q in &queries {
+                    for (id, w) in &query_vectors[*q] {
+                        *v.entry(*id).or_default() += w / queries.len() as f32;
+                    }
+                }
+                (*t, v)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let jaccard = |l: &HashMap<TrackId, f32>, r: &HashMap<TrackId, f32>| {
+            let keys = l.keys().chain(r.keys()).collect::<HashSet<_>>();
+            let (min, max) = keys.into_iter().fold((0.0_f32, 0.0_f32), |(min, max), k| {
+                let (lv, rv) = (
+                    l.get(k).copied().unwrap_or(0.0),
+                    r.get(k).copied().unwrap_or(0.0),
+                );
+                (min + lv.min(rv), max + lv.max(rv))
+            });
+            if max > 0.0 {
+                1.0 - min / max
+            } else {
+                1.0
+            }
+        };
+
+        let mut results: HashMap<TrackId, Vec<(f32, TopNVotingElt)>> = HashMap::new();
+        for ((q, t), d) in pairs {
+            if d > max_distance {
+                continue;
+            }
+            let reranked = self.lambda * d
+                + (1.0 - self.lambda) * jaccard(&query_vectors[&q], &track_vectors[&t]);
+            if reranked > max_distance {
+                continue;
+            }
+            results.entry(q).or_default().push((
+                reranked,
+                TopNVotingElt::new(q, t, (max_distance - reranked) as f64),
+            ));
+        }
+
+        results
+            .into_iter()
+            .map(|(q, mut winners)| {
+                winners.sort_by(|(ld, le), (rd, re)| {
+                    ld.total_cmp(rd).then(le.winner_track.cmp(&re.winner_track))
+                });
+                (
+                    q,
+                    winners
+                        .into_iter()
+                        .take(self.topn)
+                        .map(|(_, e)| e)
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::reciprocal::KReciprocalVoting;
+    use crate::track::{MetricPolarity, ObservationMetricOk};
+    use crate::voting::Voting;
+
+    #[test]
+    fn reciprocal_neighbors_boosted() {
+        // track 10 is the closest one for the query 1, but the query 1 is not among its nearest queries,
+        // while the query 1 and the track 20 are k-reciprocal neighbors
+        let distances = [
+            ObservationMetricOk::new(1, 10, None, Some(0.30)),
+            ObservationMetricOk::new(1, 20, None, Some(0.32)),
+            ObservationMetricOk::new(1, 30, None, Some(0.90)),
+            ObservationMetricOk::new(2, 10, None, Some(0.10)),
+            ObservationMetricOk::new(2, 20, None, Some(0.90)),
+            ObservationMetricOk::new(2, 30, None, Some(0.15)),
+            ObservationMetricOk::new(3, 10, None, Some(0.12)),
+            ObservationMetricOk::new(3, 20, None, Some(0.90)),
+            ObservationMetricOk::new(3, 30, None, Some(0.20)),
+        ];
+        let winners = |v: KReciprocalVoting<()>, q| {
+            v.winners(distances.clone())[&q]
+                .iter()
+                .map(|e| e.winner_track)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            winners(KReciprocalVoting::with_lambda(2, 1.0, 2, 1.0), 1),
+            vec![10, 20]
+        );
+        assert_eq!(winners(KReciprocalVoting::new(2, 1.0, 2), 1), vec![20, 10]);
+        assert_eq!(winners(KReciprocalVoting::new(2, 1.0, 2), 2), vec![10, 30]);
+
+        // the similarities `1 - distance` give the same neighborhoods
+        let similarities = distances
+            .iter()
+            .cloned()
+            .map(|mut e| {
+                e.feature_distance = e.feature_distance.map(|d| 1.0 - d);
+                e
+            })
+            .collect::<Vec<_>>();
+        let v: KReciprocalVoting<()> =
+            KReciprocalVoting::new(2, -1.0, 2).with_polarity(MetricPolarity::Similarity);
+        assert_eq!(
+            v.winners(similarities)[&1]
+                .iter()
+                .map(|e| e.winner_track)
+                .collect::<Vec<_>>(),
+            vec![20, 10]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_lambda() {
+        let _: KReciprocalVoting<()> = KReciprocalVoting::with_lambda(1, 1.0, 1, 1.5);
+    }
+}
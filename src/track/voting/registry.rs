@@ -0,0 +1,153 @@
+use crate::track::{ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::Voting;
+use crate::Errors;
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Object-safe counterpart of [Voting](Voting)
+///
+/// Implemented for every voting engine, so the engines can be used as trait objects.
+///
+pub trait DynVoting<OA, W>
+where
+    OA: ObservationAttributes,
+{
+    /// Method that selects winning tracks
+    ///
+    /// # Arguments
+    /// * `distances` - distances resulted from the distance calculation.
+    ///
+    fn dyn_winners(&self, distances: Vec<ObservationMetricOk<OA>>) -> HashMap<TrackId, Vec<W>>;
+}
+
+impl<OA, V> DynVoting<OA, V::WinnerObject> for V
+where
+    OA: ObservationAttributes,
+    V: Voting<OA>,
+{
+    fn dyn_winners(
+        &self,
+        distances: Vec<ObservationMetricOk<OA>>,
+    ) -> HashMap<TrackId, Vec<V::WinnerObject>> {
+        self.winners(distances)
+    }
+}
+
+/// Registry of named voting engines
+///
+/// Engines are registered under names and selected by the name at runtime, e.g. from a configuration file.
+///
+pub struct VotingRegistry<OA, W = TopNVotingElt>
+where
+    OA: ObservationAttributes,
+{
+    engines: HashMap<String, Box<dyn DynVoting<OA, W> + Send + Sync>>,
+}
+
+impl<OA, W> Default for VotingRegistry<OA, W>
+where
+    OA: ObservationAttributes,
+{
+    fn default() -> Self {
+        Self {
+            engines: HashMap::default(),
+        }
+    }
+}
+
+impl<OA, W> VotingRegistry<OA, W>
+where
+    OA: ObservationAttributes,
+{
+    /// Registers the engine under the name
+    ///
+    /// # Arguments
+    /// * `name` - the name of the engine
+    /// * `engine` - the engine
+    ///
+    pub fn register<V>(&mut self, name: &str, engine: V) -> Result<()>
+    where
+        V: DynVoting<OA, W> + Send + Sync + 'static,
+    {
+        if self.engines.contains_key(name) {
+            return Err(Errors::DuplicateVotingEngine(name.to_string()).into());
+        }
+        self.engines.insert(name.to_string(), Box::new(engine));
+        Ok(())
+    }
+
+    /// Removes the engine registered under the name
+    ///
+    pub fn unregister(&mut self, name: &str) -> Option<Box<dyn DynVoting<OA, W> + Send + Sync>> {
+        self.engines.remove(name)
+    }
+
+    /// Returns the engine registered under the name
+    ///
+    pub fn get(&self, name: &str) -> Result<&(dyn DynVoting<OA, W> + Send + Sync)> {
+        self.engines
+            .get(name)
+            .map(|e| e.as_ref())
+            .ok_or_else(|| Errors::VotingEngineNotFound(name.to_string()).into())
+    }
+
+    /// Names of registered engines
+    ///
+    pub fn names(&self) -> Vec<String> {
+        self.engines.keys().cloned().collect()
+    }
+
+    /// Selects winning tracks with the engine registered under the name
+    ///
+    /// # Arguments
+    /// * `name` - the name of the engine
+    /// * `distances` - distances resulted from the distance calculation.
+    ///
+    pub fn winners<T>(&self, name: &str, distances: T) -> Result<HashMap<TrackId, Vec<W>>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        Ok(self.get(name)?.dyn_winners(distances.into_iter().collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::registry::VotingRegistry;
+    use crate::track::ObservationMetricOk;
+    use crate::voting::nearest::NearestVoting;
+    use crate::voting::topn::TopNVoting;
+
+    #[test]
+    fn select_by_name() {
+        let mut registry: VotingRegistry<()> = VotingRegistry::default();
+        registry
+            .register("topn", TopNVoting::new(1, 1.0, 1))
+            .unwrap();
+        registry
+            .register("nearest", NearestVoting::new(1.0))
+            .unwrap();
+        assert!(registry
+            .register("nearest", NearestVoting::new(0.5))
+            .is_err());
+
+        let distances = || {
+            [
+                ObservationMetricOk::new(1, 10, None, Some(0.5)),
+                ObservationMetricOk::new(1, 10, None, Some(0.5)),
+                ObservationMetricOk::new(1, 20, None, Some(0.25)),
+                ObservationMetricOk::new(1, 30, None, Some(0.9)),
+            ]
+        };
+
+        let winner = |name| registry.winners(name, distances()).unwrap()[&1][0].winner_track;
+        assert_eq!(winner("topn"), 10);
+        assert_eq!(winner("nearest"), 20);
+        assert!(registry.winners("missing", distances()).is_err());
+
+        let mut names = registry.names();
+        names.sort();
+        assert_eq!(names, vec!["nearest".to_string(), "topn".to_string()]);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::borda::{rank_candidates, top_scores};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::Voting;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Default RRF rank constant
+///
+pub const DEFAULT_RRF_K: f64 = 60.0;
+
+/// Reciprocal rank fusion (RRF) voting engine.
+///
+/// Merges ranked candidate lists produced for several ballots (e.g. distances calculated for
+/// different feature classes, like appearance embeddings and bounding box metrics). The candidates are ranked
+/// for every query observation of the ballot, look at
+/// [query_observation](crate::track::ObservationMetricOk::query_observation). The candidate ranked `r` (one-based)
+/// receives `1 / (k + r)` points, points are summed across the observations and the ballots, so no manual score
+/// normalization is required.
+///
+/// [Voting::winners](Voting::winners) treats the passed distances as a single ballot, use
+/// [RRFVoting::winners_ballots](RRFVoting::winners_ballots) to fuse several ballots.
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities, `max_distance` is
+/// the min similarity permitted to participate and the most similar candidates get the top ranks.
+///
+pub struct RRFVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    topn: usize,
+    max_distance: f32,
+    polarity: MetricPolarity,
+    k: f64,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> RRFVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine with the default rank constant [DEFAULT_RRF_K](DEFAULT_RRF_K)
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance permitted to participate
+    ///
+    pub fn new(topn: usize, max_distance: f32) -> Self {
+        Self::with_k(topn, max_distance, DEFAULT_RRF_K)
+    }
+
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance permitted to participate
+    /// * `k` - rank constant, lower values increase the influence of top ranks
+    ///
+    pub fn with_k(topn: usize, max_distance: f32, k: f64) -> Self {
+        Self {
+            topn,
+            max_distance,
+            polarity: MetricPolarity::default(),
+            k,
+            _phony: PhantomData,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Fuses ranks across several ballots
+    ///
+    /// # Arguments
+    /// * `ballots` - distance sets, the candidates are ranked for every query observation of every set independently
+    ///
+    pub fn winners_ballots<B, T>(&self, ballots: B) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        B: IntoIterator<Item = T>,
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut scores: HashMap<TrackId, HashMap<TrackId, f64>> = HashMap::new();
+        for ballot in ballots {
+            for ((query, _), ranked) in rank_candidates(ballot, self.max_distance, self.polarity) {
+                let query_scores = scores.entry(query).or_default();
+                for (rank, w) in ranked.into_iter().enumerate() {
+                    *query_scores.entry(w).or_default() += 1.0 / (self.k + (rank + 1) as f64);
+                }
+            }
+        }
+        top_scores(scores, self.topn)
+    }
+}
+
+impl<OA> Voting<OA> for RRFVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        self.winners_ballots([distances])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::rrf::RRFVoting;
+    use crate::track::ObservationMetricOk;
+    use crate::voting::Voting;
+
+    #[test]
+    fn fused_ranking() {
+        let v: RRFVoting<()> = RRFVoting::with_k(5, 100.0, 1.0);
+        let res = v.winners_ballots([
+            vec![
+                ObservationMetricOk::new(0, 1, None, Some(0.1)),
+                ObservationMetricOk::new(0, 2, None, Some(0.2)),
+                ObservationMetricOk::new(0, 3, None, Some(0.3)),
+            ],
+            vec![
+                ObservationMetricOk::new(0, 3, None, Some(10.0)),
+                ObservationMetricOk::new(0, 2, None, Some(20.0)),
+            ],
+        ]);
+        let winners = res.get(&0).unwrap();
+        assert_eq!(
+            winners.iter().map(|e| e.winner_track).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+        // 1/(1+3) + 1/(1+1)
+        assert!((winners[0].weight - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn single_ballot() {
+        let v: RRFVoting<()> = RRFVoting::new(1, 0.5);
+        let res = v.winners([
+            ObservationMetricOk::new(0, 1, None, Some(0.4)),
+            ObservationMetricOk::new(0, 2, None, Some(0.3)),
+            ObservationMetricOk::new(1, 2, None, Some(0.7)),
+        ]);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res.get(&0).unwrap()[0].winner_track, 2);
+    }
+}
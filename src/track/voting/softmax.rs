@@ -0,0 +1,181 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::{oriented, Voting};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Softmax-score voting engine.
+///
+/// Distances are converted to probabilities with softmax `exp(-d / temperature)` normalized across all
+/// distances of the query track. Winners are ranked by the accumulated probability mass. Low temperature
+/// favors single very close matches, high temperature favors candidates with many moderately close votes.
+///
+/// It calculates winners as:
+/// 1. removes all distances that are greater than threshold
+/// 2. calculates softmax probabilities across all remaining distances of the query track
+/// 3. sums probabilities for every (query, winner) pair
+/// 4. sorts winners by the accumulated mass decreasingly and returns TopN
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities, `max_distance` is
+/// the min similarity permitted to participate and the probabilities are `exp(similarity / temperature)` normalized
+/// the same way.
+///
+pub struct SoftmaxVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    topn: usize,
+    max_distance: f32,
+    polarity: MetricPolarity,
+    temperature: f32,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> SoftmaxVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance permitted to participate
+    /// * `temperature` - softmax temperature, must be positive
+    ///
+    pub fn new(topn: usize, max_distance: f32, temperature: f32) -> Self {
+        assert!(temperature > 0.0, "Temperature must be positive.");
+        Self {
+            topn,
+            max_distance,
+            polarity: MetricPolarity::default(),
+            temperature,
+            _phony: PhantomData,
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+}
+
+impl<OA> Voting<OA> for SoftmaxVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let max_distance = self.polarity.orient(self.max_distance);
+        oriented(distances, self.polarity)
+            .flat_map(
+                |ObservationMetricOk {
+                     from,
+                     to,
+                     attribute_metric: _,
+                     feature_distance,
+                     weight: _,
+                     stamp: _,
+                     query_observation: _,
+                 }| {
+                    feature_distance
+                        .filter(|d| *d <= max_distance)
+                        .map(|d| (from, (to, d)))
+                },
+            )
+            .into_group_map()
+            .into_iter()
+            .map(|(query, dists)| {
+                // shift by the minimal distance to keep exponents in a safe range
+                let min_dist = dists.iter().map(|(_, d)| *d).fold(f32::MAX, f32::min) as f64;
+                let temperature = self.temperature as f64;
+                let scores = dists
+                    .into_iter()
+                    .map(|(w, d)| (w, (-(d as f64 - min_dist) / temperature).exp()))
+                    .collect::<Vec<_>>();
+                let total: f64 = scores.iter().map(|(_, s)| s).sum();
+
+                let mut winners = scores
+                    .into_iter()
+                    .into_group_map()
+                    .into_iter()
+                    .map(|(w, s)| TopNVotingElt::new(query, w, s.into_iter().sum::<f64>() / total))
+                    .collect::<Vec<_>>();
+
+                winners.sort_by(|l, r| {
+                    r.weight
+                        .total_cmp(&l.weight)
+                        .then(l.winner_track.cmp(&r.winner_track))
+                });
+                winners.truncate(self.topn);
+                (query, winners)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::softmax::SoftmaxVoting;
+    use crate::track::{MetricPolarity, ObservationMetricOk};
+    use crate::voting::Voting;
+
+    fn distances() -> Vec<ObservationMetricOk<()>> {
+        vec![
+            ObservationMetricOk::new(0, 1, None, Some(0.05)),
+            ObservationMetricOk::new(0, 2, None, Some(0.2)),
+            ObservationMetricOk::new(0, 2, None, Some(0.2)),
+            ObservationMetricOk::new(0, 2, None, Some(0.2)),
+            ObservationMetricOk::new(0, 3, None, Some(0.9)),
+        ]
+    }
+
+    #[test]
+    fn temperature_tradeoff() {
+        let v: SoftmaxVoting<()> = SoftmaxVoting::new(5, 0.5, 0.01);
+        let res = v.winners(distances());
+        let winners = res.get(&0).unwrap();
+        assert_eq!(winners.len(), 2);
+        assert_eq!(winners[0].winner_track, 1);
+
+        let v: SoftmaxVoting<()> = SoftmaxVoting::new(1, 0.5, 1.0);
+        let res = v.winners(distances());
+        let winners = res.get(&0).unwrap();
+        assert_eq!(winners.len(), 1);
+        assert_eq!(winners[0].winner_track, 2);
+    }
+
+    #[test]
+    fn mass_is_normalized() {
+        let v: SoftmaxVoting<()> = SoftmaxVoting::new(5, 1.0, 0.5);
+        let res = v.winners(distances());
+        let total: f64 = res.get(&0).unwrap().iter().map(|e| e.weight).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn similarity_mass() {
+        let v: SoftmaxVoting<()> =
+            SoftmaxVoting::new(5, 0.5, 0.01).with_polarity(MetricPolarity::Similarity);
+        let res = v.winners(
+            distances()
+                .into_iter()
+                .map(|mut e| {
+                    e.feature_distance = e.feature_distance.map(|d| 1.0 - d);
+                    e
+                })
+                .collect::<Vec<_>>(),
+        );
+        let winners = res.get(&0).unwrap();
+        assert_eq!(winners.len(), 2);
+        assert_eq!(winners[0].winner_track, 1);
+        let total: f64 = winners.iter().map(|e| e.weight).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+}
@@ -1,8 +1,89 @@
-use crate::track::{ObservationAttributes, ObservationMetricOk};
-use crate::voting::Voting;
+/// Auxiliary class that helps to build the engine
+pub mod builder;
+
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::diagnostics::VotingDiagnostics;
+use crate::voting::{BatchVoting, Voting};
 use itertools::Itertools;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::sync::Mutex;
+
+/// Policy to order winners that gathered identical weights
+///
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    /// the winner with the lowest mean distance of the votes goes first
+    #[default]
+    LowestMeanDistance,
+    /// the winner with the lowest minimal distance of the votes goes first
+    LowestMinDistance,
+    /// the winner with the lowest track id goes first
+    LowestId,
+    /// the winner with the highest track id goes first
+    HighestId,
+}
+
+/// Per-query threshold computed from the distribution of the query track distances
+///
+/// The computed threshold never exceeds the engine's `max_distance`.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdaptiveThreshold {
+    /// `mean - k * std` of the query track distances
+    MeanMinusStd(f32),
+    /// the percentile (`0.0..=1.0`) of the query track distances
+    Percentile(f32),
+}
+
+impl AdaptiveThreshold {
+    fn threshold(&self, distances: &mut [f32]) -> f32 {
+        match self {
+            AdaptiveThreshold::MeanMinusStd(k) => {
+                let n = distances.len() as f32;
+                let mean = distances.iter().sum::<f32>() / n;
+                let var = distances.iter().map(|d| (d - mean).powi(2)).sum::<f32>() / n;
+                mean - k * var.sqrt()
+            }
+            AdaptiveThreshold::Percentile(p) => {
+                distances.sort_by(|l, r| l.total_cmp(r));
+                let pos = ((distances.len() - 1) as f32 * p.clamp(0.0, 1.0)).round() as usize;
+                distances[pos]
+            }
+        }
+    }
+}
+
+/// Exponential decay of the votes contributed by old observations
+///
+/// The vote weight is multiplied by `exp(-rate * age)`, where `age` is the number of epochs elapsed from the epoch
+/// the observation of the compared track was stamped with (look at [ObservationMetricOk::stamp](ObservationMetricOk::stamp))
+/// to the current epoch. The votes stamped after the current epoch are not discounted.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoteDecay {
+    /// decay rate per epoch
+    pub rate: f32,
+    /// the current epoch, e.g. the epoch of the store
+    pub current_epoch: usize,
+}
+
+impl VoteDecay {
+    fn factor<OA>(&self, e: &ObservationMetricOk<OA>) -> f32
+    where
+        OA: ObservationAttributes,
+    {
+        let age = self.current_epoch.saturating_sub(e.stamp.epoch);
+        (-self.rate * age as f32).exp()
+    }
+}
+
+/// Default amount of distances starting from which votes are counted in parallel
+///
+#[cfg(feature = "parallel")]
+pub const DEFAULT_PARALLEL_THRESHOLD: usize = 10_000;
 
 /// TopN winners voting engine that selects Top N vectors with most close distances.
 ///
@@ -13,6 +94,16 @@ use std::marker::PhantomData;
 /// 4. sorts groups by frequency decreasingly
 /// 5. returns TopN
 ///
+/// Winners are ordered by the weight decreasingly, then by the [TieBreak](TieBreak) policy (mean distance
+/// increasingly by default), then by the winner track id, so the results are reproducible across runs.
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities: `max_distance` is the
+/// min similarity permitted to participate, the weight of the vote is `similarity - min_similarity_of_all_votes`
+/// and the tie break policies prefer higher similarities.
+///
+/// With the `parallel` feature enabled, the distances are partitioned and the votes are counted
+/// for the partitions in parallel when the amount of distances reaches the parallel threshold.
+///
 pub struct TopNVoting<OA>
 where
     OA: ObservationAttributes,
@@ -20,6 +111,13 @@ where
     topn: usize,
     max_distance: f32,
     min_votes: usize,
+    tie_break: TieBreak,
+    adaptive_threshold: Option<AdaptiveThreshold>,
+    #[cfg(feature = "parallel")]
+    parallel_threshold: usize,
+    diagnostics: Option<Mutex<VotingDiagnostics>>,
+    decay: Option<VoteDecay>,
+    polarity: MetricPolarity,
     _phony: PhantomData<OA>,
 }
 
@@ -39,24 +137,179 @@ where
             topn,
             max_distance,
             min_votes,
+            tie_break: TieBreak::default(),
+            adaptive_threshold: None,
+            #[cfg(feature = "parallel")]
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            diagnostics: None,
+            decay: None,
+            polarity: MetricPolarity::default(),
             _phony: PhantomData,
         }
     }
+
+    /// Top winners
+    ///
+    pub fn topn(&self) -> usize {
+        self.topn
+    }
+
+    /// Max distance permitted to participate
+    ///
+    pub fn max_distance(&self) -> f32 {
+        self.max_distance
+    }
+
+    /// Minimal amount of votes required the track to participate
+    ///
+    pub fn min_votes(&self) -> usize {
+        self.min_votes
+    }
+
+    /// Changes the amount of top winners
+    ///
+    pub fn set_topn(&mut self, topn: usize) {
+        self.topn = topn;
+    }
+
+    /// Changes the max distance permitted to participate
+    ///
+    pub fn set_max_distance(&mut self, max_distance: f32) {
+        self.max_distance = max_distance;
+    }
+
+    /// Changes the minimal amount of votes required the track to participate
+    ///
+    pub fn set_min_votes(&mut self, min_votes: usize) {
+        self.min_votes = min_votes;
+    }
+
+    /// Sets the policy to order winners with identical weights
+    ///
+    /// When the policy doesn't resolve the tie, the winner with the lower track id goes first.
+    ///
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Enables the threshold computed for every query track from its distances distribution
+    ///
+    /// The effective threshold is `min(max_distance, adaptive_threshold)`.
+    ///
+    pub fn with_adaptive_threshold(mut self, adaptive_threshold: AdaptiveThreshold) -> Self {
+        self.adaptive_threshold = Some(adaptive_threshold);
+        self
+    }
+
+    /// Enables the exponential decay of the votes contributed by old observations
+    ///
+    /// # Arguments
+    /// * `rate` - decay rate per epoch
+    /// * `current_epoch` - the epoch the ages of the votes are counted to, e.g. the epoch of the store
+    ///
+    pub fn with_vote_decay(mut self, rate: f32, current_epoch: usize) -> Self {
+        assert!(rate >= 0.0, "Decay rate must be non-negative");
+        self.decay = Some(VoteDecay {
+            rate,
+            current_epoch,
+        });
+        self
+    }
+
+    /// Changes the epoch the ages of the votes are counted to, e.g. every time the epoch of the store advances
+    ///
+    /// Does nothing when the vote decay is not enabled with [with_vote_decay](TopNVoting::with_vote_decay).
+    ///
+    pub fn set_current_epoch(&mut self, current_epoch: usize) {
+        if let Some(decay) = self.decay.as_mut() {
+            decay.current_epoch = current_epoch;
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Polarity of the distances
+    ///
+    pub fn polarity(&self) -> MetricPolarity {
+        self.polarity
+    }
+
+    /// Enables the diagnostics of the engine calls, retrieved with [TopNVoting::diagnostics](TopNVoting::diagnostics)
+    ///
+    pub fn with_diagnostics(mut self) -> Self {
+        self.diagnostics = Some(Mutex::new(VotingDiagnostics::default()));
+        self
+    }
+
+    /// Diagnostics of the last engine call, `None` when the diagnostics are not enabled
+    ///
+    pub fn diagnostics(&self) -> Option<VotingDiagnostics> {
+        self.diagnostics.as_ref().map(|d| d.lock().unwrap().clone())
+    }
+
+    /// Sets the amount of distances starting from which the votes are counted in parallel
+    ///
+    /// The default is [DEFAULT_PARALLEL_THRESHOLD](DEFAULT_PARALLEL_THRESHOLD).
+    ///
+    #[cfg(feature = "parallel")]
+    pub fn with_parallel_threshold(mut self, parallel_threshold: usize) -> Self {
+        self.parallel_threshold = parallel_threshold;
+        self
+    }
+
+    fn group_votes(
+        &self,
+        distances: Vec<((TrackId, TrackId), (f32, f32))>,
+        thresholds: &HashMap<TrackId, f32>,
+    ) -> HashMap<(TrackId, TrackId), Vec<(f32, f32)>> {
+        let max_distance = self.polarity.orient(self.max_distance);
+        let accepted = |((q, _), (d, _)): &((TrackId, TrackId), (f32, f32))| {
+            *d <= *thresholds.get(q).unwrap_or(&max_distance)
+        };
+
+        #[cfg(feature = "parallel")]
+        if distances.len() >= self.parallel_threshold {
+            let chunk_size = (distances.len() / rayon::current_num_threads()).max(1);
+            // partitions are merged in order, so the votes keep the sequential order
+            return distances
+                .par_chunks(chunk_size)
+                .map(|part| {
+                    part.iter()
+                        .filter(|e| accepted(e))
+                        .copied()
+                        .into_group_map()
+                })
+                .reduce(HashMap::default, |mut acc, part| {
+                    for (pair, dists) in part {
+                        acc.entry(pair).or_default().extend(dists);
+                    }
+                    acc
+                });
+        }
+
+        distances.into_iter().filter(accepted).into_group_map()
+    }
 }
 
 /// Return type fot TopN voting engine
 ///
 #[derive(Default, Debug, PartialEq)]
 pub struct TopNVotingElt {
-    pub query_track: u64,
+    pub query_track: TrackId,
     /// winning track
-    pub winner_track: u64,
+    pub winner_track: TrackId,
     /// number of votes it gathered
     pub weight: f64,
 }
 
 impl TopNVotingElt {
-    pub fn new(query_track: u64, winner_track: u64, weight: f64) -> Self {
+    pub fn new(query_track: TrackId, winner_track: TrackId, weight: f64) -> Self {
         Self {
             query_track,
             winner_track,
@@ -65,81 +318,219 @@ impl TopNVotingElt {
     }
 }
 
-impl<OA> Voting<OA> for TopNVoting<OA>
+/// TopN voting engine winner together with the statistics of the votes it gathered
+///
+#[derive(Default, Debug, PartialEq)]
+pub struct TopNVotingStatsElt {
+    /// the winner
+    pub elt: TopNVotingElt,
+    /// number of votes the winner gathered
+    pub votes: usize,
+    /// minimal distance of the votes (maximal similarity for the similarity polarity)
+    pub min_distance: f32,
+    /// mean distance of the votes
+    pub mean_distance: f32,
+    /// normalized confidence of the match in `0.0..=1.0`
+    ///
+    /// The mean of:
+    /// * the share of the query track votes the winner gathered
+    /// * `1 - mean_distance / threshold`
+    /// * the weight margin to the next ranked winner `(weight - next_weight) / weight`
+    ///
+    pub confidence: f32,
+}
+
+impl<OA> TopNVoting<OA>
 where
     OA: ObservationAttributes,
 {
-    type WinnerObject = TopNVotingElt;
-
-    fn winners<T>(&self, distances: T) -> HashMap<u64, Vec<TopNVotingElt>>
+    /// Selects winning tracks like [Voting::winners](Voting::winners) does, but keeps the statistics
+    /// of the votes for every winner, so the caller can use it as a confidence signal.
+    ///
+    /// # Arguments
+    /// * `distances` - distances resulted from the distance calculation.
+    ///
+    pub fn winners_with_stats<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingStatsElt>>
     where
         T: IntoIterator<Item = ObservationMetricOk<OA>>,
     {
-        let mut max_dist = -1.0_f32;
-        let counts: Vec<_> = distances
+        // the distances are oriented, so smaller values are better for both polarities
+        let max_distance = self.polarity.orient(self.max_distance);
+        let mut max_dist = f32::MIN;
+        let mut diagnostics = VotingDiagnostics::default();
+        let distances = distances
             .into_iter()
-            .filter(
-                |ObservationMetricOk {
-                     from: _,
-                     to: _track,
-                     attribute_metric: _f_attr_dist,
-                     feature_distance: feat_dist,
-                 }| match feat_dist {
-                    Some(e) => {
-                        if max_dist < *e {
-                            max_dist = *e;
-                        }
-                        *e <= self.max_distance
-                    }
-                    _ => false,
-                },
-            )
-            .map(
+            .inspect(|_| diagnostics.distances += 1)
+            .map(|e| match &self.decay {
+                Some(decay) => {
+                    let weight = e.weight * decay.factor(&e);
+                    e.with_weight(weight)
+                }
+                None => e,
+            })
+            .flat_map(
                 |ObservationMetricOk {
                      from: src_track,
                      to: dest_track,
                      attribute_metric: _,
                      feature_distance: dist,
-                 }| { ((src_track, dest_track), dist.unwrap()) },
+                     weight,
+                     stamp: _,
+                     query_observation: _,
+                 }| {
+                    let dist = self.polarity.orient(dist.filter(|d| !d.is_nan())?);
+                    if max_dist < dist {
+                        max_dist = dist;
+                    }
+                    Some(((src_track, dest_track), (dist, weight)))
+                },
             )
-            .into_group_map()
+            .collect::<Vec<_>>();
+
+        let thresholds = match &self.adaptive_threshold {
+            None => HashMap::default(),
+            Some(adaptive) => distances
+                .iter()
+                .map(|((q, _), (d, _))| (*q, *d))
+                .into_group_map()
+                .into_iter()
+                .map(|(q, mut dists)| (q, adaptive.threshold(&mut dists).min(max_distance)))
+                .collect::<HashMap<_, _>>(),
+        };
+
+        diagnostics.missing_distances = diagnostics.distances - distances.len();
+        let candidates = distances.len();
+        let groups = self.group_votes(distances, &thresholds);
+        diagnostics.filtered_by_threshold =
+            candidates - groups.values().map(Vec::len).sum::<usize>();
+        for c in groups.values() {
+            *diagnostics.vote_histogram.entry(c.len()).or_default() += 1;
+        }
+        let mut query_votes: HashMap<TrackId, usize> = HashMap::new();
+        for ((q, _), c) in &groups {
+            *query_votes.entry(*q).or_default() += c.len();
+        }
+
+        let counts: Vec<_> = groups
             .into_iter()
-            .filter(|(_, count)| count.len() >= self.min_votes)
+            .filter(|(_, count)| {
+                let accepted = count.len() >= self.min_votes;
+                if !accepted {
+                    diagnostics.filtered_by_votes += 1;
+                }
+                accepted
+            })
             .map(|((q, w), c)| {
-                let weight = c.into_iter().map(|d| (max_dist - d) as f64).sum();
+                let votes = c.len();
+                let min_distance = c.iter().map(|(d, _)| *d).fold(f32::MAX, f32::min);
+                let mean_distance = c.iter().map(|(d, _)| d).sum::<f32>() / votes as f32;
+                let weight = c
+                    .into_iter()
+                    .map(|(d, w)| (max_dist - d) as f64 * w as f64)
+                    .sum();
+                let threshold = *thresholds.get(&q).unwrap_or(&max_distance);
+                let vote_share = votes as f32 / query_votes[&q] as f32;
+                let closeness = if threshold != 0.0 {
+                    ((threshold - mean_distance) / threshold.abs()).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
 
-                TopNVotingElt {
-                    query_track: q,
-                    winner_track: w,
-                    weight,
+                TopNVotingStatsElt {
+                    elt: TopNVotingElt {
+                        query_track: q,
+                        winner_track: w,
+                        weight,
+                    },
+                    votes,
+                    min_distance,
+                    mean_distance,
+                    // the margin is added when winners are ranked
+                    confidence: vote_share + closeness,
                 }
             })
             .collect::<Vec<_>>();
 
-        let mut results: HashMap<u64, Vec<TopNVotingElt>> = HashMap::new();
+        let mut results: HashMap<TrackId, Vec<TopNVotingStatsElt>> = HashMap::new();
 
         for c in counts {
-            let key = c.query_track;
-            if let Some(val) = results.get_mut(&key) {
-                val.push(c);
-            } else {
-                results.insert(key, vec![c]);
-            }
+            results.entry(c.elt.query_track).or_default().push(c);
         }
 
         for counts in results.values_mut() {
-            counts.sort_by(|l, r| r.weight.partial_cmp(&l.weight).unwrap());
+            counts.sort_by(|l, r| {
+                let tie = match self.tie_break {
+                    TieBreak::LowestMeanDistance => l.mean_distance.total_cmp(&r.mean_distance),
+                    TieBreak::LowestMinDistance => l.min_distance.total_cmp(&r.min_distance),
+                    TieBreak::LowestId => l.elt.winner_track.cmp(&r.elt.winner_track),
+                    TieBreak::HighestId => r.elt.winner_track.cmp(&l.elt.winner_track),
+                };
+                r.elt
+                    .weight
+                    .total_cmp(&l.elt.weight)
+                    .then(tie)
+                    .then(l.elt.winner_track.cmp(&r.elt.winner_track))
+            });
+
+            let next_weights = counts
+                .iter()
+                .skip(1)
+                .map(|e| e.elt.weight)
+                .chain([0.0])
+                .collect::<Vec<_>>();
+            if counts.len() > 1 {
+                diagnostics
+                    .margins
+                    .push(counts[0].elt.weight - next_weights[0]);
+            }
+            for (c, next_weight) in counts.iter_mut().zip(next_weights) {
+                let margin = if c.elt.weight > 0.0 {
+                    ((c.elt.weight - next_weight) / c.elt.weight) as f32
+                } else {
+                    0.0
+                };
+                c.confidence = (c.confidence + margin) / 3.0;
+                c.min_distance = self.polarity.orient(c.min_distance);
+                c.mean_distance = self.polarity.orient(c.mean_distance);
+            }
+
             counts.truncate(self.topn);
         }
 
+        if let Some(d) = &self.diagnostics {
+            *d.lock().unwrap() = diagnostics;
+        }
+
         results
     }
 }
 
+impl<OA> Voting<OA> for TopNVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        self.winners_with_stats(distances)
+            .into_iter()
+            .map(|(q, winners)| (q, winners.into_iter().map(|e| e.elt).collect()))
+            .collect()
+    }
+}
+
+impl<OA> BatchVoting<OA> for TopNVoting<OA> where OA: ObservationAttributes {}
+
 #[cfg(test)]
 mod tests {
-    use crate::track::voting::topn::{TopNVoting, TopNVotingElt, Voting};
-    use crate::track::ObservationMetricOk;
+    use crate::track::voting::topn::{
+        AdaptiveThreshold, TieBreak, TopNVoting, TopNVotingElt, TopNVotingStatsElt, Voting,
+    };
+    use crate::track::{MetricPolarity, ObservationMetricOk, ObservationStamp, TrackId};
+    use itertools::Itertools;
     use std::collections::HashMap;
 
     #[test]
@@ -277,4 +668,272 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn tie_break() {
+        let distances = || {
+            [
+                ObservationMetricOk::new(0, 1, None, Some(0.25)),
+                ObservationMetricOk::new(0, 1, None, Some(0.75)),
+                ObservationMetricOk::new(0, 2, None, Some(0.5)),
+                ObservationMetricOk::new(0, 2, None, Some(0.5)),
+                ObservationMetricOk::new(0, 3, None, Some(0.5)),
+                ObservationMetricOk::new(0, 3, None, Some(0.5)),
+                ObservationMetricOk::new(0, 4, None, Some(1.0)),
+            ]
+        };
+        let winners = |tie_break| {
+            let v: TopNVoting<()> = TopNVoting::new(2, 1.0, 2).with_tie_break(tie_break);
+            v.winners(distances())
+                .remove(&0)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.winner_track)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(winners(TieBreak::LowestMinDistance), vec![1, 2]);
+        assert_eq!(winners(TieBreak::LowestMeanDistance), vec![1, 2]);
+        assert_eq!(winners(TieBreak::LowestId), vec![1, 2]);
+        assert_eq!(winners(TieBreak::HighestId), vec![3, 2]);
+    }
+
+    #[test]
+    fn adaptive_threshold() {
+        let distances = || {
+            [
+                ObservationMetricOk::new(0, 1, None, Some(0.1)),
+                ObservationMetricOk::new(0, 2, None, Some(0.2)),
+                ObservationMetricOk::new(0, 3, None, Some(0.3)),
+                ObservationMetricOk::new(0, 4, None, Some(0.4)),
+                ObservationMetricOk::new(0, 5, None, Some(0.5)),
+                ObservationMetricOk::new(1, 6, None, Some(1.0)),
+                ObservationMetricOk::new(1, 7, None, Some(2.0)),
+            ]
+        };
+        let winners = |v: TopNVoting<()>| {
+            let mut res = v
+                .winners(distances())
+                .into_iter()
+                .map(|(q, w)| (q, w.into_iter().map(|e| e.winner_track).sorted().collect()))
+                .collect::<Vec<(TrackId, Vec<TrackId>)>>();
+            res.sort();
+            res
+        };
+
+        assert_eq!(
+            winners(
+                TopNVoting::new(5, 1.5, 1)
+                    .with_adaptive_threshold(AdaptiveThreshold::Percentile(0.5))
+            ),
+            vec![(0, vec![1, 2, 3]), (1, vec![6])]
+        );
+
+        assert_eq!(
+            winners(
+                TopNVoting::new(5, 1.5, 1)
+                    .with_adaptive_threshold(AdaptiveThreshold::MeanMinusStd(0.0))
+            ),
+            vec![(0, vec![1, 2, 3]), (1, vec![6])]
+        );
+
+        assert_eq!(
+            winners(TopNVoting::new(5, 1.5, 1)),
+            vec![(0, vec![1, 2, 3, 4, 5]), (1, vec![6])]
+        );
+    }
+
+    #[test]
+    fn winners_with_stats() {
+        let v: TopNVoting<()> = TopNVoting::new(5, 1.0, 1);
+        let candidates = v.winners_with_stats([
+            ObservationMetricOk::new(0, 1, None, Some(0.25)),
+            ObservationMetricOk::new(0, 1, None, Some(0.75)),
+            ObservationMetricOk::new(0, 2, None, Some(1.0)),
+        ]);
+
+        assert_eq!(
+            candidates,
+            HashMap::from([(
+                0,
+                vec![
+                    TopNVotingStatsElt {
+                        elt: TopNVotingElt::new(0, 1, 1.0),
+                        votes: 2,
+                        min_distance: 0.25,
+                        mean_distance: 0.5,
+                        confidence: 0.72222227,
+                    },
+                    TopNVotingStatsElt {
+                        elt: TopNVotingElt::new(0, 2, 0.0),
+                        votes: 1,
+                        min_distance: 1.0,
+                        mean_distance: 1.0,
+                        confidence: 0.11111111,
+                    }
+                ]
+            )])
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_voting() {
+        let distances = (0..1000)
+            .map(|i| ObservationMetricOk::new(i % 7, i % 13, None, Some((i % 10) as f32 / 10.0)))
+            .collect::<Vec<ObservationMetricOk<()>>>();
+
+        let sequential: TopNVoting<()> =
+            TopNVoting::new(3, 0.5, 2).with_parallel_threshold(usize::MAX);
+        let parallel: TopNVoting<()> = TopNVoting::new(3, 0.5, 2).with_parallel_threshold(1);
+
+        assert_eq!(
+            sequential.winners(distances.clone()),
+            parallel.winners(distances)
+        );
+    }
+
+    #[test]
+    fn confidence() {
+        let v: TopNVoting<()> = TopNVoting::new(1, 1.0, 1);
+        let confidence = |distances: Vec<ObservationMetricOk<()>>| {
+            v.winners_with_stats(distances).remove(&0).unwrap()[0].confidence
+        };
+
+        let clear = confidence(vec![
+            ObservationMetricOk::new(0, 1, None, Some(0.1)),
+            ObservationMetricOk::new(0, 1, None, Some(0.1)),
+            ObservationMetricOk::new(0, 2, None, Some(0.9)),
+        ]);
+        let ambiguous = confidence(vec![
+            ObservationMetricOk::new(0, 1, None, Some(0.1)),
+            ObservationMetricOk::new(0, 2, None, Some(0.15)),
+            ObservationMetricOk::new(0, 3, None, Some(0.9)),
+        ]);
+
+        assert!((0.0..=1.0).contains(&clear));
+        assert!((0.0..=1.0).contains(&ambiguous));
+        assert!(clear > ambiguous);
+    }
+
+    #[test]
+    fn diagnostics() {
+        let v: TopNVoting<()> = TopNVoting::new(1, 0.5, 2);
+        assert_eq!(v.diagnostics(), None);
+
+        let v = v.with_diagnostics();
+        v.winners([
+            ObservationMetricOk::new(0, 1, None, Some(0.25)),
+            ObservationMetricOk::new(0, 1, None, Some(0.25)),
+            ObservationMetricOk::new(0, 2, None, Some(0.5)),
+            ObservationMetricOk::new(0, 2, None, Some(0.5)),
+            ObservationMetricOk::new(0, 3, None, Some(0.5)),
+            ObservationMetricOk::new(0, 4, None, Some(1.0)),
+            ObservationMetricOk::new(0, 5, None, None),
+        ]);
+
+        let d = v.diagnostics().unwrap();
+        assert_eq!(d.distances, 7);
+        assert_eq!(d.missing_distances, 1);
+        assert_eq!(d.filtered_by_threshold, 1);
+        assert_eq!(d.filtered_by_votes, 1);
+        assert_eq!(d.vote_histogram, HashMap::from([(1, 1), (2, 2)]));
+        assert_eq!(d.margins, vec![0.5]);
+    }
+
+    #[test]
+    fn similarity_polarity() {
+        let v: TopNVoting<()> =
+            TopNVoting::new(5, 0.5, 1).with_polarity(MetricPolarity::Similarity);
+        let res = v.winners_with_stats([
+            ObservationMetricOk::new(0, 1, None, Some(0.9)),
+            ObservationMetricOk::new(0, 1, None, Some(0.8)),
+            ObservationMetricOk::new(0, 2, None, Some(0.95)),
+            ObservationMetricOk::new(0, 3, None, Some(0.1)),
+        ]);
+
+        let winners = &res[&0];
+        assert_eq!(
+            winners
+                .iter()
+                .map(|e| e.elt.winner_track)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!((winners[0].elt.weight - 1.5).abs() < 1e-6);
+        assert!((winners[1].elt.weight - 0.85).abs() < 1e-6);
+        assert_eq!(winners[0].min_distance, 0.9);
+        assert_eq!(winners[1].mean_distance, 0.95);
+    }
+
+    #[test]
+    fn vote_weights() {
+        let v: TopNVoting<()> = TopNVoting::new(2, 1.0, 1);
+        let candidates = v.winners([
+            ObservationMetricOk::new(0, 1, None, Some(0.5)),
+            ObservationMetricOk::new(0, 1, None, Some(0.5)),
+            ObservationMetricOk::new(0, 2, None, Some(0.5)).with_weight(4.0),
+            ObservationMetricOk::new(0, 3, None, Some(1.0)),
+        ]);
+
+        assert_eq!(
+            candidates,
+            HashMap::from([(
+                0,
+                vec![TopNVotingElt::new(0, 2, 2.0), TopNVotingElt::new(0, 1, 1.0)]
+            )])
+        );
+    }
+
+    #[test]
+    fn vote_decay() {
+        let distances = || {
+            [
+                ObservationMetricOk::new(0, 1, None, Some(0.5)),
+                ObservationMetricOk::new(0, 1, None, Some(0.5)),
+                ObservationMetricOk::new(0, 1, None, Some(0.5)),
+                ObservationMetricOk::new(0, 2, None, Some(0.5))
+                    .with_stamp(ObservationStamp::new(9)),
+                ObservationMetricOk::new(0, 2, None, Some(0.5))
+                    .with_stamp(ObservationStamp::new(10)),
+                ObservationMetricOk::new(0, 3, None, Some(1.0)),
+            ]
+        };
+        let winner =
+            |v: &TopNVoting<f32>| v.winners(distances()).remove(&0).unwrap()[0].winner_track;
+
+        assert_eq!(winner(&TopNVoting::new(1, 1.0, 1)), 1);
+        let mut v = TopNVoting::new(1, 1.0, 1).with_vote_decay(0.5, 10);
+        assert_eq!(winner(&v), 2);
+        // the votes are not discounted before the epoch they are stamped with
+        v.set_current_epoch(0);
+        assert_eq!(winner(&v), 1);
+    }
+
+    #[test]
+    fn nan_distances_and_stable_order() {
+        let v: TopNVoting<()> =
+            TopNVoting::new(3, 1.0, 1).with_adaptive_threshold(AdaptiveThreshold::Percentile(1.0));
+        let winners = |distances: Vec<ObservationMetricOk<()>>| {
+            v.winners(distances)
+                .remove(&0)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.winner_track)
+                .collect::<Vec<_>>()
+        };
+
+        let distances = vec![
+            ObservationMetricOk::new(0, 3, None, Some(0.5)),
+            ObservationMetricOk::new(0, 1, None, Some(0.5)),
+            ObservationMetricOk::new(0, 4, None, Some(f32::NAN)),
+            ObservationMetricOk::new(0, 2, None, Some(0.5)),
+            ObservationMetricOk::new(0, 5, None, Some(1.0)),
+        ];
+        assert_eq!(winners(distances.clone()), vec![1, 2, 3]);
+        assert_eq!(
+            winners(distances.into_iter().rev().collect()),
+            vec![1, 2, 3]
+        );
+    }
 }
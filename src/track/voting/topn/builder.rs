@@ -0,0 +1,138 @@
+#[cfg(feature = "parallel")]
+use crate::track::voting::topn::DEFAULT_PARALLEL_THRESHOLD;
+use crate::track::voting::topn::{AdaptiveThreshold, TieBreak, TopNVoting};
+use crate::track::{MetricPolarity, ObservationAttributes};
+
+/// Builder for [TopNVoting](TopNVoting)
+///
+#[derive(Debug, Clone)]
+pub struct TopNVotingBuilder {
+    topn: usize,
+    max_distance: Option<f32>,
+    min_votes: usize,
+    tie_break: TieBreak,
+    adaptive_threshold: Option<AdaptiveThreshold>,
+    polarity: MetricPolarity,
+    #[cfg(feature = "parallel")]
+    parallel_threshold: usize,
+}
+
+/// By default the engine is constructed with: topn = 1, max_distance = f32::MAX, min_votes = 1
+///
+/// For the similarity polarity the default max_distance is f32::MIN, so all the similarities participate.
+///
+impl Default for TopNVotingBuilder {
+    fn default() -> Self {
+        TopNVotingBuilder {
+            topn: 1,
+            max_distance: None,
+            min_votes: 1,
+            tie_break: TieBreak::default(),
+            adaptive_threshold: None,
+            polarity: MetricPolarity::default(),
+            #[cfg(feature = "parallel")]
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+        }
+    }
+}
+
+impl TopNVotingBuilder {
+    pub fn topn(mut self, topn: usize) -> Self {
+        assert!(topn > 0, "TopN must be positive");
+        self.topn = topn;
+        self
+    }
+
+    pub fn max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = Some(max_distance);
+        self
+    }
+
+    pub fn min_votes(mut self, min_votes: usize) -> Self {
+        self.min_votes = min_votes;
+        self
+    }
+
+    pub fn tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    pub fn adaptive_threshold(mut self, adaptive_threshold: AdaptiveThreshold) -> Self {
+        self.adaptive_threshold = Some(adaptive_threshold);
+        self
+    }
+
+    pub fn polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn parallel_threshold(mut self, parallel_threshold: usize) -> Self {
+        self.parallel_threshold = parallel_threshold;
+        self
+    }
+
+    pub fn build<OA>(self) -> TopNVoting<OA>
+    where
+        OA: ObservationAttributes,
+    {
+        let max_distance = self.max_distance.unwrap_or(match self.polarity {
+            MetricPolarity::Distance => f32::MAX,
+            MetricPolarity::Similarity => f32::MIN,
+        });
+        let mut engine = TopNVoting::new(self.topn, max_distance, self.min_votes)
+            .with_tie_break(self.tie_break)
+            .with_polarity(self.polarity);
+        if let Some(adaptive_threshold) = self.adaptive_threshold {
+            engine = engine.with_adaptive_threshold(adaptive_threshold);
+        }
+        #[cfg(feature = "parallel")]
+        {
+            engine = engine.with_parallel_threshold(self.parallel_threshold);
+        }
+        engine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::topn::builder::TopNVotingBuilder;
+    use crate::track::voting::topn::{TieBreak, TopNVoting};
+    use crate::track::MetricPolarity;
+
+    #[test]
+    fn build() {
+        let mut v: TopNVoting<()> = TopNVotingBuilder::default()
+            .topn(3)
+            .max_distance(0.5)
+            .min_votes(2)
+            .tie_break(TieBreak::LowestId)
+            .build();
+
+        assert_eq!(v.topn(), 3);
+        assert_eq!(v.max_distance(), 0.5);
+        assert_eq!(v.min_votes(), 2);
+
+        v.set_topn(5);
+        v.set_max_distance(0.7);
+        v.set_min_votes(1);
+
+        assert_eq!(v.topn(), 5);
+        assert_eq!(v.max_distance(), 0.7);
+        assert_eq!(v.min_votes(), 1);
+    }
+
+    #[test]
+    fn similarity_defaults() {
+        let v: TopNVoting<()> = TopNVotingBuilder::default().build();
+        assert_eq!(v.max_distance(), f32::MAX);
+
+        let v: TopNVoting<()> = TopNVotingBuilder::default()
+            .polarity(MetricPolarity::Similarity)
+            .build();
+        assert_eq!(v.polarity(), MetricPolarity::Similarity);
+        assert_eq!(v.max_distance(), f32::MIN);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::track::{MetricPolarity, ObservationAttributes, ObservationMetricOk, TrackId};
+use crate::voting::topn::TopNVotingElt;
+use crate::voting::Voting;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Default vote weighting function: `1 / (1 + distance)`
+///
+pub fn inverse_distance_weight(distance: f32) -> f64 {
+    1.0 / (1.0 + distance as f64)
+}
+
+/// Default vote weighting function for the similarities: the similarity, negative similarities weigh nothing
+///
+pub fn similarity_weight(similarity: f32) -> f64 {
+    similarity.max(0.0) as f64
+}
+
+/// Distance-weighted TopN winners voting engine.
+///
+/// Unlike [TopNVoting](crate::voting::topn::TopNVoting) it doesn't count raw votes, every vote
+/// is weighted with a weighting function (`1 / (1 + distance)` by default), so the closer observations
+/// contribute more even when all candidates have the same number of observations.
+///
+/// It calculates winners as:
+/// 1. removes all distances that are greater than threshold
+/// 2. groups remaining distances by (query, winner) track pairs
+/// 3. removes groups with less than `min_votes` votes
+/// 4. sums weighted votes for every group
+/// 5. sorts groups by weight decreasingly and returns TopN for every query track
+///
+/// With [MetricPolarity::Similarity](MetricPolarity::Similarity) the values are similarities, `max_distance` is
+/// the min similarity permitted to participate and the weighting function receives the similarities, the default
+/// one is [similarity_weight](similarity_weight).
+///
+pub struct WeightedTopNVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    topn: usize,
+    max_distance: f32,
+    polarity: MetricPolarity,
+    min_votes: usize,
+    weight_fn: Option<fn(f32) -> f64>,
+    _phony: PhantomData<OA>,
+}
+
+impl<OA> WeightedTopNVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Constructs new engine with the default weighting function of the polarity: [inverse_distance_weight](inverse_distance_weight)
+    /// for the distances and [similarity_weight](similarity_weight) for the similarities
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance permitted to participate
+    /// * `min_votes` - minimal amount of votes required the track to participate
+    ///
+    pub fn new(topn: usize, max_distance: f32, min_votes: usize) -> Self {
+        Self {
+            topn,
+            max_distance,
+            polarity: MetricPolarity::default(),
+            min_votes,
+            weight_fn: None,
+            _phony: PhantomData,
+        }
+    }
+
+    /// Constructs new engine with a custom weighting function
+    ///
+    /// # Arguments
+    /// * `topn` - top winners
+    /// * `max_distance` - max distance permitted to participate
+    /// * `min_votes` - minimal amount of votes required the track to participate
+    /// * `weight_fn` - function that converts the distance to the weight of the vote
+    ///
+    pub fn with_weight_fn(
+        topn: usize,
+        max_distance: f32,
+        min_votes: usize,
+        weight_fn: fn(f32) -> f64,
+    ) -> Self {
+        Self {
+            weight_fn: Some(weight_fn),
+            ..Self::new(topn, max_distance, min_votes)
+        }
+    }
+
+    /// Sets the polarity of the distances, use the polarity of the metric that produced them
+    ///
+    pub fn with_polarity(mut self, polarity: MetricPolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    fn weight(&self, value: f32) -> f64 {
+        match (self.weight_fn, self.polarity) {
+            (Some(weight_fn), _) => weight_fn(value),
+            (None, MetricPolarity::Distance) => inverse_distance_weight(value),
+            (None, MetricPolarity::Similarity) => similarity_weight(value),
+        }
+    }
+}
+
+impl<OA> Voting<OA> for WeightedTopNVoting<OA>
+where
+    OA: ObservationAttributes,
+{
+    type WinnerObject = TopNVotingElt;
+
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<TopNVotingElt>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<OA>>,
+    {
+        let mut results: HashMap<TrackId, Vec<TopNVotingElt>> = HashMap::new();
+
+        distances
+            .into_iter()
+            .flat_map(
+                |ObservationMetricOk {
+                     from,
+                     to,
+                     attribute_metric: _,
+                     feature_distance,
+                     weight,
+                     stamp: _,
+                     query_observation: _,
+                 }| {
+                    feature_distance
+                        .filter(|d| self.polarity.passes(*d, self.max_distance))
+                        .map(|d| ((from, to), (d, weight)))
+                },
+            )
+            .into_group_map()
+            .into_iter()
+            .filter(|(_, dists)| dists.len() >= self.min_votes)
+            .for_each(|((q, w), dists)| {
+                let weight = dists
+                    .into_iter()
+                    .map(|(d, w)| self.weight(d) * w as f64)
+                    .sum();
+                results
+                    .entry(q)
+                    .or_default()
+                    .push(TopNVotingElt::new(q, w, weight));
+            });
+
+        for winners in results.values_mut() {
+            winners.sort_by(|l, r| {
+                r.weight
+                    .total_cmp(&l.weight)
+                    .then(l.winner_track.cmp(&r.winner_track))
+            });
+            winners.truncate(self.topn);
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::voting::weighted::WeightedTopNVoting;
+    use crate::track::{MetricPolarity, ObservationMetricOk};
+    use crate::voting::Voting;
+
+    #[test]
+    fn closer_single_votes_win() {
+        let v: WeightedTopNVoting<()> = WeightedTopNVoting::new(2, 0.5, 1);
+
+        let candidates = v.winners([
+            ObservationMetricOk::new(0, 1, None, Some(0.3)),
+            ObservationMetricOk::new(0, 2, None, Some(0.1)),
+            ObservationMetricOk::new(0, 3, None, Some(0.2)),
+            ObservationMetricOk::new(0, 4, None, Some(0.7)),
+        ]);
+
+        let winners = candidates.get(&0).unwrap();
+        assert_eq!(
+            winners.iter().map(|e| e.winner_track).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert!((winners[0].weight - 1.0 / 1.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn custom_weight_fn() {
+        let v: WeightedTopNVoting<()> =
+            WeightedTopNVoting::with_weight_fn(5, 1.0, 2, |d| (1.0 - d) as f64);
+
+        let candidates = v.winners([
+            ObservationMetricOk::new(0, 1, None, Some(0.5)),
+            ObservationMetricOk::new(0, 1, None, Some(0.5)),
+            ObservationMetricOk::new(0, 2, None, Some(0.1)),
+            ObservationMetricOk::new(7, 3, None, Some(0.0)),
+            ObservationMetricOk::new(7, 3, None, None),
+        ]);
+
+        assert_eq!(candidates.len(), 1);
+        let winners = candidates.get(&0).unwrap();
+        assert_eq!(winners.len(), 1);
+        assert_eq!(winners[0].winner_track, 1);
+        assert!((winners[0].weight - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn similarity_weights() {
+        let v: WeightedTopNVoting<()> =
+            WeightedTopNVoting::new(2, 0.5, 1).with_polarity(MetricPolarity::Similarity);
+
+        let candidates = v.winners([
+            ObservationMetricOk::new(0, 1, None, Some(0.75)),
+            ObservationMetricOk::new(0, 2, None, Some(0.5)),
+            ObservationMetricOk::new(0, 2, None, Some(0.5)),
+            ObservationMetricOk::new(0, 3, None, Some(0.25)),
+        ]);
+
+        let winners = candidates.get(&0).unwrap();
+        assert_eq!(
+            winners.iter().map(|e| e.winner_track).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        assert!((winners[0].weight - 1.0).abs() < 1e-6);
+        assert!((winners[1].weight - 0.75).abs() < 1e-6);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::track::{
-    LookupRequest, ObservationsDb, Track, TrackAttributes, TrackAttributesUpdate, TrackStatus,
+    LookupRequest, ObservationAttributes, ObservationsDb, Track, TrackAttributes,
+    TrackAttributesUpdate, TrackId, TrackStatus,
 };
 use crate::trackers::epoch_db::EpochDb;
 use crate::trackers::kalman_prediction::TrackAttributesKalmanPrediction;
@@ -192,7 +193,7 @@ impl LookupRequest<SortAttributes, Universal2DBox> for SortLookup {
         &self,
         attributes: &SortAttributes,
         _observations: &ObservationsDb<Universal2DBox>,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
     ) -> bool {
         match self {
             SortLookup::IdleLookup(scene_id) => {
@@ -286,7 +287,7 @@ impl TrackAttributes<SortAttributes, Universal2DBox> for SortAttributes {
 pub struct SortTrack {
     /// id of the track
     ///
-    pub id: u64,
+    pub id: TrackId,
     /// when the track was lastly updated
     ///
     pub epoch: usize,
@@ -316,7 +317,7 @@ pub struct SortTrack {
 pub struct WastedSortTrack {
     /// id of the track
     ///
-    pub id: u64,
+    pub id: TrackId,
     /// when the track was lastly updated
     ///
     pub epoch: usize,
@@ -368,6 +369,118 @@ pub enum PositionalMetricType {
     #[default]
     Mahalanobis,
     IoU(f32),
+    /// Generalized IoU with the threshold within `(-1.0, 1.0)` set on the raw GIoU scale
+    GIoU(f32),
+    /// Distance IoU with the threshold within `(-1.0, 1.0)` set on the raw DIoU scale
+    DIoU(f32),
+    /// Complete IoU with the threshold within `(-1.0, 1.0)` set on the raw CIoU scale
+    CIoU(f32),
+}
+
+impl PositionalMetricType {
+    /// The threshold of the IoU-based metric types
+    ///
+    pub fn threshold(&self) -> Option<f32> {
+        match self {
+            PositionalMetricType::Mahalanobis => None,
+            PositionalMetricType::IoU(t)
+            | PositionalMetricType::GIoU(t)
+            | PositionalMetricType::DIoU(t)
+            | PositionalMetricType::CIoU(t) => Some(*t),
+        }
+    }
+
+    /// The threshold of the weights produced by [weight](Self::weight), used by the voting to decide
+    /// whether the candidate starts a new track
+    ///
+    pub fn weight_threshold(&self) -> f32 {
+        match self {
+            PositionalMetricType::Mahalanobis => MAHALANOBIS_NEW_TRACK_THRESHOLD,
+            PositionalMetricType::IoU(t) => *t,
+            PositionalMetricType::GIoU(t)
+            | PositionalMetricType::DIoU(t)
+            | PositionalMetricType::CIoU(t) => rescale_overlap(*t),
+        }
+    }
+
+    /// Overlap of the boxes for the IoU-based metric types
+    ///
+    /// Plain IoU is `None` for the non-overlapping boxes, the GIoU, DIoU and CIoU variants lay within `[-1.0, 1.0]`,
+    /// are defined for any boxes and decrease with the distance between them, so the fast-moving objects still can
+    /// be associated.
+    ///
+    pub fn overlap(&self, l: &Universal2DBox, r: &Universal2DBox) -> Option<f32> {
+        match self {
+            PositionalMetricType::Mahalanobis => None,
+            PositionalMetricType::IoU(_) => {
+                Universal2DBox::calculate_metric_object(&Some(l), &Some(r))
+            }
+            PositionalMetricType::GIoU(_) => Some(Universal2DBox::giou(l, r)),
+            PositionalMetricType::DIoU(_) => Some(Universal2DBox::diou(l, r)),
+            PositionalMetricType::CIoU(_) => Some(Universal2DBox::ciou(l, r)),
+        }
+    }
+
+    /// Weight of the boxes for the IoU-based metric types
+    ///
+    /// The overlap is multiplied by the confidence of the candidate box, the GIoU, DIoU and CIoU overlaps are rescaled
+    /// to `[0.0, 1.0]` as `(v + 1) / 2` before, so the weights stay positive. The weights below the
+    /// [weight threshold](Self::weight_threshold) are dropped, so with the full confidence the boxes pass when the raw
+    /// overlap is at least the threshold.
+    ///
+    pub fn weight(&self, l: &Universal2DBox, r: &Universal2DBox, confidence: f32) -> Option<f32> {
+        let overlap = self.overlap(l, r)?;
+        let overlap = if self.scores_distant_boxes() {
+            rescale_overlap(overlap)
+        } else {
+            overlap
+        };
+        Some(overlap * confidence).filter(|w| *w >= self.weight_threshold())
+    }
+
+    /// Returns `true` when the metric type is defined for the boxes that don't intersect
+    ///
+    pub fn scores_distant_boxes(&self) -> bool {
+        matches!(
+            self,
+            PositionalMetricType::GIoU(_)
+                | PositionalMetricType::DIoU(_)
+                | PositionalMetricType::CIoU(_)
+        )
+    }
+
+    /// Returns `true` when the boxes are too far from each other to pass the threshold, so the metric is not calculated
+    ///
+    /// The boxes farther than the sum of their radii don't intersect. The GIoU, DIoU and CIoU of such boxes are bounded
+    /// from above by the distance of the centers, the boxes are skipped when the bound falls below the threshold.
+    ///
+    pub fn too_far(&self, l: &Universal2DBox, r: &Universal2DBox) -> bool {
+        if !Universal2DBox::too_far(l, r) {
+            return false;
+        }
+
+        let radii = l.get_radius() + r.get_radius();
+        let (dx, dy) = (l.xc - r.xc, l.yc - r.yc);
+        let distance = (dx * dx + dy * dy).sqrt();
+        match self {
+            PositionalMetricType::Mahalanobis | PositionalMetricType::IoU(_) => true,
+            // along the farther axis the enclosing box spans at least `distance / √2` plus the half extents of the boxes,
+            // so union / enclosing area <= 2 * radii / (distance / √2 + radii)
+            PositionalMetricType::GIoU(t) => {
+                2.0 * radii / (distance / std::f32::consts::SQRT_2 + radii) - 1.0 < *t
+            }
+            // the enclosing box lays within the square around the circle of `distance / 2 + radii` centered between
+            // the boxes, so the squared diagonal <= 2 * (distance + 2 * radii)^2; CIoU never exceeds DIoU
+            PositionalMetricType::DIoU(t) | PositionalMetricType::CIoU(t) => {
+                let diagonal = distance + 2.0 * radii;
+                -(distance * distance) / (2.0 * diagonal * diagonal) < *t
+            }
+        }
+    }
+}
+
+fn rescale_overlap(v: f32) -> f32 {
+    (v + 1.0) / 2.0
 }
 
 pub struct AutoWaste {
@@ -380,6 +493,7 @@ pub(crate) const MAHALANOBIS_NEW_TRACK_THRESHOLD: f32 = 1.0;
 
 #[cfg(feature = "python")]
 pub mod python {
+    use crate::track::TrackId;
     use pyo3::prelude::*;
 
     use crate::utils::bbox::python::PyUniversal2DBox;
@@ -408,6 +522,36 @@ pub mod python {
             PyPositionalMetricType(PositionalMetricType::IoU(threshold))
         }
 
+        #[staticmethod]
+        pub fn giou(threshold: f32) -> Self {
+            assert!(
+                threshold > -1.0 && threshold < 1.0,
+                "Threshold must lay between (-1.0 and 1.0)"
+            );
+
+            PyPositionalMetricType(PositionalMetricType::GIoU(threshold))
+        }
+
+        #[staticmethod]
+        pub fn diou(threshold: f32) -> Self {
+            assert!(
+                threshold > -1.0 && threshold < 1.0,
+                "Threshold must lay between (-1.0 and 1.0)"
+            );
+
+            PyPositionalMetricType(PositionalMetricType::DIoU(threshold))
+        }
+
+        #[staticmethod]
+        pub fn ciou(threshold: f32) -> Self {
+            assert!(
+                threshold > -1.0 && threshold < 1.0,
+                "Threshold must lay between (-1.0 and 1.0)"
+            );
+
+            PyPositionalMetricType(PositionalMetricType::CIoU(threshold))
+        }
+
         #[classattr]
         const __hash__: Option<Py<PyAny>> = None;
 
@@ -440,7 +584,7 @@ pub mod python {
         }
 
         #[getter]
-        fn get_id(&self) -> u64 {
+        fn get_id(&self) -> TrackId {
             self.0.id
         }
 
@@ -500,7 +644,7 @@ pub mod python {
         }
 
         #[getter]
-        fn id(&self) -> u64 {
+        fn id(&self) -> TrackId {
             self.0.id
         }
 
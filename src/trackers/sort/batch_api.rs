@@ -4,14 +4,14 @@ use crate::prelude::{
 };
 use crate::store::track_distance::TrackDistanceOkIterator;
 use crate::store::TrackStore;
-use crate::track::Track;
+use crate::track::{Track, TrackId};
 use crate::trackers::batch::{PredictionBatchRequest, PredictionBatchResult, SceneTracks};
 use crate::trackers::epoch_db::EpochDb;
 use crate::trackers::sort::metric::SortMetric;
 use crate::trackers::sort::voting::SortVoting;
 use crate::trackers::sort::{
     AutoWaste, SortAttributes, SortAttributesOptions, SortAttributesUpdate, SortLookup,
-    DEFAULT_AUTO_WASTE_PERIODICITY, MAHALANOBIS_NEW_TRACK_THRESHOLD,
+    DEFAULT_AUTO_WASTE_PERIODICITY,
 };
 
 use crate::trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
@@ -69,7 +69,7 @@ fn voting_thread(
     store: Arc<RwLock<MiddlewareSortTrackStore>>,
     rx: VotingReceiverChannel,
     method: PositionalMetricType,
-    track_id: Arc<RwLock<u64>>,
+    track_id: Arc<RwLock<TrackId>>,
 ) {
     while let Ok(command) = rx.recv() {
         match command {
@@ -86,14 +86,7 @@ fn voting_thread(
                     store.shard_stats().iter().sum()
                 };
 
-                let voting = SortVoting::new(
-                    match method {
-                        PositionalMetricType::Mahalanobis => MAHALANOBIS_NEW_TRACK_THRESHOLD,
-                        PositionalMetricType::IoU(t) => t,
-                    },
-                    candidates_num,
-                    tracks_num,
-                );
+                let voting = SortVoting::new(method.weight_threshold(), candidates_num, tracks_num);
 
                 let winners = voting.winners(distances);
                 let mut res = Vec::default();
@@ -104,7 +97,7 @@ fn voting_thread(
                         *track_id += 1;
                         *track_id
                     };
-                    let track_id: u64 = if let Some(dest) = winners.get(&source) {
+                    let track_id: TrackId = if let Some(dest) = winners.get(&source) {
                         let dest = dest[0];
                         if dest == source {
                             t.set_track_id(tid);
@@ -133,7 +126,7 @@ fn voting_thread(
                     };
 
                     let store = store.read().expect("Access to store must always succeed");
-                    let shard = store.get_store(track_id as usize);
+                    let shard = store.get_store(track_id);
                     let track = shard.get(&track_id).unwrap();
 
                     res.push(SortTrack::from(track))
@@ -300,7 +293,7 @@ impl BatchSort {
             .lookup(SortLookup::IdleLookup(scene_id))
             .iter()
             .map(|(track_id, _status)| {
-                let shard = store.get_store(*track_id as usize);
+                let shard = store.get_store(*track_id);
                 let track = shard.get(track_id).unwrap();
                 SortTrack::from(track)
             })
@@ -1,6 +1,5 @@
 use crate::track::{
-    MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric,
-    ObservationMetricOk,
+    MetricOutput, MetricQuery, Observation, ObservationMetric, ObservationMetricOk, TrackId,
 };
 use crate::trackers::kalman_prediction::TrackAttributesKalmanPrediction;
 use crate::trackers::sort::PositionalMetricType;
@@ -46,7 +45,7 @@ impl ObservationMetric<SortAttributes, Universal2DBox> for SortMetric {
             candidate_bbox.confidence
         };
 
-        if Universal2DBox::too_far(candidate_bbox, track_bbox) {
+        if self.method.too_far(candidate_bbox, track_bbox) {
             None
         } else {
             Some(match self.method {
@@ -62,16 +61,7 @@ impl ObservationMetric<SortAttributes, Universal2DBox> for SortMetric {
                         None,
                     )
                 }
-                PositionalMetricType::IoU(threshold) => {
-                    let box_m_opt = Universal2DBox::calculate_metric_object(
-                        &Some(candidate_bbox),
-                        &Some(track_bbox),
-                    );
-                    (
-                        box_m_opt.map(|e| e * conf).filter(|e| *e >= threshold),
-                        None,
-                    )
-                }
+                method => (method.weight(candidate_bbox, track_bbox, conf), None),
             })
         }
     }
@@ -79,7 +69,7 @@ impl ObservationMetric<SortAttributes, Universal2DBox> for SortMetric {
     fn optimize(
         &mut self,
         _feature_class: u64,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
         attrs: &mut SortAttributes,
         features: &mut Vec<Observation<Universal2DBox>>,
         _prev_length: usize,
@@ -94,7 +84,7 @@ impl ObservationMetric<SortAttributes, Universal2DBox> for SortMetric {
 
         *observation.attr_mut() = Some(match self.method {
             PositionalMetricType::Mahalanobis => predicted_bbox,
-            PositionalMetricType::IoU(_) => {
+            _ => {
                 predicted_bbox.gen_vertices();
                 predicted_bbox
             }
@@ -216,4 +206,79 @@ mod tests {
             "Confidence in track box must NOT be used."
         );
     }
+
+    #[test]
+    fn giou_scores_disjoint_boxes() {
+        let attr_opts = Arc::new(SortAttributesOptions::new(
+            None,
+            0,
+            5,
+            SpatioTemporalConstraints::default(),
+            1.0 / 20.0,
+            1.0 / 160.0,
+        ));
+        let attrs = SortAttributes::new(attr_opts);
+
+        let candidate_obs = Observation::new(
+            Some(BoundingBox::new_with_confidence(0.0, 0.0, 8.0, 10.0, 1.0).as_xyaah()),
+            None,
+        );
+        let track_obs = Observation::new(
+            Some(BoundingBox::new_with_confidence(12.0, 0.0, 8.0, 10.0, 1.0).as_xyaah()),
+            None,
+        );
+        let mq = MetricQuery {
+            feature_class: 0,
+            candidate_attrs: &attrs,
+            candidate_observation: &candidate_obs,
+            track_attrs: &attrs,
+            track_observation: &track_obs,
+        };
+
+        let metric = SortMetric::new(PositionalMetricType::IoU(0.3), 0.0);
+        assert!(metric.metric(&mq).unwrap().0.is_none());
+
+        // GIoU = -(200 - 160) / 200 = -0.2, rescaled to 0.4, the threshold is set on the raw scale
+        let metric = SortMetric::new(PositionalMetricType::GIoU(-0.3), 0.0);
+        assert!((metric.metric(&mq).unwrap().0.unwrap() - 0.4).abs() < EPS);
+        let metric = SortMetric::new(PositionalMetricType::GIoU(-0.1), 0.0);
+        assert!(metric.metric(&mq).unwrap().0.is_none());
+
+        // DIoU = -(12^2) / (20^2 + 10^2) = -0.288, rescaled to 0.356
+        let metric = SortMetric::new(PositionalMetricType::DIoU(-0.3), 0.0);
+        assert!((metric.metric(&mq).unwrap().0.unwrap() - 0.356).abs() < EPS);
+
+        // equal aspect ratios, CIoU = DIoU
+        let metric = SortMetric::new(PositionalMetricType::CIoU(-0.2), 0.0);
+        assert!(metric.metric(&mq).unwrap().0.is_none());
+    }
+
+    #[test]
+    fn distant_boxes_prefilter() {
+        let l = BoundingBox::new_with_confidence(0.0, 0.0, 8.0, 10.0, 1.0).as_xyaah();
+        let r = BoundingBox::new_with_confidence(100.0, 0.0, 8.0, 10.0, 1.0).as_xyaah();
+
+        // GIoU = 160 / 1080 - 1 = -0.852, DIoU = -(100^2) / (108^2 + 10^2) = -0.850
+        for (metric, loose) in [
+            (
+                PositionalMetricType::GIoU(-0.5),
+                PositionalMetricType::GIoU(-0.9),
+            ),
+            (
+                PositionalMetricType::DIoU(-0.2),
+                PositionalMetricType::DIoU(-0.9),
+            ),
+            (
+                PositionalMetricType::CIoU(-0.2),
+                PositionalMetricType::CIoU(-0.9),
+            ),
+        ] {
+            assert!(metric.too_far(&l, &r));
+            assert!(!loose.too_far(&l, &r));
+            let weight = loose.weight(&l, &r, 1.0).unwrap();
+            assert!((weight - 0.075).abs() < 0.001);
+        }
+
+        assert!(PositionalMetricType::IoU(0.3).too_far(&l, &r));
+    }
 }
@@ -5,12 +5,12 @@ use rand::Rng;
 
 use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
 use crate::store::TrackStore;
-use crate::track::Track;
+use crate::track::{Track, TrackId};
 use crate::trackers::epoch_db::EpochDb;
 use crate::trackers::sort::{
     metric::SortMetric, voting::SortVoting, AutoWaste, PositionalMetricType, SortAttributes,
     SortAttributesOptions, SortAttributesUpdate, SortLookup, SortTrack, VotingType,
-    DEFAULT_AUTO_WASTE_PERIODICITY, MAHALANOBIS_NEW_TRACK_THRESHOLD,
+    DEFAULT_AUTO_WASTE_PERIODICITY,
 };
 use crate::trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
 use crate::trackers::tracker_api::TrackerAPI;
@@ -25,7 +25,7 @@ pub struct Sort {
     method: PositionalMetricType,
     opts: Arc<SortAttributesOptions>,
     auto_waste: AutoWaste,
-    track_id: u64,
+    track_id: TrackId,
 }
 
 impl Sort {
@@ -96,7 +96,7 @@ impl Sort {
         self.predict_with_scene(0, bboxes)
     }
 
-    fn gen_track_id(&mut self) -> u64 {
+    fn gen_track_id(&mut self) -> TrackId {
         self.track_id += 1;
         self.track_id
     }
@@ -152,10 +152,7 @@ impl Sort {
         assert!(errs.all().is_empty());
         let dists = dists.all();
         let voting = SortVoting::new(
-            match self.method {
-                PositionalMetricType::Mahalanobis => MAHALANOBIS_NEW_TRACK_THRESHOLD,
-                PositionalMetricType::IoU(t) => t,
-            },
+            self.method.weight_threshold(),
             num_candidates,
             self.store.read().unwrap().shard_stats().iter().sum(),
         );
@@ -164,7 +161,7 @@ impl Sort {
 
         for mut t in tracks {
             let source = t.get_track_id();
-            let track_id: u64 = if let Some(dest) = winners.get(&source) {
+            let track_id: TrackId = if let Some(dest) = winners.get(&source) {
                 let dest = dest[0];
                 if dest == source {
                     let track_id = self.gen_track_id();
@@ -187,7 +184,7 @@ impl Sort {
             };
 
             let lock = self.store.read().unwrap();
-            let store = lock.get_store(track_id as usize);
+            let store = lock.get_store(track_id);
             let track = store.get(&track_id).unwrap();
             res.push(SortTrack::from(track));
         }
@@ -206,7 +203,7 @@ impl Sort {
             .lookup(SortLookup::IdleLookup(scene_id))
             .iter()
             .map(|(track_id, _status)| {
-                let shard = store.get_store(*track_id as usize);
+                let shard = store.get_store(*track_id);
                 let track = shard.get(track_id).unwrap();
                 SortTrack::from(track)
             })
@@ -1,4 +1,4 @@
-use crate::track::ObservationMetricOk;
+use crate::track::{ObservationMetricOk, TrackId};
 use crate::utils::bbox::Universal2DBox;
 use crate::voting::Voting;
 use core::option::Option::{None, Some};
@@ -25,9 +25,9 @@ impl SortVoting {
 }
 
 impl Voting<Universal2DBox> for SortVoting {
-    type WinnerObject = u64;
+    type WinnerObject = TrackId;
 
-    fn winners<T>(&self, distances: T) -> HashMap<u64, Vec<Self::WinnerObject>>
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<Self::WinnerObject>>
     where
         T: IntoIterator<Item = ObservationMetricOk<Universal2DBox>>,
     {
@@ -37,9 +37,9 @@ impl Voting<Universal2DBox> for SortVoting {
             return HashMap::default();
         }
 
-        let mut tracks_index: Vec<u64> = Vec::default();
+        let mut tracks_index: Vec<TrackId> = Vec::default();
         tracks_index.resize(self.candidate_num, 0);
-        let mut tracks_r_index: HashMap<u64, usize> = HashMap::default();
+        let mut tracks_r_index: HashMap<TrackId, usize> = HashMap::default();
 
         let mut cost_matrix = Matrix::new(
             self.candidate_num,
@@ -52,6 +52,9 @@ impl Voting<Universal2DBox> for SortVoting {
             to,
             attribute_metric,
             feature_distance: _,
+            weight: _,
+            stamp: _,
+            query_observation: _,
         } in distances
         {
             assert!(from > 0 && to > 0);
@@ -102,7 +105,7 @@ impl Voting<Universal2DBox> for SortVoting {
 
 #[cfg(test)]
 mod voting_tests {
-    use crate::track::ObservationMetricOk;
+    use crate::track::{ObservationMetricOk, ObservationStamp};
     use crate::trackers::sort::voting::SortVoting;
     use crate::voting::Voting;
     use std::collections::HashMap;
@@ -116,54 +119,81 @@ mod voting_tests {
                 to: 20,
                 attribute_metric: Some(0.6),
                 feature_distance: None,
+                weight: 1.0,
+                stamp: ObservationStamp::default(),
+                query_observation: 0,
             },
             ObservationMetricOk {
                 from: 10,
                 to: 25,
                 attribute_metric: Some(0.4),
                 feature_distance: None,
+                weight: 1.0,
+                stamp: ObservationStamp::default(),
+                query_observation: 0,
             },
             ObservationMetricOk {
                 from: 10,
                 to: 30,
                 attribute_metric: Some(0.4),
                 feature_distance: None,
+                weight: 1.0,
+                stamp: ObservationStamp::default(),
+                query_observation: 0,
             },
             ObservationMetricOk {
                 from: 11,
                 to: 20,
                 attribute_metric: Some(0.5),
                 feature_distance: None,
+                weight: 1.0,
+                stamp: ObservationStamp::default(),
+                query_observation: 0,
             },
             ObservationMetricOk {
                 from: 11,
                 to: 25,
                 attribute_metric: Some(0.69),
                 feature_distance: None,
+                weight: 1.0,
+                stamp: ObservationStamp::default(),
+                query_observation: 0,
             },
             ObservationMetricOk {
                 from: 11,
                 to: 30,
                 attribute_metric: Some(0.4),
                 feature_distance: None,
+                weight: 1.0,
+                stamp: ObservationStamp::default(),
+                query_observation: 0,
             },
             ObservationMetricOk {
                 from: 12,
                 to: 20,
                 attribute_metric: Some(0.2),
                 feature_distance: None,
+                weight: 1.0,
+                stamp: ObservationStamp::default(),
+                query_observation: 0,
             },
             ObservationMetricOk {
                 from: 12,
                 to: 25,
                 attribute_metric: Some(0.27),
                 feature_distance: None,
+                weight: 1.0,
+                stamp: ObservationStamp::default(),
+                query_observation: 0,
             },
             ObservationMetricOk {
                 from: 12,
                 to: 30,
                 attribute_metric: Some(0.28),
                 feature_distance: None,
+                weight: 1.0,
+                stamp: ObservationStamp::default(),
+                query_observation: 0,
             },
         ]);
 
@@ -1,3 +1,4 @@
+use crate::track::TrackId;
 use std::borrow::Cow;
 
 use crate::{
@@ -84,7 +85,7 @@ impl<'a> Default for VisualSortObservationSet<'a> {
 pub struct WastedVisualSortTrack {
     /// id of the track
     ///
-    pub id: u64,
+    pub id: TrackId,
 
     /// when the track was lastly updated
     ///
@@ -146,6 +147,7 @@ impl From<Track<VisualAttributes, VisualMetric, VisualObservationAttributes>>
 #[cfg(feature = "python")]
 pub mod python {
     use super::{VisualSortObservation, VisualSortObservationSet, WastedVisualSortTrack};
+    use crate::track::TrackId;
     use crate::utils::bbox::python::PyUniversal2DBox;
     use pyo3::prelude::*;
     use std::borrow::Cow;
@@ -168,7 +170,7 @@ pub mod python {
         }
 
         #[getter]
-        fn id(&self) -> u64 {
+        fn id(&self) -> TrackId {
             self.0.id
         }
 
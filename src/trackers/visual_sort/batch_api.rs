@@ -1,17 +1,14 @@
 use crate::prelude::{
-    NoopNotifier, ObservationBuilder, PositionalMetricType, SortTrack, TrackStoreBuilder,
-    VisualSortObservation, VisualSortOptions,
+    NoopNotifier, ObservationBuilder, SortTrack, TrackStoreBuilder, VisualSortObservation,
+    VisualSortOptions,
 };
 use crate::store::track_distance::TrackDistanceOkIterator;
 use crate::store::TrackStore;
 use crate::track::utils::FromVec;
-use crate::track::{Feature, Track};
+use crate::track::{Feature, Track, TrackId};
 use crate::trackers::batch::{PredictionBatchRequest, PredictionBatchResult, SceneTracks};
 use crate::trackers::epoch_db::EpochDb;
-use crate::trackers::sort::{
-    AutoWaste, SortAttributesOptions, DEFAULT_AUTO_WASTE_PERIODICITY,
-    MAHALANOBIS_NEW_TRACK_THRESHOLD,
-};
+use crate::trackers::sort::{AutoWaste, SortAttributesOptions, DEFAULT_AUTO_WASTE_PERIODICITY};
 use crate::trackers::tracker_api::TrackerAPI;
 use crate::trackers::visual_sort::metric::{VisualMetric, VisualMetricOptions};
 use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
@@ -78,7 +75,7 @@ fn voting_thread(
     store: Arc<RwLock<MiddlewareVisualSortTrackStore>>,
     rx: VotingReceiverChannel,
     metric_opts: Arc<VisualMetricOptions>,
-    track_id: Arc<RwLock<u64>>,
+    track_id: Arc<RwLock<TrackId>>,
 ) {
     while let Ok(command) = rx.recv() {
         match command {
@@ -90,10 +87,7 @@ fn voting_thread(
                 monitor,
             } => {
                 let voting = VisualVoting::new(
-                    match metric_opts.positional_kind {
-                        PositionalMetricType::Mahalanobis => MAHALANOBIS_NEW_TRACK_THRESHOLD,
-                        PositionalMetricType::IoU(t) => t,
-                    },
+                    metric_opts.positional_kind.weight_threshold(),
                     f32::MAX,
                     metric_opts.visual_min_votes,
                 );
@@ -108,7 +102,7 @@ fn voting_thread(
                         *track_id
                     };
 
-                    let track_id: u64 = if let Some(dest) = winners.get(&source) {
+                    let track_id: TrackId = if let Some(dest) = winners.get(&source) {
                         let (dest, vt) = dest[0];
                         if dest == source {
                             t.set_track_id(tid);
@@ -136,7 +130,7 @@ fn voting_thread(
                     };
 
                     let lock = store.read().unwrap();
-                    let store = lock.get_store(track_id as usize);
+                    let store = lock.get_store(track_id);
                     let track = store.get(&track_id).unwrap();
 
                     res.push(SortTrack::from(track))
@@ -327,7 +321,7 @@ impl BatchVisualSort {
             .lookup(VisualSortLookup::IdleLookup(scene_id))
             .iter()
             .map(|(track_id, _status)| {
-                let shard = store.get_store(*track_id as usize);
+                let shard = store.get_store(*track_id);
                 let track = shard.get(track_id).unwrap();
                 SortTrack::from(track)
             })
@@ -2,7 +2,7 @@
 pub mod builder;
 
 use crate::distance::{cosine, euclidean};
-use crate::track::{Feature, MetricQuery, ObservationAttributes, ObservationMetricOk};
+use crate::track::{Feature, MetricQuery, ObservationMetricOk, TrackId};
 use crate::track::{MetricOutput, Observation, ObservationMetric};
 use crate::trackers::kalman_prediction::TrackAttributesKalmanPrediction;
 use crate::trackers::sort::PositionalMetricType;
@@ -162,7 +162,11 @@ impl VisualMetric {
         if let (Some(candidate_observation_bbox), Some(track_observation_bbox)) =
             (candidate_observation_bbox_opt, track_observation_bbox_opt)
         {
-            if Universal2DBox::too_far(candidate_observation_bbox, track_observation_bbox) {
+            if self
+                .opts
+                .positional_kind
+                .too_far(candidate_observation_bbox, track_observation_bbox)
+            {
                 None
             } else {
                 let conf = if candidate_observation_bbox.confidence
@@ -183,13 +187,7 @@ impl VisualMetric {
                         let dist = f.distance(state, candidate_observation_bbox);
                         Some(Universal2DBoxKalmanFilter::calculate_cost(dist, true) / conf)
                     }
-                    PositionalMetricType::IoU(threshold) => {
-                        let box_m_opt = Universal2DBox::calculate_metric_object(
-                            &candidate_observation_bbox_opt.as_ref(),
-                            &track_observation_bbox_opt.as_ref(),
-                        );
-                        box_m_opt.map(|e| e * conf).filter(|e| *e >= threshold)
-                    }
+                    kind => kind.weight(candidate_observation_bbox, track_observation_bbox, conf),
                 }
             }
         } else {
@@ -297,7 +295,7 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
     fn optimize(
         &mut self,
         _feature_class: u64,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
         attrs: &mut VisualAttributes,
         observations: &mut Vec<Observation<VisualObservationAttributes>>,
         _prev_length: usize,
@@ -340,7 +338,7 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
                 feature_quality,
                 match self.opts.positional_kind {
                     PositionalMetricType::Mahalanobis => predicted_bbox,
-                    PositionalMetricType::IoU(_) => {
+                    _ => {
                         predicted_bbox.gen_vertices();
                         predicted_bbox
                     }
@@ -352,7 +350,7 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
                 feature_quality,
                 match self.opts.positional_kind {
                     PositionalMetricType::Mahalanobis => predicted_bbox,
-                    PositionalMetricType::IoU(_) => {
+                    _ => {
                         predicted_bbox.gen_vertices();
                         predicted_bbox
                     }
@@ -515,15 +513,15 @@ mod optimize {
         assert_eq!(obs.len(), 3);
         assert!(matches!(
             &obs[2],
-            Observation(Some(a), Some(o)) if a.bbox_opt().is_none() && a.visual_quality() == 1.0 && o[0].to_array()[..2] == [0.1 , 1.1]
+            Observation(Some(a), Some(o), _) if a.bbox_opt().is_none() && a.visual_quality() == 1.0 && o[0].to_array()[..2] == [0.1 , 1.1]
         ));
         assert!(matches!(
             &obs[1],
-            Observation(Some(a), Some(o)) if a.bbox_opt().is_none() && a.visual_quality() == 0.8 && o[0].to_array()[..2] == [0.0 , 1.0]
+            Observation(Some(a), Some(o), _) if a.bbox_opt().is_none() && a.visual_quality() == 0.8 && o[0].to_array()[..2] == [0.0 , 1.0]
         ));
         assert!(matches!(
             &obs[0],
-            Observation(Some(a), Some(o)) if a.bbox_opt().is_some() && a.visual_quality() == 0.6 && o[0].to_array()[..2] == [0.0 , 1.1]
+            Observation(Some(a), Some(o), _) if a.bbox_opt().is_some() && a.visual_quality() == 0.6 && o[0].to_array()[..2] == [0.0 , 1.1]
         ));
     }
 
@@ -719,7 +717,8 @@ mod metric_tests {
                 from: 1,
                 to: 2,
                 attribute_metric: None, // ignored because objects are too far
-                feature_distance: Some(x)
+                feature_distance: Some(x),
+                ..
             } if x > 0.0));
     }
 
@@ -768,7 +767,8 @@ mod metric_tests {
                 from: 1,
                 to: 2,
                 attribute_metric: Some(x),
-                feature_distance: Some(y)
+                feature_distance: Some(y),
+                ..
             } if (x - 1.0).abs() < EPS && y.abs() < EPS));
     }
 
@@ -818,7 +818,8 @@ mod metric_tests {
                 from: 1,
                 to: 2,
                 attribute_metric: Some(x),
-                feature_distance: Some(y)
+                feature_distance: Some(y),
+                ..
             } if (x - 100.0).abs() < EPS && y.abs() < EPS));
     }
 
@@ -868,7 +869,8 @@ mod metric_tests {
                 from: 1,
                 to: 2,
                 attribute_metric: Some(x),
-                feature_distance: None     // track too short
+                feature_distance: None,     // track too short
+                ..
             } if (x - 1.0).abs() < EPS));
     }
 
@@ -927,7 +929,8 @@ mod metric_tests {
                 from: 1,
                 to: 2,
                 attribute_metric: Some(x),
-                feature_distance: Some(y)     // track too short
+                feature_distance: Some(y),     // track too short
+                ..
             } if (x - 1.0).abs() < EPS && y.abs() < EPS));
 
         assert!(matches!(
@@ -936,7 +939,8 @@ mod metric_tests {
                 from: 1,
                 to: 2,
                 attribute_metric: None,
-                feature_distance: Some(y)     // track too short
+                feature_distance: Some(y),     // track too short
+                ..
             } if y.abs() < EPS));
     }
 
@@ -988,7 +992,8 @@ mod metric_tests {
                 from: 1,
                 to: 2,
                 attribute_metric: None,
-                feature_distance: None // feature box is too small to use feature
+                feature_distance: None, // feature box is too small to use feature
+                ..
             }
         ));
     }
@@ -1040,7 +1045,8 @@ mod metric_tests {
                 from: 1,
                 to: 2,
                 attribute_metric: Some(x),
-                feature_distance: None     // quality is low
+                feature_distance: None,     // quality is low
+                ..
             } if (x - 1.0).abs() < EPS));
     }
 
@@ -1093,7 +1099,8 @@ mod metric_tests {
                 from: 1,
                 to: 2,
                 attribute_metric: Some(x),
-                feature_distance: None     // own area percentage is low
+                feature_distance: None,     // own area percentage is low
+                ..
             } if (x - 1.0).abs() < EPS));
     }
 }
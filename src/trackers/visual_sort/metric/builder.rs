@@ -80,10 +80,15 @@ impl VisualMetricBuilder {
     }
 
     pub fn positional_metric(mut self, metric: PositionalMetricType) -> Self {
-        if let PositionalMetricType::IoU(t) = metric {
+        if let Some(t) = metric.threshold() {
+            let low = if metric.scores_distant_boxes() {
+                -1.0
+            } else {
+                0.0
+            };
             assert!(
-                t > 0.0 && t < 1.0,
-                "Threshold must lay between (0.0 and 1.0)"
+                t > low && t < 1.0,
+                "Threshold must lay between ({low:.1} and 1.0)"
             );
         }
         self.positional_kind = metric;
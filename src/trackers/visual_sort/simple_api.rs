@@ -1,13 +1,10 @@
 use crate::prelude::{NoopNotifier, ObservationBuilder, SortTrack, TrackStoreBuilder};
 use crate::store::TrackStore;
 use crate::track::utils::FromVec;
-use crate::track::{Feature, Track};
+use crate::track::{Feature, Track, TrackId};
 use crate::trackers::epoch_db::EpochDb;
 use crate::trackers::sort::VotingType::Positional;
-use crate::trackers::sort::{
-    AutoWaste, PositionalMetricType, SortAttributesOptions, DEFAULT_AUTO_WASTE_PERIODICITY,
-    MAHALANOBIS_NEW_TRACK_THRESHOLD,
-};
+use crate::trackers::sort::{AutoWaste, SortAttributesOptions, DEFAULT_AUTO_WASTE_PERIODICITY};
 use crate::trackers::tracker_api::TrackerAPI;
 use crate::trackers::visual_sort::metric::{VisualMetric, VisualMetricOptions};
 use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
@@ -32,7 +29,7 @@ pub struct VisualSort {
     metric_opts: Arc<VisualMetricOptions>,
     track_opts: Arc<SortAttributesOptions>,
     auto_waste: AutoWaste,
-    track_id: u64,
+    track_id: TrackId,
 }
 
 impl VisualSort {
@@ -85,7 +82,7 @@ impl VisualSort {
         self.predict_with_scene(0, observations)
     }
 
-    fn gen_track_id(&mut self) -> u64 {
+    fn gen_track_id(&mut self) -> TrackId {
         self.track_id += 1;
         self.track_id
     }
@@ -177,10 +174,7 @@ impl VisualSort {
 
         assert!(errs.all().is_empty());
         let voting = VisualVoting::new(
-            match self.metric_opts.positional_kind {
-                PositionalMetricType::Mahalanobis => MAHALANOBIS_NEW_TRACK_THRESHOLD,
-                PositionalMetricType::IoU(t) => t,
-            },
+            self.metric_opts.positional_kind.weight_threshold(),
             f32::MAX,
             self.metric_opts.visual_min_votes,
         );
@@ -188,7 +182,7 @@ impl VisualSort {
         let mut res = Vec::default();
         for t in &mut tracks {
             let source = t.get_track_id();
-            let track_id: u64 = if let Some(dest) = winners.get(&source) {
+            let track_id: TrackId = if let Some(dest) = winners.get(&source) {
                 let (dest, vt) = dest[0];
                 if dest == source {
                     let mut t = t.clone();
@@ -220,7 +214,7 @@ impl VisualSort {
             };
 
             let lock = self.store.read().unwrap();
-            let store = lock.get_store(track_id as usize);
+            let store = lock.get_store(track_id);
             let track = store.get(&track_id).unwrap();
 
             res.push(SortTrack::from(track))
@@ -239,7 +233,7 @@ impl VisualSort {
             .lookup(VisualSortLookup::IdleLookup(scene_id))
             .iter()
             .map(|(track_id, _status)| {
-                let shard = store.get_store(*track_id as usize);
+                let shard = store.get_store(*track_id);
                 let track = shard.get(track_id).unwrap();
                 SortTrack::from(track)
             })
@@ -359,7 +353,7 @@ mod tests {
         assert!(matches!(t.epoch, 1));
         let attrs = {
             let lock = tracker.store.read().unwrap();
-            let store = lock.get_store(t.id as usize);
+            let store = lock.get_store(t.id);
             let track = store.get(&t.id).unwrap();
             track.get_attributes().clone()
         };
@@ -388,7 +382,7 @@ mod tests {
             assert!(matches!(t.epoch, 1));
             let attrs = {
                 let lock = tracker.store.read().unwrap();
-                let store = lock.get_store(t.id as usize);
+                let store = lock.get_store(t.id);
                 let track = store.get(&t.id).unwrap();
                 track.get_attributes().clone()
             };
@@ -418,7 +412,7 @@ mod tests {
         assert!(matches!(t.epoch, 2));
         let attrs = {
             let lock = tracker.store.read().unwrap();
-            let store = lock.get_store(t.id as usize);
+            let store = lock.get_store(t.id);
             let track = store.get(&t.id).unwrap();
             track.get_attributes().clone()
         };
@@ -447,7 +441,7 @@ mod tests {
         assert!(matches!(t.epoch, 3));
         let attrs = {
             let lock = tracker.store.read().unwrap();
-            let store = lock.get_store(t.id as usize);
+            let store = lock.get_store(t.id);
             let track = store.get(&t.id).unwrap();
             track.get_attributes().clone()
         };
@@ -475,7 +469,7 @@ mod tests {
         assert!(matches!(t.epoch, 4));
         let attrs = {
             let lock = tracker.store.read().unwrap();
-            let store = lock.get_store(t.id as usize);
+            let store = lock.get_store(t.id);
             let track = store.get(&t.id).unwrap();
             track.get_attributes().clone()
         };
@@ -502,7 +496,7 @@ mod tests {
         assert!(matches!(t.voting_type, VotingType::Positional));
         let attrs = {
             let lock = tracker.store.read().unwrap();
-            let store = lock.get_store(t.id as usize);
+            let store = lock.get_store(t.id);
             let track = store.get(&t.id).unwrap();
             track.get_attributes().clone()
         };
@@ -526,7 +520,7 @@ mod tests {
         assert!(matches!(t.voting_type, VotingType::Visual));
         let attrs = {
             let lock = tracker.store.read().unwrap();
-            let store = lock.get_store(t.id as usize);
+            let store = lock.get_store(t.id);
             let track = store.get(&t.id).unwrap();
             track.get_attributes().clone()
         };
@@ -550,7 +544,7 @@ mod tests {
         assert!(matches!(t.voting_type, VotingType::Visual));
         let attrs = {
             let lock = tracker.store.read().unwrap();
-            let store = lock.get_store(t.id as usize);
+            let store = lock.get_store(t.id);
             let track = store.get(&t.id).unwrap();
             let observations = track.get_observations(0).unwrap();
 
@@ -587,7 +581,7 @@ mod tests {
         assert_ne!(t.id, first_track_id);
         let attrs = {
             let lock = tracker.store.read().unwrap();
-            let store = lock.get_store(t.id as usize);
+            let store = lock.get_store(t.id);
             let track = store.get(&t.id).unwrap();
             track.get_attributes().clone()
         };
@@ -617,7 +611,7 @@ mod tests {
         assert_eq!(t.id, other_track_id);
         let attrs = {
             let lock = tracker.store.read().unwrap();
-            let store = lock.get_store(t.id as usize);
+            let store = lock.get_store(t.id);
             let track = store.get(&t.id).unwrap();
             track.get_attributes().clone()
         };
@@ -646,7 +640,7 @@ mod tests {
         assert_eq!(t.id, other_track_id);
         let attrs = {
             let lock = tracker.store.read().unwrap();
-            let store = lock.get_store(t.id as usize);
+            let store = lock.get_store(t.id);
             let track = store.get(&t.id).unwrap();
             track.get_attributes().clone()
         };
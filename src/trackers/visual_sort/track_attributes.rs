@@ -1,5 +1,6 @@
 use crate::track::{
-    Feature, LookupRequest, ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackStatus,
+    Feature, LookupRequest, ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackId,
+    TrackStatus,
 };
 use crate::trackers::epoch_db::EpochDb;
 use crate::trackers::kalman_prediction::TrackAttributesKalmanPrediction;
@@ -118,7 +119,7 @@ impl LookupRequest<VisualAttributes, VisualObservationAttributes> for VisualSort
         &self,
         attributes: &VisualAttributes,
         _observations: &ObservationsDb<VisualObservationAttributes>,
-        _merge_history: &[u64],
+        _merge_history: &[TrackId],
     ) -> bool {
         match self {
             VisualSortLookup::IdleLookup(scene_id) => {
@@ -1,4 +1,4 @@
-use crate::track::ObservationMetricOk;
+use crate::track::{ObservationMetricOk, TrackId};
 use crate::trackers::sort::voting::SortVoting;
 use crate::trackers::sort::VotingType;
 use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
@@ -38,14 +38,17 @@ impl From<ObservationMetricOk<VisualObservationAttributes>>
             to: e.to,
             attribute_metric: e.attribute_metric,
             feature_distance: e.feature_distance,
+            weight: e.weight,
+            stamp: e.stamp,
+            query_observation: e.query_observation,
         }
     }
 }
 
 impl Voting<VisualObservationAttributes> for VisualVoting {
-    type WinnerObject = (u64, VotingType);
+    type WinnerObject = (TrackId, VotingType);
 
-    fn winners<T>(&self, distances: T) -> HashMap<u64, Vec<Self::WinnerObject>>
+    fn winners<T>(&self, distances: T) -> HashMap<TrackId, Vec<Self::WinnerObject>>
     where
         T: IntoIterator<Item = ObservationMetricOk<VisualObservationAttributes>>,
     {
@@ -2,6 +2,10 @@
 ///
 pub mod bbox;
 
+/// Oriented 3D bounding boxes for LiDAR and radar tracking
+///
+pub mod bbox3d;
+
 /// Bounding box intersection calculation for oriented bounding boxes
 ///
 pub mod clipping;
@@ -19,3 +23,7 @@ pub mod kalman;
 
 /// 2D Points stuff
 pub mod point;
+
+/// Least recently used cache
+///
+pub mod lru;
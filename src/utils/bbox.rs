@@ -2,11 +2,12 @@ use crate::track::ObservationAttributes;
 use crate::utils::clipping::sutherland_hodgman_clip;
 use crate::Errors::GenericBBoxConversionError;
 use crate::{Errors, EPS};
-use geo::{Area, Coord, LineString, Polygon};
+use geo::{Area, BoundingRect, Coord, LineString, Polygon};
 use std::f32::consts::PI;
 
 /// Bounding box in the format (left, top, width, height)
 ///
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Default, Debug, Copy)]
 pub struct BoundingBox {
     pub left: f32,
@@ -75,6 +76,7 @@ impl BoundingBox {
 }
 
 /// Bounding box in the format (x, y, angle, aspect, height)
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Default, Debug)]
 pub struct Universal2DBox {
     pub xc: f32,
@@ -83,6 +85,7 @@ pub struct Universal2DBox {
     pub aspect: f32,
     pub height: f32,
     pub confidence: f32,
+    #[cfg_attr(feature = "serde", serde(skip))]
     _vertex_cache: Option<Polygon<f64>>,
 }
 
@@ -154,6 +157,23 @@ impl Universal2DBox {
         ))
     }
 
+    /// Constructs the oriented box
+    ///
+    /// # Arguments
+    /// * `xc`, `yc` - the center of the box
+    /// * `width`, `height` - the size of the box before the rotation
+    /// * `angle` - the rotation angle in radians
+    ///
+    pub fn oriented(xc: f32, yc: f32, width: f32, height: f32, angle: f32) -> Self {
+        Self::new(xc, yc, Some(angle), width / height, height)
+    }
+
+    /// The width of the box before the rotation
+    ///
+    pub fn width(&self) -> f32 {
+        self.aspect * self.height
+    }
+
     pub fn get_radius(&self) -> f32 {
         let hw = self.aspect * self.height / 2.0_f32;
         let hh = self.height / 2.0_f32;
@@ -423,6 +443,14 @@ impl PartialEq<Self> for BoundingBox {
     }
 }
 
+/// Returns the angle equivalent to `a` modulo `period` that is the closest to `reference`
+///
+/// The oriented boxes rotated by `PI` are the same boxes, so the angles of the boxes are compared modulo `PI`.
+///
+pub fn closest_equivalent_angle(a: f32, reference: f32, period: f32) -> f32 {
+    a - ((a - reference) / period).round() * period
+}
+
 pub fn normalize_angle(a: f32) -> f32 {
     let pix2 = 2.0 * PI;
     let n = (a / pix2).floor();
@@ -473,6 +501,66 @@ impl Universal2DBox {
         (x * x + y * y).sqrt() / (radial_distance * radial_distance + EPS).sqrt()
     }
 
+    /// Intersection over union of the boxes, the oriented boxes are intersected as polygons
+    ///
+    pub fn iou(l: &Universal2DBox, r: &Universal2DBox) -> f32 {
+        let intersection = Universal2DBox::intersection(l, r);
+        if intersection == 0.0 {
+            return 0.0;
+        }
+        let union = (l.area() + r.area()) as f64 - intersection;
+        (intersection / union) as f32
+    }
+
+    /// Area and squared diagonal of the smallest axis-aligned box enclosing both boxes
+    ///
+    fn enclosing(l: &Universal2DBox, r: &Universal2DBox) -> (f32, f32) {
+        let (lr, rr) = (
+            l.get_vertices().bounding_rect().unwrap(),
+            r.get_vertices().bounding_rect().unwrap(),
+        );
+        let width = lr.max().x.max(rr.max().x) - lr.min().x.min(rr.min().x);
+        let height = lr.max().y.max(rr.max().y) - lr.min().y.min(rr.min().y);
+        (
+            (width * height) as f32,
+            (width * width + height * height) as f32,
+        )
+    }
+
+    /// Generalized intersection over union within `[-1.0, 1.0]`
+    ///
+    /// Unlike IoU, it distinguishes non-overlapping boxes: the farther the boxes, the lower the value.
+    ///
+    pub fn giou(l: &Universal2DBox, r: &Universal2DBox) -> f32 {
+        let intersection = Universal2DBox::intersection(l, r) as f32;
+        let union = l.area() + r.area() - intersection;
+        let (enclosing_area, _) = Universal2DBox::enclosing(l, r);
+        intersection / union - (enclosing_area - union) / enclosing_area
+    }
+
+    /// Distance intersection over union within `[-1.0, 1.0]`
+    ///
+    /// IoU penalized by the squared distance of the centers normalized by the squared diagonal of the enclosing box.
+    ///
+    pub fn diou(l: &Universal2DBox, r: &Universal2DBox) -> f32 {
+        let (_, diagonal) = Universal2DBox::enclosing(l, r);
+        let (dx, dy) = (l.xc - r.xc, l.yc - r.yc);
+        Universal2DBox::iou(l, r) - (dx * dx + dy * dy) / diagonal
+    }
+
+    /// Complete intersection over union within `[-1.0, 1.0]`
+    ///
+    /// DIoU additionally penalized by the difference of the aspect ratios.
+    ///
+    pub fn ciou(l: &Universal2DBox, r: &Universal2DBox) -> f32 {
+        let iou = Universal2DBox::iou(l, r);
+        let (_, diagonal) = Universal2DBox::enclosing(l, r);
+        let (dx, dy) = (l.xc - r.xc, l.yc - r.yc);
+        let v = 4.0 / (PI * PI) * (l.aspect.atan() - r.aspect.atan()).powi(2);
+        let alpha = if v > 0.0 { v / (1.0 - iou + v) } else { 0.0 };
+        (iou - (dx * dx + dy * dy) / diagonal - alpha * v).max(-1.0)
+    }
+
     pub fn intersection(l: &Universal2DBox, r: &Universal2DBox) -> f64 {
         // REMOVED DUE TO: Github #84
         // need to implement better way to run simplified IoU
@@ -518,15 +606,11 @@ impl ObservationAttributes for Universal2DBox {
     ) -> Option<Self::MetricObject> {
         match (left, right) {
             (Some(l), Some(r)) => {
-                let intersection = Universal2DBox::intersection(l, r);
-                if intersection == 0.0 {
+                let iou = Universal2DBox::iou(l, r);
+                if iou == 0.0 {
                     None
                 } else {
-                    let union = (l.height * l.height * l.aspect + r.height * r.height * r.aspect)
-                        as f64
-                        - intersection;
-                    let res = intersection / union;
-                    Some(res as f32)
+                    Some(iou)
                 }
             }
             _ => None,
@@ -817,8 +901,9 @@ pub mod python {
 mod tests {
     use crate::prelude::Universal2DBox;
     use crate::track::ObservationAttributes;
-    use crate::utils::bbox::BoundingBox;
+    use crate::utils::bbox::{closest_equivalent_angle, BoundingBox};
     use crate::EPS;
+    use std::f32::consts::PI;
 
     #[test]
     fn test_radius() {
@@ -869,6 +954,57 @@ mod tests {
         assert!((d - 1.0).abs() < EPS);
     }
 
+    #[test]
+    fn oriented_iou() {
+        let square = Universal2DBox::oriented(0.0, 0.0, 2.0, 2.0, 0.0);
+        assert!((square.width() - 2.0).abs() < EPS);
+
+        // the square rotated by the quarter turn is the same square
+        let rotated = Universal2DBox::oriented(0.0, 0.0, 2.0, 2.0, PI / 2.0);
+        assert!((Universal2DBox::iou(&square, &rotated) - 1.0).abs() < 1e-4);
+
+        // the square rotated by 45 degrees: the intersection is the octagon of the area 8 * (sqrt(2) - 1)
+        let diamond = Universal2DBox::oriented(0.0, 0.0, 2.0, 2.0, PI / 4.0);
+        let intersection = 8.0 * (2.0_f32.sqrt() - 1.0);
+        let iou = intersection / (8.0 - intersection);
+        assert!((Universal2DBox::iou(&square, &diamond) - iou).abs() < 1e-4);
+
+        let far = Universal2DBox::oriented(10.0, 0.0, 2.0, 2.0, 0.3);
+        assert_eq!(Universal2DBox::iou(&square, &far), 0.0);
+        assert!(Universal2DBox::calculate_metric_object(&Some(&square), &Some(&far)).is_none());
+    }
+
+    #[test]
+    fn iou_variants() {
+        let b = Universal2DBox::ltwh(0.0, 0.0, 2.0, 2.0);
+        assert!((Universal2DBox::giou(&b, &b) - 1.0).abs() < EPS);
+        assert!((Universal2DBox::diou(&b, &b) - 1.0).abs() < EPS);
+        assert!((Universal2DBox::ciou(&b, &b) - 1.0).abs() < EPS);
+
+        // non-overlapping boxes: IoU is zero, the variants decrease with the distance
+        let near = Universal2DBox::ltwh(3.0, 0.0, 2.0, 2.0);
+        let far = Universal2DBox::ltwh(8.0, 0.0, 2.0, 2.0);
+        assert_eq!(Universal2DBox::iou(&b, &near), 0.0);
+        // enclosing box is 5x2, the union is 8
+        assert!((Universal2DBox::giou(&b, &near) - (-0.2)).abs() < EPS);
+        assert!(Universal2DBox::giou(&b, &far) < Universal2DBox::giou(&b, &near));
+        // centers are 3 apart, the squared diagonal of the enclosing box is 29
+        assert!((Universal2DBox::diou(&b, &near) - (-9.0 / 29.0)).abs() < EPS);
+        assert!(Universal2DBox::diou(&b, &far) < Universal2DBox::diou(&b, &near));
+
+        // the same centers and areas, the different aspect ratios
+        let wide = Universal2DBox::ltwh(-1.0, 0.5, 4.0, 1.0);
+        assert!(Universal2DBox::ciou(&b, &wide) < Universal2DBox::diou(&b, &wide));
+        assert!((Universal2DBox::ciou(&b, &near) - Universal2DBox::diou(&b, &near)).abs() < EPS);
+    }
+
+    #[test]
+    fn equivalent_angles() {
+        assert!((closest_equivalent_angle(0.05, PI - 0.05, PI) - (PI + 0.05)).abs() < EPS);
+        assert!((closest_equivalent_angle(0.3, 0.2, PI) - 0.3).abs() < EPS);
+        assert!((closest_equivalent_angle(-3.0 * PI + 0.1, 0.0, PI) - 0.1).abs() < 1e-4);
+    }
+
     #[test]
     fn test_iou() {
         let bb1 = BoundingBox::new(-1.0, -1.0, 2.0, 2.0);
@@ -0,0 +1,159 @@
+use crate::track::ObservationAttributes;
+use crate::utils::bbox::Universal2DBox;
+use crate::EPS;
+
+/// Oriented 3D bounding box in the format (x, y, z, length, width, height, yaw)
+///
+/// The box is rotated around the vertical `z` axis, `length` is the size along the heading direction,
+/// `width` is the size across it. The center is the center of the box volume.
+///
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Default, Debug, Copy)]
+pub struct Universal3DBox {
+    pub xc: f32,
+    pub yc: f32,
+    pub zc: f32,
+    pub length: f32,
+    pub width: f32,
+    pub height: f32,
+    pub yaw: f32,
+    pub confidence: f32,
+}
+
+impl Universal3DBox {
+    pub fn new(xc: f32, yc: f32, zc: f32, length: f32, width: f32, height: f32, yaw: f32) -> Self {
+        Self::new_with_confidence(xc, yc, zc, length, width, height, yaw, 1.0)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_confidence(
+        xc: f32,
+        yc: f32,
+        zc: f32,
+        length: f32,
+        width: f32,
+        height: f32,
+        yaw: f32,
+        confidence: f32,
+    ) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&confidence),
+            "Confidence must lay between 0.0 and 1.0"
+        );
+
+        Self {
+            xc,
+            yc,
+            zc,
+            length,
+            width,
+            height,
+            yaw,
+            confidence,
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.length * self.width * self.height
+    }
+
+    /// Bird's-eye view footprint of the box on the `xy` plane
+    ///
+    pub fn bev(&self) -> Universal2DBox {
+        let mut bev = Universal2DBox::oriented(self.xc, self.yc, self.length, self.width, self.yaw);
+        bev.set_confidence(self.confidence);
+        bev
+    }
+
+    /// Overlap of the boxes along the vertical axis
+    ///
+    fn vertical_overlap(l: &Universal3DBox, r: &Universal3DBox) -> f32 {
+        let top = (l.zc + l.height / 2.0).min(r.zc + r.height / 2.0);
+        let bottom = (l.zc - l.height / 2.0).max(r.zc - r.height / 2.0);
+        (top - bottom).max(0.0)
+    }
+
+    /// Intersection over union of the bird's-eye view footprints
+    ///
+    pub fn bev_iou(l: &Universal3DBox, r: &Universal3DBox) -> f32 {
+        Universal2DBox::iou(&l.bev(), &r.bev())
+    }
+
+    /// Intersection over union of the box volumes
+    ///
+    pub fn iou(l: &Universal3DBox, r: &Universal3DBox) -> f32 {
+        let height = Universal3DBox::vertical_overlap(l, r);
+        if height == 0.0 {
+            return 0.0;
+        }
+        let intersection = Universal2DBox::intersection(&l.bev(), &r.bev()) as f32 * height;
+        if intersection == 0.0 {
+            return 0.0;
+        }
+        intersection / (l.volume() + r.volume() - intersection)
+    }
+}
+
+impl ObservationAttributes for Universal3DBox {
+    type MetricObject = f32;
+
+    fn calculate_metric_object(
+        left: &Option<&Self>,
+        right: &Option<&Self>,
+    ) -> Option<Self::MetricObject> {
+        match (left, right) {
+            (Some(l), Some(r)) => {
+                let iou = Universal3DBox::iou(l, r);
+                if iou == 0.0 {
+                    None
+                } else {
+                    Some(iou)
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq<Self> for Universal3DBox {
+    fn eq(&self, other: &Self) -> bool {
+        (self.xc - other.xc).abs() < EPS
+            && (self.yc - other.yc).abs() < EPS
+            && (self.zc - other.zc).abs() < EPS
+            && (self.length - other.length).abs() < EPS
+            && (self.width - other.width).abs() < EPS
+            && (self.height - other.height).abs() < EPS
+            && (self.yaw - other.yaw).abs() < EPS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::ObservationAttributes;
+    use crate::utils::bbox3d::Universal3DBox;
+    use crate::EPS;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn iou_3d() {
+        let l = Universal3DBox::new(0.0, 0.0, 1.0, 4.0, 2.0, 2.0, 0.0);
+        assert!((Universal3DBox::iou(&l, &l) - 1.0).abs() < EPS);
+
+        // half of the length, the full height
+        let r = Universal3DBox::new(2.0, 0.0, 1.0, 4.0, 2.0, 2.0, 0.0);
+        assert!((Universal3DBox::bev_iou(&l, &r) - 1.0 / 3.0).abs() < EPS);
+        assert!((Universal3DBox::iou(&l, &r) - 1.0 / 3.0).abs() < EPS);
+
+        // the same footprint, the half of the height
+        let r = Universal3DBox::new(0.0, 0.0, 2.0, 4.0, 2.0, 2.0, 0.0);
+        assert!((Universal3DBox::bev_iou(&l, &r) - 1.0).abs() < EPS);
+        assert!((Universal3DBox::iou(&l, &r) - 1.0 / 3.0).abs() < EPS);
+
+        // the footprint of the box turned around is the same
+        let r = Universal3DBox::new(0.0, 0.0, 1.0, 4.0, 2.0, 2.0, PI);
+        assert!((Universal3DBox::iou(&l, &r) - 1.0).abs() < 1e-3);
+
+        let r = Universal3DBox::new(0.0, 0.0, 5.0, 4.0, 2.0, 2.0, 0.0);
+        assert!(Universal3DBox::calculate_metric_object(&Some(&l), &Some(&r)).is_none());
+    }
+}
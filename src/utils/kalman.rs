@@ -11,6 +11,9 @@ pub mod kalman_2d_point;
 /// Kalman filter for Vector of 2d points
 ///
 pub mod kalman_2d_point_vec;
+/// Kalman filter for the prediction of oriented 3D bounding boxes
+///
+pub mod kalman_3d_box;
 
 pub const CHI2_UPPER_BOUND: f32 = 100.0;
 
@@ -2,9 +2,10 @@ use std::ops::SubAssign;
 // Original source code idea from
 // https://github.com/nwojke/deep_sort/blob/master/deep_sort/kalman_filter.py
 //
-use crate::utils::bbox::Universal2DBox;
+use crate::utils::bbox::{closest_equivalent_angle, Universal2DBox};
 use crate::utils::kalman::{KalmanState, CHI2INV95, CHI2_UPPER_BOUND, DT};
 use nalgebra::{SMatrix, SVector};
+use std::f32::consts::PI;
 
 pub const DIM_2D_BOX: usize = 5;
 pub const DIM_2D_BOX_X2: usize = DIM_2D_BOX * 2;
@@ -119,6 +120,13 @@ impl Universal2DBoxKalmanFilter {
         KalmanState { mean, covariance }
     }
 
+    /// The angle of the oriented box is ambiguous modulo `PI`, so the measured angle closest to the
+    /// predicted one is used, e.g. the box measured at `0.05` after the box at `PI - 0.05` keeps rotating smoothly
+    ///
+    fn measured_angle(measurement: &Universal2DBox, predicted: f32) -> f32 {
+        closest_equivalent_angle(measurement.angle.unwrap_or(0.0), predicted, PI)
+    }
+
     /// Updates the state with the current observation
     ///
     pub fn update(
@@ -135,7 +143,7 @@ impl Universal2DBoxKalmanFilter {
         let innovation = SVector::from_iterator([
             measurement.xc,
             measurement.yc,
-            measurement.angle.unwrap_or(0.0),
+            Self::measured_angle(measurement, projected_mean[2]),
             measurement.aspect,
             measurement.height,
         ]) - projected_mean;
@@ -156,7 +164,7 @@ impl Universal2DBoxKalmanFilter {
             let mut r: SVector<f32, DIM_2D_BOX> = SVector::from_vec(vec![
                 measurement.xc,
                 measurement.yc,
-                measurement.angle.unwrap_or(0.0),
+                Self::measured_angle(measurement, mean[2]),
                 measurement.aspect,
                 measurement.height,
             ]);
@@ -189,6 +197,7 @@ mod tests {
     use crate::utils::bbox::{BoundingBox, Universal2DBox};
     use crate::utils::kalman::kalman_2d_box::Universal2DBoxKalmanFilter;
     use crate::utils::kalman::CHI2INV95;
+    use std::f32::consts::PI;
 
     #[test]
     fn constructor() {
@@ -247,6 +256,24 @@ mod tests {
         dbg!(&dist);
         assert!(dist > CHI2INV95[4]);
     }
+
+    #[test]
+    fn oriented_wraparound() {
+        let f = Universal2DBoxKalmanFilter::default();
+        let bbox = Universal2DBox::oriented(0.0, 0.0, 20.0, 10.0, PI - 0.05);
+
+        let state = f.initiate(&bbox);
+        let state = f.predict(&state);
+
+        // the same box slightly rotated further, the angle is reported after the wraparound
+        let measurement = Universal2DBox::oriented(0.0, 0.0, 20.0, 10.0, 0.05);
+        let dist = f.distance(state, &measurement);
+        assert!(dist < CHI2INV95[4]);
+
+        let state = f.update(&state, &measurement);
+        let angle = state.mean[2];
+        assert!(angle > PI - 0.05 && angle < PI + 0.05);
+    }
 }
 
 #[cfg(feature = "python")]
@@ -0,0 +1,228 @@
+use crate::utils::bbox::closest_equivalent_angle;
+use crate::utils::bbox3d::Universal3DBox;
+use crate::utils::kalman::{KalmanState, CHI2INV95, CHI2_UPPER_BOUND, DT};
+use nalgebra::{SMatrix, SVector};
+use std::f32::consts::PI;
+use std::ops::SubAssign;
+
+pub const DIM_3D_BOX: usize = 7;
+pub const DIM_3D_BOX_X2: usize = DIM_3D_BOX * 2;
+
+/// Kalman filter for the prediction of oriented 3D bounding boxes
+///
+/// The state is `(x, y, z, yaw, length, width, height)` with the constant velocity motion model,
+/// the noise of the positional components is proportional to the length of the box.
+///
+#[derive(Debug)]
+pub struct Universal3DBoxKalmanFilter {
+    motion_matrix: SMatrix<f32, DIM_3D_BOX_X2, DIM_3D_BOX_X2>,
+    update_matrix: SMatrix<f32, DIM_3D_BOX, DIM_3D_BOX_X2>,
+    std_position_weight: f32,
+    std_velocity_weight: f32,
+}
+
+/// Default initializer
+impl Default for Universal3DBoxKalmanFilter {
+    fn default() -> Self {
+        Universal3DBoxKalmanFilter::new(1.0 / 20.0, 1.0 / 160.0)
+    }
+}
+
+impl Universal3DBoxKalmanFilter {
+    /// Constructor with custom weights (shouldn't be used without the need)
+    pub fn new(position_weight: f32, velocity_weight: f32) -> Self {
+        let mut motion_matrix: SMatrix<f32, DIM_3D_BOX_X2, DIM_3D_BOX_X2> = SMatrix::identity();
+
+        for i in 0..DIM_3D_BOX {
+            motion_matrix[(i, DIM_3D_BOX + i)] = DT as f32;
+        }
+
+        Universal3DBoxKalmanFilter {
+            motion_matrix,
+            update_matrix: SMatrix::identity(),
+            std_position_weight: position_weight,
+            std_velocity_weight: velocity_weight,
+        }
+    }
+
+    fn std_position(&self, k: f32, cnst: f32, p: f32) -> [f32; DIM_3D_BOX] {
+        let pos_weight = k * self.std_position_weight * p;
+        [
+            pos_weight, pos_weight, pos_weight, cnst, pos_weight, pos_weight, pos_weight,
+        ]
+    }
+
+    fn std_velocity(&self, k: f32, cnst: f32, p: f32) -> [f32; DIM_3D_BOX] {
+        let vel_weight = k * self.std_velocity_weight * p;
+        [
+            vel_weight, vel_weight, vel_weight, cnst, vel_weight, vel_weight, vel_weight,
+        ]
+    }
+
+    fn measurement(measurement: &Universal3DBox, predicted_yaw: f32) -> SVector<f32, DIM_3D_BOX> {
+        SVector::from_iterator([
+            measurement.xc,
+            measurement.yc,
+            measurement.zc,
+            // the footprint of the box is ambiguous modulo `PI`
+            closest_equivalent_angle(measurement.yaw, predicted_yaw, PI),
+            measurement.length,
+            measurement.width,
+            measurement.height,
+        ])
+    }
+
+    /// Initialize the filter with the first observation
+    ///
+    pub fn initiate(&self, bbox: &Universal3DBox) -> KalmanState<DIM_3D_BOX_X2> {
+        let mean: SVector<f32, DIM_3D_BOX_X2> = SVector::from_iterator(
+            Self::measurement(bbox, bbox.yaw)
+                .iter()
+                .copied()
+                .chain([0.0; DIM_3D_BOX]),
+        );
+
+        let mut std: SVector<f32, DIM_3D_BOX_X2> = SVector::from_iterator(
+            self.std_position(2.0, 1e-2, bbox.length)
+                .into_iter()
+                .chain(self.std_velocity(10.0, 1e-5, bbox.length)),
+        );
+
+        std = std.component_mul(&std);
+
+        let covariance: SMatrix<f32, DIM_3D_BOX_X2, DIM_3D_BOX_X2> = SMatrix::from_diagonal(&std);
+        KalmanState { mean, covariance }
+    }
+
+    /// Predicts the state from the last state
+    ///
+    pub fn predict(&self, state: &KalmanState<DIM_3D_BOX_X2>) -> KalmanState<DIM_3D_BOX_X2> {
+        let (mean, covariance) = (state.mean, state.covariance);
+        let std_pos = self.std_position(1.0, 1.0, mean[4]);
+        let std_vel = self.std_velocity(1.0, 1.0, mean[4]);
+
+        let mut std: SVector<f32, DIM_3D_BOX_X2> =
+            SVector::from_iterator(std_pos.into_iter().chain(std_vel));
+
+        std = std.component_mul(&std);
+
+        let motion_cov: SMatrix<f32, DIM_3D_BOX_X2, DIM_3D_BOX_X2> = SMatrix::from_diagonal(&std);
+
+        let mean = self.motion_matrix * mean;
+        let covariance =
+            self.motion_matrix * covariance * self.motion_matrix.transpose() + motion_cov;
+        KalmanState { mean, covariance }
+    }
+
+    fn project(
+        &self,
+        mean: SVector<f32, DIM_3D_BOX_X2>,
+        covariance: SMatrix<f32, DIM_3D_BOX_X2, DIM_3D_BOX_X2>,
+    ) -> KalmanState<DIM_3D_BOX> {
+        let mut std: SVector<f32, DIM_3D_BOX> =
+            SVector::from_iterator(self.std_position(1.0, 1e-1, mean[4]));
+
+        std = std.component_mul(&std);
+
+        let innovation_cov: SMatrix<f32, DIM_3D_BOX, DIM_3D_BOX> = SMatrix::from_diagonal(&std);
+
+        let mean = self.update_matrix * mean;
+        let covariance =
+            self.update_matrix * covariance * self.update_matrix.transpose() + innovation_cov;
+        KalmanState { mean, covariance }
+    }
+
+    /// Updates the state with the current observation
+    ///
+    pub fn update(
+        &self,
+        state: &KalmanState<DIM_3D_BOX_X2>,
+        measurement: &Universal3DBox,
+    ) -> KalmanState<DIM_3D_BOX_X2> {
+        let (mean, covariance) = (state.mean, state.covariance);
+        let projected_state = self.project(mean, covariance);
+        let (projected_mean, projected_cov) = (projected_state.mean, projected_state.covariance);
+        let b = (covariance * self.update_matrix.transpose()).transpose();
+        let kalman_gain = projected_cov.solve_lower_triangular(&b).unwrap();
+
+        let innovation = Self::measurement(measurement, projected_mean[3]) - projected_mean;
+
+        let innovation: SMatrix<f32, 1, DIM_3D_BOX> = innovation.transpose();
+
+        let mean = mean + (innovation * kalman_gain).transpose();
+        let covariance = covariance - kalman_gain.transpose() * projected_cov * kalman_gain;
+        KalmanState { mean, covariance }
+    }
+
+    pub fn distance(&self, state: KalmanState<DIM_3D_BOX_X2>, measurement: &Universal3DBox) -> f32 {
+        let (mean, covariance) = (state.mean, state.covariance);
+        let projected_state = self.project(mean, covariance);
+        let (mean, covariance) = (projected_state.mean, projected_state.covariance);
+
+        let measurements = {
+            let mut r = Self::measurement(measurement, mean[3]);
+            r.sub_assign(&mean);
+            r
+        };
+
+        let choletsky = covariance.cholesky().unwrap().l();
+        let res = choletsky.solve_lower_triangular(&measurements).unwrap();
+        res.component_mul(&res).sum()
+    }
+
+    pub fn calculate_cost(distance: f32, inverted: bool) -> f32 {
+        if !inverted {
+            if distance > CHI2INV95[6] {
+                CHI2_UPPER_BOUND
+            } else {
+                distance
+            }
+        } else if distance > CHI2INV95[6] {
+            0.0
+        } else {
+            CHI2_UPPER_BOUND - distance
+        }
+    }
+}
+
+impl From<KalmanState<{ DIM_3D_BOX_X2 }>> for Universal3DBox {
+    fn from(s: KalmanState<{ DIM_3D_BOX_X2 }>) -> Self {
+        Universal3DBox::new(
+            s.mean[0], s.mean[1], s.mean[2], s.mean[4], s.mean[5], s.mean[6], s.mean[3],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::bbox3d::Universal3DBox;
+    use crate::utils::kalman::kalman_3d_box::Universal3DBoxKalmanFilter;
+    use crate::utils::kalman::CHI2INV95;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn constant_velocity() {
+        let f = Universal3DBoxKalmanFilter::default();
+        let bbox = Universal3DBox::new(0.0, 0.0, 1.0, 4.0, 2.0, 1.5, 0.1);
+        let mut state = f.initiate(&bbox);
+        for i in 1..10 {
+            state = f.predict(&state);
+            let bbox = Universal3DBox::new(i as f32, 0.5 * i as f32, 1.0, 4.0, 2.0, 1.5, 0.1);
+            state = f.update(&state, &bbox);
+        }
+
+        let state = f.predict(&state);
+        let predicted = Universal3DBox::from(state);
+        assert!((predicted.xc - 10.0).abs() < 0.2);
+        assert!((predicted.yc - 5.0).abs() < 0.2);
+        assert!((predicted.yaw - 0.1).abs() < 0.01);
+
+        let near = Universal3DBox::new(10.0, 5.0, 1.0, 4.0, 2.0, 1.5, 0.1);
+        assert!(f.distance(state, &near) < CHI2INV95[6]);
+        // turned around, the same footprint
+        let turned = Universal3DBox::new(10.0, 5.0, 1.0, 4.0, 2.0, 1.5, 0.1 + PI);
+        assert!(f.distance(state, &turned) < CHI2INV95[6]);
+        let far = Universal3DBox::new(20.0, 5.0, 1.0, 4.0, 2.0, 1.5, 0.1);
+        assert!(f.distance(state, &far) > CHI2INV95[6]);
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Least recently used cache with the fixed capacity.
+///
+/// Every access marks the entry as the most recently used one, when the capacity is reached, the least
+/// recently used entry is evicted.
+///
+#[derive(Debug, Clone)]
+pub struct LruCache<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K, V> LruCache<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Constructs the cache
+    ///
+    /// # Arguments
+    /// * `capacity` - max amount of entries, must be positive
+    ///
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Capacity must be positive");
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::default(),
+            order: BTreeMap::default(),
+        }
+    }
+
+    /// Max amount of entries
+    ///
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Current amount of entries
+    ///
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` when the cache has no entries
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn touch(&mut self, key: &K) -> Option<u64> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (_, last) = self.entries.get_mut(key)?;
+        let previous = std::mem::replace(last, tick);
+        let key = self.order.remove(&previous).unwrap();
+        self.order.insert(tick, key);
+        Some(tick)
+    }
+
+    /// Returns the value and marks the entry as the most recently used
+    ///
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.touch(key)?;
+        self.entries.get(key).map(|(v, _)| v)
+    }
+
+    /// Puts the value, returns the evicted least recently used entry
+    ///
+    pub fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.touch(&key).is_some() {
+            self.entries.get_mut(&key).unwrap().0 = value;
+            return None;
+        }
+
+        let evicted = if self.entries.len() >= self.capacity {
+            self.pop_lru()
+        } else {
+            None
+        };
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick));
+        evicted
+    }
+
+    /// Removes the entry
+    ///
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (value, tick) = self.entries.remove(key)?;
+        self.order.remove(&tick);
+        Some(value)
+    }
+
+    /// Removes and returns the least recently used entry
+    ///
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let (_, key) = self.order.pop_first()?;
+        let (value, _) = self.entries.remove(&key).unwrap();
+        Some((key, value))
+    }
+
+    /// Removes all the entries
+    ///
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::lru::LruCache;
+
+    #[test]
+    fn eviction_order() {
+        let mut cache = LruCache::new(2);
+        assert_eq!(cache.put(1, "a"), None);
+        assert_eq!(cache.put(2, "b"), None);
+        assert_eq!(cache.get(&1), Some(&"a"));
+        assert_eq!(cache.put(3, "c"), Some((2, "b")));
+        assert_eq!(cache.put(1, "aa"), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.pop_lru(), Some((3, "c")));
+        assert_eq!(cache.remove(&1), Some("aa"));
+        assert!(cache.is_empty());
+    }
+}